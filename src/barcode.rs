@@ -139,10 +139,10 @@ pub fn filter(bd_pairs: Vec<BirthDeath>, k: usize) -> Vec<BirthDeath> {
                 }
                 // check if next element should be in top k
                 if waiting > 0 {
-                    let mut front_index = sweep_status.get(0);
+                    let mut front_index = sweep_status.front();
                     while front_index.is_some() && nodes[*front_index.unwrap()].is_dead {
                         sweep_status.pop_front();
-                        front_index = sweep_status.get(0);
+                        front_index = sweep_status.front();
                     }
                     // If queue has no canidate events move on
                     if front_index.is_none() {
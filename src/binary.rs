@@ -0,0 +1,249 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Compact binary landscape files
+//!
+//! A file is a sequence of records, one per landscape. Every record is laid out as
+//!
+//! | bytes | content |
+//! |-------|---------|
+//! | 4 | [`MAGIC`] |
+//! | 2 | format version, little-endian `u16` |
+//! | 2 | flags, see [`Encoding`] |
+//! | 8 | number of levels `k`, little-endian `u64` |
+//! | 8 * k | number of points of every level, little-endian `u64` |
+//! | 16 * points | `x` and `y` of every point as little-endian `f64`, level by level |
+//!
+//! With [`DELTA`] set every `x` but the first of a level is stored as the wrapping difference
+//! of its bits to the bits of the previous `x`. Levels are sorted, so these are small numbers
+//! with many zero bytes. With [`ZSTD`] set the points are preceded by their compressed length
+//! as a little-endian `u64` and compressed with zstd.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// One landscape read by [`read_landscape`], or `None` at the end of the input
+pub type RecordResult = Result<Option<Vec<Vec<(f64,f64)>>>, Box<dyn Error>>;
+
+/// Every landscape read by [`load`]
+pub type BatchResult = Result<Vec<Vec<Vec<(f64,f64)>>>, Box<dyn Error>>;
+
+/// Flag for delta encoded x-coordinates
+pub const DELTA: u16 = 1;
+
+/// Flag for zstd compressed points
+pub const ZSTD: u16 = 1 << 1;
+
+/// How the points of a record are stored, the default is plain little-endian values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Encoding {
+    /// Store the difference to the previous x-coordinate, which is lossless
+    pub delta: bool,
+    /// Compress with zstd at this level, needs the `zstd` feature
+    pub compression: Option<i32>,
+}
+
+impl Encoding {
+    const fn flags(self) -> u16 {
+        let delta = if self.delta { DELTA } else { 0 };
+        let zstd = if self.compression.is_some() { ZSTD } else { 0 };
+        delta | zstd
+    }
+}
+
+/// First bytes of every record
+pub const MAGIC: [u8; 4] = *b"FPLS";
+
+/// Format version written by [`write_landscape`]
+pub const VERSION: u16 = 1;
+
+/// Writes one landscape record with plain points
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails
+pub fn write_landscape(writer: &mut impl Write, landscapes: &[Vec<(f64,f64)>]) -> std::io::Result<()> {
+    write_landscape_with(writer, landscapes, Encoding::default())
+}
+
+/// Writes one landscape record with its points stored as `encoding`
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails or compression is requested without the `zstd`
+/// feature
+pub fn write_landscape_with(
+    writer: &mut impl Write,
+    landscapes: &[Vec<(f64,f64)>],
+    encoding: Encoding,
+) -> std::io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&encoding.flags().to_le_bytes())?;
+    writer.write_all(&(landscapes.len() as u64).to_le_bytes())?;
+    for level in landscapes {
+        writer.write_all(&(level.len() as u64).to_le_bytes())?;
+    }
+    let mut points = Vec::with_capacity(16 * landscapes.iter().map(Vec::len).sum::<usize>());
+    for level in landscapes {
+        let mut previous = 0u64;
+        for &(x, y) in level {
+            let bits = x.to_bits();
+            let stored = if encoding.delta { bits.wrapping_sub(previous) } else { bits };
+            previous = bits;
+            points.extend_from_slice(&stored.to_le_bytes());
+            points.extend_from_slice(&y.to_le_bytes());
+        }
+    }
+    match encoding.compression {
+        None => writer.write_all(&points),
+        Some(level) => {
+            let compressed = compress(&points, level)?;
+            writer.write_all(&(compressed.len() as u64).to_le_bytes())?;
+            writer.write_all(&compressed)
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn compress(bytes: &[u8], level: i32) -> std::io::Result<Vec<u8>> {
+    zstd::bulk::compress(bytes, level)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress(_bytes: &[u8], _level: i32) -> std::io::Result<Vec<u8>> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Compression needs the zstd feature"))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(bytes: &[u8], capacity: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(zstd::bulk::decompress(bytes, capacity)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_bytes: &[u8], _capacity: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err("Reading compressed landscapes needs the zstd feature".into())
+}
+
+/// Reads the next landscape record, `None` at the end of the input
+///
+/// # Errors
+///
+/// Will return 'Err' if `reader` fails or the record is truncated, has the wrong magic bytes,
+/// an unsupported version or is compressed and the `zstd` feature is disabled
+pub fn read_landscape(reader: &mut impl Read) -> RecordResult {
+    let mut header = [0u8; 16];
+    match reader.read(&mut header[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut header[1..])?,
+    }
+    if header[..4] != MAGIC {
+        return Err("Not a binary landscape file".into());
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != VERSION {
+        return Err(format!("Unsupported binary landscape version {version}").into());
+    }
+    let flags = u16::from_le_bytes([header[6], header[7]]);
+    if flags & !(DELTA | ZSTD) != 0 {
+        return Err("Unsupported binary landscape flags".into());
+    }
+    let k = usize::try_from(u64_at(&header, 8))?;
+
+    let mut counts = vec![0u8; k.checked_mul(8).ok_or("Level count overflows")?];
+    reader.read_exact(&mut counts)?;
+    let counts = counts
+        .chunks_exact(8)
+        .map(|c| Ok(usize::try_from(u64_at(c, 0))?))
+        .collect::<Result<Vec<usize>, Box<dyn Error>>>()?;
+    let total = counts
+        .iter()
+        .try_fold(0usize, |total, &n| total.checked_add(n))
+        .and_then(|n| n.checked_mul(16))
+        .ok_or("Point count overflows")?;
+
+    let stored = if flags & ZSTD == 0 {
+        total
+    } else {
+        let mut length = [0u8; 8];
+        reader.read_exact(&mut length)?;
+        usize::try_from(u64::from_le_bytes(length))?
+    };
+    let mut points = Vec::new();
+    reader.take(stored as u64).read_to_end(&mut points)?;
+    if points.len() != stored {
+        return Err("Truncated binary landscape".into());
+    }
+    if flags & ZSTD != 0 {
+        points = decompress(&points, total)?;
+        if points.len() != total {
+            return Err("Corrupt compressed binary landscape".into());
+        }
+    }
+    let mut points = points
+        .chunks_exact(16)
+        .map(|p| (u64_at(p, 0), f64::from_bits(u64_at(p, 8))));
+    Ok(Some(counts
+        .iter()
+        .map(|&n| {
+            let mut previous = 0u64;
+            points
+                .by_ref()
+                .take(n)
+                .map(|(stored, y)| {
+                    previous = if flags & DELTA == 0 { stored } else { previous.wrapping_add(stored) };
+                    (f64::from_bits(previous), y)
+                })
+                .collect()
+        })
+        .collect()))
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(buf)
+}
+
+/// Writes every landscape of a batch to `path`, one record each
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be created or written to
+pub fn save(path: impl AsRef<Path>, batch: &[Vec<Vec<(f64,f64)>>]) -> std::io::Result<()> {
+    save_with(path, batch, Encoding::default())
+}
+
+/// Same as [`save`] with the points of every record stored as `encoding`
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be created or written to, or compression is
+/// requested without the `zstd` feature
+pub fn save_with(path: impl AsRef<Path>, batch: &[Vec<Vec<(f64,f64)>>], encoding: Encoding) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for landscapes in batch {
+        write_landscape_with(&mut writer, landscapes, encoding)?;
+    }
+    writer.flush()
+}
+
+/// Reads every landscape written by [`save`]
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or holds a malformed record
+pub fn load(path: impl AsRef<Path>) -> BatchResult {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut batch = Vec::new();
+    while let Some(landscapes) = read_landscape(&mut reader)? {
+        batch.push(landscapes);
+    }
+    Ok(batch)
+}
@@ -5,23 +5,489 @@
      clippy::cargo,
  )]
 
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
 
+use crate::vectorize::Grid;
+
+/// A pair of a persistence diagram, with real coordinates unless `T` is given
+///
+/// `BirthDeath<i64>` holds the steps of a discrete filtration, see
+/// [`crate::persistencelandscape::generate_integer`].
 #[derive(Debug)]
-pub struct BirthDeath {
-    pub birth: f64,
-    pub death: f64,
+pub struct BirthDeath<T = f64> {
+    pub birth: T,
+    pub death: T,
+}
+
+/// Finite pairs with `birth <= death` on a 1/65536 grid, small enough to keep the sweep's
+/// intersection arithmetic well conditioned
+impl BirthDeath {
+    /// # Errors
+    ///
+    /// Will return 'Err' if either value is NaN or `birth` > `death`
+    pub fn new(birth: f64, death: f64) -> Result<Self, &'static str> {
+        if birth.is_nan() || death.is_nan() {
+            return Err("Birth and death must not be NaN");
+        }
+        if birth > death {
+            return Err("Birth must not be after death");
+        }
+        Ok(Self { birth, death })
+    }
+
+    /// Lifetime of the pair, `death - birth`
+    #[must_use]
+    pub const fn persistence(&self) -> f64 {
+        self.death - self.birth
+    }
+
+    /// Center of the pair, where its tent peaks
+    #[must_use]
+    pub const fn midpoint(&self) -> f64 {
+        (self.birth + self.death) / 2.0
+    }
+}
+
+impl From<(f64, f64)> for BirthDeath {
+    fn from((birth, death): (f64, f64)) -> Self {
+        Self { birth, death }
+    }
+}
+
+impl From<(f32, f32)> for BirthDeath {
+    fn from((birth, death): (f32, f32)) -> Self {
+        Self { birth: f64::from(birth), death: f64::from(death) }
+    }
+}
+
+impl From<[f32; 2]> for BirthDeath {
+    fn from(pair: [f32; 2]) -> Self {
+        Self::from(<(f32, f32)>::from(pair))
+    }
+}
+
+impl From<(i64, i64)> for BirthDeath<i64> {
+    fn from((birth, death): (i64, i64)) -> Self {
+        Self { birth, death }
+    }
+}
+
+/// Finite pairs of `bd_pairs` as integers, infinite pairs are skipped
+///
+/// # Errors
+///
+/// Will return 'Err' if a finite value is not a whole number or is too large to be one exactly
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+pub fn to_integer(bd_pairs: &[BirthDeath]) -> Result<Vec<BirthDeath<i64>>, &'static str> {
+    // Every integer up to 2^53 is exact in an f64
+    let integer = |value: f64| {
+        if value.fract() != 0.0 || value.abs() > (1_i64 << 53) as f64 {
+            return Err("Expected whole numbers as births and deaths");
+        }
+        Ok(value as i64)
+    };
+    bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|bd| Ok(BirthDeath { birth: integer(bd.birth)?, death: integer(bd.death)? }))
+        .collect()
+}
+
+/// Collects anything convertible to a [`BirthDeath`], e.g. `pairs([(0.0, 1.0), (0.5, 2.0)])`
+pub fn pairs<T: Into<BirthDeath>>(iter: impl IntoIterator<Item = T>) -> Vec<BirthDeath> {
+    iter.into_iter().map(Into::into).collect()
+}
+
+/// Checks every pair with [`BirthDeath::new`]
+///
+/// # Errors
+///
+/// Will return 'Err' on the first pair that is NaN or has `birth` > `death`
+pub fn try_pairs(iter: impl IntoIterator<Item = (f64, f64)>) -> Result<Vec<BirthDeath>, &'static str> {
+    iter.into_iter().map(|(birth, death)| BirthDeath::new(birth, death)).collect()
+}
+
+/// Opaque tag of a pair, such as the id of the generator that created it, which the math
+/// ignores and provenance output carries along
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Label {
+    Integer(i64),
+    Text(String),
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(id) => write!(f, "{id}"),
+            Self::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Whole numbers become [`Label::Integer`], anything else [`Label::Text`]
+impl FromStr for Label {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse().map_or_else(|_| Self::Text(s.to_string()), Self::Integer))
+    }
+}
+
+impl From<i64> for Label {
+    fn from(id: i64) -> Self {
+        Self::Integer(id)
+    }
+}
+
+impl From<&str> for Label {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for BirthDeath {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let birth = f64::from(u.arbitrary::<i32>()?) / 65536.0;
+        let persistence = f64::from(u.arbitrary::<u32>()?) / 65536.0;
+        Ok(Self { birth, death: birth + persistence })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <(i32, u32) as arbitrary::Arbitrary>::size_hint(depth)
+    }
 }
 
 impl FromStr for BirthDeath {
-    type Err = std::string::ParseError;
+    type Err = &'static str;
 
+    /// Parses a `birth death` pair separated by a semicolon, a comma, a tab or whitespace,
+    /// values are read as in diagram files
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (b, d) = s.split_once(' ').unwrap();
+        let mut tokens = crate::io::Delimiter::detect(s).split(s);
+        let (Some(b), Some(d), None) = (tokens.next(), tokens.next(), tokens.next()) else {
+            return Err("Expected a birth and a death value");
+        };
+
+        Ok(Self {
+            birth: crate::io::parse_number(b, false).map_err(|_| "Malformed birth value")?,
+            death: crate::io::parse_number(d, false).map_err(|_| "Malformed death value")?,
+        })
+    }
+}
+
+/// How pairs that can not be part of a landscape are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Skip every pair with a NaN or infinite value
+    #[default]
+    Lenient,
+    /// Skip essential pairs, a finite birth with an infinite death, and reject every other
+    /// NaN or infinite value
+    Strict,
+}
+
+/// Most rows listed by [`InvalidPairs`]
+pub const MAX_REPORTED_PAIRS: usize = 10;
+
+/// Pair rejected by [`Strictness::Strict`], `row` is its index in the input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidPair {
+    pub row: usize,
+    pub birth: f64,
+    pub death: f64,
+}
+
+impl InvalidPair {
+    #[must_use]
+    pub const fn reason(&self) -> &'static str {
+        if self.birth.is_nan() || self.death.is_nan() {
+            "NaN value"
+        } else if self.birth.is_infinite() {
+            "infinite birth"
+        } else {
+            "negative infinite death"
+        }
+    }
+}
+
+/// Every rejected pair up to [`MAX_REPORTED_PAIRS`], and how many there were in total
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidPairs {
+    pub pairs: Vec<InvalidPair>,
+    pub count: usize,
+}
+
+impl fmt::Display for InvalidPairs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid pairs:", self.count)?;
+        for pair in &self.pairs {
+            write!(f, "\n  row {}: ({}, {}) {}", pair.row, pair.birth, pair.death, pair.reason())?;
+        }
+        if self.count > self.pairs.len() {
+            write!(f, "\n  and {} more", self.count - self.pairs.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidPairs {}
+
+/// Checks `bd_pairs` against `strictness`
+///
+/// # Errors
+///
+/// Will return 'Err' listing the offending rows if a pair is rejected
+pub fn check(bd_pairs: &[BirthDeath], strictness: Strictness) -> Result<(), InvalidPairs> {
+    if strictness == Strictness::Lenient {
+        return Ok(());
+    }
+    let mut invalid = bd_pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, bd)| {
+            let essential = bd.birth.is_finite() && bd.death == f64::INFINITY;
+            !essential && (!bd.birth.is_finite() || !bd.death.is_finite())
+        })
+        .map(|(row, bd)| InvalidPair { row, birth: bd.birth, death: bd.death });
+    let pairs: Vec<InvalidPair> = invalid.by_ref().take(MAX_REPORTED_PAIRS).collect();
+    if pairs.is_empty() {
+        return Ok(());
+    }
+    let count = pairs.len() + invalid.count();
+    Err(InvalidPairs { pairs, count })
+}
+
+/// Reads pairs given as `(birth, persistence)` into `(birth, birth + persistence)`
+///
+/// # Errors
+///
+/// Will return 'Err' if a persistence is negative or NaN
+pub fn from_birth_persistence(bd_pairs: Vec<BirthDeath>) -> Result<Vec<BirthDeath>, &'static str> {
+    bd_pairs
+        .into_iter()
+        .map(|BirthDeath { birth, death: persistence }| {
+            if persistence >= 0.0 {
+                Ok(BirthDeath { birth, death: birth + persistence })
+            } else {
+                Err("Persistence must not be negative")
+            }
+        })
+        .collect()
+}
+
+/// Sum of `death - birth` over all finite pairs
+#[must_use]
+pub fn total_persistence(bd_pairs: &[BirthDeath]) -> f64 {
+    bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|bd| (bd.death - bd.birth).abs())
+        .sum()
+}
+
+/// Randomly keeps at most `n` pairs, in their original order
+///
+/// With `weighted` each pair is drawn with probability proportional to its persistence
+/// (weighted sampling without replacement), otherwise uniformly. Pairs with no
+/// persistence are only kept when there are not enough others.
+#[must_use]
+pub fn subsample(bd_pairs: Vec<BirthDeath>, n: usize, weighted: bool, seed: u64) -> Vec<BirthDeath> {
+    if bd_pairs.len() <= n {
+        return bd_pairs;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut keep = vec![false; bd_pairs.len()];
+    if weighted {
+        // Efraimidis-Spirakis: keep the n largest u^(1/w)
+        let mut keys: Vec<(f64, usize)> = bd_pairs
+            .iter()
+            .enumerate()
+            .map(|(i, bd)| {
+                let weight = (bd.death - bd.birth).abs();
+                let key = if weight > 0.0 && weight.is_finite() {
+                    rng.gen::<f64>().ln() / weight
+                } else {
+                    f64::NEG_INFINITY
+                };
+                (key, i)
+            })
+            .collect();
+        keys.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        keys.iter().take(n).for_each(|&(_, i)| keep[i] = true);
+    } else {
+        sample(&mut rng, bd_pairs.len(), n).into_iter().for_each(|i| keep[i] = true);
+    }
+    bd_pairs
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(bd, keep)| keep.then_some(bd))
+        .collect()
+}
+
+/// Keeps the `n` most persistent pairs, in their original order
+///
+/// This is not the same as the exact filter in [`crate::barcode::filter`]: a short pair can
+/// still be among the top k where no long pair is alive. Every level of the resulting
+/// landscape differs from the exact one by at most half the persistence of the longest
+/// dropped pair in sup norm, so the result is exact when only zero-length pairs are dropped
+/// and a close approximation when the dropped pairs are short.
+#[must_use]
+pub fn top_pairs(bd_pairs: Vec<BirthDeath>, n: usize) -> Vec<BirthDeath> {
+    if bd_pairs.len() <= n {
+        return bd_pairs;
+    }
+    let mut order: Vec<usize> = (0..bd_pairs.len()).collect();
+    let persistence = |i: usize| (bd_pairs[i].death - bd_pairs[i].birth).abs();
+    order.sort_by(|&a, &b| persistence(b).total_cmp(&persistence(a)));
+    let mut keep = vec![false; bd_pairs.len()];
+    order.iter().take(n).for_each(|&i| keep[i] = true);
+    bd_pairs
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(bd, keep)| keep.then_some(bd))
+        .collect()
+}
+
+/// Total order of pairs by birth and then death, see [`sort_canonical`]
+#[must_use]
+pub fn canonical_cmp(a: &BirthDeath, b: &BirthDeath) -> std::cmp::Ordering {
+    a.birth.total_cmp(&b.birth).then(a.death.total_cmp(&b.death))
+}
+
+/// Sorts `bd_pairs` into the order of [`canonical_cmp`] so everything computed from them
+/// depends only on the set of pairs and not on the order they were read in
+///
+/// The sweeps break ties between events at the same point by the order of the pairs, so
+/// after this their output is bit-identical for every permutation of the input. Rust never
+/// reorders or fuses floating point operations on its own and `mul_add` is always rounded
+/// once, so the output is also the same for every number of threads and on every platform
+/// that follows IEEE 754, which excludes only 32-bit x86 targets without SSE2.
+pub fn sort_canonical(bd_pairs: &mut [BirthDeath]) {
+    bd_pairs.sort_by(canonical_cmp);
+}
+
+/// Persistent Betti number of `bd_pairs` at `(b, d)`, the number of intervals
+/// `[birth, death]` containing `[b, d]`
+///
+/// Pairs with an infinite death contain every interval after their birth. The result is zero
+/// when `b > d` or either bound is NaN.
+#[must_use]
+pub fn betti_at(bd_pairs: &[BirthDeath], b: f64, d: f64) -> usize {
+    if b > d {
+        return 0;
+    }
+    bd_pairs.iter().filter(|bd| bd.birth <= b && d <= bd.death).count()
+}
+
+/// Rank function of `bd_pairs` sampled on `grid`, entry `[i][j]` is [`betti_at`] the `i`-th
+/// and `j`-th grid point and zero below the diagonal
+#[must_use]
+pub fn rank_function(bd_pairs: &[BirthDeath], grid: &Grid) -> Vec<Vec<usize>> {
+    let points = grid.points();
+    let mut by_birth: Vec<&BirthDeath> = bd_pairs.iter().filter(|bd| !bd.birth.is_nan() && !bd.death.is_nan()).collect();
+    by_birth.sort_by(|a, b| a.birth.total_cmp(&b.birth));
+    let mut deaths: Vec<f64> = Vec::with_capacity(by_birth.len());
+    let mut born = by_birth.iter().peekable();
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            while let Some(bd) = born.next_if(|bd| bd.birth <= b) {
+                let at = deaths.partition_point(|&d| d < bd.death);
+                deaths.insert(at, bd.death);
+            }
+            let mut row = vec![0; points.len()];
+            for (j, &d) in points.iter().enumerate().skip(i) {
+                row[j] = deaths.len() - deaths.partition_point(|&death| death < d);
+            }
+            row
+        })
+        .collect()
+}
+
+/// Exact rank function of a diagram, constant on the cells between consecutive births and
+/// deaths, see [`RankFunction::new`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankFunction {
+    /// Distinct births in increasing order
+    pub births: Vec<f64>,
+    /// Distinct deaths in increasing order, infinite ones included
+    pub deaths: Vec<f64>,
+    /// `values[i][j]` is [`betti_at`] `births[i]` and `deaths[j]`, which holds for every `b`
+    /// from `births[i]` up to the next birth and every `d` above the previous death up to
+    /// `deaths[j]`
+    pub values: Vec<Vec<usize>>,
+}
+
+impl RankFunction {
+    /// Rank function of `bd_pairs`, skipping pairs with a NaN value
+    ///
+    /// As the number of births times the number of deaths is stored, large diagrams are
+    /// better sampled on a grid with [`rank_function`].
+    #[must_use]
+    pub fn new(bd_pairs: &[BirthDeath]) -> Self {
+        let pairs: Vec<&BirthDeath> = bd_pairs.iter().filter(|bd| !bd.birth.is_nan() && !bd.death.is_nan()).collect();
+        let distinct = |values: Vec<f64>| {
+            let mut values = values;
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            values
+        };
+        let births = distinct(pairs.iter().map(|bd| bd.birth).collect());
+        let deaths = distinct(pairs.iter().map(|bd| bd.death).collect());
+        // Pairs born at every birth, counted by the index of their death
+        let mut born = vec![vec![0; deaths.len()]; births.len()];
+        for bd in &pairs {
+            let i = births.partition_point(|&b| b < bd.birth);
+            born[i][deaths.partition_point(|&d| d < bd.death)] += 1;
+        }
+        let mut alive = vec![0; deaths.len()];
+        let values = born
+            .into_iter()
+            .map(|counts| {
+                alive.iter_mut().zip(counts).for_each(|(alive, count)| *alive += count);
+                // Pairs alive at a death are those dying at it or later
+                let mut row: Vec<usize> = alive
+                    .iter()
+                    .rev()
+                    .scan(0, |sum, &n| {
+                        *sum += n;
+                        Some(*sum)
+                    })
+                    .collect();
+                row.reverse();
+                row
+            })
+            .collect();
+        Self { births, deaths, values }
+    }
+
+    /// [`betti_at`] `(b, d)`, zero when `b > d` or either bound is NaN
+    #[must_use]
+    pub fn evaluate(&self, b: f64, d: f64) -> usize {
+        if b > d || b.is_nan() || d.is_nan() {
+            return 0;
+        }
+        let (i, j) = (self.births.partition_point(|&x| x <= b), self.deaths.partition_point(|&x| x < d));
+        match (i.checked_sub(1), self.values.first().map_or(0, Vec::len)) {
+            (Some(i), columns) if j < columns => self.values[i][j],
+            _ => 0,
+        }
+    }
 
-        return Ok(Self {
-            birth: b.trim().parse().unwrap(),
-            death: d.trim().parse().unwrap(),
-        });
+    /// The function sampled on `grid` as [`rank_function`] samples it
+    #[must_use]
+    pub fn sample(&self, grid: &Grid) -> Vec<Vec<usize>> {
+        let points = grid.points();
+        points
+            .iter()
+            .map(|&b| points.iter().map(|&d| self.evaluate(b, d)).collect())
+            .collect()
     }
 }
@@ -0,0 +1,140 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use crate::birthdeath::BirthDeath;
+use crate::landscape::{Landscape, Simplification};
+use crate::persistencelandscape::{generate, generate_parallel};
+
+/// What to do with pairs that never die (or were born at minus infinity)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InfinitePolicy {
+    /// Skip them, as [`generate`] does
+    #[default]
+    Drop,
+    /// Replace an infinite death with this value, and an infinite birth with its negation
+    Cap(f64),
+}
+
+/// Configures and computes a landscape, new options are added here instead of to the
+/// signature of [`generate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LandscapeBuilder {
+    k: usize,
+    infinite_policy: InfinitePolicy,
+    min_persistence: f64,
+    tolerance: f64,
+    ranges: usize,
+    debug: bool,
+}
+
+impl Default for LandscapeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LandscapeBuilder {
+    /// One level, infinite pairs dropped, nothing filtered or simplified, a single sweep
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            k: 1,
+            infinite_policy: InfinitePolicy::Drop,
+            min_persistence: 0.0,
+            tolerance: 0.0,
+            ranges: 1,
+            debug: false,
+        }
+    }
+
+    /// Number of levels to compute, zero for every nonempty level
+    #[must_use]
+    pub const fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    #[must_use]
+    pub const fn infinite_policy(mut self, policy: InfinitePolicy) -> Self {
+        self.infinite_policy = policy;
+        self
+    }
+
+    /// Pairs with less persistence than this are skipped
+    #[must_use]
+    pub const fn min_persistence(mut self, min_persistence: f64) -> Self {
+        self.min_persistence = min_persistence;
+        self
+    }
+
+    /// Output points within this distance of the line through their neighbours are dropped,
+    /// see [`Simplification::Redundant`]
+    #[must_use]
+    pub const fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Split the x-axis into this many ranges and sweep them in parallel, see
+    /// [`generate_parallel`], one range is a single sweep
+    #[must_use]
+    pub const fn parallel(mut self, ranges: usize) -> Self {
+        self.ranges = ranges;
+        self
+    }
+
+    /// Print every event of the sweep, only a single sweep prints them
+    #[must_use]
+    pub const fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Computes the landscape of `bd_pairs`
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if a setting is negative or not finite or there are no ranges
+    pub fn build(&self, bd_pairs: Vec<BirthDeath>) -> Result<Landscape, &'static str> {
+        if !(self.min_persistence >= 0.0 && self.min_persistence.is_finite()) {
+            return Err("Minimum persistence must be non-negative and finite");
+        }
+        if !(self.tolerance >= 0.0 && self.tolerance.is_finite()) {
+            return Err("Tolerance must be non-negative and finite");
+        }
+        if self.ranges == 0 {
+            return Err("Number of ranges must be positive");
+        }
+        if let InfinitePolicy::Cap(cap) = self.infinite_policy {
+            if !cap.is_finite() {
+                return Err("Cap for infinite pairs must be finite");
+            }
+        }
+        let pairs = bd_pairs
+            .into_iter()
+            .map(|BirthDeath { birth, death }| match self.infinite_policy {
+                InfinitePolicy::Drop => BirthDeath { birth, death },
+                InfinitePolicy::Cap(cap) => BirthDeath {
+                    birth: if birth == f64::NEG_INFINITY { -cap } else { birth },
+                    death: if death == f64::INFINITY { cap } else { death },
+                },
+            })
+            .filter(|bd| bd.death - bd.birth >= self.min_persistence)
+            .collect();
+        let levels = if self.ranges > 1 {
+            generate_parallel(pairs, self.k, self.ranges)
+        } else {
+            generate(pairs, self.k, self.debug)
+        };
+        let landscape = Landscape::new(levels);
+        Ok(if self.tolerance > 0.0 {
+            landscape.simplify(Simplification::Redundant { tolerance: self.tolerance })
+        } else {
+            landscape
+        })
+    }
+}
@@ -0,0 +1,64 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::birthdeath::BirthDeath;
+use crate::io::{self, LevelsResult};
+use crate::landscape::Fnv1a;
+use crate::rpls;
+
+/// Stable hash of a diagram together with the parameters that influence its landscape
+#[must_use]
+pub fn input_key(bd_pairs: &[BirthDeath], k: usize, disable_filter: bool) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(&(k as u64).to_le_bytes());
+    hasher.write(&[u8::from(disable_filter)]);
+    hasher.write(&(bd_pairs.len() as u64).to_le_bytes());
+    for bd in bd_pairs {
+        hasher.write(&bd.birth.to_bits().to_le_bytes());
+        hasher.write(&bd.death.to_bits().to_le_bytes());
+    }
+    hasher.finish()
+}
+
+/// Location of the cached landscape for an input key
+#[must_use]
+pub fn cache_path(cache_dir: &Path, key: u64) -> PathBuf {
+    cache_dir.join(format!("{key:016x}.csv"))
+}
+
+/// Same as [`rpls::pairs_to_landscape`] but reuses a landscape previously computed for the
+/// same diagram and parameters from `cache_dir`, and stores newly computed landscapes there.
+///
+/// # Errors
+///
+/// Will return 'Err' if the landscape can not be computed or the cache can not be read or
+/// written
+pub fn pairs_to_landscape(
+    cache_dir: &Path,
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    disable_filter: bool,
+) -> LevelsResult {
+    let path = cache_path(cache_dir, input_key(&bd_pairs, k, disable_filter));
+    if path.is_file() {
+        if debug {
+            eprintln!("Using cached landscape {}", path.display());
+        }
+        return io::read_csv(&path);
+    }
+    let landscapes = rpls::pairs_to_landscape(bd_pairs, k, debug, disable_filter)?;
+    fs::create_dir_all(cache_dir)?;
+    // Write to a temporary file first so an interrupted run never leaves a partial entry
+    let partial = path.with_extension("partial");
+    io::write_csv(&partial, &landscapes)?;
+    fs::rename(&partial, &path)?;
+    Ok(landscapes)
+}
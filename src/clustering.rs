@@ -0,0 +1,105 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::vectorize::{sample, unsample, Grid};
+
+/// Result of clustering a set of landscapes
+#[derive(Debug, Clone)]
+pub struct Clustering {
+    /// Cluster index of every input landscape
+    pub assignments: Vec<usize>,
+    /// Centroid of every cluster as a landscape sampled on the grid
+    pub centroids: Vec<Vec<Vec<(f64,f64)>>>,
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+fn nearest(point: &[f64], centroids: &[Vec<f64>]) -> (usize, f64) {
+    centroids
+        .iter()
+        .map(|c| squared_distance(point, c))
+        .enumerate()
+        .min_by_key(|(_, d)| FloatOrd(*d))
+        .expect("No centroids")
+}
+
+/// k-means++ seeding, every next centroid is drawn proportional to the squared distance to the
+/// closest centroid chosen so far
+fn initial_centroids(vectors: &[Vec<f64>], n_clusters: usize, rng: &mut StdRng) -> Vec<Vec<f64>> {
+    let mut centroids = vec![vectors[rng.gen_range(0..vectors.len())].clone()];
+    while centroids.len() < n_clusters {
+        let distances: Vec<f64> = vectors.iter().map(|v| nearest(v, &centroids).1).collect();
+        // All remaining points coincide with a centroid, fall back to uniform sampling
+        let next = match WeightedIndex::new(&distances) {
+            Ok(weights) => weights.sample(rng),
+            Err(_) => rng.gen_range(0..vectors.len()),
+        };
+        centroids.push(vectors[next].clone());
+    }
+    centroids
+}
+
+/// Clusters landscapes with k-means after sampling the landscapes on `grid`. Centroids are
+/// initialised with k-means++ using an RNG seeded with `seed`.
+///
+/// # Errors
+///
+/// Will return 'Err' if `n_clusters` is zero or larger than the number of landscapes
+pub fn kmeans(
+    landscapes: &[Vec<Vec<(f64,f64)>>],
+    n_clusters: usize,
+    grid: &Grid,
+    max_iterations: usize,
+    seed: u64,
+) -> Result<Clustering, &'static str> {
+    if n_clusters == 0 || n_clusters > landscapes.len() {
+        return Err("Number of clusters must be within 1 and the number of landscapes");
+    }
+    let k = landscapes.iter().map(Vec::len).max().unwrap_or(0);
+    let vectors: Vec<Vec<f64>> = landscapes.iter().map(|l| sample(l, k, grid)).collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut centroids = initial_centroids(&vectors, n_clusters, &mut rng);
+    let mut assignments = vec![usize::MAX; vectors.len()];
+
+    for _ in 0..max_iterations {
+        let updated: Vec<usize> = vectors.iter().map(|v| nearest(v, &centroids).0).collect();
+        if updated == assignments {
+            break;
+        }
+        assignments = updated;
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f64>> = vectors
+                .iter()
+                .zip(&assignments)
+                .filter(|(_, a)| **a == cluster)
+                .map(|(v, _)| v)
+                .collect();
+            // Empty clusters keep their previous centroid
+            if members.is_empty() {
+                continue;
+            }
+            let count = f64::from(u32::try_from(members.len()).map_err(|_| "Too many landscapes")?);
+            for (i, value) in centroid.iter_mut().enumerate() {
+                *value = members.iter().map(|m| m[i]).sum::<f64>() / count;
+            }
+        }
+    }
+    if assignments.contains(&usize::MAX) {
+        assignments = vectors.iter().map(|v| nearest(v, &centroids).0).collect();
+    }
+    Ok(Clustering {
+        assignments,
+        centroids: centroids.iter().map(|c| unsample(c, grid)).collect(),
+    })
+}
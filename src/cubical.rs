@@ -0,0 +1,92 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Cubical sublevel set persistence of grayscale images
+//!
+//! Pixels are the vertices of the cubical complex and edges and squares enter with their
+//! highest pixel, so pixels are connected to their four direct neighbors. Dimension 1 is
+//! computed by duality as dimension 0 of the superlevel sets of the complement, where pixels
+//! are connected to all eight neighbors and the image is surrounded by pixels that are always
+//! outside.
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::generate;
+use crate::sublevel::{components, Levels};
+
+/// Indices of the pixels around `i` in a grid of `width` x `height`, only the four direct
+/// neighbors unless `diagonal`
+fn neighbors(i: usize, width: usize, height: usize, diagonal: bool) -> Vec<usize> {
+    let (x, y) = (i % width, i / width);
+    let mut adjacent = Vec::with_capacity(8);
+    for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+        for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+            let j = ny * width + nx;
+            if j != i && (nx == x || ny == y || diagonal) {
+                adjacent.push(j);
+            }
+        }
+    }
+    adjacent
+}
+
+/// Persistence diagrams of the sublevel sets of `image`, given as rows of pixel values, in
+/// dimensions 0 and 1
+///
+/// Pairs of zero persistence are left out. The component of the lowest pixel never dies, there
+/// are no other essential classes.
+///
+/// # Errors
+///
+/// Will return 'Err' if the rows differ in length or a pixel is NaN
+pub fn persistence(image: &[Vec<f64>]) -> Result<[Vec<BirthDeath>; 2], &'static str> {
+    let width = image.first().map_or(0, Vec::len);
+    if image.iter().any(|row| row.len() != width) {
+        return Err("Every row of the image must have the same length");
+    }
+    if image.iter().flatten().any(|v| v.is_nan()) {
+        return Err("Image must not contain NaN");
+    }
+    if width == 0 {
+        return Ok([Vec::new(), Vec::new()]);
+    }
+    let height = image.len();
+    let pixels: Vec<f64> = image.iter().flatten().copied().collect();
+    let dimension_0 = components(&pixels, |i| neighbors(i, width, height, false));
+
+    // Negated pixels inside a border that enters first, a component of the complement dies
+    // when it reaches the border or an older component, which is when its hole is born
+    let (outer_width, outer_height) = (width + 2, height + 2);
+    let complement: Vec<f64> = (0..outer_width * outer_height)
+        .map(|i| {
+            let (x, y) = (i % outer_width, i / outer_width);
+            if x == 0 || y == 0 || x > width || y > height {
+                f64::NEG_INFINITY
+            } else {
+                -pixels[(y - 1) * width + x - 1]
+            }
+        })
+        .collect();
+    let dimension_1 = components(&complement, |i| neighbors(i, outer_width, outer_height, true))
+        .into_iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|bd| BirthDeath { birth: -bd.death, death: -bd.birth })
+        .collect();
+    Ok([dimension_0, dimension_1])
+}
+
+/// First `k` levels of the landscapes of the diagrams of `image` in dimensions 0 and 1, see
+/// [`persistence`]
+///
+/// The pair that never dies is left out and `k` of zero keeps every nonempty level.
+///
+/// # Errors
+///
+/// Will return 'Err' if the rows differ in length or a pixel is NaN
+pub fn landscapes(image: &[Vec<f64>], k: usize) -> Result<[Levels; 2], &'static str> {
+    Ok(persistence(image)?
+        .map(|diagram| generate(diagram.into_iter().filter(|bd| bd.death.is_finite()).collect(), k, false)))
+}
@@ -0,0 +1,138 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Distances between persistence diagrams
+//!
+//! Pairs are compared in the sup norm of the plane and may be matched to the diagonal instead
+//! of a pair of the other diagram. Pairs with NaN or an infinite birth are ignored and pairs
+//! with an infinite death are matched among themselves by birth, so both distances are
+//! infinite when the diagrams have a different number of them.
+
+use crate::birthdeath::BirthDeath;
+
+pub use crate::stability::bottleneck;
+
+/// Distance of two pairs in the sup norm of the plane
+fn cost(a: &BirthDeath, b: &BirthDeath) -> f64 {
+    (a.birth - b.birth).abs().max((a.death - b.death).abs())
+}
+
+/// Distance of a pair to the diagonal in the sup norm of the plane
+fn to_diagonal(bd: &BirthDeath) -> f64 {
+    (bd.death - bd.birth).abs() / 2.0
+}
+
+/// Sorted births of the pairs with a finite birth and an infinite death
+fn essential_births(diagram: &[BirthDeath]) -> Vec<f64> {
+    let mut births: Vec<f64> = diagram
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_infinite())
+        .map(|bd| bd.birth)
+        .collect();
+    births.sort_by(f64::total_cmp);
+    births
+}
+
+fn finite_pairs(diagram: &[BirthDeath]) -> Vec<&BirthDeath> {
+    diagram.iter().filter(|bd| bd.birth.is_finite() && bd.death.is_finite()).collect()
+}
+
+/// Smallest total of `weight(row, column)` over all perfect matchings of an `n` x `n`
+/// bipartite graph, with the Hungarian algorithm in `O(n^3)`
+fn min_cost_matching(n: usize, weight: impl Fn(usize, usize) -> f64) -> f64 {
+    // Potentials and matches are one-based, row zero is a sentinel
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; n + 1];
+    let mut row_of = vec![0; n + 1];
+    let mut way = vec![0; n + 1];
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        // Grow a tree of tight edges from `row` until it reaches a free column
+        loop {
+            used[column] = true;
+            let current = row_of[column];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let slack = weight(current - 1, j - 1) - u[current] - v[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    way[j] = column;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    next = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+            column = next;
+            if row_of[column] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path
+        while column != 0 {
+            let previous = way[column];
+            row_of[column] = row_of[previous];
+            column = previous;
+        }
+    }
+    (1..=n).map(|j| weight(row_of[j] - 1, j - 1)).sum()
+}
+
+/// p-Wasserstein distance of two diagrams with the sup norm of the plane, the bottleneck
+/// distance if `p` is infinite
+///
+/// The optimal matching is exact and takes `O((n + m)^3)` for diagrams of `n` and `m` finite
+/// pairs.
+///
+/// # Errors
+///
+/// Will return 'Err' if `p` is NaN or less than one
+pub fn wasserstein(a: &[BirthDeath], b: &[BirthDeath], p: f64) -> Result<f64, &'static str> {
+    if p.is_nan() || p < 1.0 {
+        return Err("The exponent of the Wasserstein distance must be at least 1");
+    }
+    if p.is_infinite() {
+        return Ok(bottleneck(a, b));
+    }
+    let (essential_a, essential_b) = (essential_births(a), essential_births(b));
+    if essential_a.len() != essential_b.len() {
+        return Ok(f64::INFINITY);
+    }
+    let essential: f64 = essential_a.iter().zip(&essential_b).map(|(s, t)| (s - t).abs().powf(p)).sum();
+
+    let (a, b) = (finite_pairs(a), finite_pairs(b));
+    // Rows are the pairs of `a` and then one diagonal slot per pair of `b`, columns the pairs
+    // of `b` and then one diagonal slot per pair of `a`. Every pair may take any diagonal slot
+    // since they are interchangeable, and two diagonal slots match for free.
+    // Powers are taken once up front, the matching looks every weight up O(n) times
+    let costs: Vec<Vec<f64>> = a.iter().map(|x| b.iter().map(|y| cost(x, y).powf(p)).collect()).collect();
+    let diagonal = |diagram: &[&BirthDeath]| -> Vec<f64> { diagram.iter().map(|bd| to_diagonal(bd).powf(p)).collect() };
+    let (diagonal_a, diagonal_b) = (diagonal(&a), diagonal(&b));
+    let weight = |row: usize, column: usize| match (costs.get(row), diagonal_b.get(column)) {
+        (Some(costs), Some(_)) => costs[column],
+        (Some(_), None) => diagonal_a[row],
+        (None, Some(&right)) => right,
+        (None, None) => 0.0,
+    };
+    let total = min_cost_matching(a.len() + b.len(), weight) + essential;
+    Ok(total.powf(p.recip()))
+}
@@ -0,0 +1,432 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+use rayon::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::persistencelandscape::evaluate;
+use crate::statistics::union_grid;
+use crate::vectorize::{sample_level, Grid};
+
+/// Difference `a - b` of two levels at every point of their union grid
+fn difference_level(a: &[(f64,f64)], b: &[(f64,f64)]) -> Vec<(f64,f64)> {
+    let grid = union_grid(&[a, b]);
+    let (a, b) = (sample_level(a, &grid), sample_level(b, &grid));
+    grid.into_iter()
+        .zip(a.iter().zip(b))
+        .map(|(x, (a, b))| (x, a - b))
+        .collect()
+}
+
+/// Difference `a - b` of every level of two landscapes, levels missing in one of them count as
+/// zero. Every level has a point wherever `a` or `b` has one.
+#[must_use]
+pub fn difference(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> Vec<Vec<(f64,f64)>> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            difference_level(
+                a.get(i).map_or(&[][..], Vec::as_slice),
+                b.get(i).map_or(&[][..], Vec::as_slice),
+            )
+        })
+        .collect()
+}
+
+/// Integral of |f| over a linear segment from (x0, y0) to (x1, y1)
+fn abs_segment_integral((x0, y0): (f64,f64), (x1, y1): (f64,f64)) -> f64 {
+    let width = x1 - x0;
+    if y0 * y1 >= 0.0 {
+        width * (y0.abs() + y1.abs()) / 2.0
+    } else {
+        // The segment crosses zero, integrate both triangles separately
+        width * y0.mul_add(y0, y1 * y1) / (2.0 * (y0.abs() + y1.abs()))
+    }
+}
+
+/// Integral of f^2 over a linear segment from (x0, y0) to (x1, y1)
+fn squared_segment_integral((x0, y0): (f64,f64), (x1, y1): (f64,f64)) -> f64 {
+    (x1 - x0) * y0.mul_add(y0, y0.mul_add(y1, y1 * y1)) / 3.0
+}
+
+/// Integral of |f|^p over a linear segment from (x0, y0) to (x1, y1)
+fn power_segment_integral(p: f64, (x0, y0): (f64,f64), (x1, y1): (f64,f64)) -> f64 {
+    let width = x1 - x0;
+    let (a, b) = (y0.abs(), y1.abs());
+    if y0 * y1 < 0.0 {
+        width * (a.powf(p + 1.0) + b.powf(p + 1.0)) / ((p + 1.0) * (a + b))
+    } else if (a - b).abs() <= 1e-9 * a.max(b) {
+        // The closed form below cancels badly when |f| is nearly constant
+        width * ((a + b) / 2.0).powf(p)
+    } else {
+        width * (b.powf(p + 1.0) - a.powf(p + 1.0)) / ((p + 1.0) * (b - a))
+    }
+}
+
+fn segment_sum(levels: &[Vec<(f64,f64)>], f: impl Fn((f64,f64), (f64,f64)) -> f64) -> f64 {
+    levels
+        .iter()
+        .flat_map(|level| level.windows(2).map(|w| f(w[0], w[1])))
+        .sum()
+}
+
+/// Integral of f * g over a segment where both are linear
+fn product_segment_integral(x0: f64, x1: f64, (f0, f1): (f64,f64), (g0, g1): (f64,f64)) -> f64 {
+    (x1 - x0) * (2.0 * f0).mul_add(g0, f0.mul_add(g1, f1.mul_add(g0, 2.0 * f1 * g1))) / 6.0
+}
+
+/// Exact L2 inner product of two landscapes, summed over all levels
+#[must_use]
+pub fn inner_product(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let grid = union_grid(&[a, b]);
+            grid.windows(2)
+                .map(|w| {
+                    product_segment_integral(
+                        w[0],
+                        w[1],
+                        (evaluate(a, w[0]), evaluate(a, w[1])),
+                        (evaluate(b, w[0]), evaluate(b, w[1])),
+                    )
+                })
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+/// Exact L1 distance between two landscapes, summed over all levels
+#[must_use]
+pub fn l1_distance(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> f64 {
+    segment_sum(&difference(a, b), abs_segment_integral)
+}
+
+/// Exact L2 distance between two landscapes, summed over all levels
+#[must_use]
+pub fn l2_distance(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> f64 {
+    segment_sum(&difference(a, b), squared_segment_integral).sqrt()
+}
+
+/// Exact sup-norm distance between two landscapes, the maximum over all levels
+#[must_use]
+pub fn sup_distance(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> f64 {
+    difference(a, b)
+        .iter()
+        .flatten()
+        .map(|p| FloatOrd(p.1.abs()))
+        .max()
+        .map_or(0.0, |d| d.0)
+}
+
+/// Exact Lp distance between two landscapes for `p` of at least one, summed over all levels,
+/// [`sup_distance`] if `p` is infinite
+#[must_use]
+pub fn lp_distance(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>], p: f64) -> f64 {
+    if p.is_infinite() {
+        return sup_distance(a, b);
+    }
+    segment_sum(&difference(a, b), |s, t| power_segment_integral(p, s, t)).powf(p.recip())
+}
+
+/// Lp distance between two landscapes sampled at `resolution` evenly spaced points covering
+/// both, interpolated linearly in between
+///
+/// Faster than [`lp_distance`] for landscapes with many more critical points than samples,
+/// but features narrower than the spacing of the samples are missed.
+#[must_use]
+pub fn sampled_distance(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>], p: f64, resolution: usize) -> f64 {
+    let xs = a.iter().chain(b).flatten().map(|point| FloatOrd(point.0));
+    let (Some(x_min), Some(x_max)) = (xs.clone().min(), xs.max()) else {
+        return 0.0;
+    };
+    let Ok(grid) = Grid::new(x_min.0, x_max.0, resolution.max(2)) else {
+        return 0.0;
+    };
+    let points = grid.points();
+    let empty: &[(f64,f64)] = &[];
+    let differences: Vec<Vec<(f64,f64)>> = (0..a.len().max(b.len()))
+        .map(|i| {
+            let (a, b) = (a.get(i).map_or(empty, Vec::as_slice), b.get(i).map_or(empty, Vec::as_slice));
+            let (a, b) = (sample_level(a, &points), sample_level(b, &points));
+            points.iter().zip(a.iter().zip(b)).map(|(&x, (a, b))| (x, a - b)).collect()
+        })
+        .collect();
+    if p.is_infinite() {
+        return differences.iter().flatten().map(|d| d.1.abs()).fold(0.0, f64::max);
+    }
+    segment_sum(&differences, |s, t| power_segment_integral(p, s, t)).powf(p.recip())
+}
+
+/// Nonnegative weight of every x in a weighted distance, linear between its points and
+/// constant beyond the first and the last
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weight {
+    points: Vec<(f64,f64)>,
+}
+
+impl Weight {
+    /// # Errors
+    ///
+    /// Will return 'Err' if there are no points, x is not strictly increasing or a weight is
+    /// negative or not finite
+    pub fn new(points: Vec<(f64,f64)>) -> Result<Self, &'static str> {
+        if points.is_empty() {
+            return Err("A weight needs at least one point");
+        }
+        if points.windows(2).any(|w| w[0].0 >= w[1].0 || w[1].0.is_nan()) || !points[0].0.is_finite() {
+            return Err("Points of a weight must have finite, strictly increasing x");
+        }
+        if points.iter().any(|&(_, w)| !(w >= 0.0 && w.is_finite())) {
+            return Err("Weights must be nonnegative and finite");
+        }
+        Ok(Self { points })
+    }
+
+    /// `weight` sampled at the points of `grid` and linear in between
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the points of the grid are not distinct or a sample is negative
+    /// or not finite
+    pub fn sampled(weight: impl Fn(f64) -> f64, grid: &Grid) -> Result<Self, &'static str> {
+        Self::new(grid.points().into_iter().map(|x| (x, weight(x))).collect())
+    }
+
+    #[must_use]
+    pub fn points(&self) -> &[(f64,f64)] {
+        &self.points
+    }
+
+    /// Weight of `x`
+    #[must_use]
+    pub fn at(&self, x: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if x <= first.0 {
+            return first.1;
+        }
+        if x >= last.0 {
+            return last.1;
+        }
+        let i = self.points.partition_point(|p| p.0 <= x);
+        let ((x0, w0), (x1, w1)) = (self.points[i - 1], self.points[i]);
+        (w1 - w0).mul_add((x - x0) / (x1 - x0), w0)
+    }
+
+    /// Splits the segment from (x0, y0) to (x1, y1) where the weight bends or the segment
+    /// crosses zero, into pieces of (x, |y|, weight) at both ends on which all are linear
+    fn pieces(&self, (x0, y0): (f64,f64), (x1, y1): (f64,f64)) -> Vec<[(f64,f64,f64); 2]> {
+        let first = self.points.partition_point(|p| p.0 <= x0);
+        let mut xs: Vec<f64> = std::iter::once(x0)
+            .chain(self.points[first..].iter().map(|p| p.0).take_while(|&x| x < x1))
+            .collect();
+        if y0 * y1 < 0.0 {
+            xs.push(x0 - y0 * (x1 - x0) / (y1 - y0));
+            xs.sort_by(f64::total_cmp);
+        }
+        xs.push(x1);
+        let at = |x: f64| {
+            let y = if x1 > x0 { (y1 - y0).mul_add((x - x0) / (x1 - x0), y0) } else { y1 };
+            (x, y.abs(), self.at(x))
+        };
+        xs.windows(2).filter(|w| w[1] > w[0]).map(|w| [at(w[0]), at(w[1])]).collect()
+    }
+}
+
+/// Gauss-Legendre nodes on [0, 1] and their weights, exact for polynomials up to degree nine
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+    (0.046_910_077_030_668, 0.118_463_442_528_094_5),
+    (0.230_765_344_947_158_5, 0.239_314_335_249_683_2),
+    (0.5, 0.284_444_444_444_444_4),
+    (0.769_234_655_052_841_5, 0.239_314_335_249_683_2),
+    (0.953_089_922_969_332, 0.118_463_442_528_094_5),
+];
+
+/// Integral of weight * |f|^p over a piece where both are linear
+fn weighted_piece_integral(p: f64, [(x0, a0, w0), (x1, a1, w1)]: [(f64,f64,f64); 2]) -> f64 {
+    (x1 - x0)
+        * GAUSS_LEGENDRE
+            .iter()
+            .map(|&(t, c)| c * (w1 - w0).mul_add(t, w0) * (a1 - a0).mul_add(t, a0).powf(p))
+            .sum::<f64>()
+}
+
+/// Largest weight * |f| over a piece where both are linear, a parabola
+fn weighted_piece_max([(_, a0, w0), (_, a1, w1)]: [(f64,f64,f64); 2]) -> f64 {
+    let (da, dw) = (a1 - a0, w1 - w0);
+    let vertex = -dw.mul_add(a0, w0 * da) / (2.0 * dw * da);
+    let inside = if vertex > 0.0 && vertex < 1.0 { dw.mul_add(vertex, w0) * da.mul_add(vertex, a0) } else { 0.0 };
+    (w0 * a0).max(w1 * a1).max(inside)
+}
+
+/// Lp distance between two landscapes with every x weighted by `weight`, the p-th root of the
+/// integral of weight * |a - b|^p summed over all levels, or the largest weight * |a - b| if
+/// `p` is infinite
+///
+/// Regions of the filtration scale with a larger weight count more in the comparison. The
+/// integral is exact for whole `p` up to eight and for the sup norm.
+#[must_use]
+pub fn weighted_lp_distance(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>], p: f64, weight: &Weight) -> f64 {
+    let pieces: Vec<[(f64,f64,f64); 2]> = difference(a, b)
+        .iter()
+        .flat_map(|level| level.windows(2).flat_map(|w| weight.pieces(w[0], w[1])).collect::<Vec<_>>())
+        .collect();
+    if p.is_infinite() {
+        return pieces.into_iter().map(weighted_piece_max).fold(0.0, f64::max);
+    }
+    pieces.into_iter().map(|piece| weighted_piece_integral(p, piece)).sum::<f64>().powf(p.recip())
+}
+
+/// Weighted Lp norm of a landscape, its [`weighted_lp_distance`] to the empty landscape
+#[must_use]
+pub fn weighted_lp_norm(levels: &[Vec<(f64,f64)>], p: f64, weight: &Weight) -> f64 {
+    weighted_lp_distance(levels, &[], p, weight)
+}
+
+/// Distance between two landscapes, see [`Metric::evaluate`]
+///
+/// Parses from and prints as `l1`, `l2`, `sup`, or `l<p>` such as `l3` or `l1.5` for other
+/// values of `p`, followed by `@<resolution>` for [`Metric::Sampled`], such as `l2@1000`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    L1,
+    L2,
+    Sup,
+    /// Exact Lp distance for `p` of at least one, see [`lp_distance`]
+    Lp(f64),
+    /// Lp distance of the landscapes sampled on a grid, see [`sampled_distance`]. `p` is
+    /// infinite for the sup norm.
+    Sampled { p: f64, resolution: usize },
+}
+
+impl Metric {
+    /// Distance between two landscapes
+    #[must_use]
+    pub fn evaluate(self, a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> f64 {
+        match self {
+            Self::L1 => l1_distance(a, b),
+            Self::L2 => l2_distance(a, b),
+            Self::Sup => sup_distance(a, b),
+            Self::Lp(p) => lp_distance(a, b, p),
+            Self::Sampled { p, resolution } => sampled_distance(a, b, p, resolution),
+        }
+    }
+
+    /// [`distance_matrix`] of this metric
+    #[must_use]
+    pub fn matrix(self, landscapes: &[Vec<Vec<(f64,f64)>>]) -> Vec<Vec<f64>> {
+        distance_matrix(landscapes, |a, b| self.evaluate(a, b))
+    }
+
+    /// Exponent of the norm, infinite for the sup norm
+    #[must_use]
+    pub const fn p(self) -> f64 {
+        match self {
+            Self::L1 => 1.0,
+            Self::L2 => 2.0,
+            Self::Sup => f64::INFINITY,
+            Self::Lp(p) | Self::Sampled { p, .. } => p,
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.p() {
+            p if p.is_infinite() => write!(f, "sup")?,
+            p => write!(f, "l{p}")?,
+        }
+        if let Self::Sampled { resolution, .. } = self {
+            write!(f, "@{resolution}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Metric {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (norm, resolution) = match s.split_once('@') {
+            Some((norm, resolution)) => (norm, Some(resolution)),
+            None => (s, None),
+        };
+        let p = match norm.to_ascii_lowercase().as_str() {
+            "sup" | "linf" => f64::INFINITY,
+            norm => norm
+                .strip_prefix('l')
+                .and_then(|p| p.parse::<f64>().ok())
+                .ok_or("Expected a metric such as l1, l2, sup or l3, optionally followed by @<resolution>")?,
+        };
+        if p.is_nan() || p < 1.0 {
+            return Err("The exponent of a metric must be at least 1");
+        }
+        let Some(resolution) = resolution else {
+            return Ok(match p {
+                1.0 => Self::L1,
+                2.0 => Self::L2,
+                p if p.is_infinite() => Self::Sup,
+                p => Self::Lp(p),
+            });
+        };
+        match resolution.parse::<usize>() {
+            Ok(resolution) if resolution >= 2 => Ok(Self::Sampled { p, resolution }),
+            _ => Err("The resolution of a sampled metric must be a whole number of at least 2"),
+        }
+    }
+}
+
+/// Exact norms and integral of one level, see [`level_norms`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LevelNorms {
+    pub l1: f64,
+    pub l2: f64,
+    pub sup: f64,
+    /// Signed area under the level, the same as `l1` for levels that are never negative
+    pub integral: f64,
+}
+
+/// [`LevelNorms`] of every level of `levels`
+#[must_use]
+pub fn level_norms(levels: &[Vec<(f64,f64)>]) -> Vec<LevelNorms> {
+    levels
+        .iter()
+        .map(|level| {
+            let level = std::slice::from_ref(level);
+            LevelNorms {
+                l1: segment_sum(level, abs_segment_integral),
+                l2: segment_sum(level, squared_segment_integral).sqrt(),
+                sup: level[0].iter().map(|p| p.1.abs()).fold(0.0, f64::max),
+                integral: segment_sum(level, |(x0, y0), (x1, y1)| (x1 - x0) * (y0 + y1) / 2.0),
+            }
+        })
+        .collect()
+}
+
+/// N x N matrix of `metric` over all pairs of `landscapes`. Rows are computed in parallel and
+/// only the upper triangle is evaluated, the diagonal is zero.
+#[must_use]
+pub fn distance_matrix(
+    landscapes: &[Vec<Vec<(f64,f64)>>],
+    metric: impl Fn(&[Vec<(f64,f64)>], &[Vec<(f64,f64)>]) -> f64 + Sync,
+) -> Vec<Vec<f64>> {
+    let upper: Vec<Vec<f64>> = landscapes
+        .par_iter()
+        .enumerate()
+        .map(|(i, a)| landscapes[i + 1..].iter().map(|b| metric(a, b)).collect())
+        .collect();
+    let n = landscapes.len();
+    let mut matrix = vec![vec![0.0; n]; n];
+    for (i, row) in upper.iter().enumerate() {
+        for (offset, value) in row.iter().enumerate() {
+            matrix[i][i + 1 + offset] = *value;
+            matrix[i + 1 + offset][i] = *value;
+        }
+    }
+    matrix
+}
@@ -0,0 +1,152 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Summaries of a collection of landscapes
+//!
+//! A [`LandscapeEnsemble`] is never empty, so unlike the free functions of
+//! [`crate::statistics`] its summaries can not fail.
+
+use crate::landscape::Landscape;
+use crate::statistics;
+
+/// Landscapes summarized together, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct LandscapeEnsemble {
+    landscapes: Vec<Vec<Vec<(f64,f64)>>>,
+}
+
+/// A center landscape between a lower and an upper one, level by level
+#[derive(Debug, Clone, PartialEq)]
+pub struct Band {
+    pub lower: Landscape,
+    pub center: Landscape,
+    pub upper: Landscape,
+}
+
+impl Band {
+    /// Plots the band, see [`crate::plot::band`]
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the chart can not be drawn or saved
+    #[cfg(feature = "plot")]
+    pub fn plot(&self, path: &std::path::Path, height: u32, width: u32) -> Result<(), Box<dyn std::error::Error>> {
+        crate::plot::band(self.lower.levels(), self.center.levels(), self.upper.levels(), path, height, width)
+    }
+}
+
+/// Summaries of [`crate::statistics`] only fail for no landscapes, which an ensemble never is
+fn summary(levels: Result<Vec<Vec<(f64,f64)>>, &'static str>) -> Landscape {
+    Landscape::new(levels.unwrap_or_default())
+}
+
+impl LandscapeEnsemble {
+    /// # Errors
+    ///
+    /// Will return 'Err' if there are no landscapes
+    pub fn new(landscapes: impl IntoIterator<Item = impl Into<Vec<Vec<(f64,f64)>>>>) -> Result<Self, &'static str> {
+        let landscapes: Vec<_> = landscapes.into_iter().map(Into::into).collect();
+        if landscapes.is_empty() {
+            return Err("An ensemble needs at least one landscape");
+        }
+        Ok(Self { landscapes })
+    }
+
+    /// Number of landscapes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.landscapes.len()
+    }
+
+    /// Always `false`, present for symmetry with [`LandscapeEnsemble::len`]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.landscapes.is_empty()
+    }
+
+    #[must_use]
+    pub fn landscapes(&self) -> &[Vec<Vec<(f64,f64)>>] {
+        &self.landscapes
+    }
+
+    /// Adds `landscape` to the ensemble
+    pub fn push(&mut self, landscape: impl Into<Vec<Vec<(f64,f64)>>>) {
+        self.landscapes.push(landscape.into());
+    }
+
+    /// Pointwise mean, see [`statistics::average`]
+    #[must_use]
+    pub fn mean(&self) -> Landscape {
+        summary(statistics::average(&self.landscapes))
+    }
+
+    /// Pointwise median, see [`statistics::median`]
+    #[must_use]
+    pub fn median(&self) -> Landscape {
+        summary(statistics::median(&self.landscapes))
+    }
+
+    /// Pointwise `q`-quantile, see [`statistics::quantile`]
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if `q` is not within [0, 1]
+    pub fn quantile(&self, q: f64) -> Result<Landscape, &'static str> {
+        statistics::quantile(&self.landscapes, q).map(Landscape::new)
+    }
+
+    /// Exact pointwise minimum, see [`statistics::envelope_min`]
+    #[must_use]
+    pub fn lower_envelope(&self) -> Landscape {
+        summary(statistics::envelope_min(&self.landscapes))
+    }
+
+    /// Exact pointwise maximum, see [`statistics::envelope_max`]
+    #[must_use]
+    pub fn upper_envelope(&self) -> Landscape {
+        summary(statistics::envelope_max(&self.landscapes))
+    }
+
+    /// Pointwise standard deviation, see [`statistics::standard_deviation`]
+    #[must_use]
+    pub fn std_dev(&self) -> Landscape {
+        summary(statistics::standard_deviation(&self.landscapes))
+    }
+
+    /// The mean between both envelopes, which contains every landscape
+    #[must_use]
+    pub fn envelope_band(&self) -> Band {
+        Band { lower: self.lower_envelope(), center: self.mean(), upper: self.upper_envelope() }
+    }
+
+    /// The median between the quantiles leaving out `(1 - coverage) / 2` of the landscapes at
+    /// every point on either side
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if `coverage` is not within [0, 1]
+    pub fn quantile_band(&self, coverage: f64) -> Result<Band, &'static str> {
+        if !(0.0..=1.0).contains(&coverage) {
+            return Err("Coverage must be within [0, 1]");
+        }
+        let tail = (1.0 - coverage) / 2.0;
+        Ok(Band { lower: self.quantile(tail)?, center: self.median(), upper: self.quantile(1.0 - tail)? })
+    }
+
+    /// The mean between the mean minus and plus `width` standard deviations
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if `width` is negative or not finite
+    pub fn std_band(&self, width: f64) -> Result<Band, &'static str> {
+        if !(width >= 0.0 && width.is_finite()) {
+            return Err("Band width must be finite and non-negative");
+        }
+        let (mean, spread) = (self.mean(), &self.std_dev() * width);
+        Ok(Band { lower: &mean - &spread, upper: &mean + &spread, center: mean })
+    }
+}
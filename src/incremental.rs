@@ -0,0 +1,155 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use crate::birthdeath::BirthDeath;
+use crate::landscape::Landscape;
+use crate::persistencelandscape::{empty_landscape, generate};
+
+/// Pairs whose intervals overlap, together with their landscape
+#[derive(Debug, Clone, PartialEq)]
+struct Component {
+    start: f64,
+    end: f64,
+    pairs: Vec<(f64, f64)>,
+    levels: Vec<Vec<(f64,f64)>>,
+}
+
+impl Component {
+    fn new(pairs: Vec<(f64, f64)>, k: usize) -> Self {
+        let start = pairs.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let end = pairs.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let levels = generate(
+            crate::birthdeath::pairs(pairs.iter().copied()),
+            k,
+            false,
+        );
+        Self { start, end, pairs, levels }
+    }
+}
+
+/// A landscape that can be updated one pair at a time
+///
+/// The landscape on an interval only depends on the pairs alive on it, so the pairs are
+/// kept in groups of overlapping intervals. Inserting or removing a pair only re-runs the
+/// sweep on the group it touches, the rest of the landscape is left as is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrementalLandscape {
+    k: usize,
+    /// Sorted by start, the supports of two components never touch
+    components: Vec<Component>,
+}
+
+impl IncrementalLandscape {
+    #[must_use]
+    pub const fn new(k: usize) -> Self {
+        Self { k, components: Vec::new() }
+    }
+
+    /// Builds the landscape of `bd_pairs`, skipping pairs that are not finite
+    #[must_use]
+    pub fn from_pairs(bd_pairs: Vec<BirthDeath>, k: usize) -> Self {
+        let pairs = bd_pairs
+            .into_iter()
+            .filter(|bd| bd.birth.is_finite() && bd.death.is_finite() && bd.birth <= bd.death)
+            .map(|bd| (bd.birth, bd.death))
+            .collect();
+        Self { k, components: split(pairs, k) }
+    }
+
+    /// Number of pairs
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.components.iter().map(|c| c.pairs.len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Adds a pair and recomputes the region it overlaps
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pair is not finite or dies before it is born
+    pub fn insert(&mut self, bd: &BirthDeath) -> Result<(), &'static str> {
+        if !(bd.birth.is_finite() && bd.death.is_finite()) {
+            return Err("Pair must be finite");
+        }
+        if bd.birth > bd.death {
+            return Err("Pair must not die before it is born");
+        }
+        let first = self.components.partition_point(|c| c.end < bd.birth);
+        let last = self.components.partition_point(|c| c.start <= bd.death);
+        let mut pairs = vec![(bd.birth, bd.death)];
+        for component in self.components.drain(first..last.max(first)) {
+            pairs.extend(component.pairs);
+        }
+        self.components.insert(first, Component::new(pairs, self.k));
+        Ok(())
+    }
+
+    /// Removes one pair equal to `bd` and recomputes the region it covered
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no such pair exists
+    pub fn remove(&mut self, bd: &BirthDeath) -> Result<(), &'static str> {
+        let index = self.components.partition_point(|c| c.end < bd.birth);
+        let component = self.components
+            .get_mut(index)
+            .filter(|c| c.start <= bd.birth)
+            .ok_or("Pair not found")?;
+        let position = component.pairs
+            .iter()
+            .position(|&(birth, death)| birth.to_bits() == bd.birth.to_bits() && death.to_bits() == bd.death.to_bits())
+            .ok_or("Pair not found")?;
+        component.pairs.swap_remove(position);
+        let pairs = self.components.remove(index).pairs;
+        // Removing a pair can split its group in several
+        for (offset, component) in split(pairs, self.k).into_iter().enumerate() {
+            self.components.insert(index + offset, component);
+        }
+        Ok(())
+    }
+
+    /// Critical points of every level, the same as [`generate`] on all pairs
+    #[must_use]
+    pub fn levels(&self) -> Vec<Vec<(f64,f64)>> {
+        let mut levels = empty_landscape(self.k);
+        for component in &self.components {
+            for (level, points) in levels.iter_mut().zip(&component.levels) {
+                level.extend_from_slice(points);
+            }
+        }
+        levels
+    }
+
+    #[must_use]
+    pub fn landscape(&self) -> Landscape {
+        Landscape::new(self.levels())
+    }
+}
+
+/// Groups pairs into components of overlapping intervals
+fn split(mut pairs: Vec<(f64, f64)>, k: usize) -> Vec<Component> {
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut components = Vec::new();
+    let mut current: Vec<(f64, f64)> = Vec::new();
+    let mut end = f64::NEG_INFINITY;
+    for pair in pairs {
+        if !current.is_empty() && pair.0 > end {
+            components.push(Component::new(std::mem::take(&mut current), k));
+        }
+        end = if current.is_empty() { pair.1 } else { end.max(pair.1) };
+        current.push(pair);
+    }
+    if !current.is_empty() {
+        components.push(Component::new(current, k));
+    }
+    components
+}
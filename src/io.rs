@@ -0,0 +1,1224 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use csv::{ReaderBuilder, Writer};
+use rayon::prelude::*;
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+use crate::birthdeath::{rank_function, BirthDeath, Label, RankFunction};
+use crate::landscape::Landscape;
+use crate::landscapeset::{Key, LandscapeSet};
+use crate::persistencelandscape::Provenance;
+use crate::vectorize::{encode, sample, Grid, Precision};
+
+/// Landscape levels, or the error that prevented reading or computing them
+pub type LevelsResult = Result<Vec<Vec<(f64,f64)>>, Box<dyn Error>>;
+
+/// Pairs of a diagram and the label of each, see [`read_labeled_diagram`]
+pub type LabeledDiagram = (Vec<BirthDeath>, Vec<Option<Label>>);
+
+/// Writes the landscape as CSV, one `x,y` row per critical point and an empty row after every
+/// level
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be created or written to
+pub fn write_csv(path: impl AsRef<Path>, landscapes: &[Vec<(f64,f64)>]) -> Result<(), Box<dyn Error>> {
+    write_csv_to(std::fs::File::create(path)?, landscapes, &WriteOptions::default())
+}
+
+/// How numbers are written to text outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    /// Shortest representation that reads back to the same value
+    #[default]
+    Shortest,
+    /// Rounded to this many significant digits, then written as [`NumberFormat::Shortest`]
+    Significant(usize),
+}
+
+impl NumberFormat {
+    /// Appends `value` to `out`
+    pub fn write(self, out: &mut String, value: f64) {
+        let value = match self {
+            Self::Significant(digits) if value.is_finite() && value != 0.0 => {
+                let rounded = format!("{:.*e}", digits.max(1) - 1, value);
+                rounded.parse().unwrap_or(value)
+            }
+            _ => value,
+        };
+        // Writing to a String can not fail
+        let _ = write!(out, "{value}");
+    }
+
+    #[must_use]
+    pub fn format(self, value: f64) -> String {
+        let mut out = String::new();
+        self.write(&mut out, value);
+        out
+    }
+}
+
+/// Options of the text writers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// Flush after this many points so consumers of a pipe see partial output
+    pub flush_every: Option<usize>,
+    pub format: NumberFormat,
+}
+
+/// Streams the landscape to `writer` in the format of [`write_csv`] through a buffer
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails
+pub fn write_csv_to(
+    writer: impl std::io::Write,
+    landscapes: &[Vec<(f64,f64)>],
+    options: &WriteOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(writer);
+    let (mut x, mut y) = (String::new(), String::new());
+    let mut written = 0;
+    for landscape in landscapes {
+        for point in landscape {
+            x.clear();
+            y.clear();
+            options.format.write(&mut x, point.0);
+            options.format.write(&mut y, point.1);
+            wtr.write_record([&x, &y])?;
+            written += 1;
+            if options.flush_every.is_some_and(|n| written % n == 0) {
+                wtr.flush()?;
+            }
+        }
+        wtr.write_record(["", ""])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes a diagram as CSV, one `birth,death` row per pair, which the readers of diagrams accept
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails
+pub fn write_diagram_to(
+    writer: impl std::io::Write,
+    pairs: &[BirthDeath],
+    format: NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(writer);
+    let (mut birth, mut death) = (String::new(), String::new());
+    for pair in pairs {
+        birth.clear();
+        death.clear();
+        format.write(&mut birth, pair.birth);
+        format.write(&mut death, pair.death);
+        wtr.write_record([&birth, &death])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the critical points of a [`Provenance`] as CSV with a
+/// `level,x,y,pair,crossing,label,crossing_label` header, levels counting from one
+///
+/// `labels` holds the label of every pair the sources number, fields of pairs without one
+/// and of points that are not crossings are left empty.
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails
+pub fn write_provenance_to(
+    writer: impl std::io::Write,
+    provenance: &Provenance,
+    labels: &[Option<Label>],
+    format: NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(writer);
+    wtr.write_record(["level", "x", "y", "pair", "crossing", "label", "crossing_label"])?;
+    let label = |pair: usize| labels.get(pair).and_then(Option::as_ref).map(ToString::to_string).unwrap_or_default();
+    for (level, (points, sources)) in provenance.landscapes.iter().zip(&provenance.sources).enumerate() {
+        for (&(x, y), source) in points.iter().zip(sources) {
+            wtr.write_record([
+                (level + 1).to_string(),
+                format.format(x),
+                format.format(y),
+                source.pair.to_string(),
+                source.crossing.map(|pair| pair.to_string()).unwrap_or_default(),
+                label(source.pair),
+                source.crossing.map(label).unwrap_or_default(),
+            ])?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Reads a landscape written by [`write_csv`]
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or contains a malformed row
+pub fn read_csv(path: impl AsRef<Path>) -> LevelsResult {
+    let mut rdr = ReaderBuilder::new().has_headers(false).from_path(path)?;
+    let mut landscapes = Vec::new();
+    let mut level = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        match (record.get(0), record.get(1)) {
+            (Some(""), Some("")) => landscapes.push(std::mem::take(&mut level)),
+            (Some(x), Some(y)) => level.push((x.trim().parse()?, y.trim().parse()?)),
+            _ => return Err("Malformed landscape CSV row".into()),
+        }
+    }
+    if !level.is_empty() {
+        landscapes.push(level);
+    }
+    Ok(landscapes)
+}
+
+/// Separator between the birth and death of a pair in a diagram file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delimiter {
+    Comma,
+    /// As spreadsheets export CSV in locales with decimal commas
+    Semicolon,
+    Tab,
+    /// Any run of spaces or tabs
+    #[default]
+    Whitespace,
+}
+
+impl Delimiter {
+    /// Picks the delimiter of the first line holding a semicolon, a comma or a tab, falling
+    /// back to whitespace. Comments are ignored.
+    #[must_use]
+    pub fn detect(text: &str) -> Self {
+        Self::detect_with(text, false)
+    }
+
+    /// Same as [`Delimiter::detect`], with `decimal_comma` commas belong to numbers and never
+    /// separate them
+    #[must_use]
+    pub fn detect_with(text: &str, decimal_comma: bool) -> Self {
+        text.lines()
+            .map(strip_comment)
+            .find_map(|line| {
+                if line.contains(';') {
+                    Some(Self::Semicolon)
+                } else if line.contains(',') && !decimal_comma {
+                    Some(Self::Comma)
+                } else if line.contains('\t') {
+                    Some(Self::Tab)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// The trimmed fields of `line`
+    pub fn split(self, line: &str) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Self::Comma => Box::new(line.split(',').map(str::trim)),
+            Self::Semicolon => Box::new(line.split(';').map(str::trim)),
+            Self::Tab => Box::new(line.split('\t').map(str::trim)),
+            Self::Whitespace => Box::new(line.split_whitespace()),
+        }
+    }
+}
+
+/// Whether a diagram file starts with a header row such as `birth,death`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Header {
+    /// Skip the first row if it does not start with a number
+    #[default]
+    Detect,
+    /// Always skip the first row
+    Present,
+}
+
+/// How diagram and table files are read, a [`Header`] converts to the options with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadOptions {
+    pub header: Header,
+    /// Read a comma as the decimal point, as in `1,5`, fields are then separated by
+    /// semicolons, tabs or whitespace
+    pub decimal_comma: bool,
+}
+
+impl From<Header> for ReadOptions {
+    fn from(header: Header) -> Self {
+        Self { header, decimal_comma: false }
+    }
+}
+
+/// Line of a diagram file that is not a valid pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// One-based line number
+    pub line: usize,
+    pub reason: &'static str,
+    pub text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on line {}: {}", self.reason, self.line, self.text)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Value of a field of a diagram or table file
+///
+/// Whitespace and double quotes around the value, as spreadsheets export them, are ignored.
+/// Signs, exponents and infinities are read as [`f64::from_str`] reads them, with
+/// `decimal_comma` after replacing the comma by a point. The error is the reason for a
+/// [`ParseError`].
+pub(crate) fn parse_number(field: &str, decimal_comma: bool) -> Result<f64, &'static str> {
+    let field = field.trim();
+    let field = field.strip_prefix('"').and_then(|f| f.strip_suffix('"')).map_or(field, str::trim);
+    let value = if decimal_comma {
+        field.replacen(',', ".", 1).parse::<f64>()
+    } else {
+        field.parse::<f64>()
+    };
+    match value {
+        Ok(value) => Ok(value),
+        Err(_) if field.contains(',') && !decimal_comma => Err(DECIMAL_COMMA_HINT),
+        Err(_) => Err("Malformed number"),
+    }
+}
+
+/// Reason of a [`ParseError`] on values that look like they use decimal commas
+const DECIMAL_COMMA_HINT: &str = "Malformed number, decimal commas need the decimal comma option";
+
+/// `line` without a trailing `#` comment
+fn strip_comment(line: &str) -> &str {
+    line.split_once('#').map_or(line, |(content, _)| content).trim()
+}
+
+/// Reads a diagram with one `birth death` pair per line, see [`read_diagram_with`]
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a line is malformed
+pub fn read_diagram(path: impl AsRef<Path>) -> Result<Vec<BirthDeath>, Box<dyn Error>> {
+    read_diagram_with(path, Header::Detect)
+}
+
+/// Reads a diagram with one `birth death` pair per line, skipping blank lines, `#` comments,
+/// the `header` row and infinite pairs
+///
+/// Values may be separated by semicolons, commas, tabs or whitespace, see
+/// [`Delimiter::detect`], and are read by the rules of [`ReadOptions`] given as `options` or
+/// as just a [`Header`].
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a line is malformed
+pub fn read_diagram_with(path: impl AsRef<Path>, options: impl Into<ReadOptions>) -> Result<Vec<BirthDeath>, Box<dyn Error>> {
+    let options = options.into();
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect_with(&text, options.decimal_comma);
+    Ok(rows(&text, options, delimiter)
+        .filter(|(_, line)| !line.is_empty() && !line.contains("inf"))
+        .map(|(number, line)| parse_pair(number, line, delimiter, options.decimal_comma))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Same as [`read_diagram_with`] for diagrams of discrete filtrations, every value is parsed
+/// as an integer without going through a float
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a line is malformed or not integral
+pub fn read_integer_diagram(path: impl AsRef<Path>, header: Header) -> Result<Vec<BirthDeath<i64>>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect(&text);
+    Ok(rows(&text, header.into(), delimiter)
+        .filter(|(_, line)| !line.is_empty() && !line.contains("inf"))
+        .map(|(number, line)| {
+            let tokens: Vec<&str> = delimiter.split(line).collect();
+            let [birth, death] = tokens[..] else {
+                return Err(ParseError { line: number, reason: "Malformed pair", text: line.to_string() });
+            };
+            let (Ok(birth), Ok(death)) = (birth.parse::<i64>(), death.parse::<i64>()) else {
+                return Err(ParseError { line: number, reason: "Expected whole numbers", text: line.to_string() });
+            };
+            Ok(BirthDeath { birth, death })
+        })
+        .collect::<Result<_, _>>()?)
+}
+
+/// Same as [`read_diagram_with`] for weighted diagrams, every line may hold a weight after
+/// the pair which defaults to one
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a line is malformed
+pub fn read_weighted_diagram(
+    path: impl AsRef<Path>,
+    options: impl Into<ReadOptions>,
+) -> Result<(Vec<BirthDeath>, Vec<f64>), Box<dyn Error>> {
+    let options = options.into();
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect_with(&text, options.decimal_comma);
+    Ok(rows(&text, options, delimiter)
+        .filter(|(_, line)| !line.is_empty() && !line.contains("inf"))
+        .map(|(number, line)| parse_weighted_pair(number, line, delimiter, options.decimal_comma))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Same as [`read_diagram_with`] for labeled diagrams, every line may hold a label after the
+/// pair, see [`Label`]
+///
+/// Labels may hold spaces in comma or tab separated files. Unlike the other readers, lines
+/// are not skipped for holding `inf` anywhere, only pairs with an infinite value are.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a line is malformed
+pub fn read_labeled_diagram(
+    path: impl AsRef<Path>,
+    options: impl Into<ReadOptions>,
+) -> Result<LabeledDiagram, Box<dyn Error>> {
+    let options = options.into();
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect_with(&text, options.decimal_comma);
+    let labeled: Vec<_> = rows(&text, options, delimiter)
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| parse_labeled_pair(number, line, delimiter, options.decimal_comma))
+        .collect::<Result<_, _>>()?;
+    Ok(labeled
+        .into_iter()
+        .filter(|(bd, _)| bd.birth.is_finite() && bd.death.is_finite())
+        .unzip())
+}
+
+/// Bytes inspected to detect the delimiter and header of a memory-mapped or streamed file
+const MMAP_PREFIX: usize = 1 << 16;
+
+/// Smallest chunk parsed by one thread in [`read_diagram_mmap`]
+const MMAP_MIN_CHUNK: usize = 1 << 20;
+
+/// Same as [`read_diagram_with`] but reads the pairs one line at a time as the iterator is
+/// advanced, so the file is never held in memory
+///
+/// The delimiter and header are detected from the start of the file only.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be opened, the iterator yields an error for every
+/// line that can not be read or is malformed
+pub fn stream_diagram(
+    path: impl AsRef<Path>,
+    options: impl Into<ReadOptions>,
+) -> Result<impl Iterator<Item = Result<BirthDeath, Box<dyn Error>>>, Box<dyn Error>> {
+    use std::io::{BufRead, Read};
+    let options = options.into();
+    let mut prefix = Vec::new();
+    std::fs::File::open(&path)?.take(MMAP_PREFIX as u64).read_to_end(&mut prefix)?;
+    if prefix.len() == MMAP_PREFIX {
+        prefix.truncate(prefix.iter().rposition(|&b| b == b'\n').map_or(MMAP_PREFIX, |i| i + 1));
+    }
+    let prefix = String::from_utf8_lossy(&prefix);
+    let delimiter = Delimiter::detect_with(&prefix, options.decimal_comma);
+    let header_row = header_row(&prefix, options, delimiter);
+    let lines = std::io::BufReader::new(std::fs::File::open(&path)?).lines();
+    Ok(lines
+        .enumerate()
+        .filter(move |&(i, _)| Some(i) != header_row)
+        .filter_map(move |(i, line)| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let content = strip_comment(&line);
+            if content.is_empty() || content.contains("inf") {
+                return None;
+            }
+            Some(parse_pair(i + 1, content, delimiter, options.decimal_comma).map_err(Into::into))
+        }))
+}
+
+/// Same as [`read_diagram_with`] but memory-maps the file and parses it in parallel chunks,
+/// for inputs too large to hold twice in memory
+///
+/// The delimiter and header are detected from the start of the file only.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be mapped, is not UTF-8 or a line is malformed
+#[allow(clippy::naive_bytecount)]
+pub fn read_diagram_mmap(path: impl AsRef<Path>, options: impl Into<ReadOptions>) -> Result<Vec<BirthDeath>, Box<dyn Error>> {
+    let options = options.into();
+    let file = std::fs::File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(Vec::new());
+    }
+    // SAFETY: the map is only read while it is alive, changing the file from another
+    // process meanwhile can at worst produce malformed pairs
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    let bytes: &[u8] = &map;
+
+    let prefix_end = if bytes.len() <= MMAP_PREFIX {
+        bytes.len()
+    } else {
+        bytes[..MMAP_PREFIX].iter().rposition(|&b| b == b'\n').map_or(MMAP_PREFIX, |i| i + 1)
+    };
+    let prefix = String::from_utf8_lossy(&bytes[..prefix_end]);
+    let delimiter = Delimiter::detect_with(&prefix, options.decimal_comma);
+    let header_row = header_row(&prefix, options, delimiter);
+
+    let chunk_len = (bytes.len() / rayon::current_num_threads() / 4).max(MMAP_MIN_CHUNK);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = (start + chunk_len).min(bytes.len());
+        let end = bytes[end..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |i| end + i + 1);
+        chunks.push(&bytes[start..end]);
+        start = end;
+    }
+    let first_lines: Vec<usize> = chunks
+        .par_iter()
+        .map(|chunk| chunk.iter().filter(|&&b| b == b'\n').count())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .scan(0, |lines, count| {
+            let first = *lines;
+            *lines += count;
+            Some(first)
+        })
+        .collect();
+
+    let parsed = chunks
+        .par_iter()
+        .zip(first_lines)
+        .map(|(chunk, first_line)| {
+            let text = std::str::from_utf8(chunk)?;
+            Ok(text.lines()
+                .map(strip_comment)
+                .enumerate()
+                .map(|(i, line)| (first_line + i, line))
+                .filter(|&(i, line)| Some(i) != header_row && !line.is_empty() && !line.contains("inf"))
+                .map(|(i, line)| parse_pair(i + 1, line, delimiter, options.decimal_comma))
+                .collect::<Result<Vec<_>, _>>()?)
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()
+        .map_err(|e| e as Box<dyn Error>)?;
+    Ok(parsed.into_iter().flatten().collect())
+}
+
+/// Reads a file holding several diagrams, see [`read_diagrams_with`]
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a pair line is malformed
+pub fn read_diagrams(path: impl AsRef<Path>) -> Result<Vec<Vec<BirthDeath>>, Box<dyn Error>> {
+    read_diagrams_with(path, Header::Detect)
+}
+
+/// Reads a file holding several diagrams, one `birth death` pair per line
+///
+/// Diagrams are separated by blank lines, `#` comment lines or label lines, which are lines
+/// that do not start with a number such as `diagram 2`. Runs of separators do not produce
+/// empty diagrams and infinite pairs are skipped. Values may be separated by commas, tabs or
+/// whitespace, see [`Delimiter::detect`].
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a pair line is malformed
+pub fn read_diagrams_with(path: impl AsRef<Path>, options: impl Into<ReadOptions>) -> Result<Vec<Vec<BirthDeath>>, Box<dyn Error>> {
+    let options = options.into();
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect_with(&text, options.decimal_comma);
+    let mut diagrams = Vec::new();
+    let mut diagram = Vec::new();
+    for (number, line) in rows(&text, options, delimiter) {
+        if !starts_with_number(line, delimiter, options.decimal_comma) {
+            if !diagram.is_empty() {
+                diagrams.push(std::mem::take(&mut diagram));
+            }
+            continue;
+        }
+        if line.contains("inf") {
+            continue;
+        }
+        diagram.push(parse_pair(number, line, delimiter, options.decimal_comma)?);
+    }
+    if !diagram.is_empty() {
+        diagrams.push(diagram);
+    }
+    Ok(diagrams)
+}
+
+/// The numbered lines of `text` without comments and without the header row, blank lines
+/// are kept
+fn rows(text: &str, options: ReadOptions, delimiter: Delimiter) -> impl Iterator<Item = (usize, &str)> {
+    let header_row = header_row(text, options, delimiter);
+    text.lines()
+        .map(strip_comment)
+        .enumerate()
+        .filter(move |&(i, _)| Some(i) != header_row)
+        .map(|(i, line)| (i + 1, line))
+}
+
+/// Index of the header line of `text`, if it has one
+fn header_row(text: &str, options: ReadOptions, delimiter: Delimiter) -> Option<usize> {
+    text.lines()
+        .map(strip_comment)
+        .enumerate()
+        .find(|(_, line)| !line.is_empty())
+        .filter(|(_, line)| {
+            options.header == Header::Present || !starts_with_number(line, delimiter, options.decimal_comma)
+        })
+        .map(|(i, _)| i)
+}
+
+fn starts_with_number(line: &str, delimiter: Delimiter, decimal_comma: bool) -> bool {
+    delimiter
+        .split(line)
+        .next()
+        .is_some_and(|token| parse_number(token, decimal_comma).is_ok())
+}
+
+/// Birth and death of a pair, the error is the reason for a [`ParseError`]
+fn parse_birth_death(birth: &str, death: &str, decimal_comma: bool) -> Result<BirthDeath, &'static str> {
+    match (parse_number(birth, decimal_comma), parse_number(death, decimal_comma)) {
+        (Ok(birth), Ok(death)) if birth.is_nan() || death.is_nan() => Err("NaN in pair"),
+        (Ok(birth), Ok(death)) => Ok(BirthDeath { birth, death }),
+        (Err(DECIMAL_COMMA_HINT), _) | (_, Err(DECIMAL_COMMA_HINT)) => Err(DECIMAL_COMMA_HINT),
+        _ => Err("Malformed pair"),
+    }
+}
+
+fn parse_pair(number: usize, line: &str, delimiter: Delimiter, decimal_comma: bool) -> Result<BirthDeath, ParseError> {
+    let error = |reason| ParseError { line: number, reason, text: line.to_string() };
+    let tokens: Vec<&str> = delimiter.split(line).collect();
+    match tokens[..] {
+        [birth, death] => parse_birth_death(birth, death, decimal_comma).map_err(error),
+        // `1,5,2,5` is a pair written with decimal commas rather than four values
+        [_, _, _, ..] if delimiter == Delimiter::Comma => Err(error(DECIMAL_COMMA_HINT)),
+        _ => Err(error("Malformed pair")),
+    }
+}
+
+fn parse_weighted_pair(
+    number: usize,
+    line: &str,
+    delimiter: Delimiter,
+    decimal_comma: bool,
+) -> Result<(BirthDeath, f64), ParseError> {
+    let tokens: Vec<&str> = delimiter.split(line).collect();
+    let (pair, weight) = match tokens[..] {
+        [birth, death] => ([birth, death], None),
+        [birth, death, weight] => ([birth, death], Some(weight)),
+        _ => return Err(ParseError { line: number, reason: "Malformed pair", text: line.to_string() }),
+    };
+    let bd = parse_birth_death(pair[0], pair[1], decimal_comma)
+        .map_err(|reason| ParseError { line: number, reason, text: line.to_string() })?;
+    let Ok(weight) = weight.map_or(Ok(1.0), |weight| parse_number(weight, decimal_comma)) else {
+        return Err(ParseError { line: number, reason: "Malformed weight", text: line.to_string() });
+    };
+    if weight.is_nan() {
+        return Err(ParseError { line: number, reason: "NaN in pair", text: line.to_string() });
+    }
+    Ok((bd, weight))
+}
+
+fn parse_labeled_pair(
+    number: usize,
+    line: &str,
+    delimiter: Delimiter,
+    decimal_comma: bool,
+) -> Result<(BirthDeath, Option<Label>), ParseError> {
+    let tokens: Vec<&str> = delimiter.split(line).collect();
+    let (birth, death, label) = match tokens[..] {
+        [birth, death] => (birth, death, None),
+        [birth, death, label] => (birth, death, Some(label).filter(|label| !label.is_empty())),
+        _ => return Err(ParseError { line: number, reason: "Malformed pair", text: line.to_string() }),
+    };
+    let bd = parse_birth_death(birth, death, decimal_comma)
+        .map_err(|reason| ParseError { line: number, reason, text: line.to_string() })?;
+    let Ok(label) = label.map(str::parse).transpose();
+    Ok((bd, label))
+}
+
+/// Reads a table of numbers such as a point cloud or a distance matrix, one row per line,
+/// skipping blank lines, `#` comments and the `header` row
+///
+/// Values are separated as in diagram files, see [`Delimiter::detect`]. Rows may differ in
+/// length, callers check the shape they need.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a value is not a number
+pub fn read_table(path: impl AsRef<Path>, options: impl Into<ReadOptions>) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let options = options.into();
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect_with(&text, options.decimal_comma);
+    Ok(rows(&text, options, delimiter)
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| parse_row(number, line, delimiter, 0, options.decimal_comma))
+        .collect::<Result<_, _>>()?)
+}
+
+/// The numbers of `line` after the first `skip` fields
+fn parse_row(
+    number: usize,
+    line: &str,
+    delimiter: Delimiter,
+    skip: usize,
+    decimal_comma: bool,
+) -> Result<Vec<f64>, ParseError> {
+    delimiter
+        .split(line)
+        .skip(skip)
+        .map(|token| match parse_number(token, decimal_comma) {
+            Ok(value) if !value.is_nan() => Ok(value),
+            Ok(_) => Err(ParseError { line: number, reason: "Malformed number", text: line.to_string() }),
+            Err(reason) => Err(ParseError { line: number, reason, text: line.to_string() }),
+        })
+        .collect()
+}
+
+/// Diagram file of a manifest with its weight and group, see [`read_manifest`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub path: std::path::PathBuf,
+    pub weight: f64,
+    pub group: String,
+}
+
+/// Reads a manifest with one `path [weight [group]]` line per diagram file, skipping blank
+/// lines, `#` comments and a header row such as `path,weight,group`
+///
+/// Weights default to one and groups to the empty label. Relative paths are taken relative to
+/// the directory of the manifest. Fields are separated as in diagram files, see
+/// [`Delimiter::detect`], so paths containing spaces need a comma or tab separated manifest.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a line is malformed
+pub fn read_manifest(path: impl AsRef<Path>) -> Result<Vec<ManifestEntry>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect(&text);
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut lines = text
+        .lines()
+        .map(strip_comment)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .peekable();
+    // Weights are numbers, so a first row with anything else after the path is a header
+    let header = lines.peek().is_some_and(|(_, line)| {
+        delimiter.split(line).nth(1).is_some_and(|weight| parse_number(weight, false).is_err())
+    });
+    Ok(lines
+        .skip(usize::from(header))
+        .map(|(i, line)| parse_manifest_entry(i + 1, line, delimiter, base))
+        .collect::<Result<_, _>>()?)
+}
+
+fn parse_manifest_entry(number: usize, line: &str, delimiter: Delimiter, base: &Path) -> Result<ManifestEntry, ParseError> {
+    let tokens: Vec<&str> = delimiter.split(line).collect();
+    let (file, weight, group) = match tokens[..] {
+        [file] => (file, None, ""),
+        [file, weight] => (file, Some(weight), ""),
+        [file, weight, group] => (file, Some(weight), group),
+        _ => return Err(ParseError { line: number, reason: "Malformed manifest entry", text: line.to_string() }),
+    };
+    let Ok(weight) = weight.map_or(Ok(1.0), |weight| parse_number(weight, false)) else {
+        return Err(ParseError { line: number, reason: "Malformed weight", text: line.to_string() });
+    };
+    if file.is_empty() || weight.is_nan() {
+        return Err(ParseError { line: number, reason: "Malformed manifest entry", text: line.to_string() });
+    }
+    Ok(ManifestEntry { path: base.join(file), weight, group: group.to_string() })
+}
+
+/// Computes the landscape of every diagram in a file read by [`read_diagrams`] in parallel
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a pair line is malformed
+pub fn read_landscapes(path: impl AsRef<Path>, k: usize) -> Result<Vec<Landscape>, Box<dyn Error>> {
+    let mut source = crate::source::DiagramFile::new(path.as_ref());
+    Ok(crate::source::landscapes(&mut source, k)?.into_iter().map(Landscape::new).collect())
+}
+
+/// Writes a square matrix as CSV with `labels` as the header row and first column
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be created or written to
+pub fn write_matrix_csv(
+    writer: impl std::io::Write,
+    labels: &[String],
+    matrix: &[Vec<f64>],
+    format: NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(writer);
+    wtr.write_record(std::iter::once("").chain(labels.iter().map(String::as_str)))?;
+    for (label, row) in labels.iter().zip(matrix) {
+        wtr.write_record(std::iter::once(label.clone()).chain(row.iter().map(|&v| format.format(v))))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Reads a matrix written by [`write_matrix_csv`], or a plain one without labels
+///
+/// Rows are separated as in diagram files, see [`Delimiter::detect`]. A first row or a first
+/// column that does not start with a number holds labels and is skipped.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read, a value is not a number or the rows differ
+/// in length
+pub fn read_matrix_csv(path: impl AsRef<Path>) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = Delimiter::detect(&text);
+    let matrix: Vec<Vec<f64>> = rows(&text, ReadOptions::default(), delimiter)
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(number, line)| {
+            let skip = usize::from(!starts_with_number(line, delimiter, false));
+            parse_row(number, line, delimiter, skip, false)
+        })
+        .collect::<Result<_, _>>()?;
+    if matrix.iter().any(|row| row.len() != matrix[0].len()) {
+        return Err("Every row of the matrix must have the same length".into());
+    }
+    Ok(matrix)
+}
+
+/// Writes one row of [`crate::distance::LevelNorms`] per diagram as CSV
+///
+/// Every level has the columns `l1_<level>`, `l2_<level>`, `sup_<level>` and
+/// `integral_<level>`. Rows with fewer levels than others are padded with zeros.
+///
+/// # Errors
+///
+/// Will return 'Err' if the output can not be written to
+pub fn write_norms_csv(
+    writer: impl std::io::Write,
+    norms: &[Vec<crate::distance::LevelNorms>],
+    format: NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let depth = norms.iter().map(Vec::len).max().unwrap_or(0);
+    let mut wtr = Writer::from_writer(writer);
+    let columns = (0..depth).flat_map(|level| ["l1", "l2", "sup", "integral"].map(|norm| format!("{norm}_{level}")));
+    wtr.write_record(std::iter::once("diagram".to_string()).chain(columns))?;
+    for (diagram, levels) in norms.iter().enumerate() {
+        let values = (0..depth).flat_map(|level| {
+            let n = levels.get(level).copied().unwrap_or_default();
+            [n.l1, n.l2, n.sup, n.integral].map(|v| format.format(v))
+        });
+        wtr.write_record(std::iter::once(diagram.to_string()).chain(values))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes a [`LandscapeSet`] as CSV with a `dimension,sample,level,x,y` header, one row per
+/// critical point and levels counting from one
+///
+/// Landscapes without a sample id leave its field empty. An empty level is a single row with
+/// empty `x` and `y`, so [`read_landscape_set`] restores the number of levels.
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails
+pub fn write_landscape_set_to(
+    writer: impl std::io::Write,
+    set: &LandscapeSet,
+    options: &WriteOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(writer);
+    wtr.write_record(["dimension", "sample", "level", "x", "y"])?;
+    let mut written = 0;
+    for ((dimension, sample), landscape) in set.iter() {
+        let (dimension, sample) = (dimension.to_string(), sample.as_deref().unwrap_or_default());
+        for (level, points) in landscape.levels().iter().enumerate() {
+            let level = (level + 1).to_string();
+            if points.is_empty() {
+                wtr.write_record([dimension.as_str(), sample, &level, "", ""])?;
+            }
+            for &(x, y) in points {
+                wtr.write_record([dimension.as_str(), sample, &level, &options.format.format(x), &options.format.format(y)])?;
+                written += 1;
+                if options.flush_every.is_some_and(|n| written % n == 0) {
+                    wtr.flush()?;
+                }
+            }
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Reads a [`LandscapeSet`] written by [`write_landscape_set_to`]
+///
+/// Rows may come in any order as long as the points of a level are in order. An empty sample
+/// field reads as no sample id.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or a row is malformed
+pub fn read_landscape_set(path: impl AsRef<Path>) -> Result<LandscapeSet, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new().from_path(path)?;
+    let mut landscapes: std::collections::BTreeMap<Key, Vec<Vec<(f64,f64)>>> = std::collections::BTreeMap::new();
+    for record in rdr.records() {
+        let record = record?;
+        let [Some(dimension), Some(sample), Some(level), Some(x), Some(y)] = [0, 1, 2, 3, 4].map(|i| record.get(i)) else {
+            return Err("Malformed landscape set row".into());
+        };
+        let sample = Some(sample.trim()).filter(|s| !s.is_empty()).map(str::to_string);
+        let level = match level.trim().parse::<usize>() {
+            Ok(level) if level > 0 => level - 1,
+            _ => return Err("Levels of a landscape set count from one".into()),
+        };
+        let levels = landscapes.entry((dimension.trim().parse()?, sample)).or_default();
+        if levels.len() <= level {
+            levels.resize_with(level + 1, Vec::new);
+        }
+        if !(x.trim().is_empty() && y.trim().is_empty()) {
+            levels[level].push((x.trim().parse()?, y.trim().parse()?));
+        }
+    }
+    Ok(landscapes.into_iter().map(|(key, levels)| (key, Landscape::new(levels))).collect())
+}
+
+/// Writes the preamble of a `.npy` file holding a row-major array of `shape` and type `descr`
+fn write_npy_header(writer: &mut impl std::io::Write, descr: &str, shape: (usize, usize)) -> Result<(), Box<dyn Error>> {
+    let mut header = format!(
+        "{{'descr': '{descr}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        shape.0, shape.1
+    );
+    // Magic, version and header length take 10 bytes, the whole preamble is padded to 64
+    let padding = 63 - (10 + header.len()) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&u16::try_from(header.len())?.to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    Ok(())
+}
+
+/// Writes a matrix as a `NumPy` `.npy` file of little-endian `f64` in row-major order
+///
+/// # Errors
+///
+/// Will return 'Err' if the rows differ in length or the file can not be written
+pub fn write_npy(writer: &mut impl std::io::Write, matrix: &[Vec<f64>]) -> Result<(), Box<dyn Error>> {
+    let columns = matrix.first().map_or(0, Vec::len);
+    if matrix.iter().any(|row| row.len() != columns) {
+        return Err("Matrix rows differ in length".into());
+    }
+    write_npy_header(writer, "<f8", (matrix.len(), columns))?;
+    for value in matrix.iter().flatten() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes a square matrix as a Parquet file with a `label` column of `labels` followed by one
+/// `f64` column per label, uncompressed in a single row group
+///
+/// # Errors
+///
+/// Will return 'Err' if the labels and rows differ in number or length, the file can not be
+/// written, or the crate was built without the `parquet` feature
+#[cfg(feature = "parquet")]
+pub fn write_matrix_parquet(
+    writer: impl std::io::Write + Send,
+    labels: &[String],
+    matrix: &[Vec<f64>],
+) -> Result<(), Box<dyn Error>> {
+    use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+    use parquet::data_type::{ByteArray, ByteArrayType, DoubleType};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::types::Type;
+    use std::sync::Arc;
+
+    if matrix.len() != labels.len() || matrix.iter().any(|row| row.len() != labels.len()) {
+        return Err("Matrix must be square with one label per row".into());
+    }
+    let label = Type::primitive_type_builder("label", PhysicalType::BYTE_ARRAY)
+        .with_repetition(Repetition::REQUIRED)
+        .with_logical_type(Some(LogicalType::String))
+        .build()?;
+    let mut fields = vec![Arc::new(label)];
+    for name in labels {
+        let column = Type::primitive_type_builder(name, PhysicalType::DOUBLE)
+            .with_repetition(Repetition::REQUIRED)
+            .build()?;
+        fields.push(Arc::new(column));
+    }
+    let schema = Type::group_type_builder("distances").with_fields(fields).build()?;
+    let properties = WriterProperties::builder().build();
+    let mut file = SerializedFileWriter::new(writer, Arc::new(schema), Arc::new(properties))?;
+    let mut row_group = file.next_row_group()?;
+    let names: Vec<ByteArray> = labels.iter().map(|l| ByteArray::from(l.as_str())).collect();
+    let mut column = row_group.next_column()?.ok_or("Missing label column")?;
+    column.typed::<ByteArrayType>().write_batch(&names, None, None)?;
+    column.close()?;
+    for j in 0..labels.len() {
+        let values: Vec<f64> = matrix.iter().map(|row| row[j]).collect();
+        let mut column = row_group.next_column()?.ok_or("Missing distance column")?;
+        column.typed::<DoubleType>().write_batch(&values, None, None)?;
+        column.close()?;
+    }
+    row_group.close()?;
+    file.close()?;
+    Ok(())
+}
+
+/// Same as the `parquet` feature's [`write_matrix_parquet`], which this build lacks
+///
+/// # Errors
+///
+/// Always returns 'Err' as the crate was built without the `parquet` feature
+#[cfg(not(feature = "parquet"))]
+pub fn write_matrix_parquet(
+    _writer: impl std::io::Write + Send,
+    _labels: &[String],
+    _matrix: &[Vec<f64>],
+) -> Result<(), Box<dyn Error>> {
+    Err("Parquet output needs the parquet feature".into())
+}
+
+/// Value of `key` in the header dictionary of a `.npy` file
+fn npy_field<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+    let value = header[start..].trim_start();
+    let end = if value.starts_with('(') { value.find(')')? + 1 } else { value.find(',')? };
+    Some(value[..end].trim().trim_matches('\''))
+}
+
+/// Reads a two-dimensional `NumPy` `.npy` array in row-major order as rows of `f64`, a
+/// one-dimensional array is a single row
+///
+/// Little-endian `f64`, `f32` and integers of up to 32 bits are supported.
+///
+/// # Errors
+///
+/// Will return 'Err' if the reader fails, the file is not a `.npy` file or its element type,
+/// order or shape is not supported
+pub fn read_npy(reader: &mut impl std::io::Read) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let mut preamble = [0; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != b"\x93NUMPY" {
+        return Err("Not a NumPy .npy file".into());
+    }
+    let header_len = if preamble[6] == 1 {
+        let mut len = [0; 2];
+        reader.read_exact(&mut len)?;
+        usize::from(u16::from_le_bytes(len))
+    } else {
+        let mut len = [0; 4];
+        reader.read_exact(&mut len)?;
+        usize::try_from(u32::from_le_bytes(len))?
+    };
+    let mut header = vec![0; header_len];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8(header)?;
+    let (Some(descr), Some(order), Some(shape)) =
+        (npy_field(&header, "descr"), npy_field(&header, "fortran_order"), npy_field(&header, "shape"))
+    else {
+        return Err("Malformed .npy header".into());
+    };
+    if order != "False" {
+        return Err("Only row-major .npy arrays are supported".into());
+    }
+    let shape = shape
+        .trim_matches(['(', ')'])
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<usize>, _>>()?;
+    let (rows, columns) = match shape[..] {
+        [columns] => (1, columns),
+        [rows, columns] => (rows, columns),
+        _ => return Err("Only one- and two-dimensional .npy arrays are supported".into()),
+    };
+    let convert: fn(&[u8]) -> f64 = match descr {
+        "<f8" => |b| f64::from_le_bytes(b.try_into().unwrap_or_default()),
+        "<f4" => |b| f64::from(f32::from_le_bytes(b.try_into().unwrap_or_default())),
+        "|u1" | "<u1" => |b| f64::from(b[0]),
+        "|i1" | "<i1" => |b| f64::from(i8::from_le_bytes([b[0]])),
+        "<u2" => |b| f64::from(u16::from_le_bytes(b.try_into().unwrap_or_default())),
+        "<i2" => |b| f64::from(i16::from_le_bytes(b.try_into().unwrap_or_default())),
+        "<u4" => |b| f64::from(u32::from_le_bytes(b.try_into().unwrap_or_default())),
+        "<i4" => |b| f64::from(i32::from_le_bytes(b.try_into().unwrap_or_default())),
+        _ => return Err(format!("Unsupported .npy element type {descr}").into()),
+    };
+    let width: usize = descr[2..].parse()?;
+    let mut data = vec![0; rows * columns * width];
+    reader.read_exact(&mut data)?;
+    Ok(data
+        .chunks_exact(columns.max(1) * width)
+        .map(|row| row.chunks_exact(width).map(convert).collect())
+        .collect())
+}
+
+/// Reads a PNG as rows of grayscale pixel values in `0..=255`, or `0..=65535` for 16 bit
+/// images
+///
+/// Palettes are expanded, color pixels are converted to their luma with the Rec. 601 weights
+/// and alpha is ignored.
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be read or decoded
+#[cfg(feature = "image")]
+pub fn read_png(path: impl AsRef<Path>) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(std::fs::File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let samples: Vec<f64> = match info.bit_depth {
+        png::BitDepth::Sixteen => buffer[..info.buffer_size()]
+            .chunks_exact(2)
+            .map(|b| f64::from(u16::from_be_bytes([b[0], b[1]])))
+            .collect(),
+        _ => buffer[..info.buffer_size()].iter().map(|&b| f64::from(b)).collect(),
+    };
+    let channels = info.color_type.samples();
+    let luma = |pixel: &[f64]| match pixel {
+        [gray] | [gray, _] => *gray,
+        [red, green, blue, ..] => 0.114f64.mul_add(*blue, 0.299f64.mul_add(*red, 0.587 * green)),
+        _ => 0.0,
+    };
+    Ok(samples
+        .chunks_exact(channels * usize::try_from(info.width)?)
+        .map(|row| row.chunks_exact(channels).map(luma).collect())
+        .collect())
+}
+
+/// Samples the first `k` levels on `grid` and writes them as raw little-endian values in
+/// `precision`, level by level
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be written
+pub fn write_grid(
+    path: impl AsRef<Path>,
+    landscapes: &[Vec<(f64,f64)>],
+    k: usize,
+    grid: &Grid,
+    precision: Precision,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, encode(&sample(landscapes, k, grid), precision))?;
+    Ok(())
+}
+
+/// Same as [`write_grid`] but as a `NumPy` `.npy` file with one row per level
+///
+/// The samples are stored exactly as [`write_grid`] stores them behind a small header, so
+/// `numpy.load(path, mmap_mode='r')` maps them without reading or copying the file.
+///
+/// # Errors
+///
+/// Will return 'Err' if `precision` has no `NumPy` type, which is the case for bfloat16, or
+/// the file can not be written
+pub fn write_grid_npy(
+    path: impl AsRef<Path>,
+    landscapes: &[Vec<(f64,f64)>],
+    k: usize,
+    grid: &Grid,
+    precision: Precision,
+) -> Result<(), Box<dyn Error>> {
+    write_samples_npy(path, &sample(landscapes, k, grid), (k, grid.resolution), precision)
+}
+
+/// Writes `values` in `precision` as a `.npy` array of `shape`
+fn write_samples_npy(
+    path: impl AsRef<Path>,
+    values: &[f64],
+    shape: (usize, usize),
+    precision: Precision,
+) -> Result<(), Box<dyn Error>> {
+    let descr = match precision {
+        Precision::F32 => "<f4",
+        Precision::F16 => "<f2",
+        Precision::Bf16 => return Err("NumPy has no bfloat16 type, use f32 or f16".into()),
+    };
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_npy_header(&mut out, descr, shape)?;
+    std::io::Write::write_all(&mut out, &encode(values, precision))?;
+    std::io::Write::flush(&mut out)?;
+    Ok(())
+}
+
+/// [`rank_function`] of `bd_pairs` on `grid` in row-major order, births as rows
+#[allow(clippy::cast_precision_loss)]
+fn sample_rank(bd_pairs: &[BirthDeath], grid: &Grid) -> Vec<f64> {
+    rank_function(bd_pairs, grid).into_iter().flatten().map(|n| n as f64).collect()
+}
+
+/// Samples the rank function of `bd_pairs` on `grid` in both directions and writes it as raw
+/// little-endian values in `precision`, births as rows, the same layout as [`write_grid`]
+///
+/// # Errors
+///
+/// Will return 'Err' if the file can not be written
+pub fn write_rank_grid(
+    path: impl AsRef<Path>,
+    bd_pairs: &[BirthDeath],
+    grid: &Grid,
+    precision: Precision,
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, encode(&sample_rank(bd_pairs, grid), precision))?;
+    Ok(())
+}
+
+/// Same as [`write_rank_grid`] but as a square `NumPy` `.npy` array, see [`write_grid_npy`]
+///
+/// # Errors
+///
+/// Will return 'Err' if `precision` has no `NumPy` type or the file can not be written
+pub fn write_rank_grid_npy(
+    path: impl AsRef<Path>,
+    bd_pairs: &[BirthDeath],
+    grid: &Grid,
+    precision: Precision,
+) -> Result<(), Box<dyn Error>> {
+    write_samples_npy(path, &sample_rank(bd_pairs, grid), (grid.resolution, grid.resolution), precision)
+}
+
+/// Writes a [`RankFunction`] as CSV, the deaths as the header row and one row per birth
+/// starting with it, see [`RankFunction::values`] for the cells the counts hold on
+///
+/// # Errors
+///
+/// Will return 'Err' if `writer` fails
+pub fn write_rank_function_to(
+    writer: impl std::io::Write,
+    rank: &RankFunction,
+    format: NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(writer);
+    wtr.write_record(std::iter::once(String::new()).chain(rank.deaths.iter().map(|&d| format.format(d))))?;
+    for (&birth, row) in rank.births.iter().zip(&rank.values) {
+        wtr.write_record(std::iter::once(format.format(birth)).chain(row.iter().map(ToString::to_string)))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
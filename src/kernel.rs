@@ -0,0 +1,50 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use rayon::prelude::*;
+
+use crate::distance::{inner_product, l2_distance};
+
+/// Kernel used to compare two landscapes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel {
+    /// L2 inner product of the landscapes
+    Linear,
+    /// exp(-gamma * d^2) where d is the L2 distance between the landscapes
+    Rbf { gamma: f64 },
+}
+
+impl Kernel {
+    /// Evaluates the kernel on two landscapes
+    #[must_use]
+    pub fn evaluate(&self, a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>]) -> f64 {
+        match self {
+            Self::Linear => inner_product(a, b),
+            Self::Rbf { gamma } => (-gamma * l2_distance(a, b).powi(2)).exp(),
+        }
+    }
+}
+
+/// N x N Gram matrix of `kernel` over all pairs of `landscapes`. Rows are computed in parallel
+/// and only the upper triangle is evaluated since the matrix is symmetric.
+#[must_use]
+pub fn gram_matrix(landscapes: &[Vec<Vec<(f64,f64)>>], kernel: Kernel) -> Vec<Vec<f64>> {
+    let upper: Vec<Vec<f64>> = landscapes
+        .par_iter()
+        .enumerate()
+        .map(|(i, a)| landscapes[i..].iter().map(|b| kernel.evaluate(a, b)).collect())
+        .collect();
+    let n = landscapes.len();
+    let mut gram = vec![vec![0.0; n]; n];
+    for (i, row) in upper.iter().enumerate() {
+        for (offset, value) in row.iter().enumerate() {
+            gram[i][i + offset] = *value;
+            gram[i + offset][i] = *value;
+        }
+    }
+    gram
+}
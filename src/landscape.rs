@@ -0,0 +1,864 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Index, Mul, Neg, Sub};
+
+use crate::birthdeath::BirthDeath;
+use crate::distance;
+use crate::persistencelandscape;
+use crate::statistics::union_grid;
+use crate::vectorize::{sample, unsample, FeatureDescriptor, Grid};
+
+/// Scalar summary of a single landscape level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSummary {
+    /// Area under the level
+    pub integral: f64,
+    /// Largest value of the level, zero for empty levels
+    pub max: f64,
+    /// Leftmost x where the maximum is attained, `None` for empty levels
+    pub argmax: Option<f64>,
+    /// Total length of the intervals where the level is positive
+    pub support_width: f64,
+    /// x-coordinate of the center of mass, `None` when the integral is zero
+    pub centroid: Option<f64>,
+}
+
+/// Constant slope of a level over [`start`, `end`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DerivativePiece {
+    pub start: f64,
+    pub end: f64,
+    pub slope: f64,
+}
+
+/// Linear piece of a level from `start` to `end`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub start: (f64,f64),
+    pub end: (f64,f64),
+}
+
+impl Segment {
+    #[must_use]
+    pub fn slope(&self) -> f64 {
+        (self.end.1 - self.start.1) / (self.end.0 - self.start.0)
+    }
+
+    /// Euclidean length of the segment
+    #[must_use]
+    pub fn length(&self) -> f64 {
+        (self.end.0 - self.start.0).hypot(self.end.1 - self.start.1)
+    }
+}
+
+impl From<Segment> for geo::Line<f64> {
+    fn from(segment: Segment) -> Self {
+        Self::new(segment.start, segment.end)
+    }
+}
+
+/// Kernel used by [`Landscape::smooth`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    /// Mean over a centered window of `window` grid points, even windows are widened by one
+    MovingAverage { window: usize },
+    /// Gaussian kernel with standard deviation `bandwidth` in x units
+    Gaussian { bandwidth: f64 },
+}
+
+/// What [`Landscape::normalize`] divides the landscape by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// Sum of the integrals of all levels
+    L1,
+    /// L2 norm over all levels
+    L2,
+    /// Total persistence of the diagram, see [`crate::birthdeath::total_persistence`]
+    TotalPersistence(f64),
+    /// Number of pairs in the diagram
+    PairCount(usize),
+}
+
+/// How [`Landscape::simplify`] reduces the number of critical points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Simplification {
+    /// Drops points within `tolerance` of the line through their neighbours and points within
+    /// `tolerance` of the previous kept point. With a tolerance of zero the function is
+    /// unchanged.
+    Redundant { tolerance: f64 },
+    /// Lossy Douglas-Peucker simplification, the result is within `epsilon` of the original
+    DouglasPeucker { epsilon: f64 },
+}
+
+/// First property of a persistence landscape found to be violated by [`Landscape::validate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    /// The level has a negative value at x
+    Negative { level: usize, x: f64 },
+    /// The slope of the level exceeds one in absolute value after x
+    NotLipschitz { level: usize, x: f64 },
+    /// The x-coordinates of the level decrease at x
+    Unsorted { level: usize, x: f64 },
+    /// The level does not start or end at zero, x is the offending endpoint
+    NonZeroEndpoint { level: usize, x: f64 },
+    /// The level is above the previous level at x
+    NotDecreasing { level: usize, x: f64 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Negative { level, x } => write!(f, "Level {level} is negative at x = {x}"),
+            Self::NotLipschitz { level, x } => write!(f, "Level {level} is not 1-Lipschitz after x = {x}"),
+            Self::Unsorted { level, x } => write!(f, "Level {level} has decreasing x at x = {x}"),
+            Self::NonZeroEndpoint { level, x } => write!(f, "Level {level} has a non-zero endpoint at x = {x}"),
+            Self::NotDecreasing { level, x } => write!(f, "Level {level} is above level {} at x = {x}", level - 1),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+/// A persistence landscape, one list of critical points per level as produced by
+/// [`persistencelandscape::generate`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Landscape {
+    levels: Vec<Vec<(f64,f64)>>,
+}
+
+impl Landscape {
+    #[must_use]
+    pub const fn new(levels: Vec<Vec<(f64,f64)>>) -> Self {
+        Self { levels }
+    }
+
+    /// Critical points of every level
+    #[must_use]
+    pub fn levels(&self) -> &[Vec<(f64,f64)>] {
+        &self.levels
+    }
+
+    #[must_use]
+    pub fn into_levels(self) -> Vec<Vec<(f64,f64)>> {
+        self.levels
+    }
+
+    /// Number of levels, including empty ones
+    #[must_use]
+    pub fn k(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Number of nonempty levels
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.levels.iter().filter(|level| !level.is_empty()).count()
+    }
+
+    /// Value of `level` at `x`, zero for levels that do not exist
+    #[must_use]
+    pub fn evaluate(&self, level: usize, x: f64) -> f64 {
+        self.levels
+            .get(level)
+            .map_or(0.0, |l| persistencelandscape::evaluate(l, x))
+    }
+
+    /// Exact area under `level`, zero for levels that do not exist
+    #[must_use]
+    pub fn integral(&self, level: usize) -> f64 {
+        self.levels.get(level).map_or(0.0, |l| level_integral(l))
+    }
+
+    /// Sum of the areas under all levels
+    #[must_use]
+    pub fn total_integral(&self) -> f64 {
+        self.levels.iter().map(|l| level_integral(l)).sum()
+    }
+
+    /// Piecewise-constant derivative of `level`, one piece per linear segment. Zero-width
+    /// segments are skipped.
+    #[must_use]
+    pub fn derivative(&self, level: usize) -> Vec<DerivativePiece> {
+        self.levels.get(level).map_or_else(Vec::new, |l| level_derivative(l))
+    }
+
+    /// Copy with [`persistencelandscape::finalize`] applied to every level, e.g. after
+    /// building a landscape by hand
+    #[must_use]
+    pub fn finalized(&self, endpoints: bool) -> Self {
+        let mut levels = self.levels.clone();
+        persistencelandscape::finalize(&mut levels, endpoints);
+        Self { levels }
+    }
+
+    /// Linear pieces of `level` from left to right, zero-width pieces are skipped
+    pub fn segments(&self, level: usize) -> impl Iterator<Item = Segment> + '_ {
+        self.levels
+            .get(level)
+            .map_or(&[][..], Vec::as_slice)
+            .windows(2)
+            .filter(|w| w[1].0 > w[0].0)
+            .map(|w| Segment { start: w[0], end: w[1] })
+    }
+
+    /// [`Landscape::derivative`] of every level
+    #[must_use]
+    pub fn derivatives(&self) -> Vec<Vec<DerivativePiece>> {
+        self.levels.iter().map(|l| level_derivative(l)).collect()
+    }
+
+    /// Local maxima of `level` as (x, value) pairs, ordered by x
+    #[must_use]
+    pub fn maxima(&self, level: usize) -> Vec<(f64,f64)> {
+        self.levels.get(level).map_or_else(Vec::new, |l| level_maxima(l))
+    }
+
+    /// Leftmost global maximum of every level, `None` for empty levels
+    #[must_use]
+    pub fn global_max(&self) -> Vec<Option<(f64,f64)>> {
+        self.summaries()
+            .into_iter()
+            .map(|s| s.argmax.map(|x| (x, s.max)))
+            .collect()
+    }
+
+    /// Integral, maximum, argmax, support width and centroid of `level`, all zero or `None`
+    /// for levels that do not exist
+    #[must_use]
+    pub fn summary(&self, level: usize) -> LevelSummary {
+        summarize(self.levels.get(level).map_or(&[][..], Vec::as_slice))
+    }
+
+    /// [`Landscape::summary`] of every level
+    #[must_use]
+    pub fn summaries(&self) -> Vec<LevelSummary> {
+        self.levels.iter().map(|l| summarize(l)).collect()
+    }
+
+    /// Scales the x-coordinate of every critical point by `s`, leaving the values untouched.
+    /// The landscape of a diagram scaled by `s` is this with the values scaled by `s` as well.
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if `s` is not positive and finite
+    pub fn scale_x(&self, s: f64) -> Result<Self, &'static str> {
+        if !(s > 0.0 && s.is_finite()) {
+            return Err("Scale factor must be positive and finite");
+        }
+        Ok(self.map_x(|x| x * s))
+    }
+
+    /// Shifts every critical point by `dx` along the x-axis
+    #[must_use]
+    pub fn shift_x(&self, dx: f64) -> Self {
+        self.map_x(|x| x + dx)
+    }
+
+    /// Divides every value by the norm or diagram quantity selected by `normalization`
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the divisor is zero or not finite
+    #[allow(clippy::cast_precision_loss)]
+    pub fn normalize(&self, normalization: Normalization) -> Result<Self, &'static str> {
+        let divisor = match normalization {
+            Normalization::L1 => self.total_integral(),
+            Normalization::L2 => distance::l2_distance(&self.levels, &[]),
+            Normalization::TotalPersistence(persistence) => persistence,
+            Normalization::PairCount(count) => count as f64,
+        };
+        if !(divisor > 0.0 && divisor.is_finite()) {
+            return Err("Cannot normalize by a zero or non-finite value");
+        }
+        Ok(self.map_y(|y| y / divisor))
+    }
+
+    /// Reduces the number of critical points in every level
+    #[must_use]
+    pub fn simplify(&self, simplification: Simplification) -> Self {
+        Self::new(
+            self.levels
+                .iter()
+                .map(|l| match simplification {
+                    Simplification::Redundant { tolerance } => remove_redundant(l, tolerance),
+                    Simplification::DouglasPeucker { epsilon } => douglas_peucker(l, epsilon),
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether both landscapes have the same critical points up to the given tolerances, see
+    /// [`approx_eq_point`]. Missing levels compare equal to empty ones.
+    #[must_use]
+    pub fn approx_eq(&self, other: &Self, abs_tol: f64, rel_tol: f64) -> bool {
+        (0..self.k().max(other.k())).all(|i| {
+            let a = self.levels.get(i).map_or(&[][..], Vec::as_slice);
+            let b = other.levels.get(i).map_or(&[][..], Vec::as_slice);
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(p, q)| approx_eq_point(*p, *q, abs_tol, rel_tol))
+        })
+    }
+
+    /// Stable 64-bit hash of the critical points after rounding every coordinate to a multiple
+    /// of `quantum`. Landscapes whose points agree up to the quantization hash the same, except
+    /// for values straddling a rounding boundary. A `quantum` of zero hashes the exact bits.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn fingerprint(&self, quantum: f64) -> u64 {
+        let quantize = |v: f64| -> [u8; 8] {
+            if quantum > 0.0 {
+                // Saturating float to int conversion
+                ((v / quantum).round() as i64).to_le_bytes()
+            } else {
+                // Normalise -0.0 so it hashes like 0.0
+                (v + 0.0).to_bits().to_le_bytes()
+            }
+        };
+        let mut hasher = Fnv1a::new();
+        hasher.write(&(self.k() as u64).to_le_bytes());
+        for level in &self.levels {
+            hasher.write(&(level.len() as u64).to_le_bytes());
+            for (x, y) in level {
+                hasher.write(&quantize(*x));
+                hasher.write(&quantize(*y));
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Checks the defining properties of a persistence landscape: every level is non-negative,
+    /// 1-Lipschitz, has sorted x-coordinates and starts and ends at zero, and the levels are
+    /// weakly decreasing in k. Comparisons allow for an absolute error of `tolerance`.
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' describing the first violated property
+    pub fn validate(&self, tolerance: f64) -> Result<(), Violation> {
+        for (index, level) in self.levels.iter().enumerate() {
+            validate_level(level, index, tolerance)?;
+        }
+        for (index, pair) in self.levels.windows(2).enumerate() {
+            let (upper, lower) = (pair[0].as_slice(), pair[1].as_slice());
+            for x in union_grid(&[upper, lower]) {
+                let (above, below) = (
+                    persistencelandscape::evaluate(upper, x),
+                    persistencelandscape::evaluate(lower, x),
+                );
+                if below > above + tolerance {
+                    return Err(Violation::NotDecreasing { level: index + 1, x });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Recovers the birth death pairs the landscape was computed from.
+    ///
+    /// Every apex of a tent is a local maximum of some level that is not also a point of the
+    /// level above it (those are crossings of two tents). The pairs are read off these apexes
+    /// and the landscape is regenerated from them to check the inversion is exact.
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the levels are not the landscape of any diagram, e.g. because it
+    /// was truncated to too few levels or has been transformed
+    pub fn to_barcode(&self) -> Result<Vec<BirthDeath>, &'static str> {
+        let scale = self
+            .levels
+            .iter()
+            .flatten()
+            .map(|p| FloatOrd(p.0.abs().max(p.1.abs())))
+            .max()
+            .map_or(1.0, |m| m.0.max(1.0));
+        let tolerance = 1e-9 * scale;
+        let mut pairs = Vec::new();
+        for (j, level) in self.levels.iter().enumerate() {
+            for (x, y) in level_maxima(level) {
+                let is_crossing = j > 0
+                    && self.levels[j - 1]
+                        .iter()
+                        .any(|p| (p.0 - x).abs() <= tolerance && (p.1 - y).abs() <= tolerance);
+                if !is_crossing && y > 0.0 {
+                    pairs.push(BirthDeath { birth: x - y, death: x + y });
+                }
+            }
+        }
+        let regenerated = persistencelandscape::generate(
+            pairs.iter().map(|bd| BirthDeath { birth: bd.birth, death: bd.death }).collect(),
+            self.k(),
+            false,
+        );
+        if distance::sup_distance(&self.levels, &regenerated) > tolerance {
+            return Err("Landscape is not invertible, it does not match the landscape of any recovered diagram");
+        }
+        Ok(pairs)
+    }
+
+    /// The [`fmt::Display`] table followed by the first `max_points` critical points of every
+    /// level
+    #[must_use]
+    pub fn to_pretty_string(&self, max_points: usize) -> String {
+        let mut pretty = self.to_string();
+        for (i, level) in self.levels.iter().enumerate() {
+            let points: Vec<String> = level
+                .iter()
+                .take(max_points)
+                .map(|(x, y)| format!("({x:.6}, {y:.6})"))
+                .collect();
+            let more = if level.len() > max_points {
+                format!(" ... {} more", level.len() - max_points)
+            } else {
+                String::new()
+            };
+            pretty.push_str(&format!("level {i}: {}{more}\n", points.join(" ")));
+        }
+        pretty
+    }
+
+    /// Combines the levels of two landscapes pointwise on the union grid of each level, exact
+    /// for any `f` that is linear in both arguments
+    fn zip_levels(&self, other: &Self, f: impl Fn(f64, f64) -> f64) -> Self {
+        Self::new(
+            (0..self.k().max(other.k()))
+                .map(|i| {
+                    let a = self.levels.get(i).map_or(&[][..], Vec::as_slice);
+                    let b = other.levels.get(i).map_or(&[][..], Vec::as_slice);
+                    union_grid(&[a, b])
+                        .into_iter()
+                        .map(|x| {
+                            (x, f(persistencelandscape::evaluate(a, x), persistencelandscape::evaluate(b, x)))
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    fn map_y(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self::new(
+            self.levels
+                .iter()
+                .map(|l| l.iter().map(|(x, y)| (*x, f(*y))).collect())
+                .collect(),
+        )
+    }
+
+    fn map_x(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self::new(
+            self.levels
+                .iter()
+                .map(|l| l.iter().map(|(x, y)| (f(*x), *y)).collect())
+                .collect(),
+        )
+    }
+
+    /// Clips every level to [`x_min`, `x_max`], inserting interpolated points on the boundaries.
+    /// Levels without support in the interval become empty.
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if `x_min` > `x_max`
+    pub fn restrict(&self, x_min: f64, x_max: f64) -> Result<Self, &'static str> {
+        if x_min > x_max || x_min.is_nan() || x_max.is_nan() {
+            return Err("Restriction interval must not be empty");
+        }
+        Ok(Self::new(
+            self.levels
+                .iter()
+                .map(|l| level_restrict(l, x_min, x_max))
+                .collect(),
+        ))
+    }
+
+    /// Smooths every level by sampling it on `grid` and convolving the samples with the given
+    /// kernel. The result has a critical point at every grid point.
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the Gaussian bandwidth is not positive
+    pub fn smooth(&self, grid: &Grid, smoothing: Smoothing) -> Result<Self, &'static str> {
+        if let Smoothing::Gaussian { bandwidth } = smoothing {
+            if !(bandwidth > 0.0 && bandwidth.is_finite()) {
+                return Err("Smoothing bandwidth must be positive");
+            }
+        }
+        let weights = kernel_weights(smoothing, grid);
+        let smoothed: Vec<f64> = sample(&self.levels, self.k(), grid)
+            .chunks(grid.resolution)
+            .flat_map(|level| convolve(level, &weights))
+            .collect();
+        Ok(Self::new(unsample(&smoothed, grid)))
+    }
+
+    /// Samples every level on `grid` and concatenates them level by level into a single
+    /// feature vector. The returned descriptor records the layout needed to interpret or
+    /// invert the vector.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_feature_vector(&self, grid: &Grid) -> (Vec<f32>, FeatureDescriptor) {
+        let features = sample(&self.levels, self.k(), grid)
+            .into_iter()
+            .map(|v| v as f32)
+            .collect();
+        (features, FeatureDescriptor::new(self.k(), grid))
+    }
+}
+
+/// Exact area under a level, trapezoid rule on every linear piece
+fn level_integral(level: &[(f64,f64)]) -> f64 {
+    level
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0) * (w[0].1 + w[1].1) / 2.0)
+        .sum()
+}
+
+fn level_derivative(level: &[(f64,f64)]) -> Vec<DerivativePiece> {
+    level
+        .windows(2)
+        .filter(|w| w[1].0 > w[0].0)
+        .map(|w| DerivativePiece {
+            start: w[0].0,
+            end: w[1].0,
+            slope: (w[1].1 - w[0].1) / (w[1].0 - w[0].0),
+        })
+        .collect()
+}
+
+/// Local maxima of a level. A plateau counts once, at its left end, if the level rises before
+/// and falls after it.
+fn level_maxima(level: &[(f64,f64)]) -> Vec<(f64,f64)> {
+    let mut maxima = Vec::new();
+    let mut rising = false;
+    let mut candidate = None;
+    for w in level.windows(2) {
+        let ((_, y0), (_, y1)) = (w[0], w[1]);
+        if y1 > y0 {
+            rising = true;
+            candidate = None;
+        } else if y1 < y0 {
+            if rising {
+                maxima.push(candidate.unwrap_or(w[0]));
+            }
+            rising = false;
+            candidate = None;
+        } else if rising && candidate.is_none() {
+            candidate = Some(w[0]);
+        }
+    }
+    maxima
+}
+
+/// Discrete convolution of grid samples, the kernel is renormalised near the ends of the grid
+fn convolve(values: &[f64], weights: &[f64]) -> Vec<f64> {
+    let half = weights.len() / 2;
+    (0..values.len())
+        .map(|i| {
+            let (sum, total) = weights
+                .iter()
+                .enumerate()
+                .filter_map(|(j, w)| {
+                    let v = (i + j).checked_sub(half).and_then(|k| values.get(k))?;
+                    Some((v * w, w))
+                })
+                .fold((0.0, 0.0), |(sum, total), (v, w)| (sum + v, total + w));
+            sum / total
+        })
+        .collect()
+}
+
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn kernel_weights(smoothing: Smoothing, grid: &Grid) -> Vec<f64> {
+    match smoothing {
+        Smoothing::MovingAverage { window } => vec![1.0; window.max(1) | 1],
+        Smoothing::Gaussian { bandwidth } => {
+            let step = if grid.resolution > 1 {
+                (grid.x_max - grid.x_min) / (grid.resolution - 1) as f64
+            } else {
+                1.0
+            };
+            // Truncate the kernel at 4 standard deviations
+            let half = (4.0 * bandwidth / step).ceil().min(grid.resolution as f64) as usize;
+            (0..=2 * half)
+                .map(|i| {
+                    let dx = (i as f64 - half as f64) * step;
+                    (-dx * dx / (2.0 * bandwidth * bandwidth)).exp()
+                })
+                .collect()
+        }
+    }
+}
+
+fn level_restrict(level: &[(f64,f64)], x_min: f64, x_max: f64) -> Vec<(f64,f64)> {
+    let (Some(first), Some(last)) = (level.first(), level.last()) else {
+        return Vec::new();
+    };
+    let lo = x_min.max(first.0);
+    let hi = x_max.min(last.0);
+    if lo > hi {
+        return Vec::new();
+    }
+    let mut restricted = vec![(lo, persistencelandscape::evaluate(level, lo))];
+    restricted.extend(level.iter().filter(|p| p.0 > lo && p.0 < hi));
+    if hi > lo {
+        restricted.push((hi, persistencelandscape::evaluate(level, hi)));
+    }
+    restricted
+}
+
+/// Distance of `p` from the line through `a` and `b`
+fn line_distance(p: (f64,f64), a: (f64,f64), b: (f64,f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        return (p.0 - a.0).hypot(p.1 - a.1);
+    }
+    dx.mul_add(a.1 - p.1, -(dy * (a.0 - p.0))).abs() / length
+}
+
+fn remove_redundant(level: &[(f64,f64)], tolerance: f64) -> Vec<(f64,f64)> {
+    let mut kept: Vec<(f64,f64)> = Vec::with_capacity(level.len());
+    for (i, &p) in level.iter().enumerate() {
+        if let Some(&last) = kept.last() {
+            if (p.0 - last.0).hypot(p.1 - last.1) <= tolerance {
+                if i < level.len() - 1 {
+                    continue;
+                }
+                // Always keep the final point so the support does not shrink, replacing the
+                // point it is close to unless that is the first one
+                if kept.len() > 1 {
+                    kept.pop();
+                }
+            }
+        }
+        while kept.len() >= 2 && line_distance(kept[kept.len() - 1], kept[kept.len() - 2], p) <= tolerance {
+            kept.pop();
+        }
+        kept.push(p);
+    }
+    kept
+}
+
+fn douglas_peucker(level: &[(f64,f64)], epsilon: f64) -> Vec<(f64,f64)> {
+    if level.len() < 3 {
+        return level.to_vec();
+    }
+    let mut keep = vec![false; level.len()];
+    keep[0] = true;
+    keep[level.len() - 1] = true;
+    let mut ranges = vec![(0, level.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| (i, line_distance(level[i], level[start], level[end])))
+            .max_by_key(|(_, d)| FloatOrd(*d));
+        if let Some((i, d)) = farthest {
+            if d > epsilon {
+                keep[i] = true;
+                ranges.push((start, i));
+                ranges.push((i, end));
+            }
+        }
+    }
+    level
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, k)| k.then_some(*p))
+        .collect()
+}
+
+// The exact comparison makes equal infinities compare equal
+#[allow(clippy::float_cmp)]
+fn approx_eq_float(a: f64, b: f64, abs_tol: f64, rel_tol: f64) -> bool {
+    a == b || (a - b).abs() <= abs_tol.max(rel_tol * a.abs().max(b.abs()))
+}
+
+/// Whether both coordinates of two points differ by at most `abs_tol` or by at most `rel_tol`
+/// relative to the larger magnitude
+#[must_use]
+pub fn approx_eq_point(a: (f64,f64), b: (f64,f64), abs_tol: f64, rel_tol: f64) -> bool {
+    approx_eq_float(a.0, b.0, abs_tol, rel_tol) && approx_eq_float(a.1, b.1, abs_tol, rel_tol)
+}
+
+/// 64-bit FNV-1a, used instead of the std hasher because its output is stable across Rust
+/// versions and platforms
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(crate) const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) const fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn validate_level(level: &[(f64,f64)], index: usize, tolerance: f64) -> Result<(), Violation> {
+    for &(x, y) in level {
+        if y < -tolerance {
+            return Err(Violation::Negative { level: index, x });
+        }
+    }
+    for w in level.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        if x1 < x0 - tolerance {
+            return Err(Violation::Unsorted { level: index, x: x1 });
+        }
+        if (y1 - y0).abs() > (x1 - x0).abs() + tolerance {
+            return Err(Violation::NotLipschitz { level: index, x: x0 });
+        }
+    }
+    for &(x, y) in level.first().into_iter().chain(level.last()) {
+        if y.abs() > tolerance {
+            return Err(Violation::NonZeroEndpoint { level: index, x });
+        }
+    }
+    Ok(())
+}
+
+fn summarize(level: &[(f64,f64)]) -> LevelSummary {
+    let integral = level_integral(level);
+    let mut moment = 0.0;
+    let mut support_width = 0.0;
+    for w in level.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        let width = x1 - x0;
+        moment += width * x0.mul_add(2.0f64.mul_add(y0, y1), x1 * 2.0f64.mul_add(y1, y0)) / 6.0;
+        if y0 > 0.0 || y1 > 0.0 {
+            support_width += width;
+        }
+    }
+    let peak = level
+        .iter()
+        .copied()
+        .reduce(|best, p| if p.1 > best.1 { p } else { best });
+    LevelSummary {
+        integral,
+        max: peak.map_or(0.0, |p| p.1),
+        argmax: peak.map(|p| p.0),
+        support_width,
+        centroid: (integral > 0.0).then(|| moment / integral),
+    }
+}
+
+impl From<Vec<Vec<(f64,f64)>>> for Landscape {
+    fn from(levels: Vec<Vec<(f64,f64)>>) -> Self {
+        Self::new(levels)
+    }
+}
+
+impl From<Landscape> for Vec<Vec<(f64,f64)>> {
+    fn from(landscape: Landscape) -> Self {
+        landscape.levels
+    }
+}
+
+/// Table with one row per level: number of critical points, maximum and integral
+impl fmt::Display for Landscape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>5} {:>8} {:>12} {:>12}", "level", "points", "max", "integral")?;
+        for (i, (level, summary)) in self.levels.iter().zip(self.summaries()).enumerate() {
+            writeln!(f, "{i:>5} {:>8} {:>12.6} {:>12.6}", level.len(), summary.max, summary.integral)?;
+        }
+        Ok(())
+    }
+}
+
+impl Add for &Landscape {
+    type Output = Landscape;
+
+    fn add(self, other: Self) -> Landscape {
+        self.zip_levels(other, |a, b| a + b)
+    }
+}
+
+impl Add for Landscape {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        &self + &other
+    }
+}
+
+impl Sub for &Landscape {
+    type Output = Landscape;
+
+    fn sub(self, other: Self) -> Landscape {
+        self.zip_levels(other, |a, b| a - b)
+    }
+}
+
+impl Sub for Landscape {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        &self - &other
+    }
+}
+
+impl Mul<f64> for &Landscape {
+    type Output = Landscape;
+
+    fn mul(self, factor: f64) -> Landscape {
+        self.map_y(|y| y * factor)
+    }
+}
+
+impl Mul<f64> for Landscape {
+    type Output = Self;
+
+    fn mul(self, factor: f64) -> Self {
+        &self * factor
+    }
+}
+
+impl Neg for &Landscape {
+    type Output = Landscape;
+
+    fn neg(self) -> Landscape {
+        self.map_y(|y| -y)
+    }
+}
+
+impl Neg for Landscape {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        -&self
+    }
+}
+
+impl Sum for Landscape {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, l| &acc + &l)
+    }
+}
+
+impl<'a> Sum<&'a Self> for Landscape {
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, l| &acc + l)
+    }
+}
+
+/// Critical points of a level
+impl Index<usize> for Landscape {
+    type Output = [(f64,f64)];
+
+    fn index(&self, level: usize) -> &Self::Output {
+        &self.levels[level]
+    }
+}
@@ -0,0 +1,159 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Landscapes of several homology dimensions and samples kept together
+//!
+//! A [`LandscapeSet`] holds at most one landscape per dimension and sample id, so the results
+//! of `rips::landscapes` or `cubical::landscapes` for a whole dataset can be averaged,
+//! measured and written per dimension, see [`crate::io::write_landscape_set_to`]. Entries
+//! without a sample id stand for the dimension as a whole, such as the means of
+//! [`LandscapeSet::mean`].
+
+use std::collections::BTreeMap;
+
+use crate::distance::{level_norms, LevelNorms};
+use crate::landscape::Landscape;
+use crate::statistics;
+use crate::sublevel::Levels;
+
+/// Homology dimension and sample id of a landscape in a [`LandscapeSet`]
+pub type Key = (usize, Option<String>);
+
+/// Landscapes keyed by homology dimension and optional sample id, see the module documentation
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LandscapeSet {
+    landscapes: BTreeMap<Key, Landscape>,
+}
+
+impl LandscapeSet {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The landscape of every dimension of one sample, the dimension being the index as
+    /// returned by `rips::landscapes`
+    #[must_use]
+    pub fn from_dimensions(landscapes: impl IntoIterator<Item = Levels>, sample: Option<&str>) -> Self {
+        let mut set = Self::new();
+        for (dimension, levels) in landscapes.into_iter().enumerate() {
+            set.landscapes.insert((dimension, sample.map(str::to_string)), Landscape::new(levels));
+        }
+        set
+    }
+
+    /// Number of landscapes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.landscapes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.landscapes.is_empty()
+    }
+
+    /// Sets the landscape of `dimension` without a sample id, returning the one it replaces
+    pub fn insert(&mut self, dimension: usize, landscape: impl Into<Landscape>) -> Option<Landscape> {
+        self.landscapes.insert((dimension, None), landscape.into())
+    }
+
+    /// Sets the landscape of `dimension` for `sample`, returning the one it replaces
+    pub fn insert_sample(
+        &mut self,
+        dimension: usize,
+        sample: impl Into<String>,
+        landscape: impl Into<Landscape>,
+    ) -> Option<Landscape> {
+        self.landscapes.insert((dimension, Some(sample.into())), landscape.into())
+    }
+
+    /// The landscape of `dimension` for `sample`, or without a sample id for `None`
+    #[must_use]
+    pub fn get(&self, dimension: usize, sample: Option<&str>) -> Option<&Landscape> {
+        self.landscapes.get(&(dimension, sample.map(str::to_string)))
+    }
+
+    /// Removes and returns the landscape of `dimension` for `sample`
+    pub fn remove(&mut self, dimension: usize, sample: Option<&str>) -> Option<Landscape> {
+        self.landscapes.remove(&(dimension, sample.map(str::to_string)))
+    }
+
+    /// Every landscape with its key, by dimension and then by sample id with the entry
+    /// without one first
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &Landscape)> {
+        self.landscapes.iter()
+    }
+
+    /// The landscapes of `dimension` with their sample ids
+    pub fn dimension(&self, dimension: usize) -> impl Iterator<Item = (Option<&str>, &Landscape)> {
+        self.landscapes
+            .range((dimension, None)..)
+            .take_while(move |((d, _), _)| *d == dimension)
+            .map(|((_, sample), landscape)| (sample.as_deref(), landscape))
+    }
+
+    /// The dimensions holding at least one landscape, in increasing order
+    #[must_use]
+    pub fn dimensions(&self) -> Vec<usize> {
+        let mut dimensions: Vec<usize> = self.landscapes.keys().map(|(d, _)| *d).collect();
+        dimensions.dedup();
+        dimensions
+    }
+
+    /// The distinct sample ids, in increasing order
+    #[must_use]
+    pub fn samples(&self) -> Vec<&str> {
+        let mut samples: Vec<&str> = self.landscapes.keys().filter_map(|(_, s)| s.as_deref()).collect();
+        samples.sort_unstable();
+        samples.dedup();
+        samples
+    }
+
+    /// Pointwise mean of the landscapes of every dimension, see [`statistics::average`], each
+    /// stored without a sample id
+    #[must_use]
+    pub fn mean(&self) -> Self {
+        let mut means = Self::new();
+        for dimension in self.dimensions() {
+            let levels: Vec<Levels> = self.dimension(dimension).map(|(_, l)| l.levels().to_vec()).collect();
+            // Every listed dimension has a landscape, so the mean can not fail
+            means.insert(dimension, Landscape::new(statistics::average(&levels).unwrap_or_default()));
+        }
+        means
+    }
+
+    /// [`LevelNorms`] of every level of every landscape, see [`level_norms`]
+    #[must_use]
+    pub fn norms(&self) -> BTreeMap<Key, Vec<LevelNorms>> {
+        self.landscapes
+            .iter()
+            .map(|(key, landscape)| (key.clone(), level_norms(landscape.levels())))
+            .collect()
+    }
+
+    /// Applies `f` to every landscape, keeping the keys
+    #[must_use]
+    pub fn map(&self, f: impl Fn(&Landscape) -> Landscape) -> Self {
+        Self { landscapes: self.landscapes.iter().map(|(key, l)| (key.clone(), f(l))).collect() }
+    }
+}
+
+impl FromIterator<(Key, Landscape)> for LandscapeSet {
+    fn from_iter<I: IntoIterator<Item = (Key, Landscape)>>(iter: I) -> Self {
+        Self { landscapes: iter.into_iter().collect() }
+    }
+}
+
+impl IntoIterator for LandscapeSet {
+    type Item = (Key, Landscape);
+    type IntoIter = std::collections::btree_map::IntoIter<Key, Landscape>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.landscapes.into_iter()
+    }
+}
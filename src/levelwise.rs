@@ -0,0 +1,136 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+
+use crate::birthdeath::BirthDeath;
+
+/// Tent of a pair, `middle` is where it stops rising
+#[derive(Debug, Clone, Copy)]
+struct Tent {
+    birth: f64,
+    middle: f64,
+    death: f64,
+}
+
+/// j-th largest (1-indexed) of the union of two lists sorted in decreasing order, missing
+/// entries count as zero
+fn jth_largest(rising: &[f64], falling: &[f64], j: usize) -> f64 {
+    let at = |values: &[f64], r: usize| if r == 0 { f64::INFINITY } else { values.get(r - 1).copied().unwrap_or(0.0) };
+    (0..=j)
+        .map(|r| at(rising, r).min(at(falling, j - r)))
+        .fold(0.0, f64::max)
+}
+
+/// Drops points on the line through their neighbours and the zero runs before and after the
+/// support
+fn tidy(points: Vec<(f64,f64)>) -> Vec<(f64,f64)> {
+    let slope = |a: (f64,f64), b: (f64,f64)| (b.1 - a.1) / (b.0 - a.0);
+    let mut tidy: Vec<(f64,f64)> = Vec::with_capacity(points.len());
+    for point in points {
+        if tidy.last().is_some_and(|last| last.0 >= point.0) {
+            continue;
+        }
+        if let [.., a, b] = tidy[..] {
+            if (slope(a, b) - slope(b, point)).abs() < 1e-9 {
+                tidy.pop();
+            }
+        }
+        tidy.push(point);
+    }
+    let first = tidy.iter().position(|p| p.1 != 0.0);
+    let last = tidy.iter().rposition(|p| p.1 != 0.0);
+    match (first, last) {
+        (Some(first), Some(last)) => tidy[first.saturating_sub(1)..=(last + 1).min(tidy.len() - 1)].to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// Computes level `j` (1-indexed) as the j-th largest tent function
+///
+/// Between two consecutive births, middles or deaths every alive tent is linear, so the
+/// level can only bend where a rising tent ranked p crosses a falling tent ranked q with
+/// p + q equal to j or j + 1. Only the top j + 1 tents of each kind are needed for that.
+fn level(tents: &[Tent], xs: &[f64], j: usize) -> Vec<(f64,f64)> {
+    // (birth, id) of rising tents and (-death, id) of falling tents, best first
+    let mut rising: BTreeSet<(FloatOrd<f64>, usize)> = BTreeSet::new();
+    let mut falling: BTreeSet<(FloatOrd<f64>, usize)> = BTreeSet::new();
+    let mut by_birth: Vec<usize> = (0..tents.len()).collect();
+    let mut by_middle = by_birth.clone();
+    let mut by_death = by_birth.clone();
+    by_birth.sort_by(|&a, &b| tents[a].birth.total_cmp(&tents[b].birth));
+    by_middle.sort_by(|&a, &b| tents[a].middle.total_cmp(&tents[b].middle));
+    by_death.sort_by(|&a, &b| tents[a].death.total_cmp(&tents[b].death));
+    let (mut next_birth, mut next_middle, mut next_death) = (0, 0, 0);
+
+    let mut points = Vec::new();
+    for w in xs.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        while next_death < by_death.len() && tents[by_death[next_death]].death <= a {
+            let id = by_death[next_death];
+            falling.remove(&(FloatOrd(-tents[id].death), id));
+            next_death += 1;
+        }
+        while next_middle < by_middle.len() && tents[by_middle[next_middle]].middle <= a {
+            let id = by_middle[next_middle];
+            rising.remove(&(FloatOrd(tents[id].birth), id));
+            falling.insert((FloatOrd(-tents[id].death), id));
+            next_middle += 1;
+        }
+        while next_birth < by_birth.len() && tents[by_birth[next_birth]].birth <= a {
+            let id = by_birth[next_birth];
+            rising.insert((FloatOrd(tents[id].birth), id));
+            next_birth += 1;
+        }
+        let up: Vec<f64> = rising.iter().take(j + 1).map(|(birth, _)| a - birth.0).collect();
+        let down: Vec<f64> = falling.iter().take(j + 1).map(|(death, _)| -death.0 - a).collect();
+        let mut ts = vec![0.0];
+        for (p, u) in up.iter().enumerate() {
+            for (q, d) in down.iter().enumerate() {
+                let t = (d - u) / 2.0;
+                if (j..=j + 1).contains(&(p + q + 2)) && t > 0.0 && t < b - a {
+                    ts.push(t);
+                }
+            }
+        }
+        ts.sort_by(f64::total_cmp);
+        for t in ts {
+            let shifted_up: Vec<f64> = up.iter().map(|u| u + t).collect();
+            let shifted_down: Vec<f64> = down.iter().map(|d| d - t).collect();
+            points.push((a + t, jth_largest(&shifted_up, &shifted_down, j)));
+        }
+    }
+    if let Some(&x) = xs.last() {
+        points.push((x, 0.0));
+    }
+    tidy(points)
+}
+
+/// Exact landscape computed one level at a time, each level on its own thread
+///
+/// Level j is computed directly as the j-th largest tent function, independently of the
+/// other levels. This does more work in total than the sweep in
+/// [`crate::persistencelandscape::generate`], but with many cores and a moderate `k` it can
+/// finish sooner. The levels describe the same functions as the sweep, although collinear
+/// critical points may be listed differently.
+#[must_use]
+pub fn generate_levelwise(bd_pairs: &[BirthDeath], k: usize) -> Vec<Vec<(f64,f64)>> {
+    let tents: Vec<Tent> = bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite() && bd.birth < bd.death)
+        .map(|bd| Tent { birth: bd.birth, middle: (bd.birth + bd.death) / 2.0, death: bd.death })
+        .collect();
+    let mut xs: Vec<f64> = tents.iter().flat_map(|t| [t.birth, t.middle, t.death]).collect();
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+    (1..=k)
+        .into_par_iter()
+        .map(|j| level(&tents, &xs, j))
+        .collect()
+}
@@ -1,6 +1,45 @@
+pub mod binary;
 pub mod birthdeath;
+pub mod cache;
+pub mod diagramdistance;
+pub mod distance;
+pub mod ensemble;
+pub mod incremental;
 pub mod persistencelandscape;
 pub mod barcode;
+pub mod builder;
+pub mod clustering;
+#[cfg(feature = "image")]
+pub mod cubical;
+pub mod io;
+pub mod kernel;
+pub mod landscape;
+pub mod landscapeset;
+pub mod levelwise;
+pub mod neighbors;
+pub mod pca;
+pub mod pipeline;
 #[cfg(feature = "plot")]
 pub mod plot;
+pub mod prelude;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod report;
+#[cfg(feature = "rips")]
+pub mod rips;
 pub mod rpls;
+pub mod silhouette;
+pub mod source;
+pub mod sparse;
+pub mod stability;
+pub mod statistics;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod sublevel;
+pub mod synthetic;
+pub mod task;
+pub mod transform;
+pub mod vectorize;
+pub mod verify;
+pub mod vineyard;
+pub mod weighted;
@@ -5,80 +5,1691 @@
      clippy::cargo,
  )]
 
-use clap::Parser;
-use csv::Writer;
+use clap::{Parser, Subcommand, ValueEnum};
+use fast_pl::source::{DiagramFile, DiagramFiles, DiagramSource};
 use std::error::Error;
-use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::time::Instant;
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum NormalizeBy {
+    L1,
+    L2,
+    Persistence,
+    Pairs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum RescaleTo {
+    /// Sweep on [0, 1] and write the landscape on [0, 1]
+    Normalized,
+    /// Sweep on [0, 1] and write the landscape in the units of the input
+    Original,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum MatrixFormat {
+    Csv,
+    Npy,
+    /// Needs the parquet feature
+    Parquet,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportFormat {
+    Json,
+    Html,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Kind of failure, each with its own exit code. Invalid arguments exit with 2 like other
+/// usage errors reported by clap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Failure {
+    /// An input file could not be parsed
+    Parse,
+    /// The input was parsed but can not be used, or an option value is out of range
+    InvalidInput,
+    /// A file could not be read or written
+    Io,
+    /// A limit on memory, disk space or work was hit
+    ResourceLimit,
+    /// A bug, such as a panic
+    Internal,
+}
+
+impl Failure {
+    const fn code(self) -> u8 {
+        match self {
+            Self::Parse => 3,
+            Self::InvalidInput => 4,
+            Self::Io => 5,
+            Self::ResourceLimit => 6,
+            Self::Internal => 70,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Parse => "parse_error",
+            Self::InvalidInput => "invalid_input",
+            Self::Io => "io_error",
+            Self::ResourceLimit => "resource_limit",
+            Self::Internal => "internal_error",
+        }
+    }
+
+    /// Errors not recognised here are messages about invalid input
+    fn of(error: &(dyn Error + 'static)) -> Self {
+        use std::io::ErrorKind;
+        if error.is::<fast_pl::io::ParseError>()
+            || error.is::<std::num::ParseFloatError>()
+            || error.is::<std::num::ParseIntError>()
+            || error.is::<std::str::Utf8Error>()
+        {
+            return Self::Parse;
+        }
+        if error.is::<fast_pl::persistencelandscape::LimitExceeded>()
+            || error.is::<std::collections::TryReserveError>()
+        {
+            return Self::ResourceLimit;
+        }
+        if let Some(error) = error.downcast_ref::<csv::Error>() {
+            return if error.is_io_error() { Self::Io } else { Self::Parse };
+        }
+        if let Some(error) = error.downcast_ref::<std::io::Error>() {
+            return match error.kind() {
+                ErrorKind::InvalidData | ErrorKind::UnexpectedEof => Self::Parse,
+                ErrorKind::OutOfMemory | ErrorKind::StorageFull => Self::ResourceLimit,
+                _ => Self::Io,
+            };
+        }
+        Self::InvalidInput
+    }
+}
+
+/// What is written to stderr besides errors, see `-q` and `-v`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    Quiet,
+    /// Total time taken
+    Summary,
+    /// Size and duration of every phase
+    Progress,
+    /// Every pair and event of the sweep
+    Trace,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SchemaFormat {
+    JsonSchema,
+    Typescript,
+}
+
+/// Shape of the diagrams of `synth`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Distribution {
+    /// Births and persistences uniform in [0, 1)
+    Uniform,
+    /// Topological noise, persistences exponential with mean 0.02
+    NearDiagonal,
+    /// Persistences uniform in [0.5, 1)
+    LongBars,
+    /// Noise with one long bar in every hundred pairs
+    Noisy,
+    /// Pairs around --clusters centers
+    Clustered,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GridPrecision {
+    F32,
+    F16,
+    Bf16,
+}
+
+impl From<GridPrecision> for fast_pl::vectorize::Precision {
+    fn from(precision: GridPrecision) -> Self {
+        match precision {
+            GridPrecision::F32 => Self::F32,
+            GridPrecision::F16 => Self::F16,
+            GridPrecision::Bf16 => Self::Bf16,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the norm of every landscape of a sequence of diagrams and the distance between
+    /// consecutive landscapes as CSV
+    Series {
+        /// Diagram files in order, or a directory whose files are read in name order
+        #[clap(required = true, value_parser)]
+        inputs: Vec<PathBuf>,
+        /// Max kth-landscape to calculate
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Norm and distance to report: l1, l2, sup, l<p> for other exponents, optionally
+        /// sampled on a grid with @<resolution> such as l2@1000
+        #[clap(long, value_parser, default_value = "l2")]
+        norm: fast_pl::distance::Metric,
+        /// Write the series to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Write the weighted mean landscape of every group of diagrams as CSV
+    Average {
+        /// Diagram files, or directories whose files are read in name order, averaged with
+        /// equal weights
+        #[clap(value_parser, required_unless_present = "manifest", conflicts_with = "manifest")]
+        inputs: Vec<PathBuf>,
+        /// File with one `path [weight [group]]` line per diagram file, weights default to one
+        #[clap(long, value_parser)]
+        manifest: Option<PathBuf>,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Output file of the unlabelled group, other groups are written next to it with
+        /// `_<group>` inserted before the extension
+        #[clap(short, long, value_parser)]
+        output: PathBuf,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Write the distance between the landscapes of every pair of diagrams as a matrix
+    Distmat {
+        /// Diagram files, or directories whose files are read in name order
+        #[clap(required = true, value_parser)]
+        inputs: Vec<PathBuf>,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Distance between two landscapes: l1, l2, sup, l<p> for other exponents, optionally
+        /// sampled on a grid with @<resolution> such as l2@1000
+        #[clap(long, value_parser, default_value = "l2")]
+        metric: fast_pl::distance::Metric,
+        /// Output file, `.npy` files also get the labels in a `.txt` file next to them and
+        /// `.parquet` files need the parquet feature
+        #[clap(short, long, value_parser)]
+        output: PathBuf,
+        /// Output format, by default taken from the extension of the output file
+        #[clap(long, value_enum)]
+        format: Option<MatrixFormat>,
+    },
+    /// Print the norms of the difference `a - b` of two landscapes per level as CSV
+    Diff {
+        /// Landscape CSV file, or a diagram file with --diagrams
+        #[clap(value_parser)]
+        a: PathBuf,
+        /// Landscape CSV file, or a diagram file with --diagrams
+        #[clap(value_parser)]
+        b: PathBuf,
+        /// Read diagrams and compare their landscapes up to this level, 0 for every nonempty
+        /// level
+        #[clap(long, value_parser)]
+        diagrams: Option<usize>,
+        /// Save the difference landscape as CSV
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Plot the difference to this PNG, shading where it is at least half its maximum
+        #[clap(long, value_parser)]
+        plot: Option<PathBuf>,
+        /// Save both landscapes side by side with their difference and its norms per level as
+        /// a self-contained HTML page, or as SVG if the file ends in `.svg`
+        #[clap(long, value_parser)]
+        report: Option<PathBuf>,
+    },
+    /// Compare two groups of diagrams: mean landscapes with confidence bands, the distance
+    /// between the means and a permutation test
+    Compare {
+        /// Diagram files of the first group, or directories whose files are read in name order
+        #[clap(short, long, required = true, num_args = 1.., value_parser)]
+        a: Vec<PathBuf>,
+        /// Diagram files of the second group, or directories whose files are read in name order
+        #[clap(short, long, required = true, num_args = 1.., value_parser)]
+        b: Vec<PathBuf>,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Confidence bands cover 1 - alpha
+        #[clap(long, value_parser, default_value_t = 0.05)]
+        alpha: f64,
+        /// Number of bootstrap samples of every band
+        #[clap(long, value_parser, default_value_t = 1000)]
+        bootstrap: usize,
+        /// Number of permutations of the test
+        #[clap(long, value_parser, default_value_t = 1000)]
+        permutations: usize,
+        /// Seed of the bootstrap and the permutations
+        #[clap(long, value_parser, default_value_t = 0)]
+        seed: u64,
+        /// Report file
+        #[clap(short, long, value_parser)]
+        output: PathBuf,
+        /// Report format, by default taken from the extension of the report file
+        #[clap(long, value_enum)]
+        format: Option<ReportFormat>,
+    },
+    /// Print the persistent Betti number of a diagram at one point, or its rank function on a
+    /// grid as CSV with births as rows and deaths as columns
+    Rank {
+        /// Diagram file
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Birth of the queried interval
+        #[clap(long, value_parser, requires = "death", allow_hyphen_values = true)]
+        birth: Option<f64>,
+        /// Death of the queried interval
+        #[clap(long, value_parser, requires = "birth", allow_hyphen_values = true)]
+        death: Option<f64>,
+        /// Number of grid points between the smallest and largest finite value of the diagram
+        #[clap(long, value_parser, default_value_t = 100)]
+        resolution: usize,
+        /// Write the rank function to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Write the exact rank function instead of sampling it, one row per distinct birth
+        /// and one column per distinct death
+        #[clap(long, value_parser, conflicts_with = "birth")]
+        exact: bool,
+        /// Also save the sampled rank function as raw little-endian values with births as
+        /// rows, or as a square array if the file ends in .npy
+        #[clap(long, value_parser, conflicts_with = "birth")]
+        grid: Option<PathBuf>,
+        /// Number format of the grid samples
+        #[clap(long, value_enum, default_value = "f32")]
+        grid_precision: GridPrecision,
+        /// Plot the sampled rank function to this PNG as a heatmap
+        #[clap(long, value_parser, conflicts_with = "birth")]
+        plot: Option<PathBuf>,
+    },
+    /// Compute the Vietoris-Rips persistence of a point cloud with one point per line and
+    /// write the landscape of one dimension as CSV, needs the rips feature
+    FromPoints {
+        /// Point cloud file
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Homology dimension of the diagram, 0 or 1
+        #[clap(long, value_parser, default_value_t = 1)]
+        dim: usize,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Leave out edges longer than this, which keeps large point clouds tractable
+        #[clap(long, value_parser, default_value_t = f64::INFINITY)]
+        threshold: f64,
+        /// Write the landscape to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Compute the Vietoris-Rips persistence of a distance matrix and write the landscape of
+    /// every dimension up to --maxdim as CSV, needs the rips feature
+    FromDistmat {
+        /// Square distance matrix, optionally with the labels written by `distmat`
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Highest homology dimension, 0 or 1
+        #[clap(long, value_parser, default_value_t = 1)]
+        maxdim: usize,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Leave out edges longer than this, which keeps large matrices tractable
+        #[clap(long, value_parser, default_value_t = f64::INFINITY)]
+        threshold: f64,
+        /// Output file of dimension 0, higher dimensions are written next to it with `_h<dim>`
+        /// inserted before the extension. Without it every dimension is printed in turn.
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Compute the sublevel set persistence of a time series with one value per line and
+    /// write its landscape as CSV
+    TimeSeries {
+        /// Time series file, a table is read one column at a time
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Zero-based column of the series in a table
+        #[clap(long, value_parser, default_value_t = 0)]
+        column: usize,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Write the landscape to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Compute the cubical sublevel set persistence of a grayscale image and write the
+    /// landscapes of dimensions 0 and 1 as CSV, needs the image feature
+    Image {
+        /// PNG, `.npy` array or table of pixel values with one row of the image per line
+        #[clap(value_parser)]
+        input: PathBuf,
+        /// Max kth-landscape to calculate, 0 for every nonempty level
+        #[clap(short, long, value_parser, default_value_t = 1)]
+        k: usize,
+        /// Output file of dimension 0, dimension 1 is written next to it with `_h1` inserted
+        /// before the extension. Without it both dimensions are printed in turn.
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Write a reproducible random diagram for benchmarks and stress tests as CSV
+    Synth {
+        /// Number of pairs, also in scientific notation such as 1e6
+        #[clap(long, value_parser = parse_count)]
+        pairs: usize,
+        /// Shape of the diagram
+        #[clap(long, value_enum, default_value = "noisy")]
+        distribution: Distribution,
+        /// Number of centers of a clustered diagram
+        #[clap(long, value_parser, default_value_t = 8)]
+        clusters: usize,
+        /// Seed of the generator, the same seed gives the same diagram
+        #[clap(long, value_parser, default_value_t = 0)]
+        seed: u64,
+        /// Write the diagram to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+        /// Significant digits of the output, shortest round-trip representation by default
+        #[clap(long, value_parser = clap::value_parser!(u8).range(1..=17))]
+        precision: Option<u8>,
+    },
+    /// Print the schema of the JSON report of `compare`
+    Schema {
+        /// Schema language
+        #[clap(long, value_enum, default_value = "json-schema")]
+        format: SchemaFormat,
+        /// Write the schema to this file instead of stdout
+        #[clap(short, long, value_parser)]
+        output: Option<PathBuf>,
+    },
+}
+
 /// Generates the PL for a set of birth death pairs
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+#[clap(after_help = "Every option can also be set with the environment variable shown next to it. \
+Options given on the command line take precedence over the environment.")]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
     /// Name of the file to read birth death pairs from
-    #[clap(short, long, value_parser)]
-    name: String,
-    /// Max kth-landscape to calculate
-    #[clap(short, long, value_parser, default_value_t = 1)]
+    #[clap(short, long, env = "FAST_PL_NAME", value_parser, required_unless_present = "resume")]
+    name: Option<String>,
+    /// Max kth-landscape to calculate, 0 for every nonempty level
+    #[clap(short, long, env = "FAST_PL_K", value_parser, default_value_t = 1)]
     k: usize,
     /// Height of output image
-    #[clap(short, long, value_parser, default_value_t = 720)]
+    #[clap(long, env = "FAST_PL_HEIGHT", value_parser, default_value_t = 720)]
     height: u32,
     /// Width of output image
-    #[clap(short, long, value_parser, default_value_t = 1280)]
+    #[clap(short, long, env = "FAST_PL_WIDTH", value_parser, default_value_t = 1280)]
     width: u32,
-    /// Enable debug messages
-    #[clap(short, long, value_parser)]
+    /// Print nothing but errors to stderr
+    #[clap(short, long, env = "FAST_PL_QUIET", value_parser, conflicts_with_all = ["verbose", "debug"])]
+    quiet: bool,
+    /// Report every phase on stderr, twice to also trace every pair and event of the sweep
+    #[clap(short, long, env = "FAST_PL_VERBOSE", action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Same as -vv
+    #[clap(short, long, env = "FAST_PL_DEBUG", value_parser, hide = true)]
     debug: bool,
     /// Save output image
-    #[clap(short, long, value_parser)]
+    #[clap(short, long, env = "FAST_PL_GRAPH", value_parser)]
     graph: bool,
-    /// Save to CSV
-    #[clap(short, long, value_parser, default_value = "")]
+    /// Save to CSV, `-` streams to stdout
+    #[clap(short, long, env = "FAST_PL_CSV", value_parser, default_value = "")]
     csv: String,
+    /// Significant digits of the CSV output, shortest round-trip representation by default
+    #[clap(long, env = "FAST_PL_PRECISION", value_parser = clap::value_parser!(u8).range(1..=17))]
+    precision: Option<u8>,
+    /// Flush the CSV output after this many points
+    #[clap(long, env = "FAST_PL_FLUSH_EVERY", value_parser = clap::value_parser!(u64).range(1..))]
+    flush_every: Option<u64>,
+    /// Save to the compact binary format of `fast_pl::binary`
+    #[clap(long, env = "FAST_PL_BINARY", value_parser)]
+    binary: Option<PathBuf>,
+    /// Delta encode the x-coordinates of the binary output
+    #[clap(long, env = "FAST_PL_BINARY_DELTA", value_parser, requires = "binary")]
+    binary_delta: bool,
+    /// Compress the binary output with zstd at this level, needs the zstd feature
+    #[clap(long, env = "FAST_PL_BINARY_COMPRESSION", value_parser, requires = "binary")]
+    binary_compression: Option<i32>,
+    /// Save the landscape sampled on an even grid as raw little-endian values, or as an
+    /// array with one row per level if the file ends in .npy
+    #[clap(long, env = "FAST_PL_GRID", value_parser)]
+    grid: Option<PathBuf>,
+    /// Number of grid samples per level
+    #[clap(long, env = "FAST_PL_GRID_RESOLUTION", value_parser, default_value_t = 100)]
+    grid_resolution: usize,
+    /// Number format of the grid samples
+    #[clap(long, env = "FAST_PL_GRID_PRECISION", value_enum, default_value = "f32")]
+    grid_precision: GridPrecision,
+    /// Save the L1, L2 and sup norms and the integral of every level of every diagram, as
+    /// JSON if the file ends in .json and as CSV otherwise
+    #[clap(long, env = "FAST_PL_NORMS", value_parser)]
+    norms: Option<PathBuf>,
+    /// Always treat the first row of the input as a header, by default it is skipped only
+    /// when it does not start with a number
+    #[clap(long, env = "FAST_PL_HEADER", value_parser)]
+    header: bool,
+
+    /// Read a comma in the input as the decimal point, as in `1,5;2,25`, values are then
+    /// separated by semicolons, tabs or whitespace
+    #[clap(long, env = "FAST_PL_DECIMAL_COMMA", value_parser)]
+    decimal_comma: bool,
+    /// Memory-map the input and parse it on every core, for very large single diagram files
+    #[clap(long, env = "FAST_PL_MMAP", value_parser)]
+    mmap: bool,
+    /// Fail on pairs with NaN or unexpected infinite values instead of skipping them, pairs
+    /// with only an infinite death are still skipped
+    #[clap(long, env = "FAST_PL_STRICT", value_parser)]
+    strict: bool,
+    /// Read a weight per pair from an optional third column, scaling the tent of the pair
+    #[clap(
+        long,
+        env = "FAST_PL_WEIGHTED",
+        value_parser,
+        conflicts_with_all = ["mmap", "cache_dir", "checkpoint", "top_pairs", "subsample", "resume"]
+    )]
+    weighted: bool,
     /// Disables filtering
-    #[clap(short, long, value_parser)]
+    #[clap(long, env = "FAST_PL_DISABLE_FILTER", value_parser)]
     disable_filter: bool,
+    /// Split the x-axis into this many ranges and sweep them in parallel
+    #[clap(long, env = "FAST_PL_PARALLEL", value_parser, conflicts_with_all = ["cache_dir", "checkpoint", "resume", "weighted"])]
+    parallel: Option<usize>,
+    /// Read births and deaths as whole numbers, as in discrete filtrations, and round every
+    /// point of the landscape to the nearest half unit
+    #[clap(long, env = "FAST_PL_INTEGER", value_parser, conflicts_with_all = ["cache_dir", "checkpoint", "resume", "weighted", "parallel", "quantize"])]
+    integer: bool,
+    /// Skip the levels before this one, counting from one, and only output it up to level k
+    #[clap(
+        long,
+        env = "FAST_PL_FIRST_LEVEL",
+        value_parser,
+        default_value_t = 1,
+        conflicts_with_all = ["cache_dir", "checkpoint", "resume", "weighted", "parallel", "quantize", "integer"]
+    )]
+    first_level: usize,
+    /// Round every coordinate to a multiple of this quantum and every point of the landscape
+    /// to a multiple of half of it
+    #[clap(long, env = "FAST_PL_QUANTIZE", value_parser, conflicts_with_all = ["cache_dir", "checkpoint", "resume", "weighted", "parallel"])]
+    quantize: Option<f64>,
+    /// Approximate every level to within half of this in the sup norm, dropping pairs of less
+    /// persistence and snapping the others to multiples of it
+    #[clap(
+        long,
+        env = "FAST_PL_EPSILON",
+        value_parser,
+        conflicts_with_all = ["cache_dir", "checkpoint", "resume", "weighted", "parallel", "quantize", "integer", "first_level"]
+    )]
+    epsilon: Option<f64>,
+    /// Sort the pairs into a canonical order first so the output depends only on the set of
+    /// pairs and is bit-identical on every platform and for every thread count
+    #[clap(long, env = "FAST_PL_REPRODUCIBLE", value_parser, conflicts_with_all = ["cache_dir", "resume", "subsample_weighted"])]
+    reproducible: bool,
+    /// Drop pairs with less persistence than this before computing
+    #[clap(long, env = "FAST_PL_MIN_PERSISTENCE", value_parser)]
+    min_persistence: Option<f64>,
+    /// Read the second value of every pair as its persistence instead of its death
+    #[clap(long, env = "FAST_PL_BIRTH_PERSISTENCE", value_parser)]
+    birth_persistence: bool,
+    /// Preprocess every diagram with these comma separated steps instead of the individual
+    /// options: birth-persistence, scale=<factor>[:<shift>], shift=<shift>, cap-infinite=<value>,
+    /// min-persistence=<value>, strict, canonical, top=<n>, subsample=<n>[:<seed>] and
+    /// weighted-subsample=<n>[:<seed>]
+    #[clap(
+        long,
+        env = "FAST_PL_PIPELINE",
+        value_parser,
+        conflicts_with_all = ["birth_persistence", "scale_x", "shift_x", "strict", "min_persistence", "reproducible", "top_pairs", "subsample"]
+    )]
+    pipeline: Option<fast_pl::pipeline::DiagramPipeline>,
+    /// Map every diagram onto [0, 1] after preprocessing, writing the landscape in these units
+    #[clap(long, env = "FAST_PL_RESCALE", value_enum, conflicts_with_all = ["cache_dir", "checkpoint", "resume", "integer", "quantize"])]
+    rescale: Option<RescaleTo>,
+    /// Multiply every birth and death by this factor before computing
+    #[clap(long, env = "FAST_PL_SCALE_X", value_parser, default_value_t = 1.0)]
+    scale_x: f64,
+    /// Add this offset to every birth and death before computing (applied after scaling)
+    #[clap(long, env = "FAST_PL_SHIFT_X", value_parser, default_value_t = 0.0, allow_hyphen_values = true)]
+    shift_x: f64,
+    /// Normalize the output landscape
+    #[clap(long, env = "FAST_PL_NORMALIZE", value_enum)]
+    normalize: Option<NormalizeBy>,
+    /// Drop output points that are collinear with or closer than this tolerance to their
+    /// neighbours
+    #[clap(long, env = "FAST_PL_SIMPLIFY", value_parser)]
+    simplify: Option<f64>,
+    /// Lossy Douglas-Peucker simplification of the output with this maximum error
+    #[clap(long, env = "FAST_PL_SIMPLIFY_LOSSY", value_parser, conflicts_with = "simplify")]
+    simplify_lossy: Option<f64>,
+    /// Postprocess the output with these comma separated transforms instead of the
+    /// simplification options: crop=<min>:<max>, moving-average=<window>[@<sampling>],
+    /// gaussian=<bandwidth>[@<sampling>], normalize=l1|l2, normalize-by=<divisor>,
+    /// resample=<sampling>, simplify=<tolerance> and simplify-lossy=<epsilon>, where a
+    /// sampling is <resolution> or <min>:<max>:<resolution>
+    #[clap(long, env = "FAST_PL_TRANSFORM", value_parser, conflicts_with_all = ["simplify", "simplify_lossy"])]
+    transform: Option<fast_pl::transform::Pipeline>,
+    /// Reuse landscapes computed for identical inputs from this directory
+    #[clap(long, env = "FAST_PL_CACHE_DIR", value_parser)]
+    cache_dir: Option<std::path::PathBuf>,
+    /// Keep only this many of the longest pairs before computing. Each level then differs
+    /// from the exact one by at most half the persistence of the longest dropped pair
+    #[clap(long, env = "FAST_PL_TOP_PAIRS", value_parser)]
+    top_pairs: Option<usize>,
+    /// Randomly keep at most this many pairs before computing
+    #[clap(long, env = "FAST_PL_SUBSAMPLE", value_parser)]
+    subsample: Option<usize>,
+    /// Draw the subsample with probability proportional to persistence
+    #[clap(long, env = "FAST_PL_SUBSAMPLE_WEIGHTED", value_parser, requires = "subsample")]
+    subsample_weighted: bool,
+    /// Seed for random choices such as --subsample
+    #[clap(long, env = "FAST_PL_SEED", value_parser, default_value_t = 0)]
+    seed: u64,
+    /// Periodically save the sweep state to this file
+    #[clap(long, env = "FAST_PL_CHECKPOINT", value_parser)]
+    checkpoint: Option<std::path::PathBuf>,
+    /// Number of events between two checkpoints
+    #[clap(long, env = "FAST_PL_CHECKPOINT_EVERY", value_parser, default_value_t = 1_000_000)]
+    checkpoint_every: usize,
+    /// Also compute the landscape of a sample of the pairs with a naive reference evaluated
+    /// point by point and fail if the sweep differs from it by more than --verify-tolerance. The
+    /// sample is swept like the diagram, so --quantize and --epsilon need a tolerance for their error
+    #[clap(long, env = "FAST_PL_VERIFY", value_parser, conflicts_with = "resume")]
+    verify: bool,
+    /// Largest difference to the reference accepted by --verify
+    #[clap(long, env = "FAST_PL_VERIFY_TOLERANCE", value_parser, default_value_t = 1e-9)]
+    verify_tolerance: f64,
+    /// Number of pairs --verify samples, drawn with --seed
+    #[clap(long, env = "FAST_PL_VERIFY_SAMPLE", value_parser, default_value_t = 2000)]
+    verify_sample: usize,
+    /// Write snapshots of the status and the queues of the sweep to this file as JSON lines,
+    /// for diagnosing slow or failing runs afterwards
+    #[clap(
+        long,
+        env = "FAST_PL_SNAPSHOTS",
+        value_parser,
+        conflicts_with_all = ["cache_dir", "checkpoint", "resume", "weighted", "parallel", "quantize", "integer", "epsilon", "first_level"]
+    )]
+    snapshots: Option<std::path::PathBuf>,
+    /// Number of events between two snapshots
+    #[clap(long, env = "FAST_PL_SNAPSHOT_EVERY", value_parser, default_value_t = 10_000)]
+    snapshot_every: usize,
+    /// Write the pairs behind every critical point of the landscape to this file as CSV
+    #[clap(
+        long,
+        env = "FAST_PL_PROVENANCE",
+        value_parser,
+        conflicts_with_all = [
+            "mmap", "cache_dir", "checkpoint", "resume", "weighted", "parallel", "quantize", "integer", "epsilon",
+            "first_level", "snapshots", "top_pairs", "subsample", "rescale"
+        ]
+    )]
+    provenance: Option<std::path::PathBuf>,
+    /// Read a label per pair from an optional third column and write it to --provenance
+    #[clap(long, env = "FAST_PL_LABELED", value_parser, requires = "provenance")]
+    labeled: bool,
+    /// Sweep in bounded memory of this many bytes, reading the diagram sorted from temporary
+    /// files and writing the levels straight to --csv
+    #[clap(
+        long,
+        env = "FAST_PL_STREAM_MEMORY",
+        value_parser = parse_count,
+        conflicts_with_all = [
+            "graph", "binary", "grid", "norms", "mmap", "weighted", "parallel", "integer", "first_level", "quantize",
+            "epsilon", "reproducible", "pipeline", "rescale", "normalize", "simplify", "simplify_lossy", "transform",
+            "cache_dir", "top_pairs", "subsample", "checkpoint", "verify", "snapshots", "provenance", "resume"
+        ]
+    )]
+    stream_memory: Option<usize>,
+    /// Directory for the temporary files of --stream-memory, by default the system's one
+    #[clap(long, env = "FAST_PL_SPILL_DIR", value_parser, requires = "stream_memory")]
+    spill_dir: Option<std::path::PathBuf>,
+    /// Continue an interrupted run from this checkpoint file instead of reading --name
+    #[clap(long, env = "FAST_PL_RESUME", value_parser, conflicts_with = "cache_dir")]
+    resume: Option<std::path::PathBuf>,
+    /// Format of error messages on stderr. Exit codes are 2 for invalid arguments, 3 for
+    /// unparsable input, 4 for invalid input, 5 for I/O errors, 6 for resource limits and 70
+    /// for internal errors
+    #[clap(long, env = "FAST_PL_ERROR_FORMAT", value_enum, default_value = "text", global = true)]
+    error_format: ErrorFormat,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+impl Args {
+    const fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose, self.debug) {
+            (true, _, _) => Verbosity::Quiet,
+            (_, 2.., _) | (_, _, true) => Verbosity::Trace,
+            (_, 1, _) => Verbosity::Progress,
+            _ => Verbosity::Summary,
+        }
+    }
 
-    let now = Instant::now();
-    let bd_paris: Vec<fast_pl::birthdeath::BirthDeath> = fs::read_to_string(args.name)?
-        .lines()
-        .filter(|s| !s.contains("inf") && !s.is_empty())
-        .map(str::parse)
-        .map(Result::unwrap)
-        .collect();
+    const fn trace(&self) -> bool {
+        matches!(self.verbosity(), Verbosity::Trace)
+    }
+
+    /// Prints the message of a finished phase at `-v` and above
+    fn progress(&self, message: std::fmt::Arguments) {
+        if self.verbosity() >= Verbosity::Progress {
+            eprintln!("{message}");
+        }
+    }
+
+    fn summary(&self, started: Instant) {
+        if self.verbosity() >= Verbosity::Summary {
+            eprintln!("Elapsed: {:.?}", started.elapsed());
+        }
+    }
+}
+
+/// Preprocessing of every diagram according to `args`, the steps of `--pipeline` or the
+/// individual options in the order scale, strict check, minimum persistence, canonical order,
+/// top pairs and subsample
+fn pipeline(args: &Args) -> fast_pl::pipeline::DiagramPipeline {
+    if let Some(pipeline) = &args.pipeline {
+        return pipeline.clone();
+    }
+    let mut pipeline = fast_pl::pipeline::DiagramPipeline::new();
+    if args.birth_persistence {
+        pipeline = pipeline.birth_persistence();
+    }
+    pipeline = pipeline.scale(args.scale_x, args.shift_x);
+    if args.strict {
+        pipeline = pipeline.strict();
+    }
+    if let Some(min_persistence) = args.min_persistence {
+        pipeline = pipeline.min_persistence(min_persistence);
+    }
+    if args.reproducible {
+        pipeline = pipeline.canonical();
+    }
+    if let Some(n) = args.top_pairs {
+        pipeline = pipeline.top_pairs(n);
+    }
+    if let Some(n) = args.subsample {
+        pipeline = pipeline.subsample(n, args.subsample_weighted, args.seed);
+    }
+    pipeline
+}
+
+/// Postprocessing of the output asked for by `args`
+fn transforms(args: &Args) -> fast_pl::transform::Pipeline {
+    use fast_pl::landscape::Simplification;
+    use fast_pl::transform::{Pipeline, Transform};
+    if let Some(transform) = &args.transform {
+        return transform.clone();
+    }
+    let simplification = args
+        .simplify
+        .map(|tolerance| Simplification::Redundant { tolerance })
+        .or_else(|| args.simplify_lossy.map(|epsilon| Simplification::DouglasPeucker { epsilon }));
+    simplification.map_or_else(Pipeline::new, |simplification| Pipeline::new().push(Transform::Simplify(simplification)))
+}
+
+/// Normalization of the output asked for by `args`, diagram based ones use `bd_paris`
+fn normalization(
+    args: &Args,
+    bd_paris: &[fast_pl::birthdeath::BirthDeath],
+) -> Option<fast_pl::landscape::Normalization> {
+    args.normalize.map(|by| match by {
+        NormalizeBy::L1 => fast_pl::landscape::Normalization::L1,
+        NormalizeBy::L2 => fast_pl::landscape::Normalization::L2,
+        NormalizeBy::Persistence => fast_pl::landscape::Normalization::TotalPersistence(
+            fast_pl::birthdeath::total_persistence(bd_paris)),
+        NormalizeBy::Pairs => fast_pl::landscape::Normalization::PairCount(bd_paris.len()),
+    })
+}
+
+/// Pairs of integers as values, which is exact up to 2^53 and `--integer` rejects larger ones
+#[allow(clippy::cast_precision_loss)]
+fn from_integers(bd_pairs: &[fast_pl::birthdeath::BirthDeath<i64>]) -> Vec<fast_pl::birthdeath::BirthDeath> {
+    bd_pairs
+        .iter()
+        .map(|bd| fast_pl::birthdeath::BirthDeath { birth: bd.birth as f64, death: bd.death as f64 })
+        .collect()
+}
+
+/// Levels in half units as values, which is exact as every half unit is below 2^41
+#[allow(clippy::cast_precision_loss)]
+fn from_half_units(levels: Vec<Vec<(i64, i64)>>) -> Vec<Vec<(f64,f64)>> {
+    levels
+        .into_iter()
+        .map(|level| level.into_iter().map(|(x, y)| (x as f64 / 2.0, y as f64 / 2.0)).collect())
+        .collect()
+}
+
+/// Compares the sweep chosen by `args` on a sample of `bd_pairs` to the naive reference, see
+/// `--verify`
+fn verify(args: &Args, bd_pairs: &[fast_pl::birthdeath::BirthDeath]) -> Result<(), Box<dyn Error>> {
+    let started = Instant::now();
+    let copy = || bd_pairs.iter().map(|bd| fast_pl::birthdeath::BirthDeath { birth: bd.birth, death: bd.death }).collect();
+    let sample = fast_pl::birthdeath::subsample(copy(), args.verify_sample, false, args.seed);
+    let copy = || sample.iter().map(|bd| fast_pl::birthdeath::BirthDeath { birth: bd.birth, death: bd.death }).collect();
+    let landscapes = sweep(args, copy(), false)?;
+    let verification = fast_pl::verify::verify(&sample, &landscapes, args.k, 1000);
+    if args.verbosity() >= Verbosity::Summary {
+        eprintln!(
+            "Verified {} pairs at {} points in {:.?}, largest difference {}",
+            sample.len(),
+            verification.points,
+            started.elapsed(),
+            verification.max_discrepancy()
+        );
+    }
+    match verification.worst {
+        Some(worst) if worst.value > args.verify_tolerance || worst.value.is_nan() => Err(format!(
+            "Verification failed: level {} differs from the reference by {} at x = {}, more than {}",
+            worst.level, worst.value, worst.x, args.verify_tolerance
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Computes the landscape of `bd_paris` with the sweep chosen by `--parallel`, `--integer`,
+/// `--quantize` and `--epsilon`
+fn sweep(args: &Args, bd_paris: Vec<fast_pl::birthdeath::BirthDeath>, debug: bool) -> fast_pl::io::LevelsResult {
+    let prepare = |bd_paris| fast_pl::rpls::prepare_pairs(bd_paris, args.k, debug, args.disable_filter);
+    Ok(match (args.parallel, args.quantize, args.epsilon) {
+        (Some(ranges), _, _) => fast_pl::persistencelandscape::generate_parallel(prepare(bd_paris)?, args.k, ranges),
+        (None, _, _) if args.integer => {
+            let integers = fast_pl::birthdeath::to_integer(&prepare(bd_paris)?)?;
+            from_half_units(fast_pl::persistencelandscape::generate_integer(&integers, args.k)?)
+        }
+        (None, Some(quantum), _) => fast_pl::persistencelandscape::generate_quantized(prepare(bd_paris)?, args.k, quantum)?,
+        (None, None, Some(epsilon)) => {
+            fast_pl::persistencelandscape::generate_approximate(prepare(bd_paris)?, args.k, epsilon)?
+        }
+        (None, None, None) => fast_pl::rpls::pairs_to_landscape(bd_paris, args.k, debug, args.disable_filter)?,
+    })
+}
+
+/// Computes the landscape of one diagram according to `args`
+fn compute(
+    args: &Args,
+    bd_paris: Vec<fast_pl::birthdeath::BirthDeath>,
+    checkpoint: Option<&fast_pl::persistencelandscape::Checkpoint>,
+) -> fast_pl::io::LevelsResult {
+    let started = Instant::now();
+    let bd_paris = pipeline(args).apply(bd_paris)?;
+
+    let mut normalization = normalization(args, &bd_paris);
+    let rescaling = args.rescale.and_then(|_| fast_pl::pipeline::Rescaling::fit(&bd_paris));
+    let bd_paris = match rescaling {
+        Some(rescaling) => {
+            args.progress(format_args!("Rescaled by factor {} and shift {}", rescaling.factor, rescaling.shift));
+            if let (Some(RescaleTo::Normalized), Some(fast_pl::landscape::Normalization::TotalPersistence(persistence))) = (args.rescale, normalization) {
+                normalization = Some(fast_pl::landscape::Normalization::TotalPersistence(persistence * rescaling.factor));
+            }
+            rescaling.apply(bd_paris)
+        }
+        None => bd_paris,
+    };
+    args.progress(format_args!("Prepared {} pairs in {:.?}", bd_paris.len(), started.elapsed()));
+    if args.verify {
+        verify(args, &bd_paris)?;
+    }
+
+    let started = Instant::now();
+    let mut landscapes = match (&args.cache_dir, checkpoint) {
+        (_, Some(checkpoint)) => {
+            let filtered = fast_pl::rpls::prepare_pairs(bd_paris, args.k, args.trace(), args.disable_filter)?;
+            fast_pl::persistencelandscape::generate_checkpointed(filtered, args.k, args.trace(), checkpoint)?
+        }
+        (Some(cache_dir), None) => fast_pl::cache::pairs_to_landscape(
+            cache_dir, bd_paris, args.k, args.trace(), args.disable_filter)?,
+        (None, None) if args.first_level > 1 => {
+            if args.first_level > args.k {
+                return Err("--first-level needs -k of at least the first level".into());
+            }
+            let filtered = fast_pl::rpls::prepare_pairs(bd_paris, args.k, args.trace(), args.disable_filter)?;
+            fast_pl::persistencelandscape::generate_levels(filtered, args.first_level..=args.k, args.trace())
+        }
+        (None, None) => match &args.snapshots {
+            Some(path) => {
+                let filtered = fast_pl::rpls::prepare_pairs(bd_paris, args.k, args.trace(), args.disable_filter)?;
+                let snapshots = fast_pl::persistencelandscape::Snapshots { path: path.clone(), every: args.snapshot_every };
+                fast_pl::persistencelandscape::generate_with_snapshots(filtered, args.k, args.trace(), &snapshots)?
+            }
+            None => sweep(args, bd_paris, args.trace())?,
+        },
+    };
+    if let (Some(rescaling), Some(RescaleTo::Original)) = (rescaling, args.rescale) {
+        landscapes = rescaling.invert(landscapes);
+    }
+    if let Some(normalization) = normalization {
+        landscapes = fast_pl::landscape::Landscape::new(landscapes)
+            .normalize(normalization)?
+            .into_levels();
+    }
+    args.progress(format_args!(
+        "Computed {} levels with {} points in {:.?}",
+        landscapes.len(),
+        landscapes.iter().map(Vec::len).sum::<usize>(),
+        started.elapsed()
+    ));
+    Ok(landscapes)
+}
+
+/// Computes the landscape of a weighted diagram according to `args`
+fn compute_weighted(
+    args: &Args,
+    bd_paris: Vec<fast_pl::birthdeath::BirthDeath>,
+    weights: Vec<f64>,
+) -> fast_pl::io::LevelsResult {
+    let started = Instant::now();
+    let (bd_paris, weights) = pipeline(args).apply_weighted(bd_paris, weights)?;
+    let landscapes = fast_pl::weighted::generate_weighted(&bd_paris, &weights, args.k)?;
+    args.progress(format_args!("Computed {} levels in {:.?}", landscapes.len(), started.elapsed()));
+    match normalization(args, &bd_paris) {
+        Some(normalization) => Ok(fast_pl::landscape::Landscape::new(landscapes)
+            .normalize(normalization)?
+            .into_levels()),
+        None => Ok(landscapes),
+    }
+}
+
+/// Computes the landscape of the diagram in `name` and writes the pairs behind its critical
+/// points to the file of `--provenance`, with the labels of the diagram if `--labeled`
+fn compute_provenance(args: &Args, name: &str, options: fast_pl::io::ReadOptions) -> fast_pl::io::LevelsResult {
+    let started = Instant::now();
+    let (bd_paris, labels) = if args.labeled {
+        fast_pl::io::read_labeled_diagram(name, options)?
+    } else {
+        let bd_paris = fast_pl::io::read_diagram_with(name, options)?;
+        let labels = vec![None; bd_paris.len()];
+        (bd_paris, labels)
+    };
+    args.progress(format_args!("Read {} pairs from {name} in {:.?}", bd_paris.len(), started.elapsed()));
+    let (bd_paris, labels) = pipeline(args).apply_labeled(bd_paris, labels)?;
+    let normalization = normalization(args, &bd_paris);
+    let provenance = fast_pl::persistencelandscape::generate_with_provenance(bd_paris, args.k, args.trace());
+    args.progress(format_args!("Computed {} levels in {:.?}", provenance.landscapes.len(), started.elapsed()));
+    if let Some(path) = &args.provenance {
+        fast_pl::io::write_provenance_to(
+            std::io::BufWriter::new(std::fs::File::create(path)?),
+            &provenance,
+            &labels,
+            number_format(args.precision),
+        )?;
+    }
+    match normalization {
+        Some(normalization) => Ok(fast_pl::landscape::Landscape::new(provenance.landscapes)
+            .normalize(normalization)?
+            .into_levels()),
+        None => Ok(provenance.landscapes),
+    }
+}
+
+/// Sweeps the diagram in `name` within `memory` bytes and writes its levels to `--csv`, see
+/// `--stream-memory`
+fn stream(args: &Args, name: &str, read: fast_pl::io::ReadOptions, memory: usize) -> Result<(), Box<dyn Error>> {
+    if args.csv.is_empty() {
+        return Err("Streaming needs an output file given with --csv".into());
+    }
+    let started = Instant::now();
+    let settings = fast_pl::persistencelandscape::Streaming {
+        dir: args.spill_dir.clone().unwrap_or_else(std::env::temp_dir),
+        memory,
+    };
+    let options = fast_pl::io::WriteOptions {
+        flush_every: args.flush_every.and_then(|n| usize::try_from(n).ok()),
+        format: number_format(args.precision),
+    };
+    // Only steps on single pairs are left, the others conflict with --stream-memory
+    let pipeline = pipeline(args);
+    let mut error = None;
+    let pairs = fast_pl::io::stream_diagram(name, read)?
+        .map(|pair| pair.and_then(|bd| pipeline.apply(vec![bd])))
+        .map_while(|pairs| pairs.map_err(|e| error = Some(e)).ok())
+        .flatten();
+    let generate = |writer: Box<dyn std::io::Write>| fast_pl::persistencelandscape::generate_streaming(pairs, args.k, args.trace(), &settings, writer, &options);
+    match args.csv.as_str() {
+        "-" => generate(Box::new(std::io::stdout().lock()))?,
+        csv => generate(Box::new(std::fs::File::create(csv)?))?,
+    }
+    if let Some(error) = error {
+        return Err(error);
+    }
+    args.progress(format_args!("Streamed {name} in {:.?}", started.elapsed()));
+    Ok(())
+}
+
+/// Expands directories in `inputs` to the files they contain, sorted by name
+fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut entries = std::fs::read_dir(input)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.retain(|path| path.is_file());
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    Ok(files)
+}
+
+fn number_format(precision: Option<u8>) -> fast_pl::io::NumberFormat {
+    precision.map_or(fast_pl::io::NumberFormat::Shortest, |digits| {
+        fast_pl::io::NumberFormat::Significant(usize::from(digits))
+    })
+}
+
+fn series(
+    inputs: &[PathBuf],
+    k: usize,
+    norm: fast_pl::distance::Metric,
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let files = expand_inputs(inputs)?;
+    let diagrams = DiagramFiles::new(&files).diagrams()?.collect();
+    let vineyard = fast_pl::vineyard::vineyard(diagrams, k, Some(norm));
+
+    let mut wtr = match output {
+        Some(path) => csv::Writer::from_writer(Box::new(std::fs::File::create(path)?) as Box<dyn std::io::Write>),
+        None => csv::Writer::from_writer(Box::new(std::io::stdout()) as Box<dyn std::io::Write>),
+    };
+    wtr.write_record(["step", "file", "norm", "distance"])?;
+    for (step, (file, landscape)) in files.iter().zip(&vineyard.landscapes).enumerate() {
+        let change = step
+            .checked_sub(1)
+            .map_or_else(String::new, |previous| format.format(vineyard.distances[previous]));
+        wtr.write_record([
+            step.to_string(),
+            file.display().to_string(),
+            format.format(norm.evaluate(landscape, &[])),
+            change,
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Writes the mean landscape of every group of the `manifest`, or of `inputs` if there is none
+fn average(
+    inputs: &[PathBuf],
+    manifest: Option<&PathBuf>,
+    k: usize,
+    output: &std::path::Path,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let entries = match manifest {
+        Some(manifest) => fast_pl::io::read_manifest(manifest)?,
+        None => expand_inputs(inputs)?
+            .into_iter()
+            .map(|path| fast_pl::io::ManifestEntry { path, weight: 1.0, group: String::new() })
+            .collect(),
+    };
+    if entries.iter().any(|e| e.group.contains(['/', '\\'])) {
+        return Err("Group labels must not contain path separators".into());
+    }
+    // Every file is read and swept once, whichever group it is in
+    let landscapes = fast_pl::source::landscapes(&mut DiagramFiles::new(entries.iter().map(|e| &e.path)), k)?;
+    let weights: Vec<f64> = entries.iter().map(|e| e.weight).collect();
+    let groups: Vec<String> = entries.into_iter().map(|e| e.group).collect();
+    let options = fast_pl::io::WriteOptions { flush_every: None, format };
+    for (group, mean) in fast_pl::statistics::group_averages(landscapes, &weights, &groups)? {
+        let path = if group.is_empty() {
+            output.to_path_buf()
+        } else {
+            suffixed_path(&output.to_string_lossy(), group)
+        };
+        fast_pl::io::write_csv_to(std::fs::File::create(path)?, &mean, &options)?;
+    }
+    Ok(())
+}
+
+/// Writes the landscape of the Vietoris-Rips diagram in dimension `dim` of the points in `input`
+#[cfg(feature = "rips")]
+fn from_points(
+    input: &PathBuf,
+    dim: usize,
+    k: usize,
+    threshold: f64,
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let points = fast_pl::io::read_table(input, fast_pl::io::Header::Detect)?;
+    let distances = fast_pl::rips::euclidean_distances(&points)?;
+    let landscapes = fast_pl::rips::landscape(&distances, dim, k, threshold)?;
+    let options = fast_pl::io::WriteOptions { flush_every: None, format };
+    match output {
+        Some(path) => fast_pl::io::write_csv_to(std::fs::File::create(path)?, &landscapes, &options),
+        None => fast_pl::io::write_csv_to(std::io::stdout().lock(), &landscapes, &options),
+    }
+}
+
+/// Writes the landscape of the Vietoris-Rips diagram of the distance matrix in `input` in
+/// every dimension up to `maxdim`
+#[cfg(feature = "rips")]
+fn from_distmat(
+    input: &PathBuf,
+    maxdim: usize,
+    k: usize,
+    threshold: f64,
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let distances = fast_pl::io::read_matrix_csv(input)?;
+    // The pairs only ever live in memory on their way from the reduction into the sweep
+    let landscapes = fast_pl::rips::landscapes(&distances, maxdim, k, threshold)?;
+    write_dimensions(&landscapes, output, format)
+}
+
+/// Writes the landscape of every dimension to `output` with `_h<dim>` inserted before the
+/// extension above dimension 0, or all of them to stdout in turn
+#[cfg(any(feature = "rips", feature = "image"))]
+fn write_dimensions(
+    landscapes: &[fast_pl::sublevel::Levels],
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let options = fast_pl::io::WriteOptions { flush_every: None, format };
+    for (dim, landscape) in landscapes.iter().enumerate() {
+        match output {
+            Some(path) if dim == 0 => fast_pl::io::write_csv_to(std::fs::File::create(path)?, landscape, &options)?,
+            Some(path) => {
+                let path = suffixed_path(&path.to_string_lossy(), format!("h{dim}"));
+                fast_pl::io::write_csv_to(std::fs::File::create(path)?, landscape, &options)?;
+            }
+            None => fast_pl::io::write_csv_to(std::io::stdout().lock(), landscape, &options)?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes the landscapes of the cubical diagrams of the image in `input`
+#[cfg(feature = "image")]
+fn image(
+    input: &PathBuf,
+    k: usize,
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let pixels = match input.extension().and_then(|e| e.to_str()) {
+        Some("png") => fast_pl::io::read_png(input)?,
+        Some("npy") => fast_pl::io::read_npy(&mut std::io::BufReader::new(std::fs::File::open(input)?))?,
+        _ => fast_pl::io::read_table(input, fast_pl::io::Header::Detect)?,
+    };
+    write_dimensions(&fast_pl::cubical::landscapes(&pixels, k)?, output, format)
+}
+
+/// Writes the landscape of the lower-star diagram of the series in `column` of `input`
+/// Parses a whole number of at least zero, also written like `1e6`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+fn parse_count(s: &str) -> Result<usize, String> {
+    if let Ok(count) = s.parse() {
+        return Ok(count);
+    }
+    match s.parse::<f64>() {
+        Ok(count) if count >= 0.0 && count.fract() == 0.0 && count <= usize::MAX as f64 => Ok(count as usize),
+        _ => Err(format!("Expected a whole number such as 1000 or 1e6, got {s}")),
+    }
+}
+
+fn synth(
+    pairs: usize,
+    distribution: Distribution,
+    clusters: usize,
+    seed: u64,
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    use fast_pl::synthetic::{clustered_diagram, diagram, noisy_diagram, Shape};
+    let diagram = match distribution {
+        Distribution::Uniform => {
+            diagram(&[(Shape::Uniform { birth_min: 0.0, birth_max: 1.0, max_persistence: 1.0 }, pairs)], seed)
+        }
+        Distribution::NearDiagonal => diagram(
+            &[(Shape::NearDiagonal { birth_min: 0.0, birth_max: 1.0, mean_persistence: 0.02 }, pairs)],
+            seed,
+        ),
+        Distribution::LongBars => diagram(
+            &[(Shape::LongBars { birth_min: 0.0, birth_max: 1.0, min_persistence: 0.5, max_persistence: 1.0 }, pairs)],
+            seed,
+        ),
+        Distribution::Noisy => noisy_diagram(pairs - pairs / 100, pairs / 100, seed),
+        Distribution::Clustered => clustered_diagram(pairs, clusters, 0.02, seed),
+    };
+    match output {
+        Some(path) => fast_pl::io::write_diagram_to(std::io::BufWriter::new(std::fs::File::create(path)?), &diagram, format),
+        None => fast_pl::io::write_diagram_to(std::io::stdout().lock(), &diagram, format),
+    }
+}
+
+fn time_series(
+    input: &PathBuf,
+    column: usize,
+    k: usize,
+    output: Option<&PathBuf>,
+    format: fast_pl::io::NumberFormat,
+) -> Result<(), Box<dyn Error>> {
+    let values = fast_pl::io::read_table(input, fast_pl::io::Header::Detect)?
+        .into_iter()
+        .map(|row| row.get(column).copied())
+        .collect::<Option<Vec<f64>>>()
+        .ok_or("Every row of the time series needs a value in the selected column")?;
+    let landscapes = fast_pl::sublevel::time_series_landscape(&values, k)?;
+    let options = fast_pl::io::WriteOptions { flush_every: None, format };
+    match output {
+        Some(path) => fast_pl::io::write_csv_to(std::fs::File::create(path)?, &landscapes, &options),
+        None => fast_pl::io::write_csv_to(std::io::stdout().lock(), &landscapes, &options),
+    }
+}
+
+fn distmat(
+    inputs: &[PathBuf],
+    k: usize,
+    metric: fast_pl::distance::Metric,
+    output: &PathBuf,
+    format: Option<MatrixFormat>,
+) -> Result<(), Box<dyn Error>> {
+    let format = match (format, output.extension().and_then(|e| e.to_str())) {
+        (Some(format), _) => format,
+        (None, Some("npy")) => MatrixFormat::Npy,
+        (None, Some("parquet")) => MatrixFormat::Parquet,
+        (None, _) => MatrixFormat::Csv,
+    };
+    let (labels, landscapes) = read_group(inputs, k)?;
+    let matrix = metric.matrix(&landscapes);
+
+    match format {
+        MatrixFormat::Csv => fast_pl::io::write_matrix_csv(
+            std::fs::File::create(output)?, &labels, &matrix, fast_pl::io::NumberFormat::Shortest)?,
+        MatrixFormat::Npy => {
+            fast_pl::io::write_npy(&mut std::io::BufWriter::new(std::fs::File::create(output)?), &matrix)?;
+            std::fs::write(output.with_extension("txt"), labels.join("\n") + "\n")?;
+        }
+        MatrixFormat::Parquet => fast_pl::io::write_matrix_parquet(
+            std::io::BufWriter::new(std::fs::File::create(output)?), &labels, &matrix)?,
+    }
+    Ok(())
+}
+
+fn diff(
+    a: &PathBuf,
+    b: &PathBuf,
+    diagrams: Option<usize>,
+    output: Option<&PathBuf>,
+    plot: Option<&PathBuf>,
+    report: Option<&PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let names = [a.display().to_string(), b.display().to_string()];
+    let read = |path: &PathBuf| match diagrams {
+        Some(k) => Ok(fast_pl::persistencelandscape::generate(fast_pl::io::read_diagram(path)?, k, false)),
+        None => fast_pl::io::read_csv(path),
+    };
+    let (a, b) = (read(a)?, read(b)?);
+    let difference = fast_pl::distance::difference(&a, &b);
+
+    let mut wtr = csv::Writer::from_writer(std::io::stdout());
+    wtr.write_record(["level", "l1", "l2", "sup", "sup_x"])?;
+    let norms = |levels: &[Vec<(f64, f64)>]| {
+        let sup = levels
+            .iter()
+            .flatten()
+            .max_by(|p, q| p.1.abs().total_cmp(&q.1.abs()))
+            .map_or((f64::NAN, 0.0), |p| (p.0, p.1.abs()));
+        [
+            fast_pl::distance::l1_distance(levels, &[]).to_string(),
+            fast_pl::distance::l2_distance(levels, &[]).to_string(),
+            sup.1.to_string(),
+            sup.0.to_string(),
+        ]
+    };
+    for (i, level) in difference.iter().enumerate() {
+        wtr.write_record(std::iter::once(i.to_string()).chain(norms(std::slice::from_ref(level))))?;
+    }
+    wtr.write_record(std::iter::once("all".to_string()).chain(norms(&difference)))?;
+    wtr.flush()?;
 
-    let landscapes = fast_pl::rpls::pairs_to_landscape(bd_paris, args.k, args.debug, args.disable_filter)?;
+    if let Some(path) = output {
+        fast_pl::io::write_csv(path, &difference)?;
+    }
+    if let Some(path) = report {
+        let names = [names[0].as_str(), names[1].as_str()];
+        let page = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")) {
+            fast_pl::report::side_by_side_svg(&a, &b, names)
+        } else {
+            fast_pl::report::side_by_side_html(&a, &b, names)
+        };
+        std::fs::write(path, page)?;
+    }
+    if let Some(path) = plot {
+        #[cfg(feature = "plot")]
+        fast_pl::plot::difference(&difference, path, 720, 1280, 0.5)?;
+        #[cfg(not(feature = "plot"))]
+        return Err(format!("Can not plot to {}, plotting needs the plot feature", path.display()).into());
+    }
+    Ok(())
+}
+
+/// Names of the diagram files in `inputs` and their landscapes
+type Group = (Vec<String>, Vec<Vec<Vec<(f64, f64)>>>);
+
+fn read_group(inputs: &[PathBuf], k: usize) -> Result<Group, Box<dyn Error>> {
+    let files = expand_inputs(inputs)?;
+    let landscapes = fast_pl::source::landscapes(&mut DiagramFiles::new(&files), k)?;
+    Ok((files.iter().map(|f| f.display().to_string()).collect(), landscapes))
+}
+
+fn compare(command: &Command) -> Result<(), Box<dyn Error>> {
+    let Command::Compare { a, b, k, alpha, bootstrap, permutations, seed, output, format } = command else {
+        return Err("Expected the compare command".into());
+    };
+    let format = match (format, output.extension().and_then(|e| e.to_str())) {
+        (Some(format), _) => *format,
+        (None, Some("html" | "htm")) => ReportFormat::Html,
+        (None, _) => ReportFormat::Json,
+    };
+    let (files_a, group_a) = read_group(a, *k)?;
+    let (files_b, group_b) = read_group(b, *k)?;
+    let settings = fast_pl::statistics::ComparisonSettings {
+        alpha: *alpha,
+        n_bootstrap: *bootstrap,
+        n_permutations: *permutations,
+        seed: *seed,
+    };
+    let comparison = fast_pl::statistics::compare_groups(&group_a, &group_b, settings)?;
+    let report = match format {
+        ReportFormat::Json => fast_pl::report::comparison_json(&comparison, &files_a, &files_b),
+        ReportFormat::Html => fast_pl::report::comparison_html(&comparison, &files_a, &files_b),
+    };
+    std::fs::write(output, report)?;
+    Ok(())
+}
+
+fn rank(command: &Command) -> Result<(), Box<dyn Error>> {
+    let Command::Rank { input, birth, death, resolution, output, exact, grid, grid_precision, plot } = command else {
+        return Err("Expected the rank command".into());
+    };
+    let diagram = fast_pl::io::read_diagram(input)?;
+    if let Some((birth, death)) = birth.zip(*death) {
+        println!("{}", fast_pl::birthdeath::betti_at(&diagram, birth, death));
+        return Ok(());
+    }
+    let writer = || -> Result<Box<dyn std::io::Write>, std::io::Error> {
+        Ok(match output {
+            Some(path) => Box::new(std::fs::File::create(path)?),
+            None => Box::new(std::io::stdout()),
+        })
+    };
+    let format = fast_pl::io::NumberFormat::Shortest;
+    if *exact {
+        fast_pl::io::write_rank_function_to(writer()?, &fast_pl::birthdeath::RankFunction::new(&diagram), format)?;
+        if grid.is_none() && plot.is_none() {
+            return Ok(());
+        }
+    }
+    let values = diagram.iter().flat_map(|bd| [bd.birth, bd.death]).filter(|v| v.is_finite());
+    let x_min = values.clone().fold(f64::INFINITY, f64::min);
+    let x_max = values.fold(f64::NEG_INFINITY, f64::max);
+    let sampling = fast_pl::vectorize::Grid::new(x_min, x_max, *resolution)
+        .map_err(|_| "Rank function needs a diagram with finite values and a positive resolution")?;
+    let rank = fast_pl::birthdeath::rank_function(&diagram, &sampling);
+    if !*exact {
+        let labels: Vec<String> = sampling.points().into_iter().map(|x| x.to_string()).collect();
+        #[allow(clippy::cast_precision_loss)]
+        let matrix: Vec<Vec<f64>> = rank.iter().map(|row| row.iter().map(|&n| n as f64).collect()).collect();
+        fast_pl::io::write_matrix_csv(writer()?, &labels, &matrix, format)?;
+    }
+    if let Some(path) = grid {
+        if path.extension().is_some_and(|e| e == "npy") {
+            fast_pl::io::write_rank_grid_npy(path, &diagram, &sampling, (*grid_precision).into())?;
+        } else {
+            fast_pl::io::write_rank_grid(path, &diagram, &sampling, (*grid_precision).into())?;
+        }
+    }
+    if let Some(path) = plot {
+        #[cfg(feature = "plot")]
+        fast_pl::plot::rank_function(&rank, &sampling, path, 720, 720)?;
+        #[cfg(not(feature = "plot"))]
+        return Err(format!("Can not plot to {}, plotting needs the plot feature", path.display()).into());
+    }
+    Ok(())
+}
+
+/// Prints a failure to stderr in `format`
+fn report_failure(format: ErrorFormat, failure: Failure, message: &str) {
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {message}"),
+        ErrorFormat::Json => eprintln!(
+            "{{\"error\":\"{}\",\"code\":{},\"message\":{}}}",
+            failure.name(),
+            failure.code(),
+            fast_pl::report::json_string(message)
+        ),
+    }
+}
+
+fn main() -> ExitCode {
+    // Arguments are not parsed yet when clap rejects them
+    let json_requested = || {
+        let args: Vec<String> = std::env::args().collect();
+        let given = args
+            .windows(2)
+            .find(|w| w[0] == "--error-format")
+            .map(|w| w[1].clone())
+            .or_else(|| args.iter().find_map(|a| a.strip_prefix("--error-format=").map(str::to_string)))
+            .or_else(|| std::env::var("FAST_PL_ERROR_FORMAT").ok());
+        given.is_some_and(|format| format == "json")
+    };
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(error) if error.use_stderr() && json_requested() => {
+            let message = error.render().to_string();
+            eprintln!(
+                "{{\"error\":\"usage_error\",\"code\":2,\"message\":{}}}",
+                fast_pl::report::json_string(message.trim())
+            );
+            return ExitCode::from(2);
+        }
+        Err(error) => error.exit(),
+    };
+    if args.error_format == ErrorFormat::Json {
+        // The payload is reported below instead
+        std::panic::set_hook(Box::new(|_| {}));
+    }
+    let (failure, message) = match std::panic::catch_unwind(|| run(&args)) {
+        Ok(Ok(())) => return ExitCode::SUCCESS,
+        Ok(Err(error)) => (Failure::of(error.as_ref()), error.to_string()),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(ToString::to_string)
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Panic without a message".to_string());
+            (Failure::Internal, message)
+        }
+    };
+    report_failure(args.error_format, failure, &message);
+    ExitCode::from(failure.code())
+}
+
+/// Runs one of the subcommands
+fn subcommand(command: &Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Schema { format, output } => {
+            let schema = match format {
+                SchemaFormat::JsonSchema => fast_pl::report::COMPARISON_SCHEMA,
+                SchemaFormat::Typescript => fast_pl::report::COMPARISON_TYPESCRIPT,
+            };
+            match output {
+                Some(path) => std::fs::write(path, schema)?,
+                None => print!("{schema}"),
+            }
+            Ok(())
+        }
+        #[cfg(feature = "rips")]
+        Command::FromPoints { input, dim, k, threshold, output, precision } => {
+            from_points(input, *dim, *k, *threshold, output.as_ref(), number_format(*precision))
+        }
+        #[cfg(feature = "rips")]
+        Command::FromDistmat { input, maxdim, k, threshold, output, precision } => {
+            from_distmat(input, *maxdim, *k, *threshold, output.as_ref(), number_format(*precision))
+        }
+        #[cfg(not(feature = "rips"))]
+        Command::FromPoints { input, .. } | Command::FromDistmat { input, .. } => {
+            Err(format!("Can not compute the persistence of {}, this needs the rips feature", input.display()).into())
+        }
+        #[cfg(feature = "image")]
+        Command::Image { input, k, output, precision } => image(input, *k, output.as_ref(), number_format(*precision)),
+        #[cfg(not(feature = "image"))]
+        Command::Image { input, .. } => {
+            Err(format!("Can not compute the persistence of {}, this needs the image feature", input.display()).into())
+        }
+        Command::Synth { pairs, distribution, clusters, seed, output, precision } => {
+            synth(*pairs, *distribution, *clusters, *seed, output.as_ref(), number_format(*precision))
+        }
+        Command::TimeSeries { input, column, k, output, precision } => {
+            time_series(input, *column, *k, output.as_ref(), number_format(*precision))
+        }
+        Command::Rank { .. } => rank(command),
+        Command::Compare { .. } => compare(command),
+        Command::Diff { a, b, diagrams, output, plot, report } => {
+            diff(a, b, *diagrams, output.as_ref(), plot.as_ref(), report.as_ref())
+        }
+        Command::Distmat { inputs, k, metric, output, format } => distmat(inputs, *k, *metric, output, *format),
+        Command::Average { inputs, manifest, k, output, precision } => {
+            average(inputs, manifest.as_ref(), *k, output, number_format(*precision))
+        }
+        Command::Series { inputs, k, norm, output, precision } => {
+            series(inputs, *k, *norm, output.as_ref(), number_format(*precision))
+        }
+    }
+}
 
-    let elapsed = now.elapsed();
-    println!("Elapsed: {elapsed:.?}");
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    if let Some(command) = &args.command {
+        return subcommand(command);
+    }
+    if !(args.scale_x > 0.0 && args.scale_x.is_finite()) {
+        return Err("Scale factor must be positive and finite".into());
+    }
 
-    if !args.csv.is_empty() {
-        let mut wtr = Writer::from_path(args.csv)?;
-        for landscape in &landscapes {
-            for point in landscape {
-                wtr.write_record(&[point.0.to_string(), point.1.to_string()])?;
+    let now = Instant::now();
+    let checkpoint = args.checkpoint.clone().map(|path| fast_pl::persistencelandscape::Checkpoint {
+        path,
+        every: args.checkpoint_every,
+    });
+    let landscapes = match (&args.resume, &args.name) {
+        (Some(resume), _) => {
+            if matches!(args.normalize, Some(NormalizeBy::Persistence | NormalizeBy::Pairs)) {
+                return Err("Diagram based normalization is not available when resuming".into());
+            }
+            let landscapes = fast_pl::persistencelandscape::resume(resume, args.trace(), checkpoint.as_ref())?;
+            args.progress(format_args!("Resumed {} in {:.?}", resume.display(), now.elapsed()));
+            match args.normalize {
+                Some(NormalizeBy::L1) => fast_pl::landscape::Landscape::new(landscapes)
+                    .normalize(fast_pl::landscape::Normalization::L1)?
+                    .into_levels(),
+                Some(NormalizeBy::L2) => fast_pl::landscape::Landscape::new(landscapes)
+                    .normalize(fast_pl::landscape::Normalization::L2)?
+                    .into_levels(),
+                _ => landscapes,
+            }
+        }
+        (None, Some(name)) => {
+            let header = if args.header {
+                fast_pl::io::Header::Present
+            } else {
+                fast_pl::io::Header::Detect
+            };
+            let options = fast_pl::io::ReadOptions { header, decimal_comma: args.decimal_comma };
+            if args.weighted {
+                let (bd_paris, weights) = fast_pl::io::read_weighted_diagram(name, options)?;
+                args.progress(format_args!("Read {} pairs from {name} in {:.?}", bd_paris.len(), now.elapsed()));
+                let landscapes = compute_weighted(args, bd_paris, weights)?;
+                args.summary(now);
+                if args.norms.is_some() {
+                    write_norms(args, &[fast_pl::distance::level_norms(&landscapes)])?;
+                }
+                return finish(args, landscapes, None);
+            }
+            if args.integer {
+                let diagram = fast_pl::io::read_integer_diagram(name, header)?;
+                args.progress(format_args!("Read {} pairs from {name} in {:.?}", diagram.len(), now.elapsed()));
+                let landscapes = compute(args, from_integers(&diagram), checkpoint.as_ref())?;
+                args.summary(now);
+                if args.norms.is_some() {
+                    write_norms(args, &[fast_pl::distance::level_norms(&landscapes)])?;
+                }
+                return finish(args, landscapes, None);
+            }
+            if let Some(memory) = args.stream_memory {
+                stream(args, name, options, memory)?;
+                args.summary(now);
+                return Ok(());
+            }
+            if args.provenance.is_some() {
+                let landscapes = compute_provenance(args, name, options)?;
+                args.summary(now);
+                if args.norms.is_some() {
+                    write_norms(args, &[fast_pl::distance::level_norms(&landscapes)])?;
+                }
+                return finish(args, landscapes, None);
             }
-            wtr.write_record(["", ""])?;
+            let mut source = DiagramFile { path: name.into(), options, mmap: args.mmap };
+            let mut diagrams: Vec<_> = source.diagrams()?.collect();
+            args.progress(format_args!(
+                "Read {} pairs in {} diagrams from {name} in {:.?}",
+                diagrams.iter().map(Vec::len).sum::<usize>(),
+                diagrams.len(),
+                now.elapsed()
+            ));
+            if diagrams.len() > 1 {
+                if checkpoint.is_some() || args.graph {
+                    return Err("Checkpoints and plots need a single diagram per file".into());
+                }
+                let norms = finish_each(args, diagrams)?;
+                args.summary(now);
+                return write_norms(args, &norms);
+            }
+            compute(args, diagrams.pop().unwrap_or_default(), checkpoint.as_ref())?
+        }
+        (None, None) => return Err("No input file given".into()),
+    };
+    args.summary(now);
+    if args.norms.is_some() {
+        write_norms(args, &[fast_pl::distance::level_norms(&landscapes)])?;
+    }
+    finish(args, landscapes, None)
+}
+
+/// Computes and writes out the landscape of every diagram of a file holding several, returns
+/// their norms if `--norms` is given
+fn finish_each(
+    args: &Args,
+    diagrams: Vec<Vec<fast_pl::birthdeath::BirthDeath>>,
+) -> Result<Vec<Vec<fast_pl::distance::LevelNorms>>, Box<dyn Error>> {
+    let mut norms = Vec::new();
+    for (index, diagram) in diagrams.into_iter().enumerate() {
+        let landscapes = compute(args, diagram, None)?;
+        if args.norms.is_some() {
+            norms.push(fast_pl::distance::level_norms(&landscapes));
+        }
+        finish(args, landscapes, Some(index))?;
+    }
+    Ok(norms)
+}
+
+/// Writes the norms of every diagram to the file of `--norms`, if given
+fn write_norms(args: &Args, norms: &[Vec<fast_pl::distance::LevelNorms>]) -> Result<(), Box<dyn Error>> {
+    let Some(path) = &args.norms else {
+        return Ok(());
+    };
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+        std::fs::write(path, fast_pl::report::norms_json(norms))?;
+    } else {
+        fast_pl::io::write_norms_csv(std::fs::File::create(path)?, norms, number_format(args.precision))?;
+    }
+    Ok(())
+}
+
+/// Inserts `_suffix` before the extension of `path`
+fn suffixed_path(path: &str, suffix: impl std::fmt::Display) -> PathBuf {
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    let name = path.extension().map_or_else(
+        || format!("{stem}_{suffix}"),
+        |extension| format!("{stem}_{suffix}.{}", extension.to_string_lossy()),
+    );
+    path.with_file_name(name)
+}
+
+/// Simplifies and writes out the landscape of the diagram at `index` of the input, `None`
+/// if the input holds a single diagram
+fn finish(args: &Args, mut landscapes: Vec<Vec<(f64, f64)>>, index: Option<usize>) -> Result<(), Box<dyn Error>> {
+    use fast_pl::transform::LandscapeTransform;
+    let started = Instant::now();
+    let transforms = transforms(args);
+    if !transforms.transforms().is_empty() {
+        landscapes = transforms.apply(fast_pl::landscape::Landscape::new(landscapes))?.into_levels();
+    }
+    if args.verbosity() >= Verbosity::Progress {
+        let landscape = fast_pl::landscape::Landscape::new(landscapes);
+        eprintln!("Area: {}", landscape.total_integral());
+        landscapes = landscape.into_levels();
+    }
+
+    let options = fast_pl::io::WriteOptions {
+        flush_every: args.flush_every.and_then(|n| usize::try_from(n).ok()),
+        format: number_format(args.precision),
+    };
+    match (args.csv.as_str(), index) {
+        ("", _) => {}
+        ("-", _) => fast_pl::io::write_csv_to(std::io::stdout().lock(), &landscapes, &options)?,
+        (csv, Some(index)) => fast_pl::io::write_csv_to(
+            std::fs::File::create(suffixed_path(csv, index))?, &landscapes, &options)?,
+        (csv, None) => fast_pl::io::write_csv_to(std::fs::File::create(csv)?, &landscapes, &options)?,
+    }
+    if let Some(path) = &args.binary {
+        let path = index.map_or_else(|| path.clone(), |index| suffixed_path(&path.to_string_lossy(), index));
+        let encoding = fast_pl::binary::Encoding {
+            delta: args.binary_delta,
+            compression: args.binary_compression,
+        };
+        fast_pl::binary::save_with(path, &[landscapes.clone()], encoding)?;
+    }
+    if let Some(path) = &args.grid {
+        let grid = fast_pl::vectorize::Grid::covering(&[landscapes.clone()], args.grid_resolution)?;
+        let path = index.map_or_else(|| path.clone(), |index| suffixed_path(&path.to_string_lossy(), index));
+        if path.extension().is_some_and(|e| e == "npy") {
+            fast_pl::io::write_grid_npy(path, &landscapes, landscapes.len(), &grid, args.grid_precision.into())?;
+        } else {
+            fast_pl::io::write_grid(path, &landscapes, landscapes.len(), &grid, args.grid_precision.into())?;
         }
-        wtr.flush()?;
+    }
+    if args.verbosity() >= Verbosity::Progress {
+        eprintln!("Wrote the output in {:.?}", started.elapsed());
     }
     #[cfg(feature = "plot")]
     if args.graph {
         return fast_pl::plot::landscape(landscapes, args.height, args.width);
     }
-    if args.debug{
-        println!("Area: {}", fast_pl::rpls::l2_norm(&landscapes));
-    }
     Ok(())
 }
 
 #[cfg(test)]
+#[allow(clippy::unreadable_literal)]
 mod tests {
+    #[test]
+    fn cli_definition() {
+        use clap::CommandFactory;
+        super::Args::command().debug_assert();
+    }
     fn test_runner(k: usize, bd_pairs_vec: Vec<(f64, f64)>, answer_vec: &[Vec<(f64, f64)>]) {
         let bd_pairs = bd_pairs_vec
             .into_iter()
@@ -99,6 +1710,7 @@ mod tests {
         let filtered_pairs = fast_pl::barcode::filter(bd_pairs, k);
         let landscape = fast_pl::persistencelandscape::generate(filtered_pairs, k, false);
         assert!(answer_vec == landscape);
+        fast_pl::landscape::Landscape::new(landscape).validate(1e-9).unwrap();
     }
 
     #[test]
@@ -320,5 +1932,2813 @@ mod tests {
                 "This test only works with f64. create new test for condition if type changes");
             test_runner(k, bd_pairs_vec, &answer_vec);
     }
-
+    #[test]
+    fn median_and_quantile() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)]],
+            vec![vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]],
+        ];
+        let median = fast_pl::statistics::median(&landscapes).unwrap();
+        assert!(median == vec![vec![(0.0, 0.0), (1.0, 1.0), (1.5, 0.5), (2.0, 1.0), (3.0, 0.0), (4.0, 0.0)]]);
+        let max = fast_pl::statistics::quantile(&landscapes, 1.0).unwrap();
+        assert!(max == vec![vec![(0.0, 0.0), (1.0, 1.0), (1.5, 1.5), (2.0, 2.0), (3.0, 1.0), (4.0, 0.0)]]);
+        // Exact between the points as well
+        for i in 0..=40 {
+            let x = f64::from(i) / 10.0;
+            let mut values: Vec<f64> = landscapes
+                .iter()
+                .map(|l| fast_pl::persistencelandscape::evaluate(&l[0], x))
+                .collect();
+            values.sort_by(f64::total_cmp);
+            assert!((fast_pl::persistencelandscape::evaluate(&median[0], x) - values[1]).abs() < 1e-12);
+        }
+        assert!(fast_pl::statistics::quantile(&landscapes, 1.5).is_err());
+    }
+    #[test]
+    fn envelopes() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]],
+        ];
+        let max = fast_pl::statistics::envelope_max(&landscapes).unwrap();
+        assert!(max == vec![vec![(0.0, 0.0), (1.0, 1.0), (1.5, 0.5), (2.0, 1.0), (3.0, 0.0)]]);
+        let min = fast_pl::statistics::envelope_min(&landscapes).unwrap();
+        assert!(min == vec![vec![(0.0, 0.0), (1.0, 0.0), (1.5, 0.5), (2.0, 0.0), (3.0, 0.0)]]);
+    }
+    #[test]
+    fn weighted_average() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)]],
+        ];
+        let average = fast_pl::statistics::weighted_average(&landscapes, &[3.0, 1.0]).unwrap();
+        assert!(average == vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.5), (4.0, 0.0)]]);
+        assert!(fast_pl::statistics::weighted_average(&landscapes, &[1.0]).is_err());
+    }
+    #[test]
+    fn bootstrap_band() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(0.0, 0.0), (1.0, 0.5), (2.0, 0.0)]],
+            vec![vec![(0.0, 0.0), (1.0, 2.0), (2.0, 0.0)]],
+        ];
+        for band_type in [fast_pl::statistics::BandType::Pointwise, fast_pl::statistics::BandType::SupNorm] {
+            let band = fast_pl::statistics::bootstrap_band(&landscapes, 200, 0.1, band_type, 7).unwrap();
+            let again = fast_pl::statistics::bootstrap_band(&landscapes, 200, 0.1, band_type, 7).unwrap();
+            assert!(band.lower == again.lower && band.upper == again.upper);
+            for ((m, l), u) in band.mean[0].iter().zip(&band.lower[0]).zip(&band.upper[0]) {
+                assert!(l.1 <= m.1 && m.1 <= u.1);
+            }
+            assert!(band.lower[0][1].1 >= 0.5 && band.upper[0][1].1 <= 2.0);
+        }
+    }
+    #[test]
+    fn distances() {
+        let a = vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]];
+        let b = vec![vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]];
+        assert!((fast_pl::distance::l1_distance(&a, &b) - 1.5).abs() < 1e-12);
+        assert!((fast_pl::distance::l2_distance(&a, &b) - 1.0).abs() < 1e-12);
+        assert!((fast_pl::distance::sup_distance(&a, &b) - 1.0).abs() < 1e-12);
+    }
+    #[test]
+    fn two_sample_test() {
+        let group_a = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(0.0, 0.0), (1.1, 1.1), (2.2, 0.0)]],
+            vec![vec![(0.0, 0.0), (0.9, 0.9), (1.8, 0.0)]],
+        ];
+        let group_b = vec![
+            vec![vec![(5.0, 0.0), (6.0, 1.0), (7.0, 0.0)]],
+            vec![vec![(5.0, 0.0), (6.1, 1.1), (7.2, 0.0)]],
+            vec![vec![(5.0, 0.0), (5.9, 0.9), (6.8, 0.0)]],
+        ];
+        let different = fast_pl::statistics::two_sample_test(
+            &group_a, &group_b, fast_pl::distance::l2_distance, 199, 3).unwrap();
+        assert!(different < 0.1);
+        let same = fast_pl::statistics::two_sample_test(
+            &group_a, &group_a, fast_pl::distance::l2_distance, 199, 3).unwrap();
+        assert!(same > 0.5);
+    }
+    #[test]
+    fn gram_matrix() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]],
+        ];
+        let gram = fast_pl::kernel::gram_matrix(&landscapes, fast_pl::kernel::Kernel::Linear);
+        assert!((gram[0][0] - 2.0 / 3.0).abs() < 1e-12);
+        assert!((gram[0][1] - 1.0 / 6.0).abs() < 1e-12);
+        assert!((gram[0][1] - gram[1][0]).abs() < f64::EPSILON);
+        let rbf = fast_pl::kernel::gram_matrix(&landscapes, fast_pl::kernel::Kernel::Rbf { gamma: 1.0 });
+        assert!((rbf[0][0] - 1.0).abs() < f64::EPSILON);
+        assert!((rbf[0][1] - (-1.0_f64).exp()).abs() < 1e-12);
+    }
+    #[test]
+    fn kmeans() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(5.0, 0.0), (6.0, 1.0), (7.0, 0.0)]],
+            vec![vec![(0.0, 0.0), (1.1, 1.1), (2.2, 0.0)]],
+            vec![vec![(5.0, 0.0), (6.1, 1.1), (7.2, 0.0)]],
+        ];
+        let grid = fast_pl::vectorize::Grid::covering(&landscapes, 50).unwrap();
+        let clustering = fast_pl::clustering::kmeans(&landscapes, 2, &grid, 100, 11).unwrap();
+        let a = &clustering.assignments;
+        assert!(a[0] == a[2] && a[1] == a[3] && a[0] != a[1]);
+        assert!(clustering.centroids.len() == 2 && clustering.centroids[0][0].len() == 50);
+    }
+    #[test]
+    fn pca() {
+        let landscapes: Vec<Vec<Vec<(f64, f64)>>> = [1.0, 2.0, 4.0]
+            .iter()
+            .map(|h| vec![vec![(0.0, 0.0), (1.0, *h), (2.0, 0.0)]])
+            .collect();
+        let grid = fast_pl::vectorize::Grid::new(0.0, 2.0, 5).unwrap();
+        let pca = fast_pl::pca::pca(&landscapes, &grid, 2).unwrap();
+        assert!(pca.components.len() == 1);
+        let scores: Vec<f64> = pca.scores.iter().map(|s| s[0]).collect();
+        assert!(scores[0] < scores[1] && scores[1] < scores[2]);
+        assert!(scores.iter().sum::<f64>().abs() < 1e-9);
+    }
+    #[test]
+    fn feature_vector() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+            vec![(0.5, 0.0), (1.0, 0.5), (1.5, 0.0)],
+        ]);
+        let grid = fast_pl::vectorize::Grid::new(0.0, 2.0, 5).unwrap();
+        let (features, descriptor) = landscape.to_feature_vector(&grid);
+        assert!(features == vec![0.0, 0.5, 1.0, 0.5, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0]);
+        assert!(descriptor.len() == features.len());
+        let restored = descriptor.to_landscape(&features).unwrap();
+        assert!(restored[1][2] == (1.0, 0.5));
+    }
+    #[test]
+    fn level_summaries() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0), (4.0, 0.0), (5.0, 1.0), (6.0, 0.0)],
+            vec![],
+        ]);
+        let summary = landscape.summary(0);
+        assert!((summary.integral - 2.0).abs() < 1e-12);
+        assert!((summary.max - 1.0).abs() < f64::EPSILON && summary.argmax == Some(2.0));
+        assert!((summary.support_width - 4.0).abs() < 1e-12);
+        assert!((summary.centroid.unwrap() - 3.5).abs() < 1e-12);
+        let empty = &landscape.summaries()[1];
+        assert!(empty.argmax.is_none() && empty.centroid.is_none());
+    }
+    #[test]
+    fn exact_integrals() {
+        let bd_pairs = vec![
+            fast_pl::birthdeath::BirthDeath { birth: 0.0, death: 6.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 3.0 },
+        ];
+        let landscape = fast_pl::landscape::Landscape::new(
+            fast_pl::persistencelandscape::generate(bd_pairs, 2, false));
+        assert!((landscape.integral(0) - 9.0).abs() < 1e-12);
+        assert!((landscape.integral(1) - 1.0).abs() < 1e-12);
+        assert!(landscape.integral(5) == 0.0);
+        assert!((landscape.total_integral() - 10.0).abs() < 1e-12);
+    }
+    #[test]
+    fn derivative() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(0.0, 0.0), (3.0, 3.0), (4.0, 2.0), (4.5, 2.5), (7.0, 0.0)],
+        ]);
+        let slopes: Vec<f64> = landscape.derivative(0).iter().map(|p| p.slope).collect();
+        assert!(slopes == vec![1.0, -1.0, 1.0, -1.0]);
+        assert!(landscape.derivative(0)[2] == fast_pl::landscape::DerivativePiece { start: 4.0, end: 4.5, slope: 1.0 });
+        assert!(landscape.derivative(1).is_empty());
+    }
+    #[test]
+    fn maxima() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(0.0, 0.0), (3.0, 3.0), (4.0, 2.0), (4.5, 2.5), (7.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 0.0)],
+            vec![],
+        ]);
+        assert!(landscape.maxima(0) == vec![(3.0, 3.0), (4.5, 2.5)]);
+        assert!(landscape.maxima(1) == vec![(1.0, 1.0)]);
+        assert!(landscape.global_max() == vec![Some((3.0, 3.0)), Some((1.0, 1.0)), None]);
+    }
+    #[test]
+    fn smoothing() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        ]);
+        let grid = fast_pl::vectorize::Grid::new(0.0, 2.0, 5).unwrap();
+        let smoothed = landscape
+            .smooth(&grid, fast_pl::landscape::Smoothing::MovingAverage { window: 3 })
+            .unwrap();
+        let values: Vec<f64> = smoothed.levels()[0].iter().map(|p| p.1).collect();
+        assert!(values == vec![0.25, 0.5, 2.0 / 3.0, 0.5, 0.25]);
+        let gaussian = landscape
+            .smooth(&grid, fast_pl::landscape::Smoothing::Gaussian { bandwidth: 0.5 })
+            .unwrap();
+        assert!(gaussian.levels()[0][2].1 < 1.0 && gaussian.levels()[0][2].1 > 0.5);
+        assert!(landscape.smooth(&grid, fast_pl::landscape::Smoothing::Gaussian { bandwidth: 0.0 }).is_err());
+    }
+    #[test]
+    fn restrict() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(0.0, 0.0), (3.0, 3.0), (4.0, 2.0), (4.5, 2.5), (7.0, 0.0)],
+            vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)],
+        ]);
+        let restricted = landscape.restrict(3.5, 5.0).unwrap();
+        assert!(restricted.levels()[0] == vec![(3.5, 2.5), (4.0, 2.0), (4.5, 2.5), (5.0, 2.0)]);
+        assert!(restricted.levels()[1].is_empty());
+        assert!(landscape.restrict(5.0, 3.0).is_err());
+    }
+    #[test]
+    fn resample_common() {
+        let landscapes = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]],
+            vec![vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)], vec![(1.5, 0.0), (2.0, 0.5), (2.5, 0.0)]],
+        ];
+        let resampled = fast_pl::statistics::resample_common(&landscapes);
+        assert!(resampled[0][0] == vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 0.0)]);
+        assert!(resampled[1][0] == vec![(0.0, 0.0), (1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]);
+        assert!(resampled[0][1] == vec![(1.5, 0.0), (2.0, 0.0), (2.5, 0.0)]);
+    }
+    #[test]
+    fn affine_x() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)],
+        ]);
+        let moved = landscape.scale_x(2.0).unwrap().shift_x(-1.0);
+        assert!(moved.levels()[0] == vec![(1.0, 0.0), (3.0, 1.0), (5.0, 0.0)]);
+        assert!(landscape.scale_x(0.0).is_err());
+    }
+    #[test]
+    fn normalization() {
+        use fast_pl::landscape::{Landscape, Normalization};
+        let landscape = Landscape::new(vec![vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)]]);
+        let l1 = landscape.normalize(Normalization::L1).unwrap();
+        assert!((l1.total_integral() - 1.0).abs() < 1e-12);
+        let l2 = landscape.normalize(Normalization::L2).unwrap();
+        assert!((fast_pl::distance::l2_distance(l2.levels(), &[]) - 1.0).abs() < 1e-12);
+        let pairs = landscape.normalize(Normalization::PairCount(4)).unwrap();
+        assert!(pairs.levels()[0][1] == (2.0, 0.5));
+        assert!(landscape.normalize(Normalization::TotalPersistence(0.0)).is_err());
+    }
+    #[test]
+    fn simplification() {
+        use fast_pl::landscape::{Landscape, Simplification};
+        let landscape = Landscape::new(vec![vec![
+            (0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (2.0, 2.0), (3.0, 1.0), (3.5, 1.5), (4.0, 1.0), (5.0, 0.0),
+        ]]);
+        let lossless = landscape.simplify(Simplification::Redundant { tolerance: 0.0 });
+        assert!(lossless.levels()[0] == vec![(0.0, 0.0), (2.0, 2.0), (3.0, 1.0), (3.5, 1.5), (5.0, 0.0)]);
+        let lossy = landscape.simplify(Simplification::DouglasPeucker { epsilon: 0.5 });
+        assert!(lossy.levels()[0] == vec![(0.0, 0.0), (2.0, 2.0), (5.0, 0.0)]);
+        let short = Landscape::new(vec![vec![(0.0, 0.0), (0.1, 0.0)]]);
+        let kept = short.simplify(Simplification::Redundant { tolerance: 0.5 });
+        assert!(kept.levels()[0] == vec![(0.0, 0.0), (0.1, 0.0)]);
+    }
+    #[test]
+    fn to_barcode() {
+        let bd_pairs = vec![(0.0, 6.0), (1.0, 3.0), (2.0, 7.0)];
+        let landscape = fast_pl::landscape::Landscape::new(fast_pl::persistencelandscape::generate(
+            bd_pairs
+                .iter()
+                .map(|(b, d)| fast_pl::birthdeath::BirthDeath { birth: *b, death: *d })
+                .collect(),
+            3,
+            false,
+        ));
+        let mut recovered: Vec<(f64, f64)> =
+            landscape.to_barcode().unwrap().iter().map(|bd| (bd.birth, bd.death)).collect();
+        recovered.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert!(recovered == bd_pairs);
+
+        // Only the pairs visible in the first level can be recovered
+        let truncated = fast_pl::landscape::Landscape::new(landscape.levels()[..1].to_vec());
+        assert!(truncated.to_barcode().unwrap().len() == 2);
+        let plateau = fast_pl::landscape::Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 0.0)]]);
+        assert!(plateau.to_barcode().is_err());
+    }
+    #[test]
+    fn validate() {
+        use fast_pl::landscape::{Landscape, Violation};
+        let steep = Landscape::new(vec![vec![(0.0, 0.0), (1.0, 2.0), (2.0, 0.0)]]);
+        assert!(steep.validate(1e-9) == Err(Violation::NotLipschitz { level: 0, x: 0.0 }));
+        let increasing = Landscape::new(vec![
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+            vec![(2.0, 0.0), (3.0, 1.0), (4.0, 0.0)],
+        ]);
+        assert!(increasing.validate(1e-9) == Err(Violation::NotDecreasing { level: 1, x: 3.0 }));
+        let floating = Landscape::new(vec![vec![(0.0, 0.5), (1.0, 1.0)]]);
+        assert!(floating.validate(1e-9) == Err(Violation::NonZeroEndpoint { level: 0, x: 0.0 }));
+    }
+    #[test]
+    fn approx_eq() {
+        use fast_pl::landscape::{approx_eq_point, Landscape};
+        let a = Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]]);
+        let b = Landscape::new(vec![vec![(0.0, 0.0), (1.0 + 1e-12, 1.0), (2.0, 1e-13)], vec![]]);
+        assert!(a.approx_eq(&b, 1e-9, 0.0));
+        assert!(!a.approx_eq(&b, 0.0, 0.0));
+        assert!(approx_eq_point((100.0, 1.0), (100.1, 1.0), 0.0, 1e-2));
+        assert!(!approx_eq_point((100.0, 1.0), (100.1, 1.0), 1e-2, 0.0));
+    }
+    #[test]
+    fn operators() {
+        use fast_pl::landscape::Landscape;
+        let a = Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]]);
+        let b = Landscape::new(vec![vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]]);
+        let sum = &a + &b;
+        assert!(sum[0] == [(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 0.0)]);
+        let mean = [a.clone(), b].iter().sum::<Landscape>() * 0.5;
+        assert!(mean[0] == [(0.0, 0.0), (1.0, 0.5), (2.0, 0.5), (3.0, 0.0)]);
+        assert!((&a - &a).total_integral() == 0.0);
+        assert!((-a)[0][1] == (1.0, -1.0));
+    }
+    #[test]
+    fn display() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+            vec![],
+        ]);
+        let table = landscape.to_string();
+        assert!(table.lines().count() == 3);
+        assert!(table.lines().nth(1).unwrap().split_whitespace().collect::<Vec<_>>()
+            == vec!["0", "3", "1.000000", "1.000000"]);
+        let pretty = landscape.to_pretty_string(2);
+        assert!(pretty.contains("level 0: (0.000000, 0.000000) (1.000000, 1.000000) ... 1 more"));
+    }
+    #[test]
+    fn fingerprint() {
+        use fast_pl::landscape::Landscape;
+        let a = Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]]);
+        let b = Landscape::new(vec![vec![(0.0, 0.0), (1.0 + 1e-12, 1.0), (2.0, 0.0)]]);
+        let c = Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], vec![]]);
+        assert!(a.fingerprint(1e-9) == b.fingerprint(1e-9));
+        assert!(a.fingerprint(0.0) != b.fingerprint(0.0));
+        assert!(a.fingerprint(1e-9) != c.fingerprint(1e-9));
+        assert!(a.fingerprint(1e-9) == 0x6b63_29c5_52d8_bb3b);
+    }
+    #[test]
+    fn cache_round_trip() {
+        let cache_dir = std::env::temp_dir().join(format!("fast_pl_cache_test_{}", std::process::id()));
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 0.0, death: 6.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 3.0 },
+        ];
+        let key = fast_pl::cache::input_key(&pairs(), 3, false);
+        assert!(key != fast_pl::cache::input_key(&pairs(), 2, false));
+        let computed = fast_pl::cache::pairs_to_landscape(&cache_dir, pairs(), 3, false, false).unwrap();
+        assert!(fast_pl::cache::cache_path(&cache_dir, key).is_file());
+        let cached = fast_pl::cache::pairs_to_landscape(&cache_dir, pairs(), 3, false, false).unwrap();
+        assert!(computed == cached && cached.len() == 3);
+        std::fs::remove_dir_all(cache_dir).unwrap();
+    }
+    #[test]
+    fn checkpoint_resume() {
+        let path = std::env::temp_dir().join(format!("fast_pl_checkpoint_test_{}", std::process::id()));
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 7.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: 9.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.2, death: 10.0 },
+        ];
+        let expected = fast_pl::persistencelandscape::generate(pairs(), 4, false);
+        // A checkpoint every 5 events leaves the last one in the middle of the sweep
+        let checkpoint = fast_pl::persistencelandscape::Checkpoint { path: path.clone(), every: 5 };
+        let checkpointed = fast_pl::persistencelandscape::generate_checkpointed(pairs(), 4, false, &checkpoint).unwrap();
+        assert!(checkpointed == expected);
+        let resumed = fast_pl::persistencelandscape::resume(&path, false, None).unwrap();
+        assert!(resumed == expected);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn memory_estimate() {
+        let pairs: Vec<fast_pl::birthdeath::BirthDeath> = (0..50)
+            .map(|i| fast_pl::birthdeath::BirthDeath { birth: f64::from(i) * 0.3, death: f64::from(i).mul_add(0.3, 5.0) })
+            .collect();
+        let landscapes = fast_pl::persistencelandscape::generate(pairs, 8, false);
+        let output: usize = landscapes.iter().map(|l| l.len() * std::mem::size_of::<(f64, f64)>()).sum();
+        let estimate = fast_pl::persistencelandscape::estimate_memory(50, 8);
+        assert!(estimate >= output);
+        assert!(fast_pl::persistencelandscape::estimate_memory(100, 8) > estimate);
+        assert!(fast_pl::persistencelandscape::estimate_memory(50, 16) > estimate);
+        assert!(fast_pl::persistencelandscape::estimate_memory(usize::MAX, usize::MAX) == usize::MAX);
+    }
+
+    #[test]
+    fn parallel_ranges() {
+        use fast_pl::persistencelandscape::{generate, generate_parallel};
+        let pairs = || fast_pl::synthetic::noisy_diagram(200, 10, 3);
+        for k in [0, 1, 4] {
+            let expected = generate(pairs(), k, false);
+            for ranges in [1, 2, 7, 1000] {
+                let parallel = generate_parallel(pairs(), k, ranges);
+                assert!(fast_pl::distance::sup_distance(&parallel, &expected) < 1e-9);
+                assert!(k == 0 || parallel.len() == k);
+            }
+        }
+        assert!(generate_parallel(Vec::new(), 2, 4) == vec![Vec::new(), Vec::new()]);
+    }
+
+    #[test]
+    fn reproducible_mode() {
+        use clap::Parser;
+        let rows = [(0.0, 4.0), (1.0, 3.0), (0.0, 4.0), (1.0, 5.0), (2.0, 4.0), (0.0, 2.0), (1.0, 3.0)];
+        let args = super::Args::parse_from(["fast_pl", "-n", "unused", "-k", "0", "--reproducible"]);
+        let forward = super::compute(&args, fast_pl::birthdeath::pairs(rows), None).unwrap();
+        let backward = super::compute(&args, fast_pl::birthdeath::pairs(rows.iter().rev().copied()), None).unwrap();
+        let bits = |levels: &[Vec<(f64, f64)>]| -> Vec<Vec<(u64, u64)>> {
+            levels.iter().map(|l| l.iter().map(|(x, y)| (x.to_bits(), y.to_bits())).collect()).collect()
+        };
+        assert!(bits(&forward) == bits(&backward));
+
+        let mut sorted = fast_pl::birthdeath::pairs(rows);
+        fast_pl::birthdeath::sort_canonical(&mut sorted);
+        assert!(sorted.windows(2).all(|w| fast_pl::birthdeath::canonical_cmp(&w[0], &w[1]).is_le()));
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--reproducible", "--cache-dir", "c"]).is_err());
+    }
+
+    #[test]
+    fn reserved_capacities() {
+        use fast_pl::persistencelandscape::{estimate_capacities, generate_report, MAX_RESERVED};
+        let pairs = || (0..50)
+            .map(|i| fast_pl::birthdeath::BirthDeath { birth: f64::from(i) * 0.3, death: f64::from(i).mul_add(0.3, 5.0) })
+            .collect::<Vec<_>>();
+        let capacities = estimate_capacities(50, 8);
+        assert!(capacities.events == 150 && capacities.status == 50);
+        assert!(capacities.level_points * 8 >= 250);
+        assert!(estimate_capacities(50, 0).level_points == 0);
+        assert!(estimate_capacities(0, 8).level_points == 0);
+        let large = estimate_capacities(1 << 30, 2);
+        assert!(large.events == 3 << 30);
+        assert!([large.level_points, large.status, large.intersections].iter().all(|&c| c == MAX_RESERVED));
+        let report = generate_report(pairs(), 8, false);
+        assert!(report.capacities == capacities);
+        assert!(report.landscapes == fast_pl::persistencelandscape::generate(pairs(), 8, false));
+        assert!(fast_pl::persistencelandscape::generate_single_level(pairs(), 3, false) == report.landscapes[2]);
+    }
+
+    #[test]
+    fn generate_limits() {
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 7.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: 9.0 },
+        ];
+        let expected = fast_pl::persistencelandscape::generate(pairs(), 3, false);
+        let unlimited = fast_pl::persistencelandscape::Limits::default();
+        assert!(fast_pl::persistencelandscape::generate_limited(pairs(), 3, false, &unlimited) == Ok(expected));
+        let events = fast_pl::persistencelandscape::Limits { max_events: Some(4), ..Default::default() };
+        assert!(fast_pl::persistencelandscape::generate_limited(pairs(), 3, false, &events)
+            == Err(fast_pl::persistencelandscape::LimitExceeded::Events { limit: 4 }));
+        let intersections = fast_pl::persistencelandscape::Limits { max_intersections: Some(0), ..Default::default() };
+        assert!(fast_pl::persistencelandscape::generate_limited(pairs(), 3, false, &intersections)
+            == Err(fast_pl::persistencelandscape::LimitExceeded::Intersections { limit: 0 }));
+        let runtime = fast_pl::persistencelandscape::Limits {
+            max_runtime: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        assert!(fast_pl::persistencelandscape::generate_limited(pairs(), 3, false, &runtime).is_err());
+
+        // The peak of the first tent starts a cascade of twenty intersections with the tents
+        // still rising below it
+        let cascade = || std::iter::once((0.0, 100.0))
+            .chain((1..=20_u32).map(|i| (f64::from(i), 200.0 + f64::from(i))))
+            .map(fast_pl::birthdeath::BirthDeath::from)
+            .collect::<Vec<_>>();
+        let intersections = fast_pl::persistencelandscape::Limits { max_intersections: Some(5), ..Default::default() };
+        assert!(fast_pl::persistencelandscape::generate_limited(cascade(), 2, false, &intersections)
+            == Err(fast_pl::persistencelandscape::LimitExceeded::Intersections { limit: 5 }));
+        let intersections = fast_pl::persistencelandscape::Limits { max_intersections: Some(1000), ..Default::default() };
+        assert!(fast_pl::persistencelandscape::generate_limited(cascade(), 2, false, &intersections)
+            == Ok(fast_pl::persistencelandscape::generate(cascade(), 2, false)));
+    }
+
+    #[test]
+    fn external_memory() {
+        let pairs = || (0..40).map(|i| fast_pl::birthdeath::BirthDeath {
+            birth: f64::from(i * 7 % 40) * 0.31,
+            death: f64::from(i * 7 % 40).mul_add(0.31, f64::from(i % 5).mul_add(0.77, 1.5)),
+        });
+        let expected = fast_pl::persistencelandscape::generate(pairs().collect(), 5, false);
+        let settings = fast_pl::persistencelandscape::ExternalMemory {
+            dir: std::env::temp_dir(),
+            chunk_events: 7,
+        };
+        let external = fast_pl::persistencelandscape::generate_external(pairs(), 5, false, &settings).unwrap();
+        for (a, b) in external.iter().zip(&expected) {
+            assert!(a.len() == b.len());
+            assert!(a.iter().zip(b).all(|(p, q)| fast_pl::landscape::approx_eq_point(*p, *q, 1e-9, 0.0)));
+        }
+        // Many shared coordinates in large runs, and more runs than are merged at once
+        let diagram = || fast_pl::birthdeath::pairs(
+            (0..3000_u32).map(|i| (f64::from(i % 97), f64::from(i % 97 + 1 + i % 13))),
+        );
+        let expected = fast_pl::persistencelandscape::generate(diagram(), 3, false);
+        for chunk_events in [1000, 3] {
+            let settings = fast_pl::persistencelandscape::ExternalMemory { dir: std::env::temp_dir(), chunk_events };
+            let external = fast_pl::persistencelandscape::generate_external(diagram(), 3, false, &settings).unwrap();
+            assert!(external.len() == expected.len());
+            for i in 0..=1200 {
+                let x = f64::from(i) / 10.0;
+                for (a, b) in external.iter().zip(&expected) {
+                    assert!((fast_pl::persistencelandscape::evaluate(a, x)
+                        - fast_pl::persistencelandscape::evaluate(b, x)).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vineyard_steps() {
+        let diagram = |shift: f64| vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0 + shift, death: 4.0 + shift },
+            fast_pl::birthdeath::BirthDeath { birth: 2.0 + shift, death: 6.0 + shift },
+        ];
+        let mut reordered = diagram(0.0);
+        reordered.reverse();
+        let diagrams = vec![diagram(0.0), reordered, diagram(1.0)];
+        let result = fast_pl::vineyard::vineyard(diagrams, 2, Some(fast_pl::vineyard::StepDistance::L1));
+        assert!(result.landscapes.len() == 3);
+        assert!(result.computed == 2);
+        assert!(result.landscapes[2] == fast_pl::persistencelandscape::generate(diagram(1.0), 2, false));
+        assert!(result.distances.len() == 2);
+        assert!(result.distances[0].abs() < 1e-12);
+        let expected = fast_pl::distance::l1_distance(&result.landscapes[1], &result.landscapes[2]);
+        assert!((result.distances[1] - expected).abs() < 1e-12);
+        assert!(fast_pl::vineyard::vineyard(Vec::new(), 2, None).landscapes.is_empty());
+    }
+
+    #[test]
+    fn incremental_landscape() {
+        let pairs = [(1.0, 8.0), (3.0, 7.0), (12.0, 15.0), (4.0, 9.0), (8.5, 10.0), (13.0, 14.0)];
+        let bd = |&(birth, death): &(f64, f64)| fast_pl::birthdeath::BirthDeath { birth, death };
+        let mut incremental = fast_pl::incremental::IncrementalLandscape::new(3);
+        for pair in &pairs {
+            incremental.insert(&bd(pair)).unwrap();
+        }
+        assert!(incremental.len() == pairs.len());
+        assert!(incremental.levels() == fast_pl::persistencelandscape::generate(pairs.iter().map(bd).collect(), 3, false));
+
+        // Removing (4, 9) disconnects (8.5, 10) from the first group
+        incremental.remove(&bd(&(4.0, 9.0))).unwrap();
+        let remaining: Vec<_> = pairs.iter().filter(|p| **p != (4.0, 9.0)).map(bd).collect();
+        assert!(incremental.levels() == fast_pl::persistencelandscape::generate(remaining, 3, false));
+        assert!(incremental.remove(&bd(&(4.0, 9.0))).is_err());
+        assert!(incremental.insert(&bd(&(2.0, 1.0))).is_err());
+        let rebuilt = fast_pl::incremental::IncrementalLandscape::from_pairs(
+            pairs.iter().filter(|p| **p != (4.0, 9.0)).map(bd).collect(), 3);
+        assert!(rebuilt.levels() == incremental.levels());
+    }
+
+    #[test]
+    fn series_command() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("fast_pl_series_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("0.txt"), "0 2\n").unwrap();
+        std::fs::write(dir.join("1.txt"), "0 4\n1 2\n").unwrap();
+        let output = dir.join("series.csv");
+        let args = super::Args::parse_from(["fast_pl", "series", "-k", "2", "--norm", "l1", "-o"]
+            .into_iter()
+            .map(std::ffi::OsString::from)
+            .chain([output.clone().into_os_string(), dir.join("0.txt").into_os_string(), dir.join("1.txt").into_os_string()]));
+        let Some(super::Command::Series { inputs, k, norm, output: Some(path), precision }) = &args.command else {
+            panic!("Expected the series command");
+        };
+        super::series(inputs, *k, *norm, Some(path), super::number_format(*precision)).unwrap();
+        let rows: Vec<Vec<String>> = std::fs::read_to_string(&output)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(',').map(String::from).collect())
+            .collect();
+        assert!(rows.len() == 3);
+        assert!((rows[1][2].parse::<f64>().unwrap() - 1.0).abs() < 1e-12);
+        assert!(rows[1][3].is_empty());
+        assert!((rows[2][2].parse::<f64>().unwrap() - 4.25).abs() < 1e-12);
+        assert!((rows[2][3].parse::<f64>().unwrap() - 3.25).abs() < 1e-12);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn multiple_diagrams() {
+        let path = std::env::temp_dir().join(format!("fast_pl_diagrams_test_{}", std::process::id()));
+        std::fs::write(&path, "# diagram 0\n0 2\n1 inf\n\n\n# diagram 1\n0 4\n1 2\n\n3 5\n").unwrap();
+        let diagrams = fast_pl::io::read_diagrams(&path).unwrap();
+        assert!(diagrams.iter().map(Vec::len).collect::<Vec<_>>() == [1, 2, 1]);
+        let landscapes = fast_pl::io::read_landscapes(&path, 2).unwrap();
+        assert!(landscapes.len() == 3);
+        assert!(landscapes[1].levels() == [vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)], vec![(1.0, 0.0), (1.5, 0.5), (2.0, 0.0)]]);
+        std::fs::write(&path, "0 2\n1 2 3\n").unwrap();
+        assert!(fast_pl::io::read_diagrams(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+        assert!(super::suffixed_path("out/landscape.csv", 3) == std::path::Path::new("out/landscape_3.csv"));
+        assert!(super::suffixed_path("landscape", 0) == std::path::Path::new("landscape_0"));
+    }
+
+    #[test]
+    fn subsample_pairs() {
+        let pairs = || -> Vec<fast_pl::birthdeath::BirthDeath> {
+            (0..100)
+                .map(|i| fast_pl::birthdeath::BirthDeath {
+                    birth: f64::from(i),
+                    death: f64::from(i) + if i < 5 { 100.0 } else { 0.01 },
+                })
+                .collect()
+        };
+        let uniform = fast_pl::birthdeath::subsample(pairs(), 10, false, 3);
+        assert!(uniform.len() == 10);
+        assert!(uniform.windows(2).all(|w| w[0].birth < w[1].birth));
+        let again = fast_pl::birthdeath::subsample(pairs(), 10, false, 3);
+        assert!(uniform.iter().zip(&again).all(|(a, b)| a.birth.to_bits() == b.birth.to_bits()));
+        // The five long bars carry almost all the persistence
+        let weighted = fast_pl::birthdeath::subsample(pairs(), 5, true, 3);
+        assert!(weighted.iter().all(|bd| bd.birth < 5.0));
+        assert!(fast_pl::birthdeath::subsample(pairs(), 200, true, 3).len() == 100);
+    }
+
+    #[test]
+    fn top_pairs_bound() {
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 0.0, death: 10.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 20.0, death: 21.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 2.0, death: 9.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 4.5 },
+        ];
+        let kept = fast_pl::birthdeath::top_pairs(pairs(), 2);
+        assert!(kept.iter().map(|bd| bd.birth).collect::<Vec<_>>() == [0.0, 2.0]);
+        let exact = fast_pl::persistencelandscape::generate(pairs(), 2, false);
+        let approx = fast_pl::persistencelandscape::generate(kept, 2, false);
+        // Longest dropped pair is (3, 4.5)
+        assert!(fast_pl::distance::sup_distance(&exact, &approx) <= 0.75 + 1e-12);
+        assert!(fast_pl::birthdeath::top_pairs(pairs(), 4).len() == 4);
+    }
+
+    #[test]
+    fn synthetic_diagrams() {
+        use fast_pl::synthetic::{diagram, noisy_diagram, Shape};
+        let groups = [
+            (Shape::Uniform { birth_min: 1.0, birth_max: 2.0, max_persistence: 0.5 }, 50),
+            (Shape::LongBars { birth_min: 0.0, birth_max: 1.0, min_persistence: 3.0, max_persistence: 4.0 }, 3),
+        ];
+        let pairs = diagram(&groups, 7);
+        assert!(pairs.len() == 53);
+        assert!(pairs[..50].iter().all(|bd| (1.0..2.0).contains(&bd.birth) && (0.0..0.5).contains(&(bd.death - bd.birth))));
+        assert!(pairs[50..].iter().all(|bd| (3.0..4.0).contains(&(bd.death - bd.birth))));
+        let again = diagram(&groups, 7);
+        assert!(pairs.iter().zip(&again).all(|(a, b)| a.birth.to_bits() == b.birth.to_bits() && a.death.to_bits() == b.death.to_bits()));
+        let noisy = noisy_diagram(200, 2, 1);
+        assert!(noisy.iter().all(|bd| bd.birth <= bd.death));
+        let mean_noise = noisy[..200].iter().map(|bd| bd.death - bd.birth).sum::<f64>() / 200.0;
+        assert!(mean_noise < 0.05);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_pairs() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut u = arbitrary::Unstructured::new(&bytes);
+        let pairs: Vec<fast_pl::birthdeath::BirthDeath> = u.arbitrary().unwrap();
+        assert!(pairs.iter().all(|bd| bd.birth.is_finite() && bd.birth <= bd.death));
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn strategy_pairs(pairs in fast_pl::strategy::diagram(0.0, 10.0, 5.0, 20)) {
+            proptest::prop_assert!(pairs.iter().all(|bd| bd.birth <= bd.death));
+            let landscape = fast_pl::landscape::Landscape::new(fast_pl::persistencelandscape::generate(pairs, 2, false));
+            proptest::prop_assert!(landscape.validate(1e-9).is_ok());
+        }
+    }
+
+    #[test]
+    fn half_precision_grid() {
+        use fast_pl::vectorize::{decode, encode, Precision};
+        let values = [0.0, 0.5, 1.0 / 3.0, 2.75, 1000.25];
+        for (precision, tolerance) in [(Precision::F32, 1e-6), (Precision::F16, 1e-3), (Precision::Bf16, 1e-2)] {
+            let bytes = encode(&values, precision);
+            assert!(bytes.len() == values.len() * precision.bytes());
+            let decoded = decode(&bytes, precision).unwrap();
+            assert!(decoded.iter().zip(values).all(|(a, b)| (a - b).abs() <= tolerance * b.abs().max(1.0)));
+        }
+        assert!(decode(&[0, 0, 0], Precision::F16).is_err());
+    }
+
+    #[test]
+    fn numpy_grid() {
+        use fast_pl::vectorize::{encode, sample, Grid, Precision};
+        let landscapes = vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], vec![(0.5, 0.0), (1.0, 0.5), (1.5, 0.0)]];
+        let grid = Grid::new(0.0, 2.0, 9).unwrap();
+        let path = std::env::temp_dir().join(format!("fast_pl_grid_{}.npy", std::process::id()));
+        fast_pl::io::write_grid_npy(&path, &landscapes, 2, &grid, Precision::F16).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let start = 10 + usize::from(u16::from_le_bytes([bytes[8], bytes[9]]));
+        assert!(bytes.starts_with(b"\x93NUMPY\x01\x00") && start % 64 == 0 && bytes[start - 1] == b'\n');
+        let header = String::from_utf8_lossy(&bytes[10..start]);
+        assert!(header.contains("'descr': '<f2'") && header.contains("'shape': (2, 9)"));
+        assert!(bytes[start..] == encode(&sample(&landscapes, 2, &grid), Precision::F16));
+        assert!(fast_pl::io::write_grid_npy(&path, &landscapes, 2, &grid, Precision::Bf16).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn simd_sampling() {
+        let level = [(0.0, 0.0), (1.5, 1.5), (1.5, 1.5), (2.0, 1.0), (2.25, 1.25), (3.5, 0.0)];
+        let points: Vec<f64> = (0..=41).map(|i| f64::from(i).mul_add(0.1, -0.3)).collect();
+        let sampled = fast_pl::vectorize::sample_level(&level, &points);
+        assert!(sampled
+            .iter()
+            .zip(&points)
+            .all(|(v, x)| v.to_bits() == fast_pl::persistencelandscape::evaluate(&level, *x).to_bits()));
+        assert!(fast_pl::vectorize::sample_level(&[], &points).iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn levelwise_matches_sweep() {
+        let pairs: Vec<fast_pl::birthdeath::BirthDeath> = (0..30)
+            .map(|i| fast_pl::birthdeath::BirthDeath {
+                birth: f64::from(i * 7 % 30) * 0.31,
+                death: f64::from(i * 7 % 30).mul_add(0.31, f64::from(i % 4).mul_add(1.13, 0.9)),
+            })
+            .collect();
+        let levelwise = fast_pl::levelwise::generate_levelwise(&pairs, 6);
+        let sweep = fast_pl::persistencelandscape::generate(pairs, 6, false);
+        assert!(levelwise.len() == 6);
+        assert!(fast_pl::distance::sup_distance(&levelwise, &sweep) < 1e-9);
+        assert!(fast_pl::distance::l1_distance(&levelwise, &sweep) < 1e-9);
+        let landscape = fast_pl::landscape::Landscape::new(levelwise);
+        assert!(landscape.validate(1e-9).is_ok());
+        let empty = fast_pl::levelwise::generate_levelwise(&[], 2);
+        assert!(empty.iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn single_level() {
+        let pairs = || -> Vec<fast_pl::birthdeath::BirthDeath> {
+            (0..30)
+                .map(|i| fast_pl::birthdeath::BirthDeath {
+                    birth: f64::from(i * 7 % 30) * 0.31,
+                    death: f64::from(i * 7 % 30).mul_add(0.31, f64::from(i % 4).mul_add(1.13, 0.9)),
+                })
+                .collect()
+        };
+        let all = fast_pl::persistencelandscape::generate(pairs(), 5, false);
+        for k in 1..=5 {
+            assert!(fast_pl::persistencelandscape::generate_single_level(pairs(), k, false) == all[k - 1]);
+        }
+        assert!(fast_pl::persistencelandscape::generate_single_level(pairs(), 0, false).is_empty());
+    }
+
+    #[test]
+    fn all_levels() {
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 7.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: 9.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 20.0, death: 22.0 },
+        ];
+        let all = fast_pl::persistencelandscape::generate(pairs(), 0, false);
+        assert!(all.len() == 3);
+        assert!(all == fast_pl::persistencelandscape::generate(pairs(), 3, false));
+        let filtered = fast_pl::rpls::pairs_to_landscape(pairs(), 0, false, false).unwrap();
+        assert!(filtered == all);
+    }
+
+    #[test]
+    fn landscape_depth() {
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 7.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: 9.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 20.0, death: 22.0 },
+        ];
+        let report = fast_pl::persistencelandscape::generate_report(pairs(), 2, false);
+        assert!(report.depth == 3 && report.truncated());
+        assert!(report.landscapes == fast_pl::persistencelandscape::generate(pairs(), 2, false));
+        let report = fast_pl::persistencelandscape::generate_report(pairs(), 5, false);
+        assert!(report.depth == 3 && !report.truncated());
+        assert!(fast_pl::landscape::Landscape::new(report.landscapes).depth() == 3);
+        assert!(!fast_pl::persistencelandscape::generate_report(pairs(), 0, false).truncated());
+    }
+
+    #[test]
+    fn provenance() {
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 0.0, death: f64::INFINITY },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 7.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: 9.0 },
+        ];
+        let result = fast_pl::persistencelandscape::generate_with_provenance(pairs(), 2, false);
+        assert!(result.landscapes == fast_pl::persistencelandscape::generate(pairs(), 2, false));
+        assert!(result.sources.iter().map(Vec::len).eq(result.landscapes.iter().map(Vec::len)));
+        // The first peak of the top level is the apex of (1, 8)
+        let apex = result.landscapes[0].iter().position(|p| (p.0 - 4.5).abs() < 1e-12).unwrap();
+        assert!(result.sources[0][apex] == fast_pl::persistencelandscape::Source { pair: 0, crossing: None });
+        // (1, 8) and (4, 9) cross at x = 6
+        let crossing = result.landscapes[0].iter().position(|p| (p.0 - 6.0).abs() < 1e-12).unwrap();
+        let source = result.sources[0][crossing];
+        assert!(source.crossing.is_some() && [source.pair, source.crossing.unwrap()].contains(&3));
+        assert!(result.sources.iter().flatten().all(|s| s.pair != 1));
+    }
+
+    #[test]
+    fn level_segments() {
+        let landscape = fast_pl::landscape::Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (1.0, 1.0), (3.0, -1.0)]]);
+        let segments: Vec<_> = landscape.segments(0).collect();
+        assert!(segments.len() == 2);
+        assert!(segments[1].start == (1.0, 1.0) && segments[1].end == (3.0, -1.0));
+        assert!((segments[1].slope() + 1.0).abs() < 1e-12);
+        assert!((segments[0].length() - 2.0_f64.sqrt()).abs() < 1e-12);
+        let line: geo::Line<f64> = segments[0].into();
+        assert!(line.end == geo::coord! { x: 1.0, y: 1.0 });
+        assert!(landscape.segments(3).next().is_none());
+    }
+
+    #[test]
+    fn finalize_levels() {
+        let raw = fast_pl::landscape::Landscape::new(vec![
+            vec![(1.0, 1.0), (1.0, 1.0), (2.0, 2.0), (2.0, 2.5), (3.0, 1.0)],
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        ]);
+        let finalized = raw.finalized(true);
+        assert!(finalized.levels()[0] == [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 1.0), (4.0, 0.0)]);
+        assert!(finalized.levels()[1] == raw.levels()[1]);
+        assert!(raw.finalized(false).levels()[0] == [(1.0, 1.0), (2.0, 2.0), (3.0, 1.0)]);
+    }
+
+    #[test]
+    fn landscape_builder() {
+        use fast_pl::builder::{InfinitePolicy, LandscapeBuilder};
+        let pairs = || vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 3.0, death: 3.0005 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: f64::INFINITY },
+        ];
+        let default = LandscapeBuilder::new().k(2).build(pairs()).unwrap();
+        assert!(default.levels() == fast_pl::persistencelandscape::generate(pairs(), 2, false));
+        let capped = LandscapeBuilder::new()
+            .k(2)
+            .infinite_policy(InfinitePolicy::Cap(10.0))
+            .min_persistence(1e-3)
+            .tolerance(1e-6)
+            .build(pairs())
+            .unwrap();
+        let expected = fast_pl::persistencelandscape::generate(vec![
+            fast_pl::birthdeath::BirthDeath { birth: 1.0, death: 8.0 },
+            fast_pl::birthdeath::BirthDeath { birth: 4.0, death: 10.0 },
+        ], 2, false);
+        assert!(capped.levels() == expected);
+        assert!(LandscapeBuilder::new().tolerance(-1.0).build(pairs()).is_err());
+        assert!(LandscapeBuilder::new().parallel(0).build(pairs()).is_err());
+        assert!(LandscapeBuilder::new().infinite_policy(InfinitePolicy::Cap(f64::NAN)).build(pairs()).is_err());
+    }
+
+    #[test]
+    fn prelude_imports() {
+        use fast_pl::prelude::*;
+        let landscape: Landscape = LandscapeBuilder::new()
+            .k(1)
+            .build(vec![BirthDeath { birth: 0.0, death: 2.0 }])
+            .unwrap();
+        let [(x0, y0), (x1, y1), (x2, y2)] = landscape[0] else {
+            panic!("Expected a single tent");
+        };
+        let expected = [0.0, 0.0, 1.0, 1.0, 2.0, 0.0];
+        assert!([x0, y0, x1, y1, x2, y2].iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(landscape.validate(0.0).is_ok());
+    }
+
+    #[test]
+    fn birthdeath_conversions() {
+        use fast_pl::birthdeath::{pairs, try_pairs, BirthDeath};
+        assert!(BirthDeath::new(2.0, 1.0).is_err());
+        assert!(BirthDeath::new(f64::NAN, 1.0).is_err());
+        let bd = BirthDeath::new(1.0, f64::INFINITY).unwrap();
+        assert!(bd.persistence().is_infinite());
+        let bd = BirthDeath::from([1.0_f32, 3.0]);
+        assert!((bd.persistence() - 2.0).abs() < 1e-12);
+        assert!((bd.midpoint() - 2.0).abs() < 1e-12);
+        let diagram = pairs([(0.0_f32, 1.0), (0.5, 2.0)]);
+        assert!(diagram.len() == 2);
+        assert!((diagram[1].death - 2.0).abs() < 1e-12);
+        assert!(try_pairs([(0.0, 1.0), (3.0, 2.0)]).is_err());
+        assert!(try_pairs([(0.0, 1.0), (2.0, 3.0)]).unwrap().len() == 2);
+    }
+
+    #[test]
+    fn delimiters() {
+        use fast_pl::io::Delimiter;
+        assert!(Delimiter::detect("0 1\n2,3\n") == Delimiter::Comma);
+        assert!(Delimiter::detect("0\t1\n") == Delimiter::Tab);
+        assert!(Delimiter::detect("0  1\n") == Delimiter::Whitespace);
+        let path = std::env::temp_dir().join(format!("fast_pl_delimiter_test_{}", std::process::id()));
+        for text in ["0,2\n1, 3\n", "0\t2\n1\t3\n", "0 2\n1   3\n"] {
+            std::fs::write(&path, text).unwrap();
+            let diagram = fast_pl::io::read_diagram(&path).unwrap();
+            let diagrams = fast_pl::io::read_diagrams(&path).unwrap();
+            assert!(diagram.len() == 2 && diagrams.len() == 1 && diagrams[0].len() == 2);
+            assert!((diagram[1].death - 3.0).abs() < 1e-12);
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!("1,2".parse::<fast_pl::birthdeath::BirthDeath>().is_ok());
+        assert!("1,x".parse::<fast_pl::birthdeath::BirthDeath>().is_err());
+    }
+
+    #[test]
+    fn header_and_comments() {
+        use fast_pl::io::{read_diagram, read_diagram_with, read_diagrams, Header};
+        let path = std::env::temp_dir().join(format!("fast_pl_header_test_{}", std::process::id()));
+        std::fs::write(&path, "# exported diagram\nbirth,death\n\n0,2 # first\n# note\n1,3\n").unwrap();
+        let diagram = read_diagram(&path).unwrap();
+        assert!(diagram.len() == 2 && (diagram[1].birth - 1.0).abs() < 1e-12);
+        assert!(read_diagrams(&path).unwrap().len() == 2);
+
+        std::fs::write(&path, "0 1\n1 3\n").unwrap();
+        assert!(read_diagram(&path).unwrap().len() == 2);
+        assert!(read_diagram_with(&path, Header::Present).unwrap().len() == 1);
+
+        std::fs::write(&path, "0 1\nbirth death\n").unwrap();
+        assert!(read_diagram(&path).is_err());
+        std::fs::write(&path, "0 nan\n").unwrap();
+        assert!(read_diagram(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn strict_pairs() {
+        use fast_pl::birthdeath::{pairs, Strictness, MAX_REPORTED_PAIRS};
+        use fast_pl::persistencelandscape::generate_checked;
+        let diagram = || pairs([(0.0, 2.0), (1.0, f64::INFINITY), (f64::NAN, 1.0), (f64::NEG_INFINITY, 3.0)]);
+        assert!(generate_checked(diagram(), 1, false, Strictness::Lenient).unwrap()[0].len() == 3);
+        let invalid = generate_checked(diagram(), 1, false, Strictness::Strict).unwrap_err();
+        assert!(invalid.count == 2 && invalid.pairs[0].row == 2 && invalid.pairs[1].row == 3);
+        assert!(invalid.to_string().contains("row 3"));
+
+        let many = pairs(vec![(f64::NAN, 0.0); MAX_REPORTED_PAIRS + 5]);
+        let invalid = fast_pl::birthdeath::check(&many, Strictness::Strict).unwrap_err();
+        assert!(invalid.pairs.len() == MAX_REPORTED_PAIRS && invalid.count == MAX_REPORTED_PAIRS + 5);
+        assert!(invalid.to_string().ends_with("and 5 more"));
+    }
+
+    #[test]
+    fn mmap_input() {
+        use fast_pl::io::{read_diagram_mmap, read_diagram_with, Header};
+        let path = std::env::temp_dir().join(format!("fast_pl_mmap_test_{}", std::process::id()));
+        let mut text = String::from("birth,death # exported\n");
+        for i in 0..200_000 {
+            text.push_str(&format!("{i},{}\n", i + 1 + i % 7));
+            if i % 1000 == 0 {
+                text.push_str("# comment\n\n3,inf\n");
+            }
+        }
+        std::fs::write(&path, &text).unwrap();
+        let mapped = read_diagram_mmap(&path, Header::Detect).unwrap();
+        let read = read_diagram_with(&path, Header::Detect).unwrap();
+        assert!(mapped.len() == 200_000 && mapped.len() == read.len());
+        assert!(mapped.iter().zip(&read).all(|(a, b)| a.birth.to_bits() == b.birth.to_bits()
+            && a.death.to_bits() == b.death.to_bits()));
+
+        text.push_str("1,x\n");
+        std::fs::write(&path, &text).unwrap();
+        let error = read_diagram_mmap(&path, Header::Detect).unwrap_err().to_string();
+        assert!(error.contains(&format!("line {}", text.lines().count())));
+        std::fs::write(&path, "").unwrap();
+        assert!(read_diagram_mmap(&path, Header::Detect).unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn streamed_csv() {
+        struct Flushes(Vec<u8>, usize);
+        impl std::io::Write for Flushes {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.1 += 1;
+                Ok(())
+            }
+        }
+        let landscapes = vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], vec![(0.5, 0.0), (1.0, 0.5), (1.5, 0.0)]];
+        let mut out = Flushes(Vec::new(), 0);
+        let options = fast_pl::io::WriteOptions { flush_every: Some(2), ..Default::default() };
+        fast_pl::io::write_csv_to(&mut out, &landscapes, &options).unwrap();
+        assert!(out.1 >= 4);
+        assert!(String::from_utf8(out.0).unwrap() == "0,0\n1,1\n2,0\n,\n0.5,0\n1,0.5\n1.5,0\n,\n");
+    }
+
+    #[test]
+    fn number_formats() {
+        use fast_pl::io::NumberFormat;
+        assert!(NumberFormat::Shortest.format(0.1 + 0.2) == "0.30000000000000004");
+        assert!(NumberFormat::Significant(3).format(0.1 + 0.2) == "0.3");
+        assert!(NumberFormat::Significant(3).format(-123_456.0) == "-123000");
+        assert!(NumberFormat::Significant(2).format(0.001_234) == "0.0012");
+        assert!(NumberFormat::Significant(2).format(0.0) == "0");
+        assert!(NumberFormat::Significant(2).format(f64::INFINITY) == "inf");
+    }
+
+    #[test]
+    fn binary_format() {
+        use fast_pl::binary::{load, read_landscape, save, write_landscape};
+        let batch = vec![
+            vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], vec![]],
+            vec![],
+            vec![vec![(-0.5, 0.0), (0.1 + 0.2, 0.8), (2.1, 0.0)]],
+        ];
+        let path = std::env::temp_dir().join(format!("fast_pl_binary_test_{}", std::process::id()));
+        save(&path, &batch).unwrap();
+        assert!(load(&path).unwrap() == batch);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut bytes = Vec::new();
+        write_landscape(&mut bytes, &batch[0]).unwrap();
+        assert!(bytes.len() == 16 + 2 * 8 + 3 * 16);
+        assert!(read_landscape(&mut &bytes[..bytes.len() - 1]).is_err());
+        bytes[4] = 9;
+        assert!(read_landscape(&mut &bytes[..]).is_err());
+        assert!(read_landscape(&mut &b"x,y\n"[..]).is_err());
+    }
+
+    #[test]
+    fn binary_delta() {
+        use fast_pl::binary::{read_landscape, write_landscape_with, Encoding};
+        let landscapes = fast_pl::persistencelandscape::generate(
+            fast_pl::synthetic::noisy_diagram(200, 5, 3), 3, false);
+        let delta = Encoding { delta: true, compression: None };
+        let mut bytes = Vec::new();
+        write_landscape_with(&mut bytes, &landscapes, delta).unwrap();
+        let read = read_landscape(&mut &bytes[..]).unwrap().unwrap();
+        assert!(read.iter().flatten().zip(landscapes.iter().flatten())
+            .all(|(a, b)| a.0.to_bits() == b.0.to_bits() && a.1.to_bits() == b.1.to_bits()));
+        #[cfg(not(feature = "zstd"))]
+        assert!(write_landscape_with(&mut Vec::new(), &landscapes, Encoding { delta: true, compression: Some(3) }).is_err());
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn python_arrays() {
+        use fast_pl::python::{feature_matrix, pairs};
+        use fast_pl::vectorize::{sample, Grid};
+        use numpy::ndarray::Array2;
+        // Births in the first row and deaths in the second, read through the transposed view
+        let columns = Array2::from_shape_vec((2, 3), vec![0.0, 1.0, 2.0, 4.0, 3.0, 6.0]).unwrap();
+        let diagram = pairs(columns.t()).unwrap();
+        assert!(diagram.iter().map(|bd| (bd.birth, bd.death)).eq([(0.0, 4.0), (1.0, 3.0), (2.0, 6.0)]));
+        assert!(pairs(columns.view()).is_err());
+
+        let grid = Grid::new(0.0, 6.0, 13).unwrap();
+        let diagrams = || vec![pairs(columns.t()).unwrap(), fast_pl::synthetic::noisy_diagram(50, 3, 1)];
+        let matrix = feature_matrix(diagrams(), 2, &grid);
+        assert!(matrix.dim() == (2, 26));
+        for (row, diagram) in matrix.rows().into_iter().zip(diagrams()) {
+            let expected = sample(&fast_pl::persistencelandscape::generate(diagram, 2, false), 2, &grid);
+            assert!(row.iter().eq(expected.iter()));
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn binary_compression() {
+        use fast_pl::binary::{read_landscape, write_landscape, write_landscape_with, Encoding};
+        let landscapes = fast_pl::persistencelandscape::generate(
+            fast_pl::synthetic::noisy_diagram(2000, 5, 3), 3, false);
+        let (mut plain, mut compressed) = (Vec::new(), Vec::new());
+        write_landscape(&mut plain, &landscapes).unwrap();
+        write_landscape_with(&mut compressed, &landscapes, Encoding { delta: true, compression: Some(3) }).unwrap();
+        assert!(compressed.len() < plain.len());
+        assert!(read_landscape(&mut &compressed[..]).unwrap().unwrap() == landscapes);
+    }
+
+    #[test]
+    fn distmat_command() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("fast_pl_distmat_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "0 2\n").unwrap();
+        std::fs::write(dir.join("b.txt"), "0 4\n").unwrap();
+        std::fs::write(dir.join("c.txt"), "0 2\n").unwrap();
+        let out = std::env::temp_dir().join(format!("fast_pl_distmat_out_{}.csv", std::process::id()));
+        let args = super::Args::parse_from(["fast_pl", "distmat", "--metric", "sup", "-o"]
+            .into_iter()
+            .map(std::ffi::OsString::from)
+            .chain([out.clone().into_os_string(), dir.clone().into_os_string()]));
+        let Some(super::Command::Distmat { inputs, k, metric, output, format }) = &args.command else {
+            panic!("Expected the distmat command");
+        };
+        super::distmat(inputs, *k, *metric, output, *format).unwrap();
+        let text = std::fs::read_to_string(&out).unwrap();
+        let rows: Vec<&str> = text.lines().collect();
+        assert!(rows.len() == 4 && rows[0].ends_with("c.txt"));
+        assert!(rows[1].ends_with(",0,2,0") && rows[2].ends_with(",2,0,2"));
+
+        let npy = out.with_extension("npy");
+        super::distmat(inputs, *k, *metric, &npy, None).unwrap();
+        let bytes = std::fs::read(&npy).unwrap();
+        assert!(bytes.starts_with(b"\x93NUMPY") && bytes.len() == 128 + 9 * 8);
+        let matrix = fast_pl::io::read_npy(&mut bytes.as_slice()).unwrap();
+        assert!(matrix.len() == 3 && matrix[1] == vec![2.0, 0.0, 2.0]);
+        assert!(fast_pl::io::read_npy(&mut &bytes[1..]).is_err());
+        assert!(std::fs::read_to_string(npy.with_extension("txt")).unwrap().lines().count() == 3);
+        let parquet = out.with_extension("parquet");
+        #[cfg(not(feature = "parquet"))]
+        assert!(super::distmat(inputs, *k, *metric, &parquet, None).is_err());
+        #[cfg(feature = "parquet")]
+        {
+            use parquet::file::reader::{FileReader, SerializedFileReader};
+            use parquet::record::RowAccessor;
+            super::distmat(inputs, *k, *metric, &parquet, None).unwrap();
+            let reader = SerializedFileReader::new(std::fs::File::open(&parquet).unwrap()).unwrap();
+            let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(Result::unwrap).collect();
+            assert!(rows.len() == 3 && rows[1].get_string(0).unwrap().ends_with("b.txt"));
+            assert!((0..3).map(|j| rows[1].get_double(j + 1).unwrap()).eq([2.0, 0.0, 2.0]));
+            std::fs::remove_file(&parquet).unwrap();
+        }
+        for path in [out, npy.clone(), npy.with_extension("txt")] {
+            std::fs::remove_file(path).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nearest_neighbors() {
+        use fast_pl::neighbors::{LandscapeIndex, Search};
+        let landscapes: Vec<_> = (0..40)
+            .map(|seed| fast_pl::persistencelandscape::generate(
+                fast_pl::synthetic::noisy_diagram(20, 2, seed), 2, false))
+            .collect();
+        let grid = fast_pl::vectorize::Grid::covering(&landscapes, 200).unwrap();
+        let exact = LandscapeIndex::new(&landscapes, 2, grid.clone(), Search::default()).unwrap();
+        assert!(exact.len() == 40);
+        let neighbors = exact.query(&landscapes[7], 5);
+        assert!(neighbors.len() == 5 && neighbors[0].index == 7 && neighbors[0].distance < 1e-12);
+        assert!(neighbors.windows(2).all(|w| w[0].distance <= w[1].distance));
+        let l2 = fast_pl::distance::l2_distance(&landscapes[7], &landscapes[neighbors[1].index]);
+        assert!((neighbors[1].distance - l2).abs() < 0.05 * l2);
+
+        let search = Search::Projected { dims: 8, candidates: 40, seed: 1 };
+        let projected = LandscapeIndex::new(&landscapes, 2, grid.clone(), search).unwrap();
+        assert!(projected.query(&landscapes[7], 5) == neighbors);
+        let search = Search::Projected { dims: 0, candidates: 40, seed: 1 };
+        assert!(LandscapeIndex::new(&landscapes, 2, grid, search).is_err());
+    }
+
+    #[test]
+    fn diff_command() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("fast_pl_diff_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        fast_pl::io::write_csv(dir.join("a.csv"), &[vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]]).unwrap();
+        fast_pl::io::write_csv(dir.join("b.csv"), &[vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)]]).unwrap();
+        let args = super::Args::parse_from(["fast_pl", "diff", "-o"]
+            .into_iter()
+            .map(std::ffi::OsString::from)
+            .chain([dir.join("d.csv"), dir.join("a.csv"), dir.join("b.csv")].map(std::path::PathBuf::into_os_string)));
+        let Some(super::Command::Diff { a, b, diagrams, output, plot, report }) = &args.command else {
+            panic!("Expected the diff command");
+        };
+        super::diff(a, b, *diagrams, output.as_ref(), plot.as_ref(), report.as_ref()).unwrap();
+        let difference = fast_pl::io::read_csv(dir.join("d.csv")).unwrap();
+        assert!(difference == fast_pl::distance::difference(
+            &fast_pl::io::read_csv(dir.join("a.csv")).unwrap(),
+            &fast_pl::io::read_csv(dir.join("b.csv")).unwrap()));
+        assert!(difference[0].iter().any(|&(x, y)| (x - 2.0).abs() < 1e-12 && (y + 2.0).abs() < 1e-12));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn environment_options() {
+        use clap::Parser;
+        // No other test depends on the grid resolution
+        std::env::set_var("FAST_PL_GRID_RESOLUTION", "7");
+        let from_env = super::Args::parse_from(["fast_pl", "-n", "x"]);
+        let from_flag = super::Args::parse_from(["fast_pl", "-n", "x", "--grid-resolution", "3"]);
+        std::env::remove_var("FAST_PL_GRID_RESOLUTION");
+        assert!(from_env.grid_resolution == 7 && from_flag.grid_resolution == 3);
+        assert!(super::Args::parse_from(["fast_pl", "-n", "x"]).grid_resolution == 100);
+    }
+
+    #[test]
+    fn failure_kinds() {
+        use super::Failure;
+        let path = std::env::temp_dir().join(format!("fast_pl_failure_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1\n1 x\n").unwrap();
+        let error = fast_pl::io::read_diagram(&path).unwrap_err();
+        assert!(Failure::of(error.as_ref()) == Failure::Parse);
+        let error = fast_pl::io::read_diagram_mmap(&path, fast_pl::io::Header::Detect).unwrap_err();
+        assert!(Failure::of(error.as_ref()) == Failure::Parse);
+        std::fs::remove_file(&path).unwrap();
+        let error = fast_pl::io::read_diagram(&path).unwrap_err();
+        assert!(Failure::of(error.as_ref()) == Failure::Io);
+
+        let limit = fast_pl::persistencelandscape::LimitExceeded::Events { limit: 1 };
+        assert!(Failure::of(&limit) == Failure::ResourceLimit);
+        let error: Box<dyn std::error::Error> = "Scale factor must be positive and finite".into();
+        assert!(Failure::of(error.as_ref()) == Failure::InvalidInput);
+        let codes = [Failure::Parse, Failure::InvalidInput, Failure::Io, Failure::ResourceLimit, Failure::Internal]
+            .map(Failure::code);
+        assert!(codes.iter().all(|&c| c > 2) && (1..codes.len()).all(|i| !codes[..i].contains(&codes[i])));
+    }
+
+    #[test]
+    fn report_schema() {
+        use fast_pl::report::{comparison_json, COMPARISON_SCHEMA, COMPARISON_TYPESCRIPT};
+        use fast_pl::statistics::{Comparison, ComparisonSettings, ConfidenceBand};
+        let band = || ConfidenceBand {
+            mean: vec![vec![(0.0, 0.0), (1.0, 1.0)]],
+            lower: vec![vec![(0.0, 0.0), (1.0, 0.5)]],
+            upper: vec![vec![(0.0, 0.0), (1.0, f64::INFINITY)]],
+        };
+        let comparison = Comparison {
+            band_a: band(),
+            band_b: band(),
+            distance: 0.5,
+            p_value: f64::NAN,
+            settings: ComparisonSettings::default(),
+        };
+        let json = comparison_json(&comparison, &["a.txt".to_string()], &["b.txt".to_string()]);
+        // Every key written is required by the schema and declared in TypeScript
+        let keys: Vec<&str> = json.split('"').skip(1).step_by(2).filter(|s| !["a.txt", "b.txt"].contains(s)).collect();
+        let required = |object: &str| {
+            let start = COMPARISON_SCHEMA.find(object).unwrap();
+            let list = &COMPARISON_SCHEMA[start..];
+            let list = &list[list.find("\"required\": [").unwrap() + 13..];
+            list[..list.find(']').unwrap()].split(", ").map(|k| k.trim_matches('"').to_string()).collect::<Vec<_>>()
+        };
+        let (top, group) = (required("\"title\""), required("\"group\": {"));
+        assert!(keys.iter().all(|k| top.iter().chain(&group).any(|r| r == k)));
+        assert!(top.iter().chain(&group).all(|r| keys.contains(&r.as_str())));
+        assert!(top.iter().chain(&group).all(|r| COMPARISON_TYPESCRIPT.contains(&format!("  {r}: "))));
+        assert!(json.contains("\"p_value\":null") && json.contains("[1,null]"));
+    }
+
+    #[test]
+    fn compare_command() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("fast_pl_compare_test_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a")).unwrap();
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        for i in 0..4 {
+            std::fs::write(dir.join("a").join(format!("{i}.txt")), format!("0 {}\n", f64::from(i).mul_add(0.1, 2.0))).unwrap();
+            std::fs::write(dir.join("b").join(format!("{i}.txt")), format!("0 {}\n", f64::from(i).mul_add(0.1, 4.0))).unwrap();
+        }
+        for (report, marker) in [("report.json", "\"p_value\":"), ("report.html", "<svg")] {
+            let args = super::Args::parse_from(["fast_pl", "compare", "--permutations", "50", "--bootstrap", "20"]
+                .into_iter()
+                .map(std::ffi::OsString::from)
+                .chain(["-a", "a", "-b", "b", "-o", report].iter().map(|arg| {
+                    if arg.starts_with('-') { arg.into() } else { dir.join(arg).into_os_string() }
+                })));
+            super::compare(args.command.as_ref().unwrap()).unwrap();
+            let text = std::fs::read_to_string(dir.join(report)).unwrap();
+            assert!(text.contains(marker) && text.contains("3.txt"));
+        }
+        let json = std::fs::read_to_string(dir.join("report.json")).unwrap();
+        let p_value: f64 = json.split("\"p_value\":").nth(1).unwrap().split(',').next().unwrap().parse().unwrap();
+        assert!(p_value < 0.1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn silhouette_powers() {
+        use fast_pl::silhouette::{silhouette, silhouettes};
+        let pairs = fast_pl::birthdeath::pairs([(0.0, 2.0), (1.0, 5.0), (1.5, 2.0), (3.0, f64::INFINITY)]);
+        let powers = [0.0, 1.0, 2.5];
+        let family = silhouettes(&pairs, &powers);
+        assert!(family.len() == 3);
+        for (curve, &p) in family.iter().zip(&powers) {
+            assert!(*curve == silhouette(&pairs, p));
+            let finite = &pairs[..3];
+            let total: f64 = finite.iter().map(|bd| bd.persistence().powf(p)).sum();
+            for x in [0.25, 1.0, 1.75, 2.0, 3.0, 4.5] {
+                let expected: f64 = finite
+                    .iter()
+                    .map(|bd| bd.persistence().powf(p) * (bd.persistence() / 2.0 - (x - bd.midpoint()).abs()).max(0.0))
+                    .sum::<f64>() / total;
+                assert!((fast_pl::persistencelandscape::evaluate(curve, x) - expected).abs() < 1e-12);
+            }
+        }
+        assert!(silhouette(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn triangle_profiles() {
+        use fast_pl::persistencelandscape::{evaluate, generate, generate_with_profile, Profile};
+        let diagram = || fast_pl::birthdeath::pairs([(0.0, 4.0), (1.0, 3.0), (1.5, 6.5), (2.5, 3.75), (5.0, 9.0)]);
+        let tents = generate(diagram(), 3, false);
+        let scaled = generate_with_profile(diagram(), 3, false, Profile::Triangle { rise: 2.0, fall: 2.0 }).unwrap();
+        for x in [0.5, 1.25, 2.0, 2.75, 3.5, 5.5, 7.0] {
+            for (tent, triangle) in tents.iter().zip(&scaled) {
+                assert!((evaluate(tent, x) - evaluate(triangle, x) / 2.0).abs() < 1e-12);
+            }
+        }
+
+        let (rise, fall) = (3.0, 0.5);
+        let landscape = generate_with_profile(diagram(), 3, false, Profile::Triangle { rise, fall }).unwrap();
+        for i in 0..=90 {
+            let x = f64::from(i) / 10.0;
+            let mut values: Vec<f64> = diagram()
+                .iter()
+                .map(|bd| (rise * (x - bd.birth)).min(fall * (bd.death - x)).max(0.0))
+                .collect();
+            values.sort_by(|a, b| b.total_cmp(a));
+            for (level, expected) in landscape.iter().zip(values) {
+                assert!((evaluate(level, x) - expected).abs() < 1e-9);
+            }
+        }
+        assert!(generate_with_profile(diagram(), 1, false, Profile::Triangle { rise: 0.0, fall: 1.0 }).is_err());
+    }
+
+    #[test]
+    fn weighted_pairs() {
+        use fast_pl::persistencelandscape::{evaluate, generate};
+        use fast_pl::weighted::generate_weighted;
+        let pairs = || fast_pl::birthdeath::pairs([(0.0, 4.0), (1.0, 3.0), (1.5, 6.5), (2.5, 3.75), (5.0, 9.0)]);
+        let diagram = pairs();
+        let weights = [1.0, 3.0, 0.5, 2.0, 1.5];
+        let landscape = generate_weighted(&diagram, &weights, 0).unwrap();
+        for i in 0..=90 {
+            let x = f64::from(i) / 10.0;
+            let mut values: Vec<f64> = diagram
+                .iter()
+                .zip(weights)
+                .map(|(bd, w)| (w * (x - bd.birth)).min(w * (bd.death - x)).max(0.0))
+                .collect();
+            values.sort_by(|a, b| b.total_cmp(a));
+            for (level, expected) in landscape.iter().zip(values) {
+                assert!((evaluate(level, x) - expected).abs() < 1e-9);
+            }
+        }
+        assert_eq!(
+            generate_weighted(&diagram[..2], &[1.0, 3.0], 1).unwrap()[0],
+            vec![(0.0, 0.0), (1.5, 1.5), (2.0, 3.0), (2.5, 1.5), (4.0, 0.0)]
+        );
+
+        let tents = generate(pairs(), 3, false);
+        let unweighted = generate_weighted(&diagram, &[1.0; 5], 3).unwrap();
+        for x in [0.5, 1.25, 2.0, 2.75, 3.5, 5.5, 7.0] {
+            for (tent, level) in tents.iter().zip(&unweighted) {
+                assert!((evaluate(tent, x) - evaluate(level, x)).abs() < 1e-12);
+            }
+        }
+        assert!(generate_weighted(&diagram, &[1.0], 1).is_err());
+        assert!(generate_weighted(&diagram, &[1.0, -1.0, 1.0, 1.0, 1.0], 1).is_err());
+
+        let path = std::env::temp_dir().join(format!("fast_pl_weighted_test_{}", std::process::id()));
+        std::fs::write(&path, "0 4 1\n1 3 3\n2 5\n").unwrap();
+        let (pairs, weights) = fast_pl::io::read_weighted_diagram(&path, fast_pl::io::Header::Detect).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(weights, vec![1.0, 3.0, 1.0]);
+    }
+
+    #[test]
+    fn betti_queries() {
+        use fast_pl::birthdeath::{betti_at, pairs, rank_function};
+        let diagram = pairs([(0.0, 4.0), (1.0, 3.0), (2.0, f64::INFINITY), (3.0, 5.0)]);
+        assert_eq!(betti_at(&diagram, 1.0, 3.0), 2);
+        assert_eq!(betti_at(&diagram, 2.0, 3.0), 3);
+        assert_eq!(betti_at(&diagram, 3.0, 4.0), 3);
+        assert_eq!(betti_at(&diagram, 3.0, 6.0), 1);
+        assert_eq!(betti_at(&diagram, 4.0, 3.0), 0);
+
+        let grid = fast_pl::vectorize::Grid::new(0.0, 5.0, 11).unwrap();
+        let rank = rank_function(&diagram, &grid);
+        let points = grid.points();
+        for (i, row) in rank.iter().enumerate() {
+            for (j, &count) in row.iter().enumerate() {
+                assert_eq!(count, betti_at(&diagram, points[i], points[j]));
+            }
+        }
+    }
+
+    #[test]
+    fn stability_bounds() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::persistencelandscape::{evaluate, generate};
+        use fast_pl::stability::{bottleneck, landscape_bound, perturbation_bounds};
+        let a = || pairs([(0.0, 4.0), (1.0, 3.0), (2.0, 2.5), (5.0, 9.0)]);
+        let b = || pairs([(0.25, 4.5), (1.0, 2.75), (5.5, 9.0)]);
+        assert!((bottleneck(&a(), &b()) - 0.5).abs() < 1e-12);
+        assert!(bottleneck(&a(), &a()).abs() < 1e-12);
+        assert!((bottleneck(&a(), &[]) - 2.0).abs() < 1e-12);
+        assert!(bottleneck(&pairs([(0.0, f64::INFINITY)]), &[]).is_infinite());
+
+        let bound = landscape_bound(&a(), &b());
+        let (la, lb) = (generate(a(), 3, false), generate(b(), 3, false));
+        for i in 0..=100 {
+            let x = f64::from(i) / 10.0;
+            for (level_a, level_b) in la.iter().zip(&lb) {
+                assert!((evaluate(level_a, x) - evaluate(level_b, x)).abs() <= bound + 1e-12);
+            }
+        }
+
+        let bounds = perturbation_bounds(&a(), 0.5, 4).unwrap();
+        assert!((bounds[0].l1 - 5.0).abs() < 1e-12);
+        assert!((bounds[1].l1 - 1.5).abs() < 1e-12);
+        assert!((bounds[2].l1 - 0.75).abs() < 1e-12);
+        assert!(bounds[3].sup.abs() < 1e-12 && bounds[3].l2.abs() < 1e-12);
+        assert!(perturbation_bounds(&a(), -1.0, 1).is_err());
+    }
+
+    #[test]
+    fn degeneracy_warnings() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::persistencelandscape::{generate_report, Near};
+        let report = generate_report(pairs([(0.0, 4.0), (1.0, 5.0)]), 2, false);
+        assert!(report.degeneracies.is_empty());
+
+        // The falling side of the first pair crosses the second exactly at its peak
+        let report = generate_report(pairs([(f64::NAN, 1.0), (0.0, 4.0), (2.0, 4.0)]), 2, false);
+        assert!(!report.degeneracies.is_empty());
+        let degeneracy = report.degeneracies[0];
+        assert!(degeneracy.near == Near::Endpoint);
+        assert!((degeneracy.point.0 - 3.0).abs() < 1e-12 && (degeneracy.point.1 - 1.0).abs() < 1e-12);
+        assert!(degeneracy.pairs == (1, 2) || degeneracy.pairs == (2, 1));
+    }
+
+    #[test]
+    fn weighted_dense_diagram() {
+        use fast_pl::persistencelandscape::evaluate;
+        let diagram = fast_pl::synthetic::noisy_diagram(300, 20, 7);
+        let weights: Vec<f64> = (0..diagram.len()).map(|i| [0.5, 1.0, 1.5, 2.0][i % 4]).collect();
+        let landscape = fast_pl::weighted::generate_weighted(&diagram, &weights, 5).unwrap();
+        let x_max = diagram.iter().map(|bd| bd.death).fold(0.0, f64::max);
+        for i in 0..=500 {
+            let x = f64::from(i) / 500.0 * x_max;
+            let mut values: Vec<f64> = diagram
+                .iter()
+                .zip(&weights)
+                .map(|(bd, w)| (w * (x - bd.birth)).min(w * (bd.death - x)).max(0.0))
+                .collect();
+            values.sort_by(|a, b| b.total_cmp(a));
+            for (level, expected) in landscape.iter().zip(values) {
+                assert!((evaluate(level, x) - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn bucketed_events() {
+        use fast_pl::persistencelandscape::{generate, generate_bucketed, Buckets};
+        let diagram = || fast_pl::synthetic::noisy_diagram(500, 10, 3);
+        let x_max = diagram().iter().map(|bd| bd.death).fold(0.0, f64::max);
+        let expected = generate(diagram(), 4, false);
+        for buckets in [
+            Buckets { x_min: 0.0, x_max, count: 64 },
+            Buckets { x_min: 0.0, x_max: x_max / 4.0, count: 7 },
+            Buckets { x_min: 0.0, x_max, count: 1 },
+        ] {
+            assert!(generate_bucketed(diagram(), 4, false, &buckets).unwrap() == expected);
+        }
+        assert!(generate_bucketed(diagram(), 4, false, &Buckets { x_min: 1.0, x_max: 1.0, count: 4 }).is_err());
+        assert!(generate_bucketed(diagram(), 4, false, &Buckets { x_min: 0.0, x_max, count: 0 }).is_err());
+    }
+
+    #[test]
+    fn bucketed_shared_births() {
+        use fast_pl::persistencelandscape::{evaluate, generate, generate_bucketed, Buckets};
+        // Births at eleven x values, each shared by tents of seven heights
+        let diagram = || {
+            fast_pl::birthdeath::pairs(
+                (1..=200_u32).map(|i| (f64::from(i % 11), f64::from(i % 11 + 2 * (1 + i % 7)))),
+            )
+        };
+        let expected = generate(diagram(), 0, false);
+        for count in [1, 3, 16] {
+            let buckets = Buckets { x_min: 0.0, x_max: 28.0, count };
+            let landscape = generate_bucketed(diagram(), 0, false, &buckets).unwrap();
+            assert!(landscape.len() == expected.len());
+            for i in 0..=280 {
+                let x = f64::from(i) / 10.0;
+                for (level, expected) in landscape.iter().zip(&expected) {
+                    assert!((evaluate(level, x) - evaluate(expected, x)).abs() < 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tied_deaths() {
+        use fast_pl::persistencelandscape::{evaluate, generate};
+        let check = |pairs: &[(f64, f64)]| {
+            let landscape = generate(fast_pl::birthdeath::pairs(pairs.to_vec()), 0, false);
+            for i in 0..=120 {
+                let x = f64::from(i) / 10.0;
+                let mut values: Vec<f64> = pairs.iter().map(|p| (x - p.0).min(p.1 - x).max(0.0)).collect();
+                values.sort_by(|a, b| b.total_cmp(a));
+                for (j, expected) in values.iter().enumerate() {
+                    let got = landscape.get(j).map_or(0.0, |level| evaluate(level, x));
+                    assert!((got - expected).abs() < 1e-9);
+                }
+            }
+        };
+        // Two mountains die at 5 in an order rounding does not settle
+        check(&[(5.0, 10.0), (2.0, 5.0), (0.0, 5.0), (3.0, 7.0)]);
+        check(&[(0.0, 4.0), (1.0, 4.0), (2.0, 4.0), (2.0, 6.0), (4.0, 8.0), (3.0, 4.0)]);
+    }
+
+    #[test]
+    fn tied_event_heights() {
+        use fast_pl::persistencelandscape::{evaluate, generate};
+        let check = |pairs: &[(f64, f64)]| {
+            let landscape = generate(fast_pl::birthdeath::pairs(pairs.to_vec()), 0, false);
+            for i in 0..=80 {
+                let x = f64::from(i) / 10.0;
+                let mut values: Vec<f64> = pairs.iter().map(|p| (x - p.0).min(p.1 - x).max(0.0)).collect();
+                values.sort_by(|a, b| b.total_cmp(a));
+                for (j, expected) in values.iter().enumerate() {
+                    let got = landscape.get(j).map_or(0.0, |level| evaluate(level, x));
+                    assert!((got - expected).abs() < 1e-9);
+                }
+            }
+        };
+        // Births, peaks of different heights and a death all at x = 2
+        check(&[(0.0, 4.0), (1.0, 3.0), (1.5, 2.5), (2.0, 6.0), (2.0, 5.0), (0.0, 2.0)]);
+        check(&[(2.0, 3.0), (1.0, 3.0), (0.0, 4.0), (2.0, 4.0), (1.0, 2.0), (0.5, 3.5), (2.0, 7.0)]);
+    }
+
+    #[test]
+    fn verbosity_levels() {
+        use super::Verbosity;
+        use clap::Parser;
+        let verbosity = |flags: &[&str]| {
+            let args = ["fast_pl", "-n", "x"].iter().chain(flags);
+            super::Args::try_parse_from(args).map(|args| args.verbosity())
+        };
+        assert!(verbosity(&["-q"]).unwrap() == Verbosity::Quiet);
+        assert!(verbosity(&[]).unwrap() == Verbosity::Summary);
+        assert!(verbosity(&["-v"]).unwrap() == Verbosity::Progress);
+        assert!(verbosity(&["-vv"]).unwrap() == Verbosity::Trace);
+        assert!(verbosity(&["-v", "-v", "-v"]).unwrap() == Verbosity::Trace);
+        assert!(verbosity(&["--debug"]).unwrap() == Verbosity::Trace);
+        assert!(verbosity(&["-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn manifest_average() {
+        let dir = std::env::temp_dir().join(format!("fast_pl_average_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "0 2\n").unwrap();
+        std::fs::write(dir.join("b.txt"), "0 4\n").unwrap();
+        std::fs::write(dir.join("manifest.csv"), "path,weight,group\na.txt,1,x\nb.txt,3,x\na.txt,2,y\n").unwrap();
+        let entries = fast_pl::io::read_manifest(dir.join("manifest.csv")).unwrap();
+        assert!(entries.len() == 3 && entries[1].path == dir.join("b.txt") && entries[2].group == "y");
+
+        let output = dir.join("mean.csv");
+        let format = fast_pl::io::NumberFormat::Shortest;
+        super::average(&[], Some(&dir.join("manifest.csv")), 1, &output, format).unwrap();
+        let x = fast_pl::landscape::Landscape::new(fast_pl::io::read_csv(dir.join("mean_x.csv")).unwrap());
+        let y = fast_pl::landscape::Landscape::new(fast_pl::io::read_csv(dir.join("mean_y.csv")).unwrap());
+        assert!((x.evaluate(0, 1.0) - 1.0).abs() < 1e-12 && (x.evaluate(0, 2.0) - 1.5).abs() < 1e-12);
+        assert!((y.evaluate(0, 1.0) - 1.0).abs() < 1e-12 && y.evaluate(0, 2.0).abs() < 1e-12);
+        assert!(!output.exists());
+
+        super::average(&[dir.join("a.txt"), dir.join("b.txt")], None, 1, &output, format).unwrap();
+        let mean = fast_pl::landscape::Landscape::new(fast_pl::io::read_csv(&output).unwrap());
+        assert!((mean.evaluate(0, 2.0) - 1.0).abs() < 1e-12);
+        std::fs::write(dir.join("manifest.csv"), "a.txt 1 x\nb.txt heavy x\n").unwrap();
+        assert!(fast_pl::io::read_manifest(dir.join("manifest.csv")).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn norm_table() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("fast_pl_norms_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("diagrams.txt"), "0 2\n\n0 4\n1 3\n").unwrap();
+        for name in ["norms.csv", "norms.json"] {
+            let args = super::Args::parse_from(["fast_pl", "-q", "-k", "2", "-n"]
+                .into_iter()
+                .map(std::ffi::OsString::from)
+                .chain([dir.join("diagrams.txt").into_os_string(), "--norms".into(), dir.join(name).into_os_string()]));
+            super::run(&args).unwrap();
+        }
+        let rows: Vec<Vec<String>> = std::fs::read_to_string(dir.join("norms.csv"))
+            .unwrap()
+            .lines()
+            .map(|line| line.split(',').map(String::from).collect())
+            .collect();
+        assert!(rows.len() == 3 && rows[0].len() == 9 && rows[0][5] == "l1_1");
+        let value = |row: usize, column: usize| rows[row][column].parse::<f64>().unwrap();
+        // The tent of (0, 2) has area 1, height 1 and squared integral 2/3
+        assert!((value(1, 1) - 1.0).abs() < 1e-12 && (value(1, 2) - (2.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert!((value(1, 3) - 1.0).abs() < 1e-12 && (value(1, 4) - 1.0).abs() < 1e-12);
+        assert!(value(1, 5).abs() < 1e-12);
+        assert!((value(2, 1) - 4.0).abs() < 1e-12 && (value(2, 5) - 1.0).abs() < 1e-12 && (value(2, 7) - 1.0).abs() < 1e-12);
+        let json = std::fs::read_to_string(dir.join("norms.json")).unwrap();
+        assert!(json.starts_with("[{\"diagram\":0,\"levels\":[{\"l1\":1,"));
+        assert!(json.matches("\"diagram\"").count() == 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn metric_selection() {
+        use fast_pl::distance::Metric;
+        let landscape = |pairs: &[(f64, f64)]| {
+            fast_pl::persistencelandscape::generate(pairs.iter().map(|&p| p.into()).collect(), 2, false)
+        };
+        let a = landscape(&[(0.0, 4.0), (1.0, 3.0)]);
+        let b = landscape(&[(0.5, 5.0), (3.0, 4.0)]);
+        for (exact, general) in [(Metric::L1, 1.0), (Metric::L2, 2.0), (Metric::Sup, f64::INFINITY)] {
+            let d = exact.evaluate(&a, &b);
+            assert!((fast_pl::distance::lp_distance(&a, &b, general) - d).abs() < 1e-9);
+            let sampled = Metric::Sampled { p: general, resolution: 100_001 }.evaluate(&a, &b);
+            assert!((sampled - d).abs() < 1e-3);
+        }
+        let l1 = Metric::L1.evaluate(&a, &b);
+        let l3 = Metric::Lp(3.0).evaluate(&a, &b);
+        assert!(l3 > 0.0 && l3 < l1 && l3 > Metric::Sup.evaluate(&a, &b) - 1.0);
+
+        for (text, metric) in [
+            ("l1", Metric::L1),
+            ("L2", Metric::L2),
+            ("linf", Metric::Sup),
+            ("l3", Metric::Lp(3.0)),
+            ("l2@500", Metric::Sampled { p: 2.0, resolution: 500 }),
+            ("sup@64", Metric::Sampled { p: f64::INFINITY, resolution: 64 }),
+        ] {
+            let parsed: Metric = text.parse().unwrap();
+            assert!(parsed == metric && parsed.to_string().parse::<Metric>().unwrap() == metric);
+        }
+        for invalid in ["l0.5", "lx", "l2@1", "cosine"] {
+            assert!(invalid.parse::<Metric>().is_err());
+        }
+        let matrix = Metric::Lp(3.0).matrix(&[a, b]);
+        assert!((matrix[0][1] - l3).abs() < 1e-12 && (matrix[1][0] - l3).abs() < 1e-12 && matrix[0][0].abs() < 1e-12);
+    }
+
+    #[test]
+    fn diagram_distances() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::diagramdistance::{bottleneck, wasserstein};
+        let a = || pairs([(0.0, 2.0), (1.0, 5.0), (3.0, f64::INFINITY)]);
+        let b = || pairs([(0.0, 3.0), (1.5, 5.0), (2.0, 2.5), (4.0, f64::INFINITY)]);
+        // (0, 2) and (0, 3) are 1 apart, (1, 5) and (1.5, 5) 0.5, (2, 2.5) is 0.25 from the
+        // diagonal and the essential pairs are 1 apart
+        assert!((wasserstein(&a(), &b(), 1.0).unwrap() - 2.75).abs() < 1e-12);
+        let w2: f64 = 1.0 + 0.25 + 0.0625 + 1.0;
+        assert!((wasserstein(&a(), &b(), 2.0).unwrap() - w2.sqrt()).abs() < 1e-12);
+        assert!((wasserstein(&a(), &b(), f64::INFINITY).unwrap() - bottleneck(&a(), &b())).abs() < 1e-12);
+        assert!(wasserstein(&a(), &a(), 1.0).unwrap().abs() < 1e-12);
+        // Matching both short pairs to the diagonal is cheaper than to each other
+        let short = pairs([(0.0, 0.2)]);
+        assert!((wasserstein(&short, &pairs([(10.0, 10.4)]), 1.0).unwrap() - 0.3).abs() < 1e-12);
+        assert!(wasserstein(&a(), &pairs([(0.0, 1.0)]), 1.0).unwrap().is_infinite());
+        assert!(wasserstein(&a(), &b(), 0.5).is_err());
+
+        // Against every matching of small random diagrams
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(7);
+        let mut random = |n: usize| {
+            pairs((0..n).map(|_| {
+                let birth = rand::Rng::gen_range(&mut rng, 0.0..5.0);
+                (birth, birth + rand::Rng::gen_range(&mut rng, 0.0..3.0))
+            }))
+        };
+        for _ in 0..20 {
+            let (first, second) = (random(3), random(2));
+            let cost = |i: usize, j: usize| match (first.get(i), second.get(j)) {
+                (Some(left), Some(right)) => (left.birth - right.birth).abs().max((left.death - right.death).abs()),
+                (Some(left), None) => (left.death - left.birth) / 2.0,
+                (None, Some(right)) => (right.death - right.birth) / 2.0,
+                (None, None) => 0.0,
+            };
+            let mut best = f64::INFINITY;
+            let mut order: Vec<usize> = (0..5).collect();
+            // Heap's algorithm over all 120 permutations
+            let mut counters = [0; 5];
+            let total = |order: &[usize]| (0..5).map(|i| cost(i, order[i])).sum::<f64>();
+            best = best.min(total(&order));
+            let mut i = 0;
+            while i < 5 {
+                if counters[i] < i {
+                    order.swap(if i % 2 == 0 { 0 } else { counters[i] }, i);
+                    best = best.min(total(&order));
+                    counters[i] += 1;
+                    i = 0;
+                } else {
+                    counters[i] = 0;
+                    i += 1;
+                }
+            }
+            assert!((wasserstein(&first, &second, 1.0).unwrap() - best).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn diagram_pipeline() {
+        use clap::Parser;
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::pipeline::{DiagramPipeline, Step};
+        let diagram = || pairs([(3.0, 4.0), (0.0, f64::INFINITY), (1.0, 1.25), (0.0, 2.0)]);
+        let pipeline = DiagramPipeline::new()
+            .cap_infinite(10.0)
+            .scale(2.0, 1.0)
+            .min_persistence(1.0)
+            .canonical()
+            .top_pairs(2);
+        let kept: Vec<(f64, f64)> = pipeline.apply(diagram()).unwrap().iter().map(|bd| (bd.birth, bd.death)).collect();
+        assert!(kept == vec![(1.0, 5.0), (1.0, 21.0)]);
+
+        let spec = "cap-infinite=10,scale=2:1,min-persistence=1,canonical,top=2";
+        let parsed: DiagramPipeline = spec.parse().unwrap();
+        assert!(parsed == pipeline && parsed.to_string() == spec);
+        assert!("shift=-1,weighted-subsample=5:3,strict".parse::<DiagramPipeline>().unwrap().steps()
+            == [Step::Scale { factor: 1.0, shift: -1.0 }, Step::Subsample { n: 5, weighted: true, seed: 3 }, Step::Strict]);
+        for invalid in ["scale=0", "top=x", "dimension=1", "min-persistence=-1"] {
+            assert!(invalid.parse::<DiagramPipeline>().is_err());
+        }
+        assert!(DiagramPipeline::new().strict().apply(diagram()).is_ok());
+        assert!(DiagramPipeline::new().strict().apply(pairs([(f64::NAN, 1.0)])).is_err());
+
+        let (kept, weights) = DiagramPipeline::new()
+            .min_persistence(1.0)
+            .canonical()
+            .apply_weighted(diagram(), vec![1.0, 2.0, 3.0, 4.0])
+            .unwrap();
+        assert!(kept.len() == 3 && weights == vec![4.0, 2.0, 1.0] && (kept[0].death - 2.0).abs() < 1e-12);
+        assert!(DiagramPipeline::new().top_pairs(1).apply_weighted(diagram(), vec![1.0; 4]).is_err());
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "--pipeline", "scale=2,top=1"]);
+        assert!(super::pipeline(&args).steps().len() == 2);
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--pipeline", "top=1", "--top-pairs", "1"]).is_err());
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "--min-persistence", "0.5"]);
+        assert!(super::pipeline(&args).steps()[1] == Step::MinPersistence(0.5));
+    }
+
+    #[cfg(feature = "rips")]
+    #[test]
+    fn rips_persistence() {
+        use fast_pl::rips::{check_distances, euclidean_distances, landscape, persistence};
+        // Unit square with a loop born at 1 that is filled in by the diagonals
+        let square = euclidean_distances(&[vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0], vec![0.0, 1.0]]).unwrap();
+        let diagrams = persistence(&square, 1, f64::INFINITY).unwrap();
+        assert!(diagrams[0].len() == 4 && diagrams[0].iter().filter(|bd| bd.death.is_infinite()).count() == 1);
+        assert!(diagrams[0].iter().filter(|bd| bd.death.is_finite()).all(|bd| (bd.death - 1.0).abs() < 1e-12));
+        assert!(diagrams[1].len() == 1);
+        assert!((diagrams[1][0].birth - 1.0).abs() < 1e-12 && (diagrams[1][0].death - 2_f64.sqrt()).abs() < 1e-12);
+
+        // Below the diagonals the loop never dies, and without edges every point is a component
+        let capped = persistence(&square, 1, 1.2).unwrap();
+        assert!(capped[1].len() == 1 && capped[1][0].death.is_infinite());
+        assert!(persistence(&square, 0, 0.5).unwrap()[0].len() == 4);
+
+        let levels = landscape(&square, 1, 1, f64::INFINITY).unwrap();
+        let peak = levels[0].iter().map(|p| p.1).fold(0.0, f64::max);
+        assert!((peak - (2_f64.sqrt() - 1.0) / 2.0).abs() < 1e-12);
+
+        assert!(check_distances(&[vec![0.0, 1.0], vec![2.0, 0.0]]).is_err());
+        assert!(check_distances(&[vec![0.0, 1.0]]).is_err());
+        assert!(euclidean_distances(&[vec![0.0], vec![0.0, 1.0]]).is_err());
+        assert!(persistence(&square, 2, f64::INFINITY).is_err());
+    }
+
+    #[cfg(feature = "rips")]
+    #[test]
+    fn distance_matrix_input() {
+        use clap::Parser;
+        use fast_pl::rips::{euclidean_distances, landscape, landscapes};
+        let points: Vec<Vec<f64>> = (0..6)
+            .map(|i| f64::from(i) * std::f64::consts::PI / 3.0)
+            .map(|angle| vec![angle.cos(), angle.sin()])
+            .collect();
+        let distances = euclidean_distances(&points).unwrap();
+        let path = std::env::temp_dir().join(format!("fast_pl_distmat_{}.csv", std::process::id()));
+        let labels: Vec<String> = (0..6).map(|i| format!("p{i}")).collect();
+        fast_pl::io::write_matrix_csv(
+            std::fs::File::create(&path).unwrap(), &labels, &distances, fast_pl::io::NumberFormat::Shortest).unwrap();
+        let read = fast_pl::io::read_matrix_csv(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(read == distances);
+
+        let all = landscapes(&read, 1, 2, f64::INFINITY).unwrap();
+        assert!(all.len() == 2 && all[1] == landscape(&read, 1, 2, f64::INFINITY).unwrap());
+        // The hexagon has a single loop, so the second level of dimension one is flat
+        assert!(!all[1][0].is_empty() && all[1][1].iter().all(|p| p.1.abs() < 1e-12));
+        assert!(super::Args::try_parse_from(["fast_pl", "from-distmat", "m.csv", "--maxdim", "1", "-k", "5"]).is_ok());
+    }
+
+    #[test]
+    fn time_series_persistence() {
+        use fast_pl::sublevel::{time_series, time_series_landscape};
+        // Minima at 0, 1 and 2 separated by maxima at 4 and 3
+        let series = [0.0, 4.0, 1.0, 3.0, 2.0, 5.0];
+        let mut diagram: Vec<(f64, f64)> = time_series(&series).unwrap().iter().map(|bd| (bd.birth, bd.death)).collect();
+        diagram.sort_by(|a, b| a.0.total_cmp(&b.0));
+        assert!(diagram == vec![(0.0, f64::INFINITY), (1.0, 4.0), (2.0, 3.0)]);
+        assert!(time_series(&[1.0, 1.0, 1.0]).unwrap().len() == 1);
+        assert!(time_series(&[]).unwrap().is_empty());
+        assert!(time_series(&[0.0, f64::NAN]).is_err());
+
+        let levels = time_series_landscape(&series, 2).unwrap();
+        let peak = |level: &Vec<(f64, f64)>| level.iter().map(|p| p.1).fold(0.0, f64::max);
+        assert!((peak(&levels[0]) - 1.5).abs() < 1e-12 && (peak(&levels[1]) - 0.5).abs() < 1e-12);
+
+        let path = std::env::temp_dir().join(format!("fast_pl_series_{}.csv", std::process::id()));
+        std::fs::write(&path, "t,value\n0,0\n1,4\n2,1\n3,3\n4,2\n5,5\n").unwrap();
+        let out = std::env::temp_dir().join(format!("fast_pl_series_out_{}.csv", std::process::id()));
+        super::time_series(&path, 1, 2, Some(&out), fast_pl::io::NumberFormat::Shortest).unwrap();
+        assert!(fast_pl::io::read_csv(&out).unwrap() == levels);
+        assert!(super::time_series(&path, 2, 2, None, fast_pl::io::NumberFormat::Shortest).is_err());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&out).unwrap();
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_persistence() {
+        use fast_pl::cubical::{landscapes, persistence};
+        let as_pairs = |diagram: &[fast_pl::birthdeath::BirthDeath]| -> Vec<(f64, f64)> {
+            diagram.iter().map(|bd| (bd.birth, bd.death)).collect()
+        };
+        // A dark ring around a bright center with a darker dip in the top left corner
+        let image = vec![vec![1.0, 2.0, 2.0], vec![2.0, 9.0, 2.0], vec![2.0, 2.0, 2.0]];
+        let [dimension_0, dimension_1] = persistence(&image).unwrap();
+        assert!(as_pairs(&dimension_0) == vec![(1.0, f64::INFINITY)]);
+        assert!(as_pairs(&dimension_1) == vec![(2.0, 9.0)]);
+        // Diagonal bright pixels do not enclose anything, the dark ones only touch at a corner
+        let [dimension_0, dimension_1] = persistence(&[vec![0.0, 5.0], vec![5.0, 0.0]]).unwrap();
+        assert!(dimension_0.len() == 2 && dimension_1.is_empty());
+        assert!(persistence(&[vec![0.0], vec![0.0, 1.0]]).is_err());
+
+        let path = std::env::temp_dir().join(format!("fast_pl_image_{}.png", std::process::id()));
+        let mut encoder = png::Encoder::new(std::fs::File::create(&path).unwrap(), 3, 3);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[1, 2, 2, 2, 9, 2, 2, 2, 2]).unwrap();
+        let pixels = fast_pl::io::read_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(pixels == image);
+        let [_, loops] = landscapes(&pixels, 1).unwrap();
+        assert!((loops[0].iter().map(|p| p.1).fold(0.0, f64::max) - 3.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn diagram_sources() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::prelude::*;
+        use fast_pl::source::{landscapes, DiagramFile, DiagramFiles};
+
+        /// Stand-in for a format the crate does not know, one diagram per line of `b:d` pairs
+        struct Colons(&'static str);
+        impl DiagramSource for Colons {
+            fn diagrams(&mut self) -> Result<impl Iterator<Item = Diagram>, Box<dyn std::error::Error>> {
+                self.0
+                    .lines()
+                    .map(|line| {
+                        line.split_whitespace()
+                            .map(|pair| {
+                                let (birth, death) = pair.split_once(':').ok_or("Expected birth:death")?;
+                                Ok(BirthDeath { birth: birth.parse()?, death: death.parse()? })
+                            })
+                            .collect::<Result<Diagram, Box<dyn std::error::Error>>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Vec::into_iter)
+            }
+        }
+        let custom = landscapes(&mut Colons("0:2 1:3\n0:4"), 1).unwrap();
+        let mut memory = vec![pairs([(0.0, 2.0), (1.0, 3.0)]), pairs([(0.0, 4.0)])];
+        assert!(custom == landscapes(&mut memory, 1).unwrap() && memory.is_empty());
+        assert!(landscapes(&mut Colons("0-2"), 1).is_err());
+
+        let path = std::env::temp_dir().join(format!("fast_pl_source_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 2\n1 3\n\n0 4\n").unwrap();
+        assert!(landscapes(&mut DiagramFile::new(&path), 1).unwrap() == custom);
+        let mut mapped = DiagramFile { mmap: true, ..DiagramFile::new(&path) };
+        assert!(mapped.diagrams().unwrap().count() == 1);
+        assert!(DiagramFiles::new([&path, &path]).diagrams().unwrap().count() == 2);
+        std::fs::remove_file(&path).unwrap();
+        assert!(DiagramFiles::new([&path]).diagrams().is_err());
+    }
+
+    #[test]
+    fn landscape_transforms() {
+        use clap::Parser;
+        use fast_pl::landscape::{Landscape, Normalization, Simplification};
+        use fast_pl::transform::{LandscapeTransform, Pipeline, Sampling, Transform, Vectorize};
+        use fast_pl::vectorize::Grid;
+        let landscape = || Landscape::new(vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 1.0), (4.0, 0.0)]]);
+
+        let spec = "crop=0:2,resample=0:2:5,normalize=l1";
+        let pipeline: Pipeline = spec.parse().unwrap();
+        assert!(pipeline.to_string() == spec && pipeline.transforms().len() == 3);
+        let transformed = pipeline.apply(landscape()).unwrap();
+        assert!(transformed.levels()[0].len() == 5 && (transformed.total_integral() - 1.0).abs() < 1e-12);
+
+        // The same chain built in code, ending in a feature vector
+        let grid = Grid::new(0.0, 2.0, 5).unwrap();
+        let chain = Transform::Crop { x_min: 0.0, x_max: 2.0 }
+            .then(Transform::Resample(Sampling::On(grid.clone())))
+            .then(Transform::Normalize(Normalization::L1))
+            .then(Vectorize(Sampling::On(grid)));
+        let (features, descriptor) = chain.apply(landscape()).unwrap();
+        assert!(descriptor.resolution == 5 && (features[2] - 1.0).abs() < 1e-6);
+
+        let smoothed: Pipeline = "gaussian=0.5@50".parse().unwrap();
+        assert!(smoothed.to_string() == "gaussian=0.5@50" && smoothed.apply(landscape()).unwrap().levels()[0].len() == 50);
+        for invalid in ["crop=2", "gaussian=x", "resample=0", "crop=0:1@5", "normalize=l3", "sharpen=1"] {
+            assert!(invalid.parse::<Pipeline>().is_err());
+        }
+        assert!("crop=3:1".parse::<Pipeline>().unwrap().apply(landscape()).is_err());
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "--simplify", "0.1"]);
+        assert!(super::transforms(&args).transforms() == [Transform::Simplify(Simplification::Redundant { tolerance: 0.1 })]);
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "--transform", "crop=0:1"]);
+        assert!(super::transforms(&args).transforms().len() == 1);
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--transform", "crop=0:1", "--simplify", "0.1"]).is_err());
+    }
+
+    #[test]
+    fn async_generation() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::task::{generate_async, generate_async_with_progress, spawn_blocking};
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        /// Smallest possible executor, parks the thread until the future wakes it
+        struct Unpark(std::thread::Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+            let mut cx = Context::from_waker(&waker);
+            let mut future = std::pin::pin!(future);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                std::thread::park();
+            }
+        }
+
+        let diagram = || pairs([(0.0_f32, 6.0), (1.0, 3.0), (2.0, 7.0), (4.0, 5.0)]);
+        let expected = fast_pl::persistencelandscape::generate(diagram(), 3, false);
+        assert!(block_on(generate_async(diagram(), 3)).unwrap() == expected);
+        assert!(generate_async(diagram(), 3).wait().unwrap() == expected);
+
+        let (task, mut progress) = generate_async_with_progress(diagram(), 3, 2);
+        assert!(block_on(task).unwrap() == expected);
+        let mut reports = Vec::new();
+        while let Some(report) = block_on(progress.next()) {
+            reports.push(report);
+        }
+        let last = reports.last().unwrap();
+        assert!(last.processed == 12 && last.total == 12 && (last.fraction() - 1.0).abs() < 1e-12);
+        assert!(reports.windows(2).all(|w| w[0].processed <= w[1].processed));
+        assert!(progress.try_next().is_none());
+
+        let panicked = spawn_blocking(|| -> usize { panic!("boom") }).wait();
+        assert!(panicked.unwrap_err().0 == "boom");
+    }
+
+    #[test]
+    fn stacked_batch() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::landscape::Landscape;
+        use fast_pl::vectorize::{generate_batch_gridded, Grid};
+
+        let diagrams = || {
+            vec![
+                pairs([(0.0_f32, 6.0), (1.0, 3.0), (2.0, 7.0)]),
+                Vec::new(),
+                pairs([(1.0_f32, 4.0), (1.5, 3.5), (2.0, 3.0), (2.5, 3.5)]),
+            ]
+        };
+        let grid = Grid::new(0.0, 8.0, 17).unwrap();
+        let stacked = generate_batch_gridded(diagrams(), 3, &grid).unwrap();
+        assert!(stacked.shape() == [3, 3, 17] && stacked.values.len() == 3 * 3 * 17);
+        for (i, diagram) in diagrams().into_iter().enumerate() {
+            let landscape = Landscape::new(fast_pl::persistencelandscape::generate(diagram, 3, false));
+            assert!(stacked.diagram(i) == landscape.to_feature_vector(&grid).0);
+        }
+        assert!(stacked.diagram(1).iter().all(|&v| v == 0.0));
+        assert!(stacked.descriptor.to_landscape(stacked.diagram(0)).unwrap().len() == 3);
+
+        assert!(generate_batch_gridded(Vec::new(), 2, &grid).unwrap().is_empty());
+        assert!(generate_batch_gridded(diagrams(), 0, &grid).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "arena")]
+    fn arena_reuse() {
+        use fast_pl::birthdeath::{pairs, BirthDeath};
+        use fast_pl::persistencelandscape::{arena_capacity, generate, generate_with_profile, release_arena, Profile};
+
+        let diagrams = [
+            pairs([(0.0_f32, 6.0), (1.0, 3.0), (2.0, 7.0), (4.0, 5.0), (2.5, 6.5)]),
+            pairs([(1.0_f32, 2.0)]),
+            Vec::new(),
+            pairs([(0.0_f32, 4.0), (1.0, 5.0), (2.0, 6.0)]),
+        ];
+        for _ in 0..3 {
+            for diagram in &diagrams {
+                let copy = || diagram.iter().map(|bd| BirthDeath { birth: bd.birth, death: bd.death }).collect();
+                assert!(generate(copy(), 0, false) == generate_with_profile(copy(), 0, false, Profile::Tent).unwrap());
+            }
+        }
+        let capacity = arena_capacity();
+        assert!(capacity > 0);
+        let _ = generate(pairs([(1.0_f32, 2.0)]), 2, false);
+        assert!(arena_capacity() == capacity);
+        release_arena();
+        assert!(arena_capacity() == 0);
+    }
+
+    #[test]
+    fn landscape_ensemble() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::ensemble::LandscapeEnsemble;
+        use fast_pl::persistencelandscape::generate;
+
+        let landscapes = vec![
+            generate(pairs([(0.0_f32, 4.0), (1.0, 3.0)]), 2, false),
+            generate(pairs([(0.5_f32, 5.0), (1.0, 2.0)]), 2, false),
+            generate(pairs([(1.0_f32, 3.0)]), 2, false),
+        ];
+        let ensemble = LandscapeEnsemble::new(landscapes.clone()).unwrap();
+        assert!(ensemble.len() == 3 && !ensemble.is_empty());
+        assert!(ensemble.mean().levels() == fast_pl::statistics::average(&landscapes).unwrap());
+        assert!(ensemble.median().levels() == fast_pl::statistics::median(&landscapes).unwrap());
+
+        let envelopes = ensemble.envelope_band();
+        let quantiles = ensemble.quantile_band(1.0).unwrap();
+        let deviations = ensemble.std_band(1.0).unwrap();
+        for x in [0.25, 1.0, 1.75, 2.5, 3.5, 4.5] {
+            for level in 0..2 {
+                let at = |landscape: &fast_pl::landscape::Landscape| landscape.evaluate(level, x);
+                assert!(at(&envelopes.lower) <= at(&envelopes.center) && at(&envelopes.center) <= at(&envelopes.upper));
+                assert!(at(&deviations.lower) <= at(&deviations.center) + 1e-12);
+                assert!(at(&deviations.center) <= at(&deviations.upper) + 1e-12);
+            }
+        }
+
+        // The quantiles are only sampled at breakpoints, where the extreme ones are the envelopes
+        for (quantile, envelope) in [(&quantiles.lower, &envelopes.lower), (&quantiles.upper, &envelopes.upper)] {
+            for (level, points) in quantile.levels().iter().enumerate() {
+                assert!(points.iter().all(|&(x, y)| (envelope.evaluate(level, x) - y).abs() < 1e-12));
+            }
+        }
+
+        let same = LandscapeEnsemble::new(vec![landscapes[0].clone(); 4]).unwrap();
+        assert!(same.std_dev().levels().iter().flatten().all(|&(_, y)| y.abs() < 1e-12));
+
+        assert!(LandscapeEnsemble::new(Vec::<Vec<Vec<(f64, f64)>>>::new()).is_err());
+        assert!(ensemble.quantile_band(1.5).is_err() && ensemble.std_band(-1.0).is_err());
+
+        #[cfg(feature = "plot")]
+        {
+            let path = std::env::temp_dir().join(format!("fast_pl_band_{}.png", std::process::id()));
+            envelopes.plot(&path, 240, 320).unwrap();
+            assert!(path.exists());
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn quantized_coordinates() {
+        use clap::Parser;
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::persistencelandscape::{generate, generate_half_units, generate_quantized, MAX_UNITS};
+
+        // The tents of [0, 4] and [1, 5] cross at 2.5 with height 1.5
+        let halves = generate_half_units(&[(0, 4), (1, 5), (3, 3)], 2).unwrap();
+        assert!(halves[0] == [(0, 0), (4, 4), (5, 3), (6, 4), (10, 0)]);
+        assert!(halves[1] == [(2, 0), (5, 3), (8, 0)]);
+        assert!(generate_half_units(&[(0, MAX_UNITS + 1)], 1).is_err());
+
+        let diagram = || pairs([(0.013_f32, 3.31), (0.62, 2.87), (1.1, 4.09), (2.26, 2.49)]);
+        let quantized = generate_quantized(diagram(), 3, 0.25).unwrap();
+        assert!(quantized.iter().flatten().all(|&(x, y)| (x / 0.125).fract() == 0.0 && (y / 0.125).fract() == 0.0));
+        let rounded = pairs([(0.0_f32, 3.25), (0.5, 2.75), (1.0, 4.0), (2.25, 2.5)]);
+        assert!(fast_pl::distance::sup_distance(&quantized, &generate(rounded, 3, false)) < 1e-12);
+        assert!(fast_pl::distance::sup_distance(&quantized, &generate(diagram(), 3, false)) < 0.25);
+
+        assert!(generate_quantized(diagram(), 3, 0.0).is_err());
+        assert!(generate_quantized(diagram(), 3, 1e-15).is_err());
+
+        assert!(super::Args::parse_from(["fast_pl", "-n", "x", "--quantize", "0.5"]).quantize == Some(0.5));
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--quantize", "0.5", "--parallel", "2"]).is_err());
+    }
+
+    #[test]
+    fn integer_coordinates() {
+        use clap::Parser;
+        use fast_pl::birthdeath::{pairs, to_integer, BirthDeath};
+        use fast_pl::persistencelandscape::{generate, generate_integer};
+
+        let diagram: Vec<BirthDeath<i64>> = vec![(0, 4).into(), (1, 5).into(), (2, 3).into()];
+        let halves = generate_integer(&diagram, 2).unwrap();
+        assert!(halves[0] == [(0, 0), (4, 4), (5, 3), (6, 4), (10, 0)]);
+        assert!(halves[1] == [(2, 0), (5, 3), (8, 0)]);
+        let floats = generate(pairs([(0.0_f32, 4.0), (1.0, 5.0), (2.0, 3.0)]), 2, false);
+        assert!(fast_pl::distance::sup_distance(&super::from_half_units(halves), &floats) < 1e-12);
+
+        assert!(to_integer(&pairs([(1.0_f32, 3.0), (0.0, f32::INFINITY)])).unwrap().len() == 1);
+        assert!(to_integer(&pairs([(1.5_f32, 3.0)])).is_err());
+
+        let path = std::env::temp_dir().join(format!("fast_pl_integer_{}.csv", std::process::id()));
+        std::fs::write(&path, "birth,death\n0,4\n1,5\n2,inf\n").unwrap();
+        let read = fast_pl::io::read_integer_diagram(&path, fast_pl::io::Header::Detect).unwrap();
+        assert!(read.len() == 2 && read[1].birth == 1 && read[1].death == 5);
+        std::fs::write(&path, "0,4\n1,5.5\n").unwrap();
+        assert!(fast_pl::io::read_integer_diagram(&path, fast_pl::io::Header::Detect).is_err());
+
+        // The command line reads the file as integers, never through a float
+        let output = std::env::temp_dir().join(format!("fast_pl_integer_out_{}.csv", std::process::id()));
+        let command = |input: &std::path::Path| {
+            super::Args::parse_from(["fast_pl", "-q", "-k", "2", "--integer", "--csv", output.to_str().unwrap(),
+                "-n", input.to_str().unwrap()])
+        };
+        let error = super::run(&command(&path)).unwrap_err().to_string();
+        assert!(error.contains("whole numbers on line 2"));
+        std::fs::write(&path, "0,4\n1,5\n2,3\n").unwrap();
+        super::run(&command(&path)).unwrap();
+        assert!(std::fs::read_to_string(&output).unwrap().contains("2.5"));
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2", "--integer"]);
+        let computed = super::compute(&args, pairs([(0.0_f32, 4.0), (1.0, 5.0), (2.0, 3.0)]), None).unwrap();
+        assert!(fast_pl::distance::sup_distance(&computed, &floats) < 1e-12);
+        assert!(super::compute(&args, pairs([(0.5_f32, 4.0)]), None).is_err());
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--integer", "--quantize", "1"]).is_err());
+    }
+
+    #[test]
+    fn birth_persistence_input() {
+        use clap::Parser;
+        use fast_pl::birthdeath::{from_birth_persistence, pairs};
+        use fast_pl::pipeline::{DiagramPipeline, Step};
+
+        let converted = from_birth_persistence(pairs([(1.0_f32, 2.0), (0.5, 0.0), (3.0, f32::INFINITY)])).unwrap();
+        assert!(converted.iter().map(|bd| (bd.birth, bd.death)).eq([(1.0, 3.0), (0.5, 0.5), (3.0, f64::INFINITY)]));
+        assert!(from_birth_persistence(pairs([(1.0_f32, -0.5)])).is_err());
+
+        let pipeline: DiagramPipeline = "birth-persistence,scale=2".parse().unwrap();
+        assert!(pipeline.steps()[0] == Step::BirthPersistence && pipeline.to_string() == "birth-persistence,scale=2");
+        let scaled = pipeline.apply(pairs([(1.0_f32, 2.0)])).unwrap();
+        assert!((scaled[0].birth - 2.0).abs() < 1e-12 && (scaled[0].death - 6.0).abs() < 1e-12);
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2", "--birth-persistence"]);
+        let landscape = super::compute(&args, pairs([(0.0_f32, 4.0), (1.0, 4.0)]), None).unwrap();
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2"]);
+        assert!(landscape == super::compute(&args, pairs([(0.0_f32, 4.0), (1.0, 5.0)]), None).unwrap());
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--birth-persistence", "--pipeline", "strict"]).is_err());
+    }
+
+    #[test]
+    fn input_rescaling() {
+        use clap::Parser;
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::persistencelandscape::{generate, generate_report, generate_report_rescaled};
+        use fast_pl::pipeline::{Rescaling, Units};
+
+        let diagram = || pairs([(200.0_f32, 600.0), (300.0, 500.0), (400.0, 1000.0), (0.0, f32::INFINITY)]);
+        let rescaling = Rescaling::fit(&diagram()).unwrap();
+        assert!((rescaling.factor - 1.0 / 800.0).abs() < 1e-15 && (rescaling.shift + 0.25).abs() < 1e-12);
+        let scaled = rescaling.apply(diagram());
+        assert!(scaled[..3].iter().all(|bd| (0.0..=1.0).contains(&bd.birth) && (0.0..=1.0).contains(&bd.death)));
+        let (x, y) = rescaling.invert_point((0.5, 0.25));
+        assert!((x - 600.0).abs() < 1e-9 && (y - 200.0).abs() < 1e-9);
+        assert!(Rescaling::fit(&pairs([(1.0_f32, 1.0)])).is_none() && Rescaling::fit(&[]).is_none());
+
+        let expected = generate(diagram(), 2, false);
+        let original = generate_report_rescaled(diagram(), 2, false, Units::Original);
+        assert!(original.rescaling == Some(rescaling));
+        assert!(fast_pl::distance::sup_distance(&original.landscapes, &expected) < 1e-9);
+        let normalized = generate_report_rescaled(diagram(), 2, false, Units::Normalized);
+        assert!(normalized.landscapes == generate(rescaling.apply(diagram()), 2, false));
+        assert!(generate_report(diagram(), 2, false).rescaling.is_none());
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2", "--rescale", "original"]);
+        let computed = super::compute(&args, diagram(), None).unwrap();
+        assert!(fast_pl::distance::sup_distance(&computed, &expected) < 1e-9);
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2", "--rescale", "normalized", "--normalize", "persistence"]);
+        let computed = super::compute(&args, diagram(), None).unwrap();
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2", "--normalize", "persistence"]);
+        let plain = super::compute(&args, diagram(), None).unwrap();
+        // Normalizing by persistence removes the scale, the two only differ in x
+        assert!(computed.iter().flatten().zip(plain.iter().flatten()).all(|(a, b)| (a.1 - b.1).abs() < 1e-12));
+    }
+
+    #[test]
+    fn level_ranges() {
+        use clap::Parser;
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::persistencelandscape::{generate, generate_levels};
+
+        let diagram = || pairs([(0.0_f32, 8.0), (1.0, 7.0), (2.0, 6.0), (3.0, 5.0), (2.5, 9.0), (0.5, 3.0)]);
+        for first in 1..=6 {
+            for last in first..=6 {
+                assert!(generate_levels(diagram(), first..=last, false) == generate(diagram(), last, false)[first - 1..]);
+            }
+        }
+        assert!(generate_levels(diagram(), 0..=3, false).is_empty());
+        assert!(generate_levels(diagram(), std::ops::RangeInclusive::new(4, 3), false).is_empty());
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "5"]);
+        let five = super::compute(&args, diagram(), None).unwrap();
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "5", "--first-level", "3"]);
+        assert!(super::compute(&args, diagram(), None).unwrap() == five[2..]);
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "-k", "2", "--first-level", "3"]);
+        assert!(super::compute(&args, diagram(), None).is_err());
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--first-level", "2", "--parallel", "2"]).is_err());
+    }
+
+    #[test]
+    fn sparse_landscapes() {
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::landscape::Landscape;
+        use fast_pl::persistencelandscape::generate;
+        use fast_pl::sparse::SparseLandscape;
+
+        let dense = Landscape::new(generate(pairs([(0.0_f32, 2.0), (5.0, 9.0), (6.0, 8.0), (20.0, 22.0), (21.0, 23.0)]), 4, false));
+        let sparse = SparseLandscape::from(&dense);
+        assert!(sparse.k() == 4 && sparse.levels()[3].is_empty() && !sparse.is_empty());
+        assert!(sparse.support(0).eq([(0.0, 2.0), (5.0, 9.0), (20.0, 23.0)]));
+        assert!(sparse.support(1).eq([(6.0, 8.0), (21.0, 22.0)]));
+        // Sampled on a grid the deeper levels are zero almost everywhere
+        let grid = fast_pl::vectorize::Grid::new(0.0, 24.0, 97).unwrap();
+        let sampled = fast_pl::vectorize::unsample(&fast_pl::vectorize::sample(dense.levels(), 4, &grid), &grid);
+        assert!(SparseLandscape::new(&sampled).len() * 3 < sampled.iter().map(Vec::len).sum::<usize>());
+        for x in [-1.0, 0.5, 3.0, 5.5, 7.0, 21.5, 22.75, 30.0] {
+            for level in 0..5 {
+                assert!((sparse.evaluate(level, x) - dense.evaluate(level, x)).abs() < 1e-12);
+            }
+        }
+        assert!(Landscape::from(&sparse) == dense);
+
+        let norms = fast_pl::distance::level_norms(dense.levels());
+        for (a, b) in sparse.level_norms().iter().zip(&norms) {
+            assert!((a.l1 - b.l1).abs() < 1e-12 && (a.l2 - b.l2).abs() < 1e-12 && (a.sup - b.sup).abs() < 1e-12);
+        }
+
+        let shifted = SparseLandscape::from(&dense.shift_x(1.0));
+        let difference = &sparse - &shifted;
+        let expected = &dense - &dense.shift_x(1.0);
+        for x in [0.5, 1.5, 6.25, 21.0] {
+            assert!((difference.evaluate(0, x) - expected.evaluate(0, x)).abs() < 1e-12);
+        }
+        assert!((&sparse - &sparse).is_empty());
+        assert!((&sparse * 0.0).is_empty());
+        assert!(((&sparse + &sparse).evaluate(0, 1.0) - 2.0).abs() < 1e-12);
+        assert!(((-&sparse).evaluate(0, 1.0) + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn partition_merge() {
+        use fast_pl::birthdeath::{pairs, BirthDeath};
+        use fast_pl::persistencelandscape::{generate, generate_partition, merge_partitions, partition};
+
+        let diagram = || pairs([(0.0_f32, 4.0), (1.0, 3.0), (2.0, 9.0), (5.0, 8.0), (6.0, 7.5), (10.0, 12.0), (0.5, 11.0)]);
+        let all = diagram();
+        for ranges in [1, 2, 3, 5] {
+            let bounds = partition(&all, ranges);
+            assert!(bounds.len() <= ranges && bounds[0].start == f64::NEG_INFINITY);
+            assert!(bounds.windows(2).all(|w| (w[0].end - w[1].start).abs() < f64::EPSILON));
+            for k in [0, 2, 4] {
+                // Every machine only gets the pairs alive in its range
+                let parts: Vec<_> = bounds
+                    .iter()
+                    .map(|range| {
+                        let alive: Vec<BirthDeath> = diagram().into_iter().filter(|bd| bd.birth < range.end && bd.death >= range.start).collect();
+                        generate_partition(&alive, k, range.clone())
+                    })
+                    .collect();
+                let merged = merge_partitions(parts, k).unwrap();
+                assert!(fast_pl::distance::sup_distance(&merged, &generate(diagram(), k, false)) < 1e-12);
+            }
+        }
+        let bounds = partition(&all, 3);
+        let mut parts: Vec<_> = bounds.iter().map(|range| generate_partition(&all, 2, range.clone())).collect();
+        parts.reverse();
+        assert!(merge_partitions(parts, 2).is_err());
+    }
+
+    #[test]
+    fn chunked_sweep() {
+        use fast_pl::birthdeath::BirthDeath;
+        use fast_pl::builder::LandscapeBuilder;
+        use fast_pl::persistencelandscape::{generate, generate_parallel, generate_partition, merge_partitions};
+
+        // Many mountains crossing the boundaries, also at equal heights
+        let integers: Vec<BirthDeath> = (0..300_u32)
+            .map(|i| BirthDeath::from((f64::from(i * 7 % 23), f64::from(i * 7 % 23 + 1 + i % 13))))
+            .collect();
+        let noisy = fast_pl::synthetic::noisy_diagram(300, 20, 5);
+        for diagram in [integers, noisy] {
+            let pairs = || diagram.iter().map(|bd| BirthDeath { birth: bd.birth, death: bd.death }).collect::<Vec<_>>();
+            for k in [0, 1, 5] {
+                let expected = generate(pairs(), k, false);
+                for ranges in [3, 16, 100] {
+                    assert!(fast_pl::distance::sup_distance(&generate_parallel(pairs(), k, ranges), &expected) < 1e-9);
+                    let built = LandscapeBuilder::new().k(k).parallel(ranges).build(pairs()).unwrap();
+                    assert!(fast_pl::distance::sup_distance(&built.into_levels(), &expected) < 1e-9);
+                }
+            }
+        }
+
+        // A single range unbounded on both sides is the sweep of generate
+        let large: Vec<BirthDeath> = (0..20_000_u32)
+            .map(|i| {
+                let birth = f64::from(i / 1000).mul_add(1000.0, f64::from(i % 1000 * 97 % 1000));
+                BirthDeath::from((birth, birth + 2.0 + f64::from(i % 5)))
+            })
+            .collect();
+        let copy = || large.iter().map(|bd| BirthDeath { birth: bd.birth, death: bd.death }).collect::<Vec<_>>();
+        let single = merge_partitions(vec![generate_partition(&large, 3, f64::NEG_INFINITY..f64::INFINITY)], 3).unwrap();
+        assert!(fast_pl::distance::sup_distance(&generate(copy(), 3, false), &single) < 1e-9);
+    }
+
+    #[test]
+    fn approximate_sweep() {
+        use clap::Parser;
+        use fast_pl::birthdeath::BirthDeath;
+        use fast_pl::persistencelandscape::{generate, generate_approximate};
+
+        let diagram = || fast_pl::synthetic::noisy_diagram(300, 10, 11);
+        for k in [0, 1, 6] {
+            let exact = generate(diagram(), k, false);
+            for epsilon in [0.001, 0.05, 0.3] {
+                let approximate = generate_approximate(diagram(), k, epsilon).unwrap();
+                assert!(fast_pl::distance::sup_distance(&approximate, &exact) <= epsilon / 2.0 + 1e-12);
+                assert!(k == 0 || approximate.len() == k);
+            }
+        }
+        // Only pairs of more persistence than epsilon are swept
+        let short = vec![BirthDeath::from((0.0, 1.0)), BirthDeath::from((5.0, 5.5))];
+        assert!(generate_approximate(short, 0, 0.5).unwrap() == [[(0.0, 0.0), (0.5, 0.5), (1.0, 0.0)]]);
+        assert!(generate_approximate(diagram(), 2, 0.0).is_err());
+        assert!(generate_approximate(diagram(), 2, f64::NAN).is_err());
+
+        assert!(super::Args::parse_from(["fast_pl", "-n", "x", "--epsilon", "0.1"]).epsilon == Some(0.1));
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--epsilon", "0.1", "--quantize", "0.1"]).is_err());
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--epsilon", "0.1", "--first-level", "2"]).is_err());
+    }
+
+    #[test]
+    fn weighted_distances() {
+        use fast_pl::distance::{lp_distance, weighted_lp_distance, weighted_lp_norm, Weight};
+        use fast_pl::persistencelandscape::generate;
+
+        let a = generate(fast_pl::synthetic::noisy_diagram(40, 4, 1), 3, false);
+        let b = generate(fast_pl::synthetic::noisy_diagram(40, 4, 2), 3, false);
+        let one = Weight::new(vec![(0.0, 1.0)]).unwrap();
+        let three = Weight::new(vec![(0.0, 3.0)]).unwrap();
+        for p in [1.0, 2.0, 3.0, f64::INFINITY] {
+            let unweighted = lp_distance(&a, &b, p);
+            assert!((weighted_lp_distance(&a, &b, p, &one) - unweighted).abs() < 1e-9);
+            let scaled = if p.is_infinite() { 3.0 * unweighted } else { 3.0_f64.powf(p.recip()) * unweighted };
+            assert!((weighted_lp_distance(&a, &b, p, &three) - scaled).abs() < 1e-9);
+        }
+
+        // The tent of [0, 2] weighted by x + 1
+        let tent = vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]];
+        let ramp = Weight::new(vec![(0.0, 1.0), (2.0, 3.0)]).unwrap();
+        assert!((weighted_lp_norm(&tent, 1.0, &ramp) - 2.0).abs() < 1e-12);
+        assert!((weighted_lp_norm(&tent, 2.0, &ramp) - (4.0_f64 / 3.0).sqrt()).abs() < 1e-12);
+        assert!((weighted_lp_norm(&tent, f64::INFINITY, &ramp) - 2.0).abs() < 1e-12);
+        let sampled = Weight::sampled(|x| x + 1.0, &fast_pl::vectorize::Grid::new(0.0, 2.0, 5).unwrap()).unwrap();
+        assert!((weighted_lp_norm(&tent, 1.0, &sampled) - 2.0).abs() < 1e-12);
+        assert!((ramp.at(-1.0) - 1.0).abs() < 1e-12 && (ramp.at(0.5) - 1.5).abs() < 1e-12 && (ramp.at(9.0) - 3.0).abs() < 1e-12);
+
+        // Suppressing where the landscapes differ hides the difference
+        let shifted = vec![vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], vec![(5.0, 0.0), (6.0, 1.0), (7.0, 0.0)]];
+        let left = Weight::new(vec![(4.0, 1.0), (5.0, 0.0)]).unwrap();
+        assert!(weighted_lp_distance(&tent, &shifted, 2.0, &left) < 1e-12);
+        assert!(weighted_lp_distance(&tent, &shifted, 2.0, &one) > 0.5);
+
+        assert!(Weight::new(Vec::new()).is_err());
+        assert!(Weight::new(vec![(1.0, 1.0), (1.0, 2.0)]).is_err());
+        assert!(Weight::new(vec![(0.0, -1.0)]).is_err());
+        assert!(Weight::sampled(|x| x - 1.0, &fast_pl::vectorize::Grid::new(0.0, 2.0, 3).unwrap()).is_err());
+    }
+
+    #[test]
+    fn synth_command() {
+        use clap::Parser;
+
+        let path = |name: &str| std::env::temp_dir().join(format!("fast_pl_synth_{name}_{}.csv", std::process::id()));
+        let run = |name: &str, extra: &[&str]| {
+            let out = path(name);
+            let mut argv = vec!["fast_pl", "synth", "--pairs", "1e3", "-o", out.to_str().unwrap()];
+            argv.extend(extra);
+            super::run(&super::Args::parse_from(argv)).unwrap();
+            std::fs::read_to_string(&out).unwrap()
+        };
+        let first = run("a", &["--distribution", "clustered", "--seed", "42"]);
+        assert!(first == run("b", &["--distribution", "clustered", "--seed", "42"]));
+        assert!(first != run("c", &["--distribution", "clustered", "--seed", "43"]));
+        for distribution in ["uniform", "near-diagonal", "long-bars", "noisy", "clustered"] {
+            run("d", &["--distribution", distribution]);
+            let diagram = fast_pl::io::read_diagram(path("d")).unwrap();
+            assert!(diagram.len() == 1000 && diagram.iter().all(|bd| bd.birth <= bd.death));
+        }
+        // Every pair is near one of the clusters
+        let clustered = fast_pl::synthetic::clustered_diagram(500, 3, 0.01, 7);
+        let mut births: Vec<f64> = clustered.iter().map(|bd| (bd.birth * 10.0).round()).collect();
+        births.sort_by(f64::total_cmp);
+        births.dedup();
+        assert!(births.len() <= 6);
+        for name in ["a", "b", "c", "d"] {
+            std::fs::remove_file(path(name)).unwrap();
+        }
+
+        assert!(super::parse_count("1e6") == Ok(1_000_000) && super::parse_count("250") == Ok(250));
+        assert!(super::parse_count("1.5").is_err() && super::parse_count("-1").is_err() && super::parse_count("x").is_err());
+    }
+
+    #[test]
+    fn status_snapshots() {
+        use clap::Parser;
+        use fast_pl::birthdeath::pairs;
+        use fast_pl::persistencelandscape::{generate, generate_with_snapshots, Snapshots};
+
+        let path = std::env::temp_dir().join(format!("fast_pl_snapshots_{}.jsonl", std::process::id()));
+        let diagram = || pairs([(0.0_f32, 4.0), (1.0, 3.0), (2.0, 6.0)]);
+        let snapshots = Snapshots { path: path.clone(), every: 3 };
+        let landscapes = generate_with_snapshots(diagram(), 2, false, &snapshots).unwrap();
+        assert!(landscapes == generate(diagram(), 2, false));
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        // After 3, 6 and 9 of the 9 events and once at the end
+        assert!(lines.len() == 4);
+        assert!(lines.iter().all(|line| line.starts_with('{') && line.ends_with('}')));
+        assert!(lines[0].starts_with("{\"processed\":3,") && lines[0].contains("\"x\":2,"));
+        assert!(lines[0].contains("\"status_len\":3,") && lines[0].contains("\"pair\":2,\"height\":0,\"rising\":true"));
+        assert!(lines[3].contains("\"x\":null,") && lines[3].contains("\"queued_events\":0,\"status_len\":0,"));
+        assert!(lines[3].ends_with("\"status\":[]}"));
+        std::fs::remove_file(&path).unwrap();
+
+        let args = super::Args::parse_from(["fast_pl", "-n", "x", "--snapshots", "s.jsonl"]);
+        assert!(args.snapshots.is_some() && args.snapshot_every == 10_000);
+        assert!(super::Args::try_parse_from(["fast_pl", "-n", "x", "--snapshots", "s.jsonl", "--parallel", "2"]).is_err());
+    }
+
+    #[test]
+    fn reference_verification() {
+        use clap::Parser;
+        use fast_pl::persistencelandscape::generate;
+        use fast_pl::verify::{naive_values, verify};
+
+        let diagram = fast_pl::synthetic::noisy_diagram(150, 8, 4);
+        let copy = || diagram.iter().map(|bd| fast_pl::birthdeath::BirthDeath { birth: bd.birth, death: bd.death }).collect();
+        for k in [0, 1, 5] {
+            let verification = verify(&diagram, &generate(copy(), k, false), k, 200);
+            assert!(verification.max_discrepancy() < 1e-12 && verification.points > 200);
+        }
+        let tents = fast_pl::birthdeath::pairs([(0.0_f32, 4.0), (1.0, 3.0)]);
+        assert!(naive_values(&tents, 3, 1.5) == [1.5, 0.5, 0.0]);
+        assert!(naive_values(&tents, 0, 3.5) == [0.5]);
+
+        // The second level shifted right by one is off by one first at x = 2
+        let mut wrong = generate(fast_pl::birthdeath::pairs([(0.0_f32, 4.0), (1.0, 3.0)]), 2, false);
+        wrong[1] = vec![(2.0, 0.0), (3.0, 1.0), (4.0, 0.0)];
+        let worst = verify(&tents, &wrong, 2, 10).worst.unwrap();
+        assert!((worst.value - 1.0).abs() < 1e-12 && worst.level == 2 && (worst.x - 2.0).abs() < 1e-12);
+        assert!(verify(&[], &[], 0, 10).worst.is_none());
+
+        let path = std::env::temp_dir().join(format!("fast_pl_verify_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 4\n1 3\n2 6\n").unwrap();
+        let run = |extra: &[&str]| {
+            let mut args = vec!["fast_pl", "-q", "-k", "3", "--verify", "-n", path.to_str().unwrap()];
+            args.extend(extra);
+            super::run(&super::Args::parse_from(args))
+        };
+        assert!(run(&[]).is_ok());
+        assert!(run(&["--verify-sample", "2"]).is_ok());
+        assert!(run(&["--verify-tolerance=-1"]).is_err());
+        // The sample is swept by the backend of the diagram, quantizing moves the pair (1, 3)
+        assert!(run(&["--parallel", "2"]).is_ok() && run(&["--integer"]).is_ok());
+        assert!(run(&["--quantize", "2"]).unwrap_err().to_string().contains("Verification failed"));
+        assert!(run(&["--quantize", "2", "--verify-tolerance", "1"]).is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn labeled_provenance() {
+        use clap::Parser;
+        use fast_pl::birthdeath::{pairs, Label};
+        use fast_pl::io::{read_labeled_diagram, write_provenance_to, Header, NumberFormat};
+        use fast_pl::pipeline::DiagramPipeline;
+
+        assert!("17".parse::<Label>() == Ok(Label::Integer(17)));
+        assert!("C4 ring".parse::<Label>() == Ok(Label::from("C4 ring")));
+        assert!(Label::from(-3).to_string() == "-3");
+
+        let path = std::env::temp_dir().join(format!("fast_pl_labeled_{}.csv", std::process::id()));
+        std::fs::write(&path, "birth,death,generator\n0,4,a b\n1,3,7\n2,inf,info\n0.5,2\n").unwrap();
+        let (diagram, labels) = read_labeled_diagram(&path, Header::Detect).unwrap();
+        assert!(diagram.len() == 3 && labels == [Some(Label::from("a b")), Some(Label::Integer(7)), None]);
+
+        let (kept, kept_labels) = DiagramPipeline::new()
+            .min_persistence(1.75)
+            .canonical()
+            .apply_labeled(pairs([(0.5, 2.0), (1.0, 3.0), (0.0, 4.0)]), vec!["c", "b", "a"])
+            .unwrap();
+        assert!(kept.len() == 2 && kept_labels == ["a", "b"]);
+        assert!(DiagramPipeline::new().top_pairs(1).apply_labeled(pairs([(0.0, 1.0)]), vec![0]).is_err());
+        assert!(DiagramPipeline::new().apply_labeled(pairs([(0.0, 1.0)]), Vec::<u8>::new()).is_err());
+
+        let provenance = fast_pl::persistencelandscape::generate_with_provenance(diagram, 1, false);
+        let mut out = Vec::new();
+        write_provenance_to(&mut out, &provenance, &labels, NumberFormat::Shortest).unwrap();
+        assert!(String::from_utf8(out).unwrap()
+            == "level,x,y,pair,crossing,label,crossing_label\n1,0,0,0,,a b,\n1,2,2,0,,a b,\n1,4,0,0,,a b,\n");
+
+        let output = std::env::temp_dir().join(format!("fast_pl_provenance_{}.csv", std::process::id()));
+        let args = vec![
+            "fast_pl", "-q", "-k", "2", "--labeled", "--provenance", output.to_str().unwrap(),
+            "-n", path.to_str().unwrap(),
+        ];
+        assert!(super::run(&super::Args::parse_from(args)).is_ok());
+        let written = std::fs::read_to_string(&output).unwrap();
+        assert!(written.lines().skip(1).all(|row| row.starts_with("1,") || row.starts_with("2,")));
+        assert!(written.lines().any(|row| row.starts_with("2,") && row.ends_with(",7,")));
+        assert!(super::Args::try_parse_from(["fast_pl", "--labeled", "-n", "x"]).is_err());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn streaming_sweep() {
+        use clap::Parser;
+        use fast_pl::birthdeath::BirthDeath;
+        use fast_pl::io::{read_csv, WriteOptions};
+        use fast_pl::persistencelandscape::{generate, generate_streaming, LimitExceeded, Streaming};
+
+        let dir = std::env::temp_dir().join(format!("fast_pl_streaming_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Short bars, so few are alive at once however many there are
+        let pairs = |n: u32| (0..n).map(|i| {
+            let birth = f64::from(i).mul_add(0.37, f64::from(i % 7) * 0.011);
+            BirthDeath { birth, death: birth + f64::from(i * 13 % 17).mul_add(0.21, 0.5) }
+        });
+        let same = |a: &[Vec<(f64, f64)>], b: &[Vec<(f64, f64)>]| {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| {
+                a.len() == b.len() && a.iter().zip(b).all(|(p, q)| fast_pl::landscape::approx_eq_point(*p, *q, 1e-9, 0.0))
+            })
+        };
+        let output = dir.join("levels.csv");
+        let stream = |bd_pairs: Vec<BirthDeath>, k: usize, memory: usize| {
+            let settings = Streaming { dir: dir.clone(), memory };
+            let file = std::fs::File::create(&output).unwrap();
+            generate_streaming(bd_pairs, k, false, &settings, file, &WriteOptions::default())
+                .map(|()| read_csv(&output).unwrap())
+        };
+        // Runs of 2048 pairs merged eight at a time need a second pass
+        let streamed = stream(pairs(20_000).collect(), 3, 1 << 16).unwrap();
+        assert!(same(&streamed, &generate(pairs(20_000).collect(), 3, false)));
+        for k in [0, 1, 4] {
+            let nested = fast_pl::synthetic::noisy_diagram(60, 5, 2);
+            let copy = || nested.iter().map(|bd| BirthDeath { birth: bd.birth, death: bd.death }).collect();
+            assert!(same(&stream(copy(), k, 1 << 20).unwrap(), &generate(copy(), k, false)));
+        }
+        let with_points = fast_pl::birthdeath::pairs([(0.0, 2.0), (1.0, 1.0), (f64::NEG_INFINITY, 3.0)]);
+        assert!(same(&stream(with_points, 2, 1 << 20).unwrap(), &[vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)], vec![]]));
+
+        let nested = (0..200).map(|i| BirthDeath { birth: f64::from(i), death: f64::from(1000 - i) }).collect();
+        let error = stream(nested, 2, 1 << 12).unwrap_err();
+        assert!(error.downcast_ref::<LimitExceeded>() == Some(&LimitExceeded::Memory { limit: 1 << 12 }));
+
+        let input = dir.join("diagram.txt");
+        let mut text = b"# short bars\n".to_vec();
+        let diagram: Vec<BirthDeath> = pairs(500).collect();
+        fast_pl::io::write_diagram_to(&mut text, &diagram, fast_pl::io::NumberFormat::Shortest).unwrap();
+        std::fs::write(&input, text).unwrap();
+        let run = |extra: &[&str]| {
+            let mut args = vec!["fast_pl", "-q", "-k", "2", "--stream-memory", "1e5", "-n", input.to_str().unwrap()];
+            args.extend(extra);
+            super::run(&super::Args::parse_from(args))
+        };
+        assert!(run(&["-c", output.to_str().unwrap(), "--spill-dir", dir.to_str().unwrap()]).is_ok());
+        assert!(same(&read_csv(&output).unwrap(), &generate(pairs(500).collect(), 2, false)));
+        assert!(run(&[]).is_err());
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&input).unwrap();
+        // Every temporary file is gone again
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn steppable_sweep() {
+        use fast_pl::persistencelandscape::{generate, StatusEntry, Sweep, SweepEventKind};
+
+        let pairs = || fast_pl::birthdeath::pairs([(0.0, 4.0), (f64::NEG_INFINITY, 1.0), (1.0, 3.0), (2.0, 6.0)]);
+        let mut sweep = Sweep::new(pairs(), 2);
+        assert!(sweep.next_x() == Some(0.0) && sweep.status().is_empty());
+        let first = sweep.step().unwrap();
+        assert!(first.kind == SweepEventKind::Birth && first.pair == 0 && first.x == 0.0 && first.intersections == 0);
+        let second = sweep.step().unwrap();
+        assert!(second.kind == SweepEventKind::Birth && second.pair == 2);
+        assert!(sweep.status() == [
+            StatusEntry { pair: 0, height: 1.0, rising: true },
+            StatusEntry { pair: 2, height: 0.0, rising: true },
+        ]);
+        assert!(sweep.landscape_so_far() == [vec![(0.0, 0.0)], vec![(1.0, 0.0)]]);
+
+        let mut events = vec![first, second];
+        while let Some(event) = sweep.step() {
+            events.push(event);
+        }
+        assert!(events.len() == 9 && sweep.processed() == 9 && sweep.next_x().is_none());
+        assert!(events.windows(2).all(|pair| pair[0].x <= pair[1].x));
+        // The last birth crosses both falling mountains
+        assert!(sweep.intersections() == 2 && events.iter().map(|e| e.intersections).sum::<usize>() == 2);
+        for pair in [0, 2, 3] {
+            let kinds: Vec<_> = events.iter().filter(|e| e.pair == pair).map(|e| e.kind).collect();
+            assert!(kinds == [SweepEventKind::Birth, SweepEventKind::Peak, SweepEventKind::Death]);
+        }
+        assert!(sweep.status().is_empty() && sweep.step().is_none());
+        assert!(sweep.finish() == generate(pairs(), 2, false));
+
+        let mut partial = Sweep::new(pairs(), 0);
+        partial.step();
+        assert!(partial.finish() == generate(pairs(), 0, false));
+    }
+
+    #[test]
+    fn side_by_side_report() {
+        use fast_pl::report::{side_by_side_html, side_by_side_svg};
+
+        let a = vec![vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)], vec![(1.0, 0.0), (2.0, 1.0), (3.0, 0.0)]];
+        let b = vec![vec![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)]];
+        let svg = side_by_side_svg(&a, &b, ["before <a>", "after"]);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\"") && svg.ends_with("</svg>\n"));
+        // Three panels per level and the escaped names in their titles
+        assert!(svg.matches("<rect").count() == 6 && svg.contains("before &lt;a&gt; - after"));
+        assert!(svg.contains("Level 1: L1 0, L2 0, sup 0") && svg.contains("Level 2: L1 1, L2 0.816"));
+        assert!(svg.contains("sup 1 at x = 2"));
+
+        let html = side_by_side_html(&a, &b, ["before", "after"]);
+        assert!(html.starts_with("<!DOCTYPE html>") && html.contains("<svg") && html.ends_with("</html>\n"));
+        assert!(html.contains("<tr><td>2</td><td>1</td>") && html.contains("<tr><td>All</td><td>1</td>"));
+
+        let dir = std::env::temp_dir().join(format!("fast_pl_side_by_side_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        fast_pl::io::write_csv(dir.join("a.csv"), &a).unwrap();
+        fast_pl::io::write_csv(dir.join("b.csv"), &b).unwrap();
+        for (report, start) in [("report.svg", "<svg"), ("report.html", "<!DOCTYPE html>")] {
+            let report = dir.join(report);
+            super::diff(&dir.join("a.csv"), &dir.join("b.csv"), None, None, None, Some(&report)).unwrap();
+            assert!(std::fs::read_to_string(&report).unwrap().starts_with(start));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn robust_parsing() {
+        use clap::Parser;
+        use fast_pl::birthdeath::BirthDeath;
+        use fast_pl::io::{read_diagram, read_diagram_with, read_table, Delimiter, Header, ReadOptions};
+
+        let decimal_comma = ReadOptions { header: Header::Detect, decimal_comma: true };
+        let path = std::env::temp_dir().join(format!("fast_pl_robust_{}.csv", std::process::id()));
+        let pair = |path: &std::path::Path, options: ReadOptions| {
+            let diagram = read_diagram_with(path, options).unwrap();
+            assert!(diagram.len() == 1);
+            (diagram[0].birth, diagram[0].death)
+        };
+        let close = |(birth, death): (f64, f64), expected: (f64, f64)| {
+            (birth - expected.0).abs() < 1e-12 && (death - expected.1).abs() < 1e-12
+        };
+
+        std::fs::write(&path, "birth,death\n \"+1.5e0\" , \" 2.5E+1 \"\n").unwrap();
+        assert!(close(pair(&path, Header::Detect.into()), (1.5, 25.0)));
+        std::fs::write(&path, "+.5\t-1e-1\n").unwrap();
+        assert!(close(pair(&path, Header::Detect.into()), (0.5, -0.1)));
+        std::fs::write(&path, "birth;death\n1.5;2\n").unwrap();
+        assert!(Delimiter::detect("1.5;2\n") == Delimiter::Semicolon);
+        assert!(close(pair(&path, Header::Detect.into()), (1.5, 2.0)));
+
+        std::fs::write(&path, "birth;death\n\"1,5\";2,25\n").unwrap();
+        assert!(close(pair(&path, decimal_comma), (1.5, 2.25)));
+        let error = read_diagram(&path).unwrap_err().to_string();
+        assert!(error.contains("line 2") && error.contains("decimal comma"));
+        std::fs::write(&path, "1,5,2,25\n").unwrap();
+        assert!(read_diagram(&path).unwrap_err().to_string().contains("decimal comma"));
+        std::fs::write(&path, "1,5 2,25\n").unwrap();
+        assert!(close(pair(&path, decimal_comma), (1.5, 2.25)));
+        assert!(read_table(&path, decimal_comma).unwrap() == [vec![1.5, 2.25]]);
+        std::fs::write(&path, "1 x\n").unwrap();
+        assert!(read_diagram(&path).unwrap_err().to_string().contains("Malformed pair"));
+        let BirthDeath { birth, death } = " +1.5e1 ; 2 ".parse::<BirthDeath>().unwrap();
+        assert!(close((birth, death), (15.0, 2.0)));
+
+        let plain = std::env::temp_dir().join(format!("fast_pl_robust_plain_{}.csv", std::process::id()));
+        std::fs::write(&plain, "0 4\n1 3.5\n2 6\n").unwrap();
+        std::fs::write(&path, "0;4\n1;3,5\n2;6\n").unwrap();
+        let mut outputs = Vec::new();
+        for (input, extra) in [(&plain, None), (&path, Some("--decimal-comma"))] {
+            let output = std::env::temp_dir().join(format!("fast_pl_robust_out_{}_{}.csv", outputs.len(), std::process::id()));
+            let mut args = vec!["fast_pl", "-q", "-k", "2", "-n", input.to_str().unwrap(), "--csv", output.to_str().unwrap()];
+            args.extend(extra);
+            assert!(super::run(&super::Args::parse_from(args)).is_ok());
+            outputs.push(std::fs::read_to_string(&output).unwrap());
+            std::fs::remove_file(&output).unwrap();
+        }
+        assert!(!outputs[0].is_empty() && outputs[0] == outputs[1]);
+        std::fs::remove_file(&plain).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn landscape_set() {
+        use fast_pl::io::{read_landscape_set, write_landscape_set_to, WriteOptions};
+        use fast_pl::landscapeset::LandscapeSet;
+
+        let tent = |height: f64| vec![vec![(0.0, 0.0), (height, height), (2.0 * height, 0.0)]];
+        let mut set = LandscapeSet::from_dimensions([tent(1.0), tent(2.0)], Some("a"));
+        assert!(set.insert_sample(0, "b", tent(3.0)).is_none());
+        assert!(set.insert_sample(1, "b", vec![vec![], vec![]]).is_none());
+        assert!(set.insert_sample(0, "a", tent(1.0)).is_some());
+        assert!(set.len() == 4 && set.dimensions() == [0, 1] && set.samples() == ["a", "b"]);
+        assert!(set.dimension(1).map(|(sample, _)| sample).eq([Some("a"), Some("b")]));
+
+        let mean = set.mean();
+        assert!(mean.len() == 2 && mean.samples().is_empty());
+        // The mean of the tents of height one and three is piecewise linear with a kink at 2
+        let zero = mean.get(0, None).unwrap();
+        assert!((zero.evaluate(0, 1.0) - 1.0).abs() < 1e-12 && (zero.evaluate(0, 3.0) - 1.5).abs() < 1e-12);
+        let norms = set.norms();
+        assert!((norms[&(0, Some("b".to_string()))][0].sup - 3.0).abs() < 1e-12);
+        assert!(norms[&(1, Some("b".to_string()))].iter().all(|n| n.l1 == 0.0));
+
+        let doubled = set.map(|l| l * 2.0);
+        assert!((doubled.get(1, Some("a")).unwrap().evaluate(0, 2.0) - 4.0).abs() < 1e-12);
+        set.insert(2, tent(0.5));
+        let path = std::env::temp_dir().join(format!("fast_pl_landscape_set_{}.csv", std::process::id()));
+        write_landscape_set_to(std::fs::File::create(&path).unwrap(), &set, &WriteOptions::default()).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("dimension,sample,level,x,y\n0,a,1,0,0\n") && written.contains("\n2,,1,0.5,0.5\n"));
+        let read = read_landscape_set(&path).unwrap();
+        assert!(read == set && read.get(1, Some("b")).unwrap().k() == 2);
+        std::fs::write(&path, "dimension,sample,level,x,y\n0,,0,1,1\n").unwrap();
+        assert!(read_landscape_set(&path).is_err());
+        assert!(set.remove(2, None).is_some() && set.get(2, None).is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rank_surface() {
+        use clap::Parser;
+        use fast_pl::birthdeath::{betti_at, pairs, rank_function, RankFunction};
+        use fast_pl::io::{write_rank_function_to, write_rank_grid, write_rank_grid_npy, NumberFormat};
+        use fast_pl::vectorize::{decode, Grid, Precision};
+
+        let diagram = pairs([(0.0, 4.0), (1.0, 3.0), (2.0, f64::INFINITY), (3.0, 5.0), (1.0, 4.0), (f64::NAN, 1.0)]);
+        let rank = RankFunction::new(&diagram);
+        assert!(rank.births == [0.0, 1.0, 2.0, 3.0] && rank.deaths == [3.0, 4.0, 5.0, f64::INFINITY]);
+        for b in [-1.0, 0.0, 0.5, 1.0, 2.5, 3.0, 4.5, 6.0] {
+            for d in [-1.0, 0.5, 2.0, 3.0, 3.5, 4.0, 5.0, 7.0] {
+                assert_eq!(rank.evaluate(b, d), betti_at(&diagram, b, d));
+            }
+        }
+        assert!(rank.evaluate(f64::NAN, 1.0) == 0 && RankFunction::new(&[]).evaluate(0.0, 1.0) == 0);
+        let grid = Grid::new(0.0, 5.0, 11).unwrap();
+        assert!(rank.sample(&grid) == rank_function(&diagram, &grid));
+
+        let mut out = Vec::new();
+        write_rank_function_to(&mut out, &RankFunction::new(&pairs([(0.0, 2.0), (1.0, 3.0)])), NumberFormat::Shortest).unwrap();
+        assert!(String::from_utf8(out).unwrap() == ",2,3\n0,1,0\n1,2,1\n");
+
+        let dir = std::env::temp_dir().join(format!("fast_pl_rank_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let raw = dir.join("rank.bin");
+        write_rank_grid(&raw, &diagram, &grid, Precision::F32).unwrap();
+        let values = decode(&std::fs::read(&raw).unwrap(), Precision::F32).unwrap();
+        #[allow(clippy::cast_precision_loss)]
+        let expected: Vec<f64> = rank_function(&diagram, &grid).into_iter().flatten().map(|n| n as f64).collect();
+        assert!(values == expected);
+        let npy = dir.join("rank.npy");
+        write_rank_grid_npy(&npy, &diagram, &grid, Precision::F16).unwrap();
+        let bytes = std::fs::read(&npy).unwrap();
+        assert!(bytes.starts_with(b"\x93NUMPY") && bytes.len() == 128 + 2 * 11 * 11);
+        assert!(String::from_utf8_lossy(&bytes).contains("'shape': (11, 11)"));
+        assert!(write_rank_grid_npy(&npy, &diagram, &grid, Precision::Bf16).is_err());
+
+        let input = dir.join("diagram.txt");
+        std::fs::write(&input, "0 2\n1 3\n").unwrap();
+        let (exact, array, png) = (dir.join("exact.csv"), dir.join("cli.npy"), dir.join("rank.png"));
+        let args = ["fast_pl", "rank", input.to_str().unwrap(), "--exact", "-o", exact.to_str().unwrap(),
+            "--resolution", "5", "--grid", array.to_str().unwrap()];
+        assert!(super::run(&super::Args::parse_from(args)).is_ok());
+        assert!(std::fs::read_to_string(&exact).unwrap() == ",2,3\n0,1,0\n1,2,1\n");
+        assert!(std::fs::read(&array).unwrap().len() == 128 + 4 * 5 * 5);
+        let plot = super::run(&super::Args::parse_from(["fast_pl", "rank", input.to_str().unwrap(), "-o",
+            exact.to_str().unwrap(), "--plot", png.to_str().unwrap()]));
+        assert!(plot.is_ok() == cfg!(feature = "plot") && png.exists() == cfg!(feature = "plot"));
+        assert!(super::Args::try_parse_from(["fast_pl", "rank", "x", "--birth", "0", "--death", "1", "--exact"]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,158 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::vectorize::{sample, Grid};
+
+/// Landscape returned by [`LandscapeIndex::query`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Neighbor {
+    /// Position of the landscape in the input of [`LandscapeIndex::new`]
+    pub index: usize,
+    /// L2 distance of the sampled landscapes, see [`LandscapeIndex`]
+    pub distance: f64,
+}
+
+/// How candidates are found, exact unless an approximation is asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Search {
+    /// Compare the query with every landscape
+    #[default]
+    Exact,
+    /// Compare random projections of `dims` dimensions first and only rank the `candidates`
+    /// closest projections exactly
+    Projected { dims: usize, candidates: usize, seed: u64 },
+}
+
+/// k-nearest-neighbour index over landscapes sampled on a common grid
+///
+/// Distances are the Euclidean distance of the samples scaled by the square root of the grid
+/// spacing, which converges to the L2 distance of the landscapes as the resolution grows.
+#[derive(Debug, Clone)]
+pub struct LandscapeIndex {
+    k: usize,
+    grid: Grid,
+    scale: f64,
+    vectors: Vec<Vec<f64>>,
+    projection: Option<Projection>,
+}
+
+#[derive(Debug, Clone)]
+struct Projection {
+    candidates: usize,
+    directions: Vec<Vec<f64>>,
+    projected: Vec<Vec<f64>>,
+}
+
+impl Projection {
+    fn project(&self, vector: &[f64]) -> Vec<f64> {
+        self.directions
+            .iter()
+            .map(|d| d.iter().zip(vector).map(|(a, b)| a * b).sum())
+            .collect()
+    }
+
+    /// The indexed landscapes with the `candidates` (at least `n`) closest projections
+    fn closest(&self, query: &[f64], n: usize) -> Vec<usize> {
+        let query = self.project(query);
+        let mut order: Vec<(f64, usize)> = self
+            .projected
+            .par_iter()
+            .map(|p| squared_distance(p, &query))
+            .enumerate()
+            .map(|(i, d)| (d, i))
+            .collect();
+        order.sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        order.into_iter().take(self.candidates.max(n)).map(|(_, i)| i).collect()
+    }
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+impl LandscapeIndex {
+    /// Samples the first `k` levels of every landscape on `grid`
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if a projected search has no dimensions or candidates
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(
+        landscapes: &[Vec<Vec<(f64,f64)>>],
+        k: usize,
+        grid: Grid,
+        search: Search,
+    ) -> Result<Self, &'static str> {
+        let vectors: Vec<Vec<f64>> = landscapes.par_iter().map(|l| sample(l, k, &grid)).collect();
+        let spacing = if grid.resolution > 1 {
+            (grid.x_max - grid.x_min) / (grid.resolution - 1) as f64
+        } else {
+            1.0
+        };
+        let projection = match search {
+            Search::Exact => None,
+            Search::Projected { dims, candidates, .. } if dims == 0 || candidates == 0 => {
+                return Err("Projected search needs at least one dimension and candidate");
+            }
+            Search::Projected { dims, candidates, seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let directions: Vec<Vec<f64>> = (0..dims)
+                    .map(|_| {
+                        (0..k * grid.resolution)
+                            .map(|_| standard_normal(&mut rng))
+                            .collect()
+                    })
+                    .collect();
+                let mut projection = Projection { candidates, directions, projected: Vec::new() };
+                projection.projected = vectors.par_iter().map(|v| projection.project(v)).collect();
+                Some(projection)
+            }
+        };
+        Ok(Self { k, grid, scale: spacing.sqrt(), vectors, projection })
+    }
+
+    /// Number of indexed landscapes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// The `n` indexed landscapes closest to `landscape`, nearest first
+    #[must_use]
+    pub fn query(&self, landscape: &[Vec<(f64,f64)>], n: usize) -> Vec<Neighbor> {
+        let query = sample(landscape, self.k, &self.grid);
+        let candidates: Vec<usize> = self
+            .projection
+            .as_ref()
+            .map_or_else(|| (0..self.vectors.len()).collect(), |p| p.closest(&query, n));
+        let mut neighbors: Vec<Neighbor> = candidates
+            .par_iter()
+            .map(|&index| Neighbor {
+                index,
+                distance: squared_distance(&self.vectors[index], &query).sqrt() * self.scale,
+            })
+            .collect();
+        neighbors.sort_unstable_by(|a, b| a.distance.total_cmp(&b.distance).then(a.index.cmp(&b.index)));
+        neighbors.truncate(n);
+        neighbors
+    }
+}
+
+/// Standard normal sample with the Box-Muller transform
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let (u, v): (f64, f64) = (rng.gen(), rng.gen());
+    (-2.0 * (1.0 - u).ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+}
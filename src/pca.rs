@@ -0,0 +1,145 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+
+use crate::vectorize::{sample, unsample, Grid};
+
+/// Principal components of a set of landscapes sampled on a grid
+#[derive(Debug, Clone)]
+pub struct Pca {
+    /// Mean landscape on the grid
+    pub mean: Vec<Vec<(f64,f64)>>,
+    /// Unit-norm component landscapes on the grid, by decreasing explained variance
+    pub components: Vec<Vec<Vec<(f64,f64)>>>,
+    /// Variance explained by every component
+    pub explained_variance: Vec<f64>,
+    /// Projection of every input landscape onto the components, indexed [sample][component]
+    pub scores: Vec<Vec<f64>>,
+}
+
+/// Eigen decomposition of a symmetric matrix with the cyclic Jacobi method. Returns the
+/// eigenvalues and the matrix whose columns are the matching eigenvectors.
+fn symmetric_eigen(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut vectors: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let scale: f64 = a.iter().flatten().map(|x| x * x).sum();
+    for _ in 0..100 {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off_diagonal <= f64::EPSILON * f64::EPSILON * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let tan = theta.signum() / (theta.abs() + theta.mul_add(theta, 1.0).sqrt());
+                let cos = 1.0 / tan.mul_add(tan, 1.0).sqrt();
+                let sin = tan * cos;
+                for row in &mut a {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = cos.mul_add(kp, -sin * kq);
+                    row[q] = sin.mul_add(kp, cos * kq);
+                }
+                for k in 0..n {
+                    let (pk, qk) = (a[p][k], a[q][k]);
+                    a[p][k] = cos.mul_add(pk, -sin * qk);
+                    a[q][k] = sin.mul_add(pk, cos * qk);
+                }
+                for row in &mut vectors {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = cos.mul_add(kp, -sin * kq);
+                    row[q] = sin.mul_add(kp, cos * kq);
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i][i]).collect(), vectors)
+}
+
+/// Principal component analysis of landscapes sampled on `grid`.
+///
+/// The decomposition is done on the N x N Gram matrix of the centered samples, which is much
+/// smaller than the covariance matrix when the grid is finer than the number of landscapes.
+/// Every component is signed so that its largest entry is positive.
+///
+/// # Errors
+///
+/// Will return 'Err' if fewer than two landscapes are given or `n_components` is zero
+pub fn pca(
+    landscapes: &[Vec<Vec<(f64,f64)>>],
+    grid: &Grid,
+    n_components: usize,
+) -> Result<Pca, &'static str> {
+    if landscapes.len() < 2 {
+        return Err("PCA requires at least two landscapes");
+    }
+    if n_components == 0 {
+        return Err("Number of components must be positive");
+    }
+    let n = landscapes.len();
+    let count = f64::from(u32::try_from(n).map_err(|_| "Too many landscapes")?);
+    let k = landscapes.iter().map(Vec::len).max().unwrap_or(0);
+    let mut samples: Vec<Vec<f64>> = landscapes.iter().map(|l| sample(l, k, grid)).collect();
+    let dims = k * grid.resolution;
+    let mean: Vec<f64> = (0..dims)
+        .map(|d| samples.iter().map(|s| s[d]).sum::<f64>() / count)
+        .collect();
+    for s in &mut samples {
+        s.iter_mut().zip(&mean).for_each(|(x, m)| *x -= m);
+    }
+
+    let gram: Vec<Vec<f64>> = samples
+        .iter()
+        .map(|a| samples.iter().map(|b| a.iter().zip(b).map(|(x, y)| x * y).sum()).collect())
+        .collect();
+    let (eigenvalues, eigenvectors) = symmetric_eigen(gram);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|&i| FloatOrd(-eigenvalues[i]));
+
+    let mut result = Pca {
+        mean: unsample(&mean, grid),
+        components: Vec::new(),
+        explained_variance: Vec::new(),
+        scores: vec![Vec::new(); n],
+    };
+    let largest_eigenvalue = eigenvalues[order[0]];
+    for &j in order.iter().take(n_components.min(dims)) {
+        let eigenvalue = eigenvalues[j].max(0.0);
+        // Remaining directions only carry rounding noise
+        if eigenvalue <= 1e-12 * largest_eigenvalue {
+            break;
+        }
+        let mut component: Vec<f64> = (0..dims)
+            .map(|d| (0..n).map(|i| samples[i][d] * eigenvectors[i][j]).sum())
+            .collect();
+        let norm = component.iter().map(|x| x * x).sum::<f64>().sqrt();
+        if norm <= f64::EPSILON {
+            break;
+        }
+        let largest = component
+            .iter()
+            .copied()
+            .max_by_key(|x| FloatOrd(x.abs()))
+            .unwrap_or(1.0);
+        let sign = largest.signum() / norm;
+        component.iter_mut().for_each(|x| *x *= sign);
+        for (score, s) in result.scores.iter_mut().zip(&samples) {
+            score.push(s.iter().zip(&component).map(|(x, c)| x * c).sum());
+        }
+        result.explained_variance.push(eigenvalue / (count - 1.0));
+        result.components.push(unsample(&component, grid));
+    }
+    Ok(result)
+}
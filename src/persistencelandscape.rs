@@ -5,28 +5,42 @@
      clippy::cargo,
  )]
 
-use crate::birthdeath::BirthDeath;
+use crate::birthdeath::{check, BirthDeath, InvalidPairs, Strictness};
+use crate::pipeline::{Rescaling, Units};
 use float_ord::FloatOrd;
 use geo::{
     line_intersection::line_intersection, line_intersection::LineIntersection, Coord, Line
 };
-use std::cmp::min;
+use rayon::prelude::*;
+use std::cmp::{min, Reverse};
 use std::collections::{BinaryHeap, VecDeque};
+use std::convert::Infallible;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+use std::fmt;
 
-#[derive(Debug)]
-struct PersistenceMountain {
-    position: Option<usize>,
-    slope_rising: bool,
-    birth: PointOrd,
-    middle: PointOrd,
-    death: PointOrd,
-    id: usize,
+/// Mountains of the sweep as parallel arrays indexed by mountain id
+///
+/// The position and slope change on every event and are kept apart from the critical points,
+/// which are only read, so the sweep touches as little memory as possible.
+#[derive(Debug, Default)]
+struct Mountains {
+    position: Vec<Option<usize>>,
+    slope_rising: Vec<bool>,
+    birth: Vec<PointOrd>,
+    middle: Vec<PointOrd>,
+    death: Vec<PointOrd>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct PointOrd {
-    pub x: FloatOrd<f64>,
-    pub y: FloatOrd<f64>,
+pub(crate) struct PointOrd {
+    pub(crate) x: FloatOrd<f64>,
+    pub(crate) y: FloatOrd<f64>,
 }
 
 impl Ord for PointOrd {
@@ -56,6 +70,7 @@ enum EventType {
 }
 
 #[derive(Debug)]
+#[allow(clippy::struct_field_names)]
 struct Event {
     value: PointOrd,
     event_type: EventType,
@@ -65,18 +80,13 @@ struct Event {
 
 // NOTE: This is opposite on purpose to flip to built in BinaryHeap
 impl Ord for Event {
-    // Compare points then event_type
+    // Compare x then event_type, the height of the point does not take part so that events
+    // at the same x with different heights still fall back to the event type
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.value != other.value{
-            return self.value.cmp(&other.value).reverse()
-        }
-        // if self.event_type != other.event_type{
-        self.event_type.cmp(&other.event_type).reverse()
-        // }
-        // if self.parent_mountain_id != other.parent_mountain_id {
-        //     return self.parent_mountain_id.cmp(&other.parent_mountain_id).reverse();
-        // }
-        // self.parent_mountain2_id.cmp(&other.parent_mountain2_id).reverse()
+        self.value
+            .cmp(&other.value)
+            .then_with(|| self.event_type.cmp(&other.event_type))
+            .reverse()
     }
 }
 
@@ -95,107 +105,172 @@ impl PartialEq for Event {
 
 impl Eq for Event {}
 
-fn create_mountain(birth: f64, death: f64, index: usize) -> PersistenceMountain {
-    let half_dist = (death - birth) / 2.0;
+/// Shape of the function every pair contributes, the landscape is the k-th largest of them
+///
+/// The sweep relies on all mountains rising with one slope and falling with another, so that
+/// two rising or two falling segments never cross. Profiles with more slopes, such as
+/// trapezoids, would need intersections to be queued by position instead.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Profile {
+    /// Rises and falls with slope one, peaking at the midpoint of the pair
+    #[default]
+    Tent,
+    /// Rises with slope `rise` from the birth and falls with slope `fall` to the death
+    Triangle { rise: f64, fall: f64 },
+}
+
+impl Profile {
+    /// Peak of the profile of a pair
+    fn peak(self, birth: f64, death: f64) -> (f64, f64) {
+        match self {
+            Self::Tent => {
+                let half_dist = (death - birth) / 2.0;
+                (half_dist + birth, half_dist)
+            }
+            Self::Triangle { rise, fall } => {
+                let x = rise.mul_add(birth, fall * death) / (rise + fall);
+                (x, rise * (x - birth))
+            }
+        }
+    }
+}
+
+impl Mountains {
+    fn len(&self) -> usize {
+        self.position.len()
+    }
+
+    /// Removes every mountain, keeping the memory
+    #[cfg(feature = "arena")]
+    fn clear(&mut self) {
+        self.position.clear();
+        self.slope_rising.clear();
+        self.birth.clear();
+        self.middle.clear();
+        self.death.clear();
+    }
 
-    PersistenceMountain {
-        position: None,
-        slope_rising: true,
-        birth: PointOrd {
+    /// Adds the mountain of a pair with `profile`, its id is the number of mountains before it
+    fn push(&mut self, birth: f64, death: f64, profile: Profile) {
+        let (peak_x, peak_y) = profile.peak(birth, death);
+        self.position.push(None);
+        self.slope_rising.push(true);
+        self.birth.push(PointOrd {
             x: FloatOrd(birth),
             y: FloatOrd(0.0),
-        },
-        middle: PointOrd {
-            x: FloatOrd(half_dist + birth),
-            y: FloatOrd(half_dist),
-        },
-        death: PointOrd {
+        });
+        self.middle.push(PointOrd {
+            x: FloatOrd(peak_x),
+            y: FloatOrd(peak_y),
+        });
+        self.death.push(PointOrd {
             x: FloatOrd(death),
             y: FloatOrd(0.0),
-        },
-        id: index,
+        });
     }
-}
 
-fn generate_mountains(bd_pairs: Vec<BirthDeath>) -> Vec<PersistenceMountain> {
-    bd_pairs
-        .into_iter()
-        .filter(|BirthDeath { birth, death }| death.is_finite() && birth.is_finite())
-        .enumerate()
-        .map(|(i, BirthDeath { birth, death })| create_mountain(birth, death, i))
-        .collect::<Vec<PersistenceMountain>>()
-}
+    /// Same as [`Mountains::push`] but takes the id of a dead mountain from `free` if there
+    /// is one, returns the id of the new mountain
+    fn insert(&mut self, free: &mut Vec<usize>, birth: f64, death: f64, profile: Profile) -> usize {
+        self.push(birth, death, profile);
+        let Some(id) = free.pop() else {
+            return self.len() - 1;
+        };
+        self.position.swap_remove(id);
+        self.slope_rising.swap_remove(id);
+        self.birth.swap_remove(id);
+        self.middle.swap_remove(id);
+        self.death.swap_remove(id);
+        id
+    }
 
-fn generate_initial_events(mountains: &Vec<&mut PersistenceMountain>) -> Vec<Event> {
-    mountains
-        .iter()
-        .flat_map(
-            |PersistenceMountain {
-                 birth,
-                 middle,
-                 death,
-                 id,
-                 ..
-             }| {
-                vec![
-                    Event {
-                        value: birth.clone(),
-                        event_type: EventType::Up,
-                        parent_mountain_id: *id,
-                        parent_mountain2_id: None,
-                    },
-                    Event {
-                        value: middle.clone(),
-                        event_type: EventType::Down,
-                        parent_mountain_id: *id,
-                        parent_mountain2_id: None,
-                    },
-                    Event {
-                        value: death.clone(),
-                        event_type: EventType::Death,
-                        parent_mountain_id: *id,
-                        parent_mountain2_id: None,
-                    },
-                ]
+    fn base_events(&self, id: usize) -> [Event; 3] {
+        [
+            Event {
+                value: self.birth[id].clone(),
+                event_type: EventType::Up,
+                parent_mountain_id: id,
+                parent_mountain2_id: None,
             },
-        )
-        .collect()
-}
+            Event {
+                value: self.middle[id].clone(),
+                event_type: EventType::Down,
+                parent_mountain_id: id,
+                parent_mountain2_id: None,
+            },
+            Event {
+                value: self.death[id].clone(),
+                event_type: EventType::Death,
+                parent_mountain_id: id,
+                parent_mountain2_id: None,
+            },
+        ]
+    }
 
-const fn current_segment_start(mountain: &PersistenceMountain) -> (f64, f64) {
-    if mountain.slope_rising {
-            ( mountain.birth.x.0, mountain.birth.y.0 )
-    } else { 
-        ( mountain.middle.x.0, mountain.middle.y.0 )
+    fn initial_events(&self) -> Vec<Event> {
+        let mut events = Vec::with_capacity(3 * self.len());
+        events.extend((0..self.len()).flat_map(|id| self.base_events(id)));
+        events
     }
-}
 
-const fn current_segment_end(mountain: &PersistenceMountain) -> (f64,f64) {
-    if mountain.slope_rising {
-        ( mountain.middle.x.0, mountain.middle.y.0 )
-    } else {
-        ( mountain.death.x.0, mountain.death.y.0)
+    fn current_segment_start(&self, id: usize) -> (f64, f64) {
+        if self.slope_rising[id] {
+            ( self.birth[id].x.0, self.birth[id].y.0 )
+        } else {
+            ( self.middle[id].x.0, self.middle[id].y.0 )
+        }
     }
-}
 
-fn create_line_segment(mountain: &PersistenceMountain) -> Line<f64> {
-    Line {
-        start: current_segment_start(mountain).into(),
-        end: current_segment_end(mountain).into(),
+    fn current_segment_end(&self, id: usize) -> (f64,f64) {
+        if self.slope_rising[id] {
+            ( self.middle[id].x.0, self.middle[id].y.0 )
+        } else {
+            ( self.death[id].x.0, self.death[id].y.0)
+        }
+    }
+
+    fn line_segment(&self, id: usize) -> Line<f64> {
+        Line {
+            start: self.current_segment_start(id).into(),
+            end: self.current_segment_end(id).into(),
+        }
     }
+
+    /// Height of the current segment of mountain `id` at `x`
+    fn value_at(&self, id: usize, x: f64) -> f64 {
+        let (x0, y0) = self.current_segment_start(id);
+        let (x1, y1) = self.current_segment_end(id);
+        if x1 <= x0 {
+            return y1;
+        }
+        (y1 - y0).mul_add((x - x0) / (x1 - x0), y0)
+    }
+}
+
+fn generate_mountains(bd_pairs: Vec<BirthDeath>) -> Mountains {
+    generate_profiled_mountains(bd_pairs, Profile::Tent)
+}
+
+fn generate_profiled_mountains(bd_pairs: Vec<BirthDeath>, profile: Profile) -> Mountains {
+    let mut mountains = Mountains::default();
+    bd_pairs
+        .into_iter()
+        .filter(|BirthDeath { birth, death }| death.is_finite() && birth.is_finite())
+        .for_each(|BirthDeath { birth, death }| mountains.push(birth, death, profile));
+    mountains
 }
 
-fn intersects_with_neighbor(m1: &PersistenceMountain, m2: &PersistenceMountain) -> Option<PointOrd> {
-    if m1.slope_rising == m2.slope_rising {
+fn intersects_with_neighbor(mountains: &Mountains, m1: usize, m2: usize) -> Option<PointOrd> {
+    if mountains.slope_rising[m1] == mountains.slope_rising[m2] {
         return None;
     }
-    let inter = line_intersection(create_line_segment(m1), create_line_segment(m2));
+    let inter = line_intersection(mountains.line_segment(m1), mountains.line_segment(m2));
     match inter {
         Some(LineIntersection::SinglePoint {
             intersection: Coord { x, y },
             ..
         }) => Some(PointOrd {
-            x: min(FloatOrd(x), min(m1.death.x, m2.death.x)),
+            x: min(FloatOrd(x), min(mountains.death[m1].x, mountains.death[m2].x)),
             y: FloatOrd(y),
         }),
         // Ignore all colinnear, not proper and no intersection results these will be resolved on
@@ -219,7 +294,6 @@ fn float_point_check(p1: (f64,f64), p2: (f64,f64))-> bool{
 }
 
 fn log_checks(
-    _mountain: &PersistenceMountain,
     event: &Event,
     landscapes: &[Vec<(f64,f64)>],
     _k: usize,
@@ -275,24 +349,64 @@ fn log_checks(
         true
 }
 
+/// Pairs that produced a critical point, as indices into the pairs given to
+/// [`generate_with_provenance`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Source {
+    /// The pair whose mountain the point lies on
+    pub pair: usize,
+    /// The pair it crosses, if the point is an intersection of two mountains
+    pub crossing: Option<usize>,
+}
+
 fn log_to_landscape(
-    mountain: &PersistenceMountain,
+    mountains: &Mountains,
+    id: usize,
     event: &Event,
-    landscapes: &mut [Vec<(f64,f64)>],
-    k: usize,
-    mountain2: Option<&PersistenceMountain>
+    landscapes: &mut Vec<Vec<(f64,f64)>>,
+    levels: Range<usize>,
+    crossing: Option<usize>,
+    mut provenance: Option<&mut Vec<Vec<Source>>>,
 ) {
-    let position = mountain.position.expect("Mountain with event is dead");
-    if position < k &&
-        log_checks(mountain, event, landscapes, k, position){
-            landscapes[position].push((event.value.x.0, event.value.y.0));
+    // Without a level limit the output grows as mountains reach new depths
+    let depth = [mountains.position[id], crossing.and_then(|m| mountains.position[m])]
+        .into_iter()
+        .flatten()
+        .filter(|position| levels.contains(position))
+        .map(|position| position - levels.start + 1)
+        .max()
+        .unwrap_or(0);
+    if landscapes.len() < depth {
+        landscapes.resize_with(depth, Vec::new);
+    }
+    if let Some(provenance) = provenance.as_deref_mut() {
+        if provenance.len() < depth {
+            provenance.resize_with(depth, Vec::new);
+        }
+    }
+    let position = mountains.position[id].expect("Mountain with event is dead");
+    if levels.contains(&position) &&
+        log_checks(event, landscapes, levels.end, position - levels.start){
+            landscapes[position - levels.start].push((event.value.x.0, event.value.y.0));
+            if let Some(provenance) = provenance.as_deref_mut() {
+                provenance[position - levels.start].push(Source {
+                    pair: id,
+                    crossing,
+                });
+            }
     }
 
-    if let Some(m2) = mountain2{
-        let position = m2.position.expect("Mountain with event is dead");
-        if position < k &&
-            log_checks(m2, event, landscapes, k, position){
-                landscapes[position].push((event.value.x.0, event.value.y.0));
+    if let Some(m2) = crossing{
+        let position = mountains.position[m2].expect("Mountain with event is dead");
+        if levels.contains(&position) &&
+            log_checks(event, landscapes, levels.end, position - levels.start){
+                landscapes[position - levels.start].push((event.value.x.0, event.value.y.0));
+                if let Some(provenance) = provenance {
+                    provenance[position - levels.start].push(Source {
+                        pair: m2,
+                        crossing: Some(id),
+                    });
+                }
         }
     }
 }
@@ -300,10 +414,10 @@ fn log_to_landscape(
 fn find_intersection(
     status: &VecDeque<usize>,
     parent_mountain_id: usize,
-    mountains: &[&mut PersistenceMountain],
+    mountains: &Mountains,
     direction_to_check: &Direction,
 ) -> Option<Event> {
-    let position = mountains[parent_mountain_id].position.expect("Intersection check for dead mountain");
+    let position = mountains.position[parent_mountain_id].expect("Intersection check for dead mountain");
     // Stop underflow of unsigned number
     if position == 0 && *direction_to_check == Direction::Above {
         return None;
@@ -314,7 +428,7 @@ fn find_intersection(
     };
 
     if let Some(neighbor) = status.get(neighbor_index) {
-        if let Some(intersection) = intersects_with_neighbor(mountains[parent_mountain_id], mountains[*neighbor]) {
+        if let Some(intersection) = intersects_with_neighbor(mountains, parent_mountain_id, *neighbor) {
             return Some(Event {
                 value: intersection,
                 event_type: EventType::Intersection,
@@ -328,6 +442,35 @@ fn find_intersection(
     None
 }
 
+/// Enforces the output contract on every level: x strictly increasing, so no duplicate
+/// consecutive points, and with `endpoints` a zero at both ends of the level
+///
+/// Of several points sharing an x the first is kept. A missing endpoint is added where a
+/// slope of one from the outermost point reaches zero, as the tent it belongs to would.
+pub fn finalize(levels: &mut [Vec<(f64,f64)>], endpoints: bool) {
+    for level in levels {
+        level.dedup_by(|next, kept| next.0 <= kept.0);
+        if !endpoints {
+            continue;
+        }
+        if let Some(&(x, y)) = level.first() {
+            if y != 0.0 {
+                level.insert(0, (x - y.abs(), 0.0));
+            }
+        }
+        if let Some(&(x, y)) = level.last() {
+            if y != 0.0 {
+                level.push((x + y.abs(), 0.0));
+            }
+        }
+    }
+}
+
+/// Level limit used by the sweep, zero means every level
+const fn level_limit(k: usize) -> usize {
+    if k == 0 { usize::MAX } else { k }
+}
+
 #[must_use]
 pub fn empty_landscape(k: usize) -> Vec<Vec<(f64,f64)>>{
     let mut landscapes = Vec::with_capacity(k);
@@ -344,22 +487,24 @@ fn handle_up(state: &mut State, event: &Event){
     state.status.push_back(event.parent_mountain_id);
     assert!(start_len + 1 == state.status.len());
     let position = state.status.len() - 1;
-    state.mountains[event.parent_mountain_id].position = Some(position);
+    state.mountains.position[event.parent_mountain_id] = Some(position);
 
     let parent_mountain_id = event.parent_mountain_id;
     // Add to output if needed
     log_to_landscape(
-        state.mountains[event.parent_mountain_id],
+        &state.mountains,
+        event.parent_mountain_id,
         event,
         &mut state.landscapes,
-        state.k,
-        None
+        state.first_level..state.k,
+        None,
+        state.provenance.as_mut(),
         );
     // Check and handle all intersections
     let new_event = find_intersection(
         &state.status,
         parent_mountain_id,
-        state.mountains,
+        &state.mountains,
         &Direction::Above,
         );
     if let Some(intersection) = new_event{
@@ -370,19 +515,31 @@ fn handle_up(state: &mut State, event: &Event){
 fn handle_intersection(state: &mut State, event: Event){
     state.weird_q.push_back(event);
     while ! state.weird_q.is_empty(){
+        // A cascade of intersections can run long after the last event, so stop it once a
+        // limit is exceeded and leave reporting it to the caller of the sweep
+        if let Some(limits) = state.limits.as_mut() {
+            if let Err(exceeded) = limits.check(state.processed, state.intersections) {
+                limits.exceeded = Some(exceeded);
+                return;
+            }
+        }
         let event = state.weird_q.pop_front().unwrap();
-        let parent_mountain2_id = event
+        state.intersections += 1;
+        let neighbor_id = event
             .parent_mountain2_id
             .expect("Intersection event with no second mountain");
         let parent_mountain_id = event.parent_mountain_id;
+        check_degeneracy(state, &event, neighbor_id);
 
         // Add to ouput if needed
         log_to_landscape(
-            state.mountains[event.parent_mountain_id],
+            &state.mountains,
+            event.parent_mountain_id,
             &event,
             &mut state.landscapes,
-            state.k,
-            Some(state.mountains[parent_mountain2_id])
+            state.first_level..state.k,
+            Some(neighbor_id),
+            state.provenance.as_mut(),
         );
         // log_to_landscape(
         //     state.mountains[parent_mountain2_id], 
@@ -390,37 +547,35 @@ fn handle_intersection(state: &mut State, event: Event){
         //     &mut state.landscapes, 
         //     state.k
         // );
-        let lower_id = if state.mountains[parent_mountain_id].slope_rising {
+        let lower_id = if state.mountains.slope_rising[parent_mountain_id] {
             parent_mountain_id
         } else{
-            parent_mountain2_id
+            neighbor_id
         // )
         };
-        let upper_id = if state.mountains[parent_mountain_id].slope_rising {
-            parent_mountain2_id
+        let upper_id = if state.mountains.slope_rising[parent_mountain_id] {
+            neighbor_id
         } else{
             parent_mountain_id
         };
         // Swap
         state.status.swap(
-            state.mountains[upper_id].position.expect("Dead mountain in intersection event"),
-            state.mountains[lower_id].position.expect("Dead mountain in intersection event"),
+            state.mountains.position[upper_id].expect("Dead mountain in intersection event"),
+            state.mountains.position[lower_id].expect("Dead mountain in intersection event"),
         );
-        assert!(state.mountains[upper_id].position != state.mountains[lower_id].position);
-        let tmp = state.mountains[lower_id].position;
-        state.mountains[lower_id].position = state.mountains[upper_id].position;
-        state.mountains[upper_id].position = tmp;
+        assert!(state.mountains.position[upper_id] != state.mountains.position[lower_id]);
+        state.mountains.position.swap(lower_id, upper_id);
         // Check for intersections
         // Must check both ways because of no sorting, intersections can be discovered in both
         // directions
         if let Some(new_event) =
-            find_intersection(&state.status, lower_id, state.mountains, &Direction::Above)
+            find_intersection(&state.status, lower_id, &state.mountains, &Direction::Above)
         {
             // handle_intersection(state, &new_event);
             state.weird_q.push_back(new_event);
         }
         if let Some(new_event) =
-            find_intersection(&state.status, upper_id, state.mountains, &Direction::Below)
+            find_intersection(&state.status, upper_id, &state.mountains, &Direction::Below)
         {
             // handle_intersection(state, new_event);
             state.weird_q.push_back(new_event);
@@ -429,63 +584,101 @@ fn handle_intersection(state: &mut State, event: Event){
 }
 
 
+/// Records the intersection `event` of its mountain with `neighbor_id` if it lies within the
+/// tolerance of an endpoint of either mountain or of the next queued event
+fn check_degeneracy(state: &mut State, event: &Event, neighbor_id: usize) {
+    let Some((tolerance, found)) = state.degeneracies.as_mut() else {
+        return;
+    };
+    let x = event.value.x.0;
+    let close = |other: f64| (x - other).abs() <= *tolerance * x.abs().max(1.0);
+    let (m1, m2) = (event.parent_mountain_id, neighbor_id);
+    let mountains = &state.mountains;
+    let near = if [m1, m2]
+        .iter()
+        .flat_map(|&m| [mountains.birth[m].x.0, mountains.middle[m].x.0, mountains.death[m].x.0])
+        .any(close)
+    {
+        Near::Endpoint
+    } else if state.events.peek().is_some_and(|next| close(next.value.x.0)) {
+        Near::Event
+    } else {
+        return;
+    };
+    found.push(Degeneracy {
+        point: (x, event.value.y.0),
+        pairs: (m1, m2),
+        near,
+    });
+}
+
+/// Removes a dying mountain from the status
+///
+/// A dying mountain is at height zero, so only mountains dying at the same x can be below it
+/// and the deaths at one x form a batch at the bottom of the status. Their order within the
+/// batch depends on rounding, so the mountain is removed wherever it is in the batch and every
+/// mountain below it moves up one level, closing the level it leaves at its current height.
 fn handle_death(state: &mut State, event: &Event){
-    let _pos = state.mountains[event.parent_mountain_id]
-        .position
+    let pos = state.mountains.position[event.parent_mountain_id]
         .expect("Death of dead mountain");
-    // Check for floating point mess up on death/intersection Ordering
-    // TODO: What is this???? feels like a bug and a bad hotfix
-    // let weird_q = &mut VecDeque::new();
-    // if pos != state.status.len() - 1 {
-    //     while pos < state.status.len() - 1 {
-    //         weird_q.push_back(state.status.pop_back().unwrap());
-    //     }
-    // }
     let parent_mountain_id = event.parent_mountain_id;
 
     // Add to ouput if needed
     log_to_landscape(
-        state.mountains[event.parent_mountain_id],
+        &state.mountains,
+        event.parent_mountain_id,
         event,
         &mut state.landscapes,
-        state.k,
-        None
+        state.first_level..state.k,
+        None,
+        state.provenance.as_mut(),
         );
     // remove and disable
-    state.status.pop_back();
-    state.mountains[parent_mountain_id].position = None;
-    // TODO: Same here???? L -17
-    // while !weird_q.is_empty() {
-    //     let element = weird_q.pop_back().unwrap();
-    //     state.mountains[element].position = Some(state.mountains[element].position.unwrap() - 1);
-    //     log_to_landscape(
-    //         state.mountains[element],
-    //         event.value,
-    //         &mut state.landscapes,
-    //         state.k,
-    //         );
-    //     state.status.push_back(element);
-    // }
+    state.status.remove(pos);
+    state.mountains.position[parent_mountain_id] = None;
+    for (position, &id) in state.status.iter().enumerate().skip(pos) {
+        let x = event.value.x.0;
+        let moved = Event {
+            value: PointOrd { x: FloatOrd(x), y: FloatOrd(state.mountains.value_at(id, x)) },
+            event_type: EventType::Death,
+            parent_mountain_id: id,
+            parent_mountain2_id: None,
+        };
+        for level in [position + 1, position] {
+            state.mountains.position[id] = Some(level);
+            log_to_landscape(
+                &state.mountains,
+                id,
+                &moved,
+                &mut state.landscapes,
+                state.first_level..state.k,
+                None,
+                state.provenance.as_mut(),
+            );
+        }
+    }
 }
 
 fn handle_down(state: &mut State, event: &Event){
     // Update status structures
-    state.mountains[event.parent_mountain_id].slope_rising = false;
+    state.mountains.slope_rising[event.parent_mountain_id] = false;
     let parent_mountain_id = event.parent_mountain_id;
 
     // Add to ouput if needed
     log_to_landscape(
-        state.mountains[event.parent_mountain_id],
+        &state.mountains,
+        event.parent_mountain_id,
         event,
         &mut state.landscapes,
-        state.k,
-        None
+        state.first_level..state.k,
+        None,
+        state.provenance.as_mut(),
         );
     // Check for intersections
     let new_event = find_intersection(
         &state.status,
         parent_mountain_id,
-        state.mountains,
+        &state.mountains,
         &Direction::Below,
         );
     // println!("{new_event:?}");
@@ -497,50 +690,1868 @@ fn handle_down(state: &mut State, event: &Event){
 
 
 #[derive(Debug)]
-struct State<'a>{
+struct State{
     status: VecDeque<usize>,
-    mountains: &'a mut Vec<&'a mut PersistenceMountain>,
+    mountains: Mountains,
     landscapes: Vec<Vec<(f64,f64)>>,
     events: BinaryHeap<Event>,
     k: usize,
-    weird_q: VecDeque<Event>
+    /// Positions below this are not logged, `landscapes[0]` holds this level
+    first_level: usize,
+    weird_q: VecDeque<Event>,
+    processed: usize,
+    intersections: usize,
+    /// Sources of every logged point, parallel to `landscapes`, when requested
+    provenance: Option<Vec<Vec<Source>>>,
+    /// Tolerance and intersections found within it of another critical point, when requested
+    degeneracies: Option<(f64, Vec<Degeneracy>)>,
+    /// Bounds checked between intersections as well as between events, when requested
+    limits: Option<LimitCheck>,
+}
+
+fn process_event(state: &mut State, event: &Event, debug: bool){
+    if debug{
+        eprintln!("{event:?}");
+    }
+    match event.event_type {
+        EventType::Up => {
+            handle_up(state, event);
+        }
+        EventType::Down => {
+            handle_down(state, event);
+        }
+        EventType::Death => {
+            handle_death(state, event);
+        }
+        EventType::Intersection => unreachable!("Event type should not be here")
+    }
+    state.processed += 1;
 }
 
+impl State {
+    /// Sweep state before the first event, recording the first `k` levels
+    fn new(mountains: Mountains, events: BinaryHeap<Event>, k: usize) -> Self {
+        Self::with_queues(mountains, events, k, VecDeque::new(), VecDeque::new())
+    }
+
+    /// Same as [`State::new`] reusing the memory of `status` and `weird_q`, which are cleared
+    fn with_queues(
+        mountains: Mountains,
+        events: BinaryHeap<Event>,
+        k: usize,
+        mut status: VecDeque<usize>,
+        mut weird_q: VecDeque<Event>,
+    ) -> Self {
+        let capacities = estimate_capacities(mountains.len(), k);
+        let mut landscapes = empty_landscape(k);
+        landscapes.iter_mut().for_each(|level| level.reserve(capacities.level_points));
+        status.clear();
+        status.reserve(capacities.status);
+        weird_q.clear();
+        weird_q.reserve(capacities.intersections);
+        Self {
+            status,
+            mountains,
+            landscapes,
+            events,
+            k: level_limit(k),
+            first_level: 0,
+            weird_q,
+            processed: 0,
+            intersections: 0,
+            provenance: None,
+            degeneracies: None,
+            limits: None,
+        }
+    }
+}
+
+/// Processes events until the queue is empty, calling `after_event` after every event
+fn sweep<E>(
+    state: &mut State,
+    debug: bool,
+    mut after_event: impl FnMut(&State) -> Result<(), E>,
+) -> Result<(), E> {
+    while let Some(event) = state.events.pop(){
+        process_event(state, &event, debug);
+        after_event(state)?;
+    }
+    Ok(())
+}
+
+/// Computes the first `k` levels of the landscape, or every nonempty level if `k` is zero
+///
+/// Every level satisfies the contract of [`finalize`] with endpoints. The diagram is always
+/// swept in one piece on the calling thread, use [`generate_parallel`] to split it into
+/// x-ranges swept on all threads.
+///
 /// # Panics
 ///
 /// Will panic if invalid state is discovered during generation
 #[must_use]
 pub fn generate(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool) -> Vec<Vec<(f64,f64)>> {
-    let mut binding = generate_mountains(bd_pairs);
-    let mut mountains: Vec<&mut PersistenceMountain> 
-        = binding.iter_mut().collect();
-
-    let mut state = State{
-        events: BinaryHeap::from(generate_initial_events(&mountains)),
-        status: VecDeque::new(),
-        mountains: &mut mountains,
-        landscapes: empty_landscape(k),
-        k,
-        weird_q: VecDeque::new(),
-    };
+    sweep_pairs(bd_pairs, k, debug)
+}
 
-    while let Some(event) = state.events.pop(){
-        if debug{
-            println!("{event:?}");
+#[cfg(not(feature = "arena"))]
+fn sweep_pairs(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool) -> Vec<Vec<(f64,f64)>> {
+    sweep_profiles(generate_mountains(bd_pairs), k, debug)
+}
+
+/// Memory of the mountains, events and queues of the sweep, kept between calls of
+/// [`generate`] on the same thread so a tight loop over small diagrams stops allocating once
+/// the buffers are large enough
+#[cfg(feature = "arena")]
+#[derive(Debug, Default)]
+struct Arena {
+    mountains: Mountains,
+    events: Vec<Event>,
+    status: VecDeque<usize>,
+    weird_q: VecDeque<Event>,
+}
+
+#[cfg(feature = "arena")]
+thread_local! {
+    static ARENA: std::cell::RefCell<Arena> = std::cell::RefCell::default();
+}
+
+/// Frees the buffers [`generate`] keeps on the calling thread, they grow back on the next call
+#[cfg(feature = "arena")]
+pub fn release_arena() {
+    ARENA.with(|arena| drop(arena.take()));
+}
+
+/// Bytes held by the buffers [`generate`] keeps on the calling thread
+#[cfg(feature = "arena")]
+#[must_use]
+pub fn arena_capacity() -> usize {
+    ARENA.with(|arena| {
+        let arena = arena.borrow();
+        let mountains = &arena.mountains;
+        mountains.position.capacity() * std::mem::size_of::<Option<usize>>()
+            + mountains.slope_rising.capacity()
+            + (mountains.birth.capacity() + mountains.middle.capacity() + mountains.death.capacity())
+                * std::mem::size_of::<PointOrd>()
+            + (arena.events.capacity() + arena.weird_q.capacity()) * std::mem::size_of::<Event>()
+            + arena.status.capacity() * std::mem::size_of::<usize>()
+    })
+}
+
+#[cfg(feature = "arena")]
+fn sweep_pairs(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool) -> Vec<Vec<(f64,f64)>> {
+    // The buffers are taken out for the sweep, a panic leaves an empty arena behind
+    let Arena { mut mountains, mut events, status, weird_q } = ARENA.with(std::cell::RefCell::take);
+    mountains.clear();
+    bd_pairs
+        .into_iter()
+        .filter(|BirthDeath { birth, death }| death.is_finite() && birth.is_finite())
+        .for_each(|BirthDeath { birth, death }| mountains.push(birth, death, Profile::Tent));
+    events.clear();
+    events.extend((0..mountains.len()).flat_map(|id| mountains.base_events(id)));
+    let mut state = State::with_queues(mountains, BinaryHeap::from(events), k, status, weird_q);
+
+    let Ok(()) = sweep(&mut state, debug, |_| Ok::<(), Infallible>(()));
+
+    finalize(&mut state.landscapes, true);
+    ARENA.with(|arena| {
+        arena.replace(Arena {
+            mountains: state.mountains,
+            events: state.events.into_vec(),
+            status: state.status,
+            weird_q: state.weird_q,
+        })
+    });
+    state.landscapes
+}
+
+/// Same as [`generate`] with every pair contributing `profile` instead of a tent
+///
+/// # Errors
+///
+/// Will return 'Err' if a slope of the profile is not positive and finite
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_with_profile(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    profile: Profile,
+) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if let Profile::Triangle { rise, fall } = profile {
+        if !(rise > 0.0 && rise.is_finite() && fall > 0.0 && fall.is_finite()) {
+            return Err("Profile slopes must be positive and finite");
         }
-        match event.event_type {
-            EventType::Up => {
-                handle_up(&mut state, &event);
-            }
-            EventType::Down => {
-                handle_down(&mut state, &event);
-            }
-            EventType::Death => {
-                handle_death(&mut state, &event);
-            }
-            EventType::Intersection => unreachable!("Event type should not be here")
+    }
+    Ok(sweep_profiles(generate_profiled_mountains(bd_pairs, profile), k, debug))
+}
+
+fn sweep_profiles(mountains: Mountains, k: usize, debug: bool) -> Vec<Vec<(f64,f64)>> {
+    let events = BinaryHeap::from(mountains.initial_events());
+    let mut state = State::new(mountains, events, k);
+
+    let Ok(()) = sweep(&mut state, debug, |_| Ok::<(), Infallible>(()));
+
+    finalize(&mut state.landscapes, true);
+    state.landscapes
+}
+
+/// Same as [`generate`] but with [`Strictness::Strict`] rejects NaN and unexpected infinite
+/// values instead of skipping them
+///
+/// # Errors
+///
+/// Will return 'Err' listing the offending rows if a pair is rejected
+pub fn generate_checked(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    strictness: Strictness,
+) -> Result<Vec<Vec<(f64,f64)>>, InvalidPairs> {
+    check(&bd_pairs, strictness)?;
+    Ok(generate(bd_pairs, k, debug))
+}
+
+/// Optional bounds on the work done by [`generate_limited`], `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of up, down and death events processed
+    pub max_events: Option<usize>,
+    /// Maximum number of intersection events processed
+    pub max_intersections: Option<usize>,
+    /// Maximum wall-clock time spent in the sweep
+    pub max_runtime: Option<Duration>,
+}
+
+/// Limit hit by [`generate_limited`] before the sweep finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Events { limit: usize },
+    Intersections { limit: usize },
+    Runtime { limit: Duration },
+    /// More memory in bytes than allowed, see [`generate_streaming`]
+    Memory { limit: usize },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Events { limit } => write!(f, "Aborted after processing more than {limit} events"),
+            Self::Intersections { limit } => write!(f, "Aborted after processing more than {limit} intersections"),
+            Self::Runtime { limit } => write!(f, "Aborted after running for more than {limit:?}"),
+            Self::Memory { limit } => write!(f, "Aborted as the sweep needed more than {limit} bytes of memory"),
+        }
+    }
+}
+
+impl Error for LimitExceeded {}
+
+/// Limits of a running sweep of [`generate_limited`]
+#[derive(Debug)]
+struct LimitCheck {
+    limits: Limits,
+    start: Instant,
+    /// Limit exceeded in the middle of an event, which the sweep stopped at
+    exceeded: Option<LimitExceeded>,
+}
+
+impl LimitCheck {
+    fn check(&self, processed: usize, intersections: usize) -> Result<(), LimitExceeded> {
+        match self.limits {
+            Limits { max_events: Some(limit), .. } if processed > limit =>
+                Err(LimitExceeded::Events { limit }),
+            Limits { max_intersections: Some(limit), .. } if intersections > limit =>
+                Err(LimitExceeded::Intersections { limit }),
+            Limits { max_runtime: Some(limit), .. } if self.start.elapsed() > limit =>
+                Err(LimitExceeded::Runtime { limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Same as [`generate`] but aborts once any of `limits` is exceeded
+///
+/// Limits are checked after every up, down or death event and before every intersection
+/// it triggers, so a long cascade of intersections is stopped as well.
+///
+/// # Errors
+///
+/// Returns the first limit that was exceeded
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_limited(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    limits: &Limits,
+) -> Result<Vec<Vec<(f64,f64)>>, LimitExceeded> {
+    let mountains = generate_mountains(bd_pairs);
+    let events = BinaryHeap::from(mountains.initial_events());
+    let mut state = State {
+        limits: Some(LimitCheck { limits: *limits, start: Instant::now(), exceeded: None }),
+        ..State::new(mountains, events, k)
+    };
+
+    sweep(&mut state, debug, |state| {
+        let check = state.limits.as_ref().expect("Limits are set for the whole sweep");
+        check.exceeded.map_or_else(|| check.check(state.processed, state.intersections), Err)
+    })?;
+
+    finalize(&mut state.landscapes, true);
+    Ok(state.landscapes)
+}
+
+/// State of the sweep passed to the callback of [`generate_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Up, down and death events processed so far
+    pub processed: usize,
+    /// Up, down and death events of the whole sweep, three per mountain
+    pub total: usize,
+    /// Intersection events processed so far, their number is not known up front
+    pub intersections: usize,
+}
+
+impl Progress {
+    /// Share of the up, down and death events processed, from zero to one
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.processed as f64 / self.total as f64
         }
     }
+}
+
+/// Same as [`generate`] but calls `report` after every `every` up, down or death events and
+/// once more when the sweep is done
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_with_progress(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    every: usize,
+    mut report: impl FnMut(Progress),
+) -> Vec<Vec<(f64,f64)>> {
+    let mountains = generate_mountains(bd_pairs);
+    let events = BinaryHeap::from(mountains.initial_events());
+    let total = events.len();
+    let mut state = State::new(mountains, events, k);
+    let progress = |state: &State| Progress { processed: state.processed, total, intersections: state.intersections };
+
+    let mut reported = 0;
+    let Ok(()) = sweep(&mut state, debug, |state| {
+        if state.processed >= reported + every.max(1) {
+            reported = state.processed;
+            report(progress(state));
+        }
+        Ok::<(), Infallible>(())
+    });
+    report(progress(&state));
 
+    finalize(&mut state.landscapes, true);
     state.landscapes
 }
+
+/// Output of [`generate_report`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// The requested levels, the same as [`generate`] returns
+    pub landscapes: Vec<Vec<(f64,f64)>>,
+    /// Number of nonempty levels of the full landscape, whatever `k` was
+    pub depth: usize,
+    /// Intersections close enough to another critical point that the result may depend on
+    /// rounding, see [`DEGENERACY_TOLERANCE`]
+    pub degeneracies: Vec<Degeneracy>,
+    /// What the sweep reserved up front
+    pub capacities: Capacities,
+    /// Map of the pairs onto [0, 1] before the sweep, see [`generate_report_rescaled`]
+    pub rescaling: Option<Rescaling>,
+}
+
+/// Relative distance below which [`generate_report`] considers two critical points
+/// coincident, scaled by the magnitude of the coordinate if it is larger than one
+pub const DEGENERACY_TOLERANCE: f64 = 1e-9;
+
+/// What an intersection in [`Degeneracy`] nearly coincides with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Near {
+    /// Birth, peak or death of one of the crossing mountains
+    Endpoint,
+    /// The next event of the sweep
+    Event,
+}
+
+/// Intersection of two mountains found within tolerance of another critical point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Degeneracy {
+    pub point: (f64,f64),
+    /// Indices of the crossing pairs into the pairs given to [`generate_report`]
+    pub pairs: (usize, usize),
+    pub near: Near,
+}
+
+impl Report {
+    /// Whether the full landscape has more nonempty levels than were computed
+    #[must_use]
+    pub fn truncated(&self) -> bool {
+        self.depth > self.landscapes.len()
+    }
+}
+
+/// Same as [`generate`] but also reports the true depth of the landscape and numerically
+/// risky intersections
+///
+/// The depth is the largest number of mountains alive at once, which the sweep tracks
+/// anyway, so this costs nothing extra. With `debug` every degeneracy is also printed.
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn generate_report(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool) -> Report {
+    // Mountain ids count finite pairs only
+    let origin: Vec<usize> = bd_pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, bd)| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|(i, _)| i)
+        .collect();
+    let mountains = generate_mountains(bd_pairs);
+    let capacities = estimate_capacities(mountains.len(), k);
+    let events = BinaryHeap::from(mountains.initial_events());
+    let mut state = State {
+        degeneracies: Some((DEGENERACY_TOLERANCE, Vec::new())),
+        ..State::new(mountains, events, k)
+    };
+
+    let mut depth = 0;
+    let Ok(()) = sweep(&mut state, debug, |state| {
+        depth = depth.max(state.status.len());
+        Ok::<(), Infallible>(())
+    });
+
+    let degeneracies: Vec<Degeneracy> = state
+        .degeneracies
+        .map(|(_, found)| found)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|d| Degeneracy { pairs: (origin[d.pairs.0], origin[d.pairs.1]), ..d })
+        .collect();
+    if debug {
+        for d in &degeneracies {
+            let near = match d.near {
+                Near::Endpoint => "an endpoint of either pair",
+                Near::Event => "the next event",
+            };
+            eprintln!("Warning: intersection of pairs {} and {} at {:?} is within tolerance of {near}", d.pairs.0, d.pairs.1, d.point);
+        }
+    }
+    finalize(&mut state.landscapes, true);
+    Report { landscapes: state.landscapes, depth, degeneracies, capacities, rescaling: None }
+}
+
+/// Same as [`generate_report`] with the pairs first mapped onto [0, 1] by [`Rescaling::fit`],
+/// which the report records, and the landscape and degeneracies given in `units`
+///
+/// Diagrams without a finite range are swept as they are.
+#[must_use]
+pub fn generate_report_rescaled(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool, units: Units) -> Report {
+    let Some(rescaling) = Rescaling::fit(&bd_pairs) else {
+        return generate_report(bd_pairs, k, debug);
+    };
+    let mut report = generate_report(rescaling.apply(bd_pairs), k, debug);
+    if units == Units::Original {
+        report.landscapes = rescaling.invert(report.landscapes);
+        for degeneracy in &mut report.degeneracies {
+            degeneracy.point = rescaling.invert_point(degeneracy.point);
+        }
+    }
+    report.rescaling = Some(rescaling);
+    report
+}
+
+/// Output of [`generate_with_provenance`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// The same levels as [`generate`] returns
+    pub landscapes: Vec<Vec<(f64,f64)>>,
+    /// `sources[i][j]` produced `landscapes[i][j]`
+    pub sources: Vec<Vec<Source>>,
+}
+
+/// Same as [`generate`] but also records, for every critical point, the pairs that produced it
+///
+/// Pairs are numbered by their position in `bd_pairs`, including any skipped non-finite
+/// pairs.
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn generate_with_provenance(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool) -> Provenance {
+    // Mountain ids count finite pairs only
+    let origin: Vec<usize> = bd_pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, bd)| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|(i, _)| i)
+        .collect();
+    let mountains = generate_mountains(bd_pairs);
+    let events = BinaryHeap::from(mountains.initial_events());
+    let mut state = State {
+        provenance: Some(empty_landscape(k).into_iter().map(|_| Vec::new()).collect()),
+        ..State::new(mountains, events, k)
+    };
+
+    let Ok(()) = sweep(&mut state, debug, |_| Ok::<(), Infallible>(()));
+
+    let (landscapes, sources) = state.landscapes
+        .into_iter()
+        .zip(state.provenance.unwrap_or_default())
+        .map(|(level, sources)| {
+            let mut points: Vec<((f64,f64), Source)> = level
+                .into_iter()
+                .zip(sources)
+                .map(|(point, source)| (point, Source {
+                    pair: origin[source.pair],
+                    crossing: source.crossing.map(|id| origin[id]),
+                }))
+                .collect();
+            // Same as finalize, added endpoints belong to the pair of their neighbour
+            points.dedup_by(|next, kept| next.0.0 <= kept.0.0);
+            if let Some(&((x, y), source)) = points.first() {
+                if y != 0.0 {
+                    points.insert(0, ((x - y.abs(), 0.0), Source { crossing: None, ..source }));
+                }
+            }
+            if let Some(&((x, y), source)) = points.last() {
+                if y != 0.0 {
+                    points.push(((x + y.abs(), 0.0), Source { crossing: None, ..source }));
+                }
+            }
+            points.into_iter().unzip::<_, _, Vec<_>, Vec<_>>()
+        })
+        .unzip();
+    Provenance { landscapes, sources }
+}
+
+/// Kind of a [`SweepEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepEventKind {
+    /// A mountain enters the status at the bottom
+    Birth,
+    /// A mountain turns from rising to falling
+    Peak,
+    /// A mountain reaches zero and leaves the status
+    Death,
+}
+
+/// Event processed by [`Sweep::step`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepEvent {
+    pub kind: SweepEventKind,
+    /// Pair of the mountain, numbered by its position in the pairs given to [`Sweep::new`]
+    pub pair: usize,
+    pub x: f64,
+    pub y: f64,
+    /// Intersections the event led to, which are resolved before [`Sweep::step`] returns
+    pub intersections: usize,
+}
+
+/// Mountain of the status, see [`Sweep::status`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusEntry {
+    /// Pair of the mountain, numbered as in [`SweepEvent::pair`]
+    pub pair: usize,
+    /// Height at the x of the last event
+    pub height: f64,
+    pub rising: bool,
+}
+
+/// The sweep of [`generate`] as a state machine that processes one birth, peak or death per
+/// [`Sweep::step`], for tools that show or check the algorithm as it runs
+#[derive(Debug)]
+pub struct Sweep {
+    state: State,
+    /// Position in the given pairs of every mountain
+    origin: Vec<usize>,
+    /// X of the last event
+    x: Option<f64>,
+}
+
+impl Sweep {
+    /// Sweep of the first `k` levels of the landscape of `bd_pairs`, or of every level if `k`
+    /// is zero, before its first event. Pairs that are not finite are skipped.
+    #[must_use]
+    pub fn new(bd_pairs: Vec<BirthDeath>, k: usize) -> Self {
+        let origin = bd_pairs
+            .iter()
+            .enumerate()
+            .filter(|(_, bd)| bd.birth.is_finite() && bd.death.is_finite())
+            .map(|(i, _)| i)
+            .collect();
+        let mountains = generate_mountains(bd_pairs);
+        let events = BinaryHeap::from(mountains.initial_events());
+        Self { state: State::new(mountains, events, k), origin, x: None }
+    }
+
+    /// Processes the next event and the intersections it leads to, `None` once the sweep is
+    /// done
+    ///
+    /// # Panics
+    ///
+    /// Will panic if invalid state is discovered
+    pub fn step(&mut self) -> Option<SweepEvent> {
+        let event = self.state.events.pop()?;
+        let intersections = self.state.intersections;
+        process_event(&mut self.state, &event, false);
+        self.x = Some(event.value.x.0);
+        Some(SweepEvent {
+            kind: match event.event_type {
+                EventType::Up => SweepEventKind::Birth,
+                EventType::Down => SweepEventKind::Peak,
+                EventType::Death => SweepEventKind::Death,
+                EventType::Intersection => unreachable!("Intersections are never queued"),
+            },
+            pair: self.origin[event.parent_mountain_id],
+            x: event.value.x.0,
+            y: event.value.y.0,
+            intersections: self.state.intersections - intersections,
+        })
+    }
+
+    /// X of the next event, `None` once the sweep is done
+    #[must_use]
+    pub fn next_x(&self) -> Option<f64> {
+        self.state.events.peek().map(|event| event.value.x.0)
+    }
+
+    /// Mountains alive after the last event from the top down, the first is on level one
+    #[must_use]
+    pub fn status(&self) -> Vec<StatusEntry> {
+        let mountains = &self.state.mountains;
+        self.state
+            .status
+            .iter()
+            .map(|&id| StatusEntry {
+                pair: self.origin[id],
+                height: self.x.map_or(0.0, |x| mountains.value_at(id, x)),
+                rising: mountains.slope_rising[id],
+            })
+            .collect()
+    }
+
+    /// Points of every level up to the last event, as [`finalize`] without endpoints leaves
+    /// them. Levels of mountains still alive end at their last critical point.
+    #[must_use]
+    pub fn landscape_so_far(&self) -> Vec<Vec<(f64,f64)>> {
+        let mut levels = self.state.landscapes.clone();
+        finalize(&mut levels, false);
+        levels
+    }
+
+    /// Up, down and death events processed so far
+    #[must_use]
+    pub const fn processed(&self) -> usize {
+        self.state.processed
+    }
+
+    /// Intersections resolved so far
+    #[must_use]
+    pub const fn intersections(&self) -> usize {
+        self.state.intersections
+    }
+
+    /// Processes every remaining event and returns the levels [`generate`] would
+    ///
+    /// # Panics
+    ///
+    /// Will panic if invalid state is discovered
+    #[must_use]
+    pub fn finish(mut self) -> Vec<Vec<(f64,f64)>> {
+        while self.step().is_some() {}
+        finalize(&mut self.state.landscapes, true);
+        self.state.landscapes
+    }
+}
+
+/// Capacities the sweep reserves up front, see [`estimate_capacities`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capacities {
+    /// Initial events, exactly three per pair
+    pub events: usize,
+    /// Intersections queued behind a single event
+    pub intersections: usize,
+    /// Mountains alive at once
+    pub status: usize,
+    /// Points of every requested level
+    pub level_points: usize,
+}
+
+/// Most points of a level, mountains of the status or queued intersections reserved up front,
+/// larger ones grow as the sweep fills them
+pub const MAX_RESERVED: usize = 1 << 12;
+
+/// Capacities [`generate`] reserves for `n_pairs` pairs and `k` levels
+///
+/// Only the initial events are known exactly. The number of intersections can be quadratic
+/// in the worst case but is assumed linear, so the `3 n` points of the events and the two
+/// points of every intersection are spread evenly over `min(k, n)` levels. Everything but the
+/// events is capped at [`MAX_RESERVED`], so a poor guess for a large diagram costs a few
+/// reallocations rather than memory that is never used. Nothing is reserved per level if `k`
+/// is zero, those levels are only created as the sweep reaches them.
+#[must_use]
+pub fn estimate_capacities(n_pairs: usize, k: usize) -> Capacities {
+    let levels = min(k, n_pairs).max(1);
+    let points = n_pairs.saturating_mul(5);
+    Capacities {
+        events: n_pairs.saturating_mul(3),
+        intersections: min(n_pairs, MAX_RESERVED),
+        status: min(n_pairs, MAX_RESERVED),
+        level_points: if k == 0 { 0 } else { min(points.div_ceil(levels), MAX_RESERVED) },
+    }
+}
+
+/// Estimates the peak number of bytes [`generate`] needs for `n_pairs` pairs and `k` levels
+///
+/// Counts the mountains, the event queue, the status structure and the output levels.
+/// Each mountain changes level at most twice per level it passes through, so the
+/// output holds at most `3 n + 2 n min(k, n)` points, doubled for vector growth.
+/// The allocator's own overhead is not included. A `k` of zero counts every level.
+#[must_use]
+pub fn estimate_memory(n_pairs: usize, k: usize) -> usize {
+    let k = if k == 0 { n_pairs } else { k };
+    let mountains = n_pairs.saturating_mul(
+        size_of::<Option<usize>>() + size_of::<bool>() + 3 * size_of::<PointOrd>());
+    // Initial events plus the deferred queue, both grow past what is reserved up front
+    let events = estimate_capacities(n_pairs, k).events
+        .saturating_add(n_pairs)
+        .saturating_mul(size_of::<Event>());
+    let status = n_pairs.saturating_mul(size_of::<usize>());
+    let points = n_pairs
+        .saturating_mul(min(k, n_pairs))
+        .saturating_mul(2)
+        .saturating_add(n_pairs.saturating_mul(3));
+    let output = k
+        .saturating_mul(size_of::<Vec<(f64,f64)>>())
+        .saturating_add(points.saturating_mul(2 * size_of::<(f64,f64)>()));
+    mountains
+        .saturating_add(events)
+        .saturating_add(status)
+        .saturating_add(output)
+}
+
+/// Computes only the `k`-th level, the last level of `generate(bd_pairs, k, debug)`
+///
+/// The sweep still tracks every mountain but only records points for that level, so the
+/// memory for levels `0..k-1` is never allocated. Returns an empty level if `k` is zero.
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn generate_single_level(bd_pairs: Vec<BirthDeath>, k: usize, debug: bool) -> Vec<(f64,f64)> {
+    if k == 0 {
+        return Vec::new();
+    }
+    generate_levels(bd_pairs, k..=k, debug).pop().unwrap_or_default()
+}
+
+/// Computes levels `first` to `last` counting from one, the same as
+/// `generate(bd_pairs, last, debug)[first - 1..]`
+///
+/// The sweep still tracks every mountain but only records points for the requested levels, so
+/// the memory for the levels before `first` is never allocated. Returns no levels if the range
+/// is empty or starts at zero.
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn generate_levels(bd_pairs: Vec<BirthDeath>, levels: RangeInclusive<usize>, debug: bool) -> Vec<Vec<(f64,f64)>> {
+    let (first, last) = levels.into_inner();
+    if first == 0 || first > last {
+        return Vec::new();
+    }
+    let mountains = generate_mountains(bd_pairs);
+    let capacity = estimate_capacities(mountains.len(), last).level_points;
+    let events = BinaryHeap::from(mountains.initial_events());
+    // Built for zero levels so the skipped ones are not reserved
+    let mut state = State {
+        landscapes: (first..=last).map(|_| Vec::with_capacity(capacity)).collect(),
+        first_level: first - 1,
+        k: last,
+        ..State::new(mountains, events, 0)
+    };
+
+    let Ok(()) = sweep(&mut state, debug, |_| Ok::<(), Infallible>(()));
+
+    finalize(&mut state.landscapes, true);
+    state.landscapes
+}
+
+/// Where and how often [`generate_checkpointed`] saves its progress
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub path: PathBuf,
+    /// Number of processed events between two checkpoints
+    pub every: usize,
+}
+
+const CHECKPOINT_HEADER: &str = "fast_pl checkpoint 1";
+
+fn write_f64(out: &mut impl Write, value: f64) -> std::io::Result<()> {
+    // Bit exact so a resumed run is identical to an uninterrupted one
+    write!(out, " {:016x}", value.to_bits())
+}
+
+/// Serializes the sweep state between two events. The intersection queue is always empty at
+/// that point and the remaining events are fully determined by their type and mountain.
+fn write_checkpoint(state: &State, path: &Path) -> std::io::Result<()> {
+    let partial = path.with_extension("partial");
+    let mut out = BufWriter::new(File::create(&partial)?);
+    writeln!(out, "{CHECKPOINT_HEADER}")?;
+    writeln!(out, "k {} processed {}", state.k, state.processed)?;
+    writeln!(out, "mountains {}", state.mountains.len())?;
+    let m = &state.mountains;
+    for id in 0..m.len() {
+        write!(out, "{} {}", m.position[id].map_or(-1, |p| i64::try_from(p).unwrap_or(-1)), u8::from(m.slope_rising[id]))?;
+        write_f64(&mut out, m.birth[id].x.0)?;
+        write_f64(&mut out, m.death[id].x.0)?;
+        writeln!(out)?;
+    }
+    write!(out, "status {}", state.status.len())?;
+    for id in &state.status {
+        write!(out, " {id}")?;
+    }
+    writeln!(out)?;
+    writeln!(out, "events {}", state.events.len())?;
+    for event in &state.events {
+        let kind = match event.event_type {
+            EventType::Up => 'u',
+            EventType::Down => 'd',
+            EventType::Death => 'x',
+            EventType::Intersection => unreachable!("Intersection events are never queued"),
+        };
+        writeln!(out, "{kind} {}", event.parent_mountain_id)?;
+    }
+    for level in &state.landscapes {
+        write!(out, "level {}", level.len())?;
+        for (x, y) in level {
+            write_f64(&mut out, *x)?;
+            write_f64(&mut out, *y)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+    drop(out);
+    fs::rename(partial, path)
+}
+
+struct CheckpointData {
+    k: usize,
+    processed: usize,
+    mountains: Mountains,
+    status: VecDeque<usize>,
+    events: Vec<(EventType, usize)>,
+    landscapes: Vec<Vec<(f64,f64)>>,
+}
+
+struct Tokens<'a> {
+    tokens: std::str::SplitWhitespace<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Result<&'a str, Box<dyn Error>> {
+        Ok(self.tokens.next().ok_or("Truncated checkpoint file")?)
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), Box<dyn Error>> {
+        if self.next()? == word {
+            Ok(())
+        } else {
+            Err(format!("Expected '{word}' in checkpoint file").into())
+        }
+    }
+
+    fn parse<T>(&mut self) -> Result<T, Box<dyn Error>>
+    where
+        T: std::str::FromStr,
+        T::Err: Error + 'static,
+    {
+        Ok(self.next()?.parse()?)
+    }
+
+    fn float(&mut self) -> Result<f64, Box<dyn Error>> {
+        Ok(f64::from_bits(u64::from_str_radix(self.next()?, 16)?))
+    }
+}
+
+fn read_checkpoint(path: &Path) -> Result<CheckpointData, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let body = text
+        .strip_prefix(CHECKPOINT_HEADER)
+        .ok_or("Not a fast_pl checkpoint file")?;
+    let mut t = Tokens { tokens: body.split_whitespace() };
+
+    t.expect("k")?;
+    let k: usize = t.parse()?;
+    t.expect("processed")?;
+    let processed: usize = t.parse()?;
+    t.expect("mountains")?;
+    let n: usize = t.parse()?;
+    let mut mountains = Mountains::default();
+    for id in 0..n {
+        let position: i64 = t.parse()?;
+        let slope_rising = t.next()? == "1";
+        let birth = t.float()?;
+        let death = t.float()?;
+        mountains.push(birth, death, Profile::Tent);
+        mountains.position[id] = usize::try_from(position).ok();
+        mountains.slope_rising[id] = slope_rising;
+    }
+    t.expect("status")?;
+    let n: usize = t.parse()?;
+    let status = (0..n).map(|_| t.parse()).collect::<Result<_, _>>()?;
+    t.expect("events")?;
+    let n: usize = t.parse()?;
+    let mut events = Vec::with_capacity(n);
+    for _ in 0..n {
+        let kind = match t.next()? {
+            "u" => EventType::Up,
+            "d" => EventType::Down,
+            "x" => EventType::Death,
+            _ => return Err("Unknown event type in checkpoint file".into()),
+        };
+        events.push((kind, t.parse()?));
+    }
+    let mut landscapes = Vec::with_capacity(k);
+    for _ in 0..k {
+        t.expect("level")?;
+        let n: usize = t.parse()?;
+        let mut level = Vec::with_capacity(n);
+        for _ in 0..n {
+            level.push((t.float()?, t.float()?));
+        }
+        landscapes.push(level);
+    }
+    Ok(CheckpointData { k, processed, mountains, status, events, landscapes })
+}
+
+/// Same as [`generate`] but writes the sweep state to `checkpoint.path` every
+/// `checkpoint.every` events so an interrupted run can be continued with [`resume`]
+///
+/// # Errors
+///
+/// Will return 'Err' if a checkpoint can not be written
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_checkpointed(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    checkpoint: &Checkpoint,
+) -> crate::io::LevelsResult {
+    let mountains = generate_mountains(bd_pairs);
+    let events = BinaryHeap::from(mountains.initial_events());
+    let mut state = State::new(mountains, events, k);
+    sweep(&mut state, debug, |state| save_if_due(state, Some(checkpoint)))?;
+    finalize(&mut state.landscapes, true);
+    Ok(state.landscapes)
+}
+
+fn save_if_due(state: &State, checkpoint: Option<&Checkpoint>) -> Result<(), Box<dyn Error>> {
+    match checkpoint {
+        Some(c) if c.every > 0 && state.processed % c.every == 0 => Ok(write_checkpoint(state, &c.path)?),
+        _ => Ok(()),
+    }
+}
+
+/// Continues a sweep from a checkpoint written by [`generate_checkpointed`], optionally
+/// writing further checkpoints
+///
+/// # Errors
+///
+/// Will return 'Err' if the checkpoint can not be read or a new checkpoint can not be written
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn resume(
+    path: &Path,
+    debug: bool,
+    checkpoint: Option<&Checkpoint>,
+) -> crate::io::LevelsResult {
+    let CheckpointData { k, processed, mountains, status, events, landscapes } = read_checkpoint(path)?;
+    // Sweeps over every level store their limit as usize::MAX
+    let k = if k == usize::MAX { 0 } else { k };
+    if status.iter().chain(events.iter().map(|(_, id)| id)).any(|id| *id >= mountains.len()) {
+        return Err("Checkpoint refers to an unknown mountain".into());
+    }
+    let events: Vec<Event> = events
+        .into_iter()
+        .map(|(event_type, id)| {
+            let value = match event_type {
+                EventType::Up => mountains.birth[id].clone(),
+                EventType::Down => mountains.middle[id].clone(),
+                _ => mountains.death[id].clone(),
+            };
+            Event { value, event_type, parent_mountain_id: id, parent_mountain2_id: None }
+        })
+        .collect();
+    let mut state = State {
+        status,
+        landscapes,
+        processed,
+        ..State::new(mountains, BinaryHeap::from(events), k)
+    };
+    sweep(&mut state, debug, |state| save_if_due(state, checkpoint))?;
+    finalize(&mut state.landscapes, true);
+    Ok(state.landscapes)
+}
+
+/// Where and how often [`generate_with_snapshots`] records the state of the sweep
+#[derive(Debug, Clone)]
+pub struct Snapshots {
+    pub path: PathBuf,
+    /// Number of processed events between two snapshots
+    pub every: usize,
+}
+
+/// Appends the state of the sweep to `out` as one line of JSON
+///
+/// The status lists the mountains from the top down as the index of their pair among the
+/// finite pairs given to the sweep, their height at the next event and whether they rise.
+fn write_snapshot(state: &State, out: &mut impl Write) -> std::io::Result<()> {
+    let x = state.events.peek().map(|event| event.value.x.0);
+    write!(out, "{{\"processed\":{},\"intersections\":{},", state.processed, state.intersections)?;
+    match x {
+        Some(x) => write!(out, "\"x\":{x},")?,
+        None => write!(out, "\"x\":null,")?,
+    }
+    write!(
+        out,
+        "\"queued_events\":{},\"status_len\":{},\"level_points\":[",
+        state.events.len(),
+        state.status.len()
+    )?;
+    for (i, level) in state.landscapes.iter().enumerate() {
+        write!(out, "{}{}", if i == 0 { "" } else { "," }, level.len())?;
+    }
+    write!(out, "],\"status\":[")?;
+    for (i, &id) in state.status.iter().enumerate() {
+        let height = x.map_or(0.0, |x| state.mountains.value_at(id, x));
+        write!(
+            out,
+            "{}{{\"pair\":{id},\"height\":{height},\"rising\":{}}}",
+            if i == 0 { "" } else { "," },
+            state.mountains.slope_rising[id]
+        )?;
+    }
+    writeln!(out, "]}}")?;
+    out.flush()
+}
+
+/// Same as [`generate`] but writes a snapshot of the status and the queues to
+/// `snapshots.path` every `snapshots.every` events and once more at the end, as JSON lines
+///
+/// Every snapshot holds the number of processed events and intersections, the x of the next
+/// event, the number of queued events, the number of points of every level and the mountains
+/// of the status from the top down. Each line is flushed as it is written, so the snapshots
+/// before a crash or an interrupt are kept.
+///
+/// # Errors
+///
+/// Will return 'Err' if the snapshots can not be written
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_with_snapshots(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    snapshots: &Snapshots,
+) -> crate::io::LevelsResult {
+    let mut out = BufWriter::new(File::create(&snapshots.path)?);
+    let mountains = generate_mountains(bd_pairs);
+    let events = BinaryHeap::from(mountains.initial_events());
+    let mut state = State::new(mountains, events, k);
+    sweep(&mut state, debug, |state| match snapshots.every {
+        every if every > 0 && state.processed % every == 0 => write_snapshot(state, &mut out),
+        _ => Ok(()),
+    })?;
+    write_snapshot(&state, &mut out)?;
+    finalize(&mut state.landscapes, true);
+    Ok(state.landscapes)
+}
+
+/// Evaluates a single landscape level at `x` by linear interpolation between its critical
+/// points. Outside the support of the level the value is zero.
+#[must_use]
+pub fn evaluate(level: &[(f64,f64)], x: f64) -> f64 {
+    let (Some(first), Some(last)) = (level.first(), level.last()) else {
+        return 0.0;
+    };
+    if x < first.0 || x > last.0 {
+        return 0.0;
+    }
+    // First point strictly right of x, guaranteed to be at least 1 by the check above
+    let i = level.partition_point(|p| p.0 <= x);
+    if i == level.len() {
+        return last.1;
+    }
+    let (x0, y0) = level[i - 1];
+    let (x1, y1) = level[i];
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+}
+
+/// Settings for [`generate_external`]
+#[derive(Debug, Clone)]
+pub struct ExternalMemory {
+    /// Directory for the temporary event files, removed again when the sweep ends
+    pub dir: PathBuf,
+    /// Number of base events, three for every pair, sorted in memory before being spilled to disk
+    pub chunk_events: usize,
+}
+
+/// Temporary files holding sorted runs of base events
+struct SpillFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            // Nothing sensible can be done if removal fails while unwinding
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Sorted runs merged at once by [`generate_external`], more are merged in passes first
+const EXTERNAL_FAN_IN: usize = 64;
+
+/// Keeps concurrent sweeps in the same process from sharing spill files
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Same as [`generate`] but keeps the pairs on disk
+///
+/// The pairs are sorted by birth in runs of a third of `chunk_events`, spilled to
+/// `settings.dir` and merged at most [`EXTERNAL_FAN_IN`] runs at a time until few enough are
+/// left to be read together. The sweep reads the births from these runs and queues the peak
+/// and death of a mountain only once it is born, reusing the ids of dead mountains, so only
+/// the mountains alive at once, the status structure, one pair per run and the output are
+/// held in memory. The pairs are consumed lazily and never collected, pairs without
+/// persistence are skipped as their tents are zero everywhere.
+///
+/// # Errors
+///
+/// Returns an error if the temporary files cannot be written or read back
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_external(
+    bd_pairs: impl IntoIterator<Item = BirthDeath>,
+    k: usize,
+    debug: bool,
+    settings: &ExternalMemory,
+) -> crate::io::LevelsResult {
+    if settings.chunk_events == 0 {
+        return Err("Chunk size must be positive".into());
+    }
+    let mut files = SpillFiles { paths: Vec::new() };
+    let run_pairs = (settings.chunk_events / 3).max(1);
+    let runs = sort_pairs_external(
+        bd_pairs,
+        &settings.dir,
+        &mut files,
+        (run_pairs, EXTERNAL_FAN_IN, STREAM_BUFFER_BYTES),
+    )?;
+    let mut births = MergedRuns::open(&runs, STREAM_BUFFER_BYTES)?;
+    let mut state = State::new(Mountains::default(), BinaryHeap::new(), k);
+    sweep_births(&mut state, &mut births, debug, |_| Ok(()))?;
+    finalize(&mut state.landscapes, true);
+    Ok(state.landscapes)
+}
+
+/// Settings for [`generate_streaming`]
+#[derive(Debug, Clone)]
+pub struct Streaming {
+    /// Directory for the temporary files of pairs and levels, removed again when the sweep ends
+    pub dir: PathBuf,
+    /// Bytes of memory for sorting the pairs, file buffers and the state of the sweep
+    pub memory: usize,
+}
+
+/// Bytes of a pair in the sorted runs and of a point in the level files
+const STREAMED_RECORD_BYTES: usize = 16;
+
+/// Largest buffer of a single temporary file in [`generate_streaming`]
+const STREAM_BUFFER_BYTES: usize = 1 << 16;
+
+/// Events between two moves of the levels to their files in [`generate_streaming`]
+const STREAM_TAIL_EVENTS: usize = 256;
+
+/// Memory of an alive mountain in [`generate_streaming`], its entries in the mountains, the
+/// status and its peak and death events
+const ALIVE_MOUNTAIN_BYTES: usize = size_of::<Option<usize>>()
+    + size_of::<bool>()
+    + 3 * size_of::<PointOrd>()
+    + size_of::<usize>()
+    + 2 * size_of::<Event>();
+
+/// Writes the records of `values` as little endian pairs of f64
+fn write_records(out: &mut impl Write, values: impl IntoIterator<Item = (f64, f64)>) -> std::io::Result<()> {
+    for (a, b) in values {
+        out.write_all(&a.to_le_bytes())?;
+        out.write_all(&b.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_record(reader: &mut impl Read) -> std::io::Result<Option<(f64, f64)>> {
+    let mut buf = [0u8; STREAMED_RECORD_BYTES];
+    match reader.read_exact(&mut buf) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let f64_at = |at: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf[at..at + 8]);
+        f64::from_le_bytes(bytes)
+    };
+    Ok(Some((f64_at(0), f64_at(8))))
+}
+
+/// New temporary file in `dir` that is removed with `files`
+fn spill_file(dir: &Path, kind: &str, files: &mut SpillFiles, buffer: usize) -> std::io::Result<(PathBuf, BufWriter<File>)> {
+    let path = dir.join(format!(
+        "fast_pl_{kind}_{}_{}.bin",
+        std::process::id(),
+        SPILL_COUNTER.fetch_add(1, AtomicOrdering::Relaxed),
+    ));
+    files.paths.push(path.clone());
+    Ok((path.clone(), BufWriter::with_capacity(buffer, File::create(path)?)))
+}
+
+/// Birth, run and death of the next pair of a run in [`MergedRuns`]
+type RunHead = Reverse<(FloatOrd<f64>, usize, FloatOrd<f64>)>;
+
+/// Pairs of several runs sorted by birth, merged into one sorted stream
+struct MergedRuns {
+    readers: Vec<BufReader<File>>,
+    heads: BinaryHeap<RunHead>,
+}
+
+impl MergedRuns {
+    fn open(paths: &[PathBuf], buffer: usize) -> std::io::Result<Self> {
+        let mut merged = Self { readers: Vec::with_capacity(paths.len()), heads: BinaryHeap::new() };
+        for path in paths {
+            merged.readers.push(BufReader::with_capacity(buffer, File::open(path)?));
+            merged.advance(merged.readers.len() - 1)?;
+        }
+        Ok(merged)
+    }
+
+    /// Queues the next pair of run `run`, if it has one
+    fn advance(&mut self, run: usize) -> std::io::Result<()> {
+        if let Some((birth, death)) = read_record(&mut self.readers[run])? {
+            self.heads.push(Reverse((FloatOrd(birth), run, FloatOrd(death))));
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> std::io::Result<Option<(f64, f64)>> {
+        let Some(Reverse((birth, run, death))) = self.heads.pop() else {
+            return Ok(None);
+        };
+        self.advance(run)?;
+        Ok(Some((birth.0, death.0)))
+    }
+}
+
+/// Sorts the finite pairs of `bd_pairs` with positive persistence by birth into runs of `run_pairs` pairs and merges
+/// them `fan_in` at a time until at most `fan_in` runs are left, returns their paths
+fn sort_pairs_external(
+    bd_pairs: impl IntoIterator<Item = BirthDeath>,
+    dir: &Path,
+    files: &mut SpillFiles,
+    (run_pairs, fan_in, buffer): (usize, usize, usize),
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut runs = Vec::new();
+    let mut run = Vec::with_capacity(run_pairs);
+    let mut bd_pairs = bd_pairs
+        .into_iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite() && bd.death > bd.birth)
+        .peekable();
+    while bd_pairs.peek().is_some() {
+        run.extend(bd_pairs.by_ref().take(run_pairs).map(|bd| (bd.birth, bd.death)));
+        run.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        let (path, mut out) = spill_file(dir, "pairs", files, buffer)?;
+        write_records(&mut out, run.iter().copied())?;
+        run.clear();
+        out.flush()?;
+        runs.push(path);
+    }
+    while runs.len() > fan_in {
+        let mut merged_runs = Vec::with_capacity(runs.len().div_ceil(fan_in));
+        for group in runs.chunks(fan_in) {
+            let mut merged = MergedRuns::open(group, buffer)?;
+            let (path, mut out) = spill_file(dir, "pairs", files, buffer)?;
+            while let Some(pair) = merged.next()? {
+                write_records(&mut out, [pair])?;
+            }
+            out.flush()?;
+            merged_runs.push(path);
+            for path in group {
+                fs::remove_file(path)?;
+            }
+        }
+        runs = merged_runs;
+    }
+    Ok(runs)
+}
+
+/// Level files of [`generate_streaming`] with the points of every level but the last, which
+/// stays in the state of the sweep for its checks
+struct LevelFiles {
+    writers: Vec<(PathBuf, BufWriter<File>)>,
+    /// Whether the first point of each level was written, with its start added if needed
+    started: Vec<bool>,
+    buffer: usize,
+}
+
+impl LevelFiles {
+    /// Moves all but the last point of every level in `levels` to its file, in the same way
+    /// [`finalize`] with endpoints would write them
+    fn drain(&mut self, levels: &mut [Vec<(f64,f64)>], dir: &Path, files: &mut SpillFiles) -> std::io::Result<()> {
+        while self.writers.len() < levels.len() {
+            self.writers.push(spill_file(dir, "level", files, self.buffer)?);
+            self.started.push(false);
+        }
+        for (i, level) in levels.iter_mut().enumerate() {
+            level.dedup_by(|next, kept| next.0 <= kept.0);
+            if level.len() < 2 {
+                continue;
+            }
+            self.start(i, level[0])?;
+            let last = level.len() - 1;
+            write_records(&mut self.writers[i].1, level.drain(..last))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the start of level `i` before its first point if the level does not start at
+    /// zero
+    fn start(&mut self, i: usize, (x, y): (f64, f64)) -> std::io::Result<()> {
+        if !self.started[i] && y != 0.0 {
+            write_records(&mut self.writers[i].1, [(x - y.abs(), 0.0)])?;
+        }
+        self.started[i] = true;
+        Ok(())
+    }
+
+    /// Writes the remaining points of `levels` and their ends, then every level to `writer`
+    /// as [`crate::io::write_csv_to`] does
+    fn finish(
+        mut self,
+        mut levels: Vec<Vec<(f64,f64)>>,
+        dir: &Path,
+        files: &mut SpillFiles,
+        writer: impl Write,
+        options: &crate::io::WriteOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        self.drain(&mut levels, dir, files)?;
+        for (i, level) in levels.into_iter().enumerate() {
+            if let Some(&(x, y)) = level.first() {
+                self.start(i, (x, y))?;
+                write_records(&mut self.writers[i].1, [(x, y)])?;
+                if y != 0.0 {
+                    write_records(&mut self.writers[i].1, [(x + y.abs(), 0.0)])?;
+                }
+            }
+        }
+        let mut wtr = csv::Writer::from_writer(writer);
+        let (mut x, mut y) = (String::new(), String::new());
+        let mut written = 0;
+        for (path, mut out) in self.writers {
+            out.flush()?;
+            drop(out);
+            let mut reader = BufReader::with_capacity(self.buffer, File::open(&path)?);
+            while let Some(point) = read_record(&mut reader)? {
+                x.clear();
+                y.clear();
+                options.format.write(&mut x, point.0);
+                options.format.write(&mut y, point.1);
+                wtr.write_record([&x, &y])?;
+                written += 1;
+                if options.flush_every.is_some_and(|n| written % n == 0) {
+                    wtr.flush()?;
+                }
+            }
+            wtr.write_record(["", ""])?;
+            drop(reader);
+            fs::remove_file(&path)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Same as [`generate`] in bounded memory, writing the levels to `writer` in the format of
+/// [`crate::io::write_csv_to`]
+///
+/// The pairs are consumed lazily and sorted by birth on disk in `settings.dir`, in runs as
+/// large as half of `settings.memory` that are merged until few enough are left to be read
+/// together. Pairs without persistence are skipped, their tents are zero everywhere. The sweep reads the births from these runs and queues the peak and death of a
+/// mountain only once it is born, so the memory it holds is that of the mountains alive at
+/// once, the intersections queued behind one event and the last points of every level, the
+/// others are moved to a file per level and written to `writer` at the end. A `k` of zero
+/// opens a file for every level the sweep reaches.
+///
+/// # Errors
+///
+/// Will return 'Err' if the temporary files or `writer` fail, and
+/// [`LimitExceeded::Memory`] if the mountains alive at once take more than `settings.memory`
+/// along with the file buffers
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_streaming(
+    bd_pairs: impl IntoIterator<Item = BirthDeath>,
+    k: usize,
+    debug: bool,
+    settings: &Streaming,
+    writer: impl Write,
+    options: &crate::io::WriteOptions,
+) -> Result<(), Box<dyn Error>> {
+    let budget = settings.memory;
+    let buffer = (budget / 16).clamp(STREAMED_RECORD_BYTES, STREAM_BUFFER_BYTES);
+    let run_pairs = (budget / 2 / STREAMED_RECORD_BYTES).max(2);
+    let fan_in = (budget / 2 / buffer).max(2);
+    let mut files = SpillFiles { paths: Vec::new() };
+    let runs = sort_pairs_external(bd_pairs, &settings.dir, &mut files, (run_pairs, fan_in, buffer))?;
+    let mut births = MergedRuns::open(&runs, buffer)?;
+
+    let mut levels = LevelFiles { writers: Vec::new(), started: Vec::new(), buffer };
+    let mut state = State::new(Mountains::default(), BinaryHeap::new(), k);
+    sweep_births(&mut state, &mut births, debug, |state| {
+        // Dead mountains keep their memory until their id is reused
+        let used = state.mountains.len().saturating_mul(ALIVE_MOUNTAIN_BYTES)
+            .saturating_add(levels.writers.len().saturating_add(runs.len()).saturating_mul(buffer));
+        if used > budget {
+            return Err(LimitExceeded::Memory { limit: budget }.into());
+        }
+        if state.processed % STREAM_TAIL_EVENTS == 0 {
+            levels.drain(&mut state.landscapes, &settings.dir, &mut files)?;
+        }
+        Ok(())
+    })?;
+    drop(births);
+    levels.finish(state.landscapes, &settings.dir, &mut files, writer, options)
+}
+
+/// Sweeps the pairs of `births`, sorted by birth, adding the mountain of a pair only when the
+/// sweep reaches its birth and reusing the ids of dead mountains, calls `after` with the state
+/// after every event
+fn sweep_births(
+    state: &mut State,
+    births: &mut MergedRuns,
+    debug: bool,
+    mut after: impl FnMut(&mut State) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut free = Vec::new();
+    let up = |birth: f64, id: usize| Event {
+        value: PointOrd { x: FloatOrd(birth), y: FloatOrd(0.0) },
+        event_type: EventType::Up,
+        parent_mountain_id: id,
+        parent_mountain2_id: None,
+    };
+    let mut next_birth = births.next()?;
+    loop {
+        // Events order in reverse for the heap, the greater one comes first
+        let event = match (state.events.peek(), next_birth) {
+            (Some(queued), Some((birth, _))) if *queued > up(birth, usize::MAX) => state.events.pop(),
+            (_, Some((birth, death))) => {
+                let id = state.mountains.insert(&mut free, birth, death, Profile::Tent);
+                let [_, peak, death] = state.mountains.base_events(id);
+                state.events.extend([peak, death]);
+                next_birth = births.next()?;
+                Some(up(birth, id))
+            }
+            (_, None) => state.events.pop(),
+        };
+        let Some(event) = event else {
+            return Ok(());
+        };
+        process_event(state, &event, debug);
+        if event.event_type == EventType::Death {
+            free.push(event.parent_mountain_id);
+        }
+        after(state)?;
+    }
+}
+
+/// Calendar queue settings for [`generate_bucketed`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Buckets {
+    /// Smallest expected coordinate, smaller ones share the first bucket
+    pub x_min: f64,
+    /// Largest expected coordinate, larger ones share the last bucket
+    pub x_max: f64,
+    /// Number of buckets the range is split into evenly
+    pub count: usize,
+}
+
+impl Buckets {
+    /// Bucket holding `x`, monotone in `x` so bucket order is event order
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+    fn index(&self, x: f64) -> usize {
+        let scaled = (x - self.x_min) / (self.x_max - self.x_min) * self.count as f64;
+        (scaled.max(0.0) as usize).min(self.count - 1)
+    }
+}
+
+/// Same as [`generate`] with the base events distributed into evenly spaced buckets over a
+/// known range instead of a binary heap
+///
+/// Every bucket is sorted only when the sweep reaches it, so with coordinates spread evenly
+/// over the range ordering costs close to constant time per event. Coordinates outside the
+/// range are still handled correctly, they only make the end buckets larger.
+///
+/// # Errors
+///
+/// Will return 'Err' if the range is not finite and non-empty or there are no buckets
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+pub fn generate_bucketed(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    debug: bool,
+    buckets: &Buckets,
+) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if !(buckets.x_min.is_finite() && buckets.x_max.is_finite() && buckets.x_min < buckets.x_max) {
+        return Err("Bucket range must be finite and non-empty");
+    }
+    if buckets.count == 0 {
+        return Err("There must be at least one bucket");
+    }
+    let mountains = generate_mountains(bd_pairs);
+    let mut calendar: Vec<Vec<Event>> = Vec::new();
+    calendar.resize_with(buckets.count, Vec::new);
+    for event in mountains.initial_events() {
+        calendar[buckets.index(event.value.x.0)].push(event);
+    }
+
+    let mut state = State::new(mountains, BinaryHeap::new(), k);
+    for mut bucket in calendar {
+        // Event ordering is reversed for the heap, so this sorts by increasing x then event type
+        bucket.sort_unstable_by(|a, b| b.cmp(a));
+        for event in bucket {
+            process_event(&mut state, &event, debug);
+        }
+    }
+    finalize(&mut state.landscapes, true);
+    Ok(state.landscapes)
+}
+
+/// Same as [`generate`] but splits the x-axis into up to `ranges` ranges holding about the
+/// same number of births and deaths and sweeps them on separate threads
+///
+/// Every range is swept with only the pairs alive in it, which is enough for the exact
+/// landscape there, and the levels of all ranges are joined at the boundaries. Pairs born
+/// before a range enter its sweep at the start of the range, so every event is processed
+/// once however long the pairs are. Idle threads take the ranges left, so more ranges than
+/// threads keep every thread busy when ranges differ in cost. Where several events share a
+/// point, critical points may be listed differently from a single sweep, but the levels
+/// describe the same functions. With `k` zero, levels that are zero everywhere are left out.
+/// The result does not depend on the number of threads.
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn generate_parallel(bd_pairs: Vec<BirthDeath>, k: usize, ranges: usize) -> Vec<Vec<(f64,f64)>> {
+    let pairs: Vec<BirthDeath> = bd_pairs
+        .into_iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .collect();
+    let parts: Vec<Vec<Vec<(f64,f64)>>> = partition(&pairs, ranges)
+        .into_par_iter()
+        .map(|range| generate_partition(&pairs, k, range))
+        .collect();
+    merge_partitions(parts, k).expect("Ranges of a partition are in order")
+}
+
+/// Splits the x-axis into at most `ranges` consecutive ranges holding about the same number of
+/// births and deaths of `bd_pairs`, the first starting and the last ending at infinity
+#[must_use]
+pub fn partition(bd_pairs: &[BirthDeath], ranges: usize) -> Vec<Range<f64>> {
+    let mut xs: Vec<f64> = bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .flat_map(|bd| [bd.birth, bd.death])
+        .collect();
+    xs.sort_by(f64::total_cmp);
+    let ranges = ranges.max(1);
+    let mut bounds: Vec<f64> = (1..ranges)
+        .filter_map(|i| xs.get(i * xs.len() / ranges).copied())
+        .collect();
+    bounds.dedup();
+    let starts = std::iter::once(f64::NEG_INFINITY).chain(bounds.iter().copied());
+    let ends = bounds.iter().copied().chain(std::iter::once(f64::INFINITY));
+    starts.zip(ends).map(|(start, end)| start..end).collect()
+}
+
+/// The first `k` levels of the landscape of `bd_pairs` on `range`, for merging with
+/// [`merge_partitions`]
+///
+/// Only the pairs alive in the range are needed for the exact landscape there, so a machine
+/// computing one part of a partition only needs those pairs. Points outside the range are
+/// left out and no endpoints are added, the merge adds them.
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn generate_partition(bd_pairs: &[BirthDeath], k: usize, range: Range<f64>) -> Vec<Vec<(f64,f64)>> {
+    let mut mountains = Mountains::default();
+    bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .filter(|bd| bd.birth < range.end && bd.death >= range.start)
+        .for_each(|bd| mountains.push(bd.birth, bd.death, Profile::Tent));
+    let mut state = state_at(mountains, k, range.start);
+    while let Some(event) = state.events.pop() {
+        if event.value.x.0 >= range.end {
+            break;
+        }
+        process_event(&mut state, &event, false);
+    }
+    // Beyond the range the pairs left out would change the levels
+    state.landscapes
+        .into_iter()
+        .map(|level| level.into_iter().filter(|&(x, _)| range.contains(&x)).collect())
+        .collect()
+}
+
+/// Sweep state at `start` before the events there, with the mountains born earlier already
+/// in the status and every level starting at its height at `start`
+///
+/// The sweep handles a crossing as soon as the two mountains become neighbors, so the status
+/// does not hold the mountains in the order of their heights at `start`. Every rising
+/// mountain below a falling one it crosses later is already above it, which is restored by
+/// handling the crossings of the mountains in height order, no event before `start` is
+/// processed.
+fn state_at(mut mountains: Mountains, k: usize, start: f64) -> State {
+    let mut events = Vec::with_capacity(3 * mountains.len());
+    let mut alive = Vec::new();
+    for id in 0..mountains.len() {
+        let [up, down, death] = mountains.base_events(id);
+        if mountains.birth[id].x.0 < start {
+            mountains.slope_rising[id] = mountains.middle[id].x.0 >= start;
+            if mountains.slope_rising[id] {
+                events.push(down);
+            }
+            events.push(death);
+            alive.push(id);
+        } else {
+            events.extend([up, down, death]);
+        }
+    }
+    // Tents rise and fall with slope one, which gives heights without rounding the slope
+    let height = |mountains: &Mountains, id: usize| {
+        if mountains.slope_rising[id] { start - mountains.birth[id].x.0 } else { mountains.death[id].x.0 - start }
+    };
+    // Of mountains at the same height, rising ones are above as after their crossing
+    alive.sort_by(|&a, &b| {
+        height(&mountains, b)
+            .total_cmp(&height(&mountains, a))
+            .then(mountains.slope_rising[b].cmp(&mountains.slope_rising[a]))
+            .then(a.cmp(&b))
+    });
+    let mut state = State::new(mountains, BinaryHeap::from(events), k);
+    for (position, &id) in alive.iter().enumerate() {
+        state.status.push_back(id);
+        state.mountains.position[id] = Some(position);
+        if position < state.k {
+            if state.landscapes.len() <= position {
+                state.landscapes.push(Vec::new());
+            }
+            state.landscapes[position].push((start, height(&state.mountains, id)));
+        }
+    }
+    for &id in &alive {
+        if state.mountains.slope_rising[id] {
+            if let Some(crossing) = find_intersection(&state.status, id, &state.mountains, &Direction::Above) {
+                handle_intersection(&mut state, crossing);
+            }
+        }
+    }
+    state
+}
+
+/// Joins the parts of [`generate_partition`] over consecutive ranges into the landscape, the
+/// same as [`generate_parallel`] over those ranges
+///
+/// Parts must be given in the order of their ranges. With `k` zero, levels that are zero
+/// everywhere are left out.
+///
+/// # Errors
+///
+/// Will return 'Err' if a level of a part starts before the same level of an earlier part
+/// ends, as happens for parts out of order or over overlapping ranges
+pub fn merge_partitions(parts: Vec<Vec<Vec<(f64,f64)>>>, k: usize) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    let mut landscapes = empty_landscape(k);
+    for part in parts {
+        if landscapes.len() < part.len() {
+            landscapes.resize_with(part.len(), Vec::new);
+        }
+        for (level, points) in landscapes.iter_mut().zip(part) {
+            if let (Some(last), Some(first)) = (level.last(), points.first()) {
+                if first.0 < last.0 {
+                    return Err("Parts must cover consecutive ranges in order");
+                }
+            }
+            level.extend(points);
+        }
+    }
+    if k == 0 {
+        // How many levels are only ever touched at zero depends on the pairs swept together
+        while landscapes.last().is_some_and(|level| level.iter().all(|&(_, y)| y == 0.0)) {
+            landscapes.pop();
+        }
+    }
+    finalize(&mut landscapes, true);
+    Ok(landscapes)
+}
+
+/// Largest magnitude of a coordinate in units of the quantum, far enough below 2^53 that every
+/// coordinate and half unit is a whole number in an f64
+pub const MAX_UNITS: i64 = 1 << 40;
+
+/// Computes the first `k` levels of the landscape of pairs given as integers, with every
+/// coordinate returned in half units
+///
+/// Tents have slopes of one, so two of them with integer births and deaths only cross at half
+/// integers and every critical point of the landscape lies on the half unit lattice. The pairs
+/// are swept in f64 with [`generate`] and every point it logs is rounded to the nearest half
+/// unit, this is not an integer sweep and gives no guarantee beyond that of [`generate`].
+///
+/// # Errors
+///
+/// Will return 'Err' if a coordinate exceeds [`MAX_UNITS`] in magnitude
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+pub fn generate_half_units(pairs: &[(i64, i64)], k: usize) -> Result<Vec<Vec<(i64, i64)>>, &'static str> {
+    if pairs.iter().any(|&(birth, death)| birth.abs() > MAX_UNITS || death.abs() > MAX_UNITS) {
+        return Err("Coordinates exceed the range of half unit sweeps");
+    }
+    let bd_pairs = pairs
+        .iter()
+        .filter(|(birth, death)| birth < death)
+        .map(|&(birth, death)| BirthDeath { birth: birth as f64, death: death as f64 })
+        .collect();
+    Ok(generate(bd_pairs, k, false)
+        .into_iter()
+        .map(|level| {
+            let mut level: Vec<(i64, i64)> = level
+                .into_iter()
+                .map(|(x, y)| ((2.0 * x).round() as i64, (2.0 * y).round() as i64))
+                .collect();
+            // Points the sweep kept apart by rounding error are the same point
+            level.dedup_by(|next, kept| next.0 <= kept.0);
+            level
+        })
+        .collect())
+}
+
+/// Computes the first `k` levels of the landscape of a diagram of integers, with every
+/// coordinate of the result in half units, see [`generate_half_units`]
+///
+/// # Errors
+///
+/// Will return 'Err' if a coordinate exceeds [`MAX_UNITS`] in magnitude
+pub fn generate_integer(bd_pairs: &[BirthDeath<i64>], k: usize) -> Result<Vec<Vec<(i64, i64)>>, &'static str> {
+    let pairs: Vec<(i64, i64)> = bd_pairs.iter().map(|bd| (bd.birth, bd.death)).collect();
+    generate_half_units(&pairs, k)
+}
+
+/// Same as [`generate`] with every coordinate first rounded to a multiple of `quantum`
+///
+/// The landscape of the quantized pairs is computed with [`generate_half_units`], so every
+/// coordinate of the result is a multiple of half the quantum. That sweep still runs in f64 and
+/// rounds its intersections to the grid, they are not computed exactly.
+///
+/// # Errors
+///
+/// Will return 'Err' if `quantum` is not positive and finite or a coordinate exceeds
+/// [`MAX_UNITS`] quanta
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+pub fn generate_quantized(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    quantum: f64,
+) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if !(quantum > 0.0 && quantum.is_finite()) {
+        return Err("Quantum must be positive and finite");
+    }
+    let units = |value: f64| {
+        let units = (value / quantum).round();
+        if units.abs() > MAX_UNITS as f64 {
+            return Err("Coordinates exceed the range of half unit sweeps");
+        }
+        Ok(units as i64)
+    };
+    let pairs = bd_pairs
+        .into_iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|bd| Ok((units(bd.birth)?, units(bd.death)?)))
+        .collect::<Result<Vec<_>, &'static str>>()?;
+    let half = quantum / 2.0;
+    Ok(generate_half_units(&pairs, k)?
+        .into_iter()
+        .map(|level| level.into_iter().map(|(x, y)| (x as f64 * half, y as f64 * half)).collect())
+        .collect())
+}
+
+/// Approximates the first `k` levels of the landscape to within half of `epsilon` in the sup
+/// norm, trading accuracy for speed on noisy diagrams
+///
+/// Pairs with a persistence of at most `epsilon` are dropped and every other coordinate is
+/// snapped to a multiple of `epsilon`, which merges events closer than that and puts every
+/// intersection on a grid of half of `epsilon`, see [`generate_quantized`]. Either change moves
+/// a pair by at most half of `epsilon`, and levels move no more than the pairs, so this bounds
+/// the error of every level. Levels that only the dropped pairs reach are left out.
+///
+/// # Errors
+///
+/// Will return 'Err' if `epsilon` is not positive and finite or a coordinate exceeds
+/// [`MAX_UNITS`] multiples of it
+pub fn generate_approximate(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    epsilon: f64,
+) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if !(epsilon > 0.0 && epsilon.is_finite()) {
+        return Err("Epsilon must be positive and finite");
+    }
+    let pairs = bd_pairs.into_iter().filter(|bd| bd.death - bd.birth > epsilon).collect();
+    generate_quantized(pairs, k, epsilon)
+}
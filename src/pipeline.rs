@@ -0,0 +1,398 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Preprocessing of diagrams before [`crate::persistencelandscape::generate`]
+//!
+//! A [`DiagramPipeline`] is an ordered list of [`Step`]s, built with its methods or parsed
+//! from a comma separated spec such as `scale=2,min-persistence=0.01,top=500`.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::birthdeath::{self, BirthDeath, Strictness};
+
+/// One preprocessing step of a [`DiagramPipeline`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// Read the second value of every pair as its persistence with
+    /// [`birthdeath::from_birth_persistence`], spelled `birth-persistence`
+    BirthPersistence,
+    /// Multiply every birth and death by `factor` and add `shift`, spelled `scale=<factor>`,
+    /// `shift=<shift>` or `scale=<factor>:<shift>`
+    Scale { factor: f64, shift: f64 },
+    /// Replace an infinite death with this value and an infinite birth with its negation,
+    /// spelled `cap-infinite=<value>`
+    CapInfinite(f64),
+    /// Drop pairs with less persistence than this, spelled `min-persistence=<value>`
+    MinPersistence(f64),
+    /// Fail on pairs rejected by [`Strictness::Strict`], spelled `strict`
+    Strict,
+    /// Sort the pairs with [`birthdeath::sort_canonical`], spelled `canonical`
+    Canonical,
+    /// Keep the most persistent pairs with [`birthdeath::top_pairs`], spelled `top=<n>`
+    TopPairs(usize),
+    /// Keep a random sample with [`birthdeath::subsample`], spelled `subsample=<n>[:<seed>]`
+    /// or `weighted-subsample=<n>[:<seed>]`
+    Subsample { n: usize, weighted: bool, seed: u64 },
+}
+
+impl Step {
+    fn validate(self) -> Result<(), &'static str> {
+        match self {
+            Self::Scale { factor, shift } if !(factor > 0.0 && factor.is_finite() && shift.is_finite()) => {
+                Err("Scale factor must be positive and finite")
+            }
+            Self::CapInfinite(cap) if !cap.is_finite() => Err("Cap for infinite pairs must be finite"),
+            Self::MinPersistence(min) if !(min >= 0.0 && min.is_finite()) => {
+                Err("Minimum persistence must be non-negative and finite")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Ordered preprocessing steps applied to a diagram, see [`Step`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DiagramPipeline {
+    steps: Vec<Step>,
+}
+
+impl DiagramPipeline {
+    /// A pipeline that leaves every diagram as it is
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Appends `step`
+    #[must_use]
+    pub fn then(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    #[must_use]
+    pub fn birth_persistence(self) -> Self {
+        self.then(Step::BirthPersistence)
+    }
+
+    #[must_use]
+    pub fn scale(self, factor: f64, shift: f64) -> Self {
+        self.then(Step::Scale { factor, shift })
+    }
+
+    #[must_use]
+    pub fn cap_infinite(self, cap: f64) -> Self {
+        self.then(Step::CapInfinite(cap))
+    }
+
+    #[must_use]
+    pub fn min_persistence(self, min_persistence: f64) -> Self {
+        self.then(Step::MinPersistence(min_persistence))
+    }
+
+    #[must_use]
+    pub fn strict(self) -> Self {
+        self.then(Step::Strict)
+    }
+
+    #[must_use]
+    pub fn canonical(self) -> Self {
+        self.then(Step::Canonical)
+    }
+
+    #[must_use]
+    pub fn top_pairs(self, n: usize) -> Self {
+        self.then(Step::TopPairs(n))
+    }
+
+    #[must_use]
+    pub fn subsample(self, n: usize, weighted: bool, seed: u64) -> Self {
+        self.then(Step::Subsample { n, weighted, seed })
+    }
+
+    #[must_use]
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Runs every step on `bd_pairs` in order
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if a step has an invalid setting or a [`Step::Strict`] check fails
+    pub fn apply(&self, mut bd_pairs: Vec<BirthDeath>) -> Result<Vec<BirthDeath>, Box<dyn Error>> {
+        for &step in &self.steps {
+            step.validate()?;
+            bd_pairs = match step {
+                Step::BirthPersistence => birthdeath::from_birth_persistence(bd_pairs)?,
+                Step::Scale { factor, shift } => bd_pairs.into_iter().map(|bd| scale(&bd, factor, shift)).collect(),
+                Step::CapInfinite(cap) => bd_pairs.into_iter().map(|bd| cap_infinite(&bd, cap)).collect(),
+                Step::MinPersistence(min) => bd_pairs.into_iter().filter(|bd| bd.death - bd.birth >= min).collect(),
+                Step::Strict => {
+                    birthdeath::check(&bd_pairs, Strictness::Strict)?;
+                    bd_pairs
+                }
+                Step::Canonical => {
+                    birthdeath::sort_canonical(&mut bd_pairs);
+                    bd_pairs
+                }
+                Step::TopPairs(n) => birthdeath::top_pairs(bd_pairs, n),
+                Step::Subsample { n, weighted, seed } => birthdeath::subsample(bd_pairs, n, weighted, seed),
+            };
+        }
+        Ok(bd_pairs)
+    }
+
+    /// Same as [`DiagramPipeline::apply`] for a weighted diagram, keeping every weight with
+    /// its pair. Pairs of equal position are sorted by weight in [`Step::Canonical`].
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if there is not one weight per pair, the pipeline holds a
+    /// [`Step::TopPairs`] or [`Step::Subsample`], a step has an invalid setting or a
+    /// [`Step::Strict`] check fails
+    pub fn apply_weighted(
+        &self,
+        mut bd_pairs: Vec<BirthDeath>,
+        mut weights: Vec<f64>,
+    ) -> Result<(Vec<BirthDeath>, Vec<f64>), Box<dyn Error>> {
+        if bd_pairs.len() != weights.len() {
+            return Err("Every pair needs exactly one weight".into());
+        }
+        for &step in &self.steps {
+            step.validate()?;
+            (bd_pairs, weights) = match step {
+                Step::BirthPersistence => (birthdeath::from_birth_persistence(bd_pairs)?, weights),
+                Step::Scale { factor, shift } => {
+                    (bd_pairs.into_iter().map(|bd| scale(&bd, factor, shift)).collect(), weights)
+                }
+                Step::CapInfinite(cap) => (bd_pairs.into_iter().map(|bd| cap_infinite(&bd, cap)).collect(), weights),
+                Step::MinPersistence(min) => bd_pairs
+                    .into_iter()
+                    .zip(weights)
+                    .filter(|(bd, _)| bd.death - bd.birth >= min)
+                    .unzip(),
+                Step::Strict => {
+                    birthdeath::check(&bd_pairs, Strictness::Strict)?;
+                    (bd_pairs, weights)
+                }
+                Step::Canonical => {
+                    let mut weighted: Vec<_> = bd_pairs.into_iter().zip(weights).collect();
+                    weighted.sort_by(|(a, w), (b, v)| birthdeath::canonical_cmp(a, b).then(w.total_cmp(v)));
+                    weighted.into_iter().unzip()
+                }
+                Step::TopPairs(_) | Step::Subsample { .. } => {
+                    return Err("Dropping pairs by rank is not available for weighted diagrams".into());
+                }
+            };
+        }
+        Ok((bd_pairs, weights))
+    }
+
+    /// Same as [`DiagramPipeline::apply`] keeping a label, see [`birthdeath::Label`], with
+    /// every pair. Pairs of equal position keep their order in [`Step::Canonical`].
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if there is not one label per pair, the pipeline holds a
+    /// [`Step::TopPairs`] or [`Step::Subsample`], a step has an invalid setting or a
+    /// [`Step::Strict`] check fails
+    pub fn apply_labeled<L>(
+        &self,
+        mut bd_pairs: Vec<BirthDeath>,
+        mut labels: Vec<L>,
+    ) -> Result<(Vec<BirthDeath>, Vec<L>), Box<dyn Error>> {
+        if bd_pairs.len() != labels.len() {
+            return Err("Every pair needs exactly one label".into());
+        }
+        for &step in &self.steps {
+            step.validate()?;
+            (bd_pairs, labels) = match step {
+                Step::BirthPersistence => (birthdeath::from_birth_persistence(bd_pairs)?, labels),
+                Step::Scale { factor, shift } => {
+                    (bd_pairs.into_iter().map(|bd| scale(&bd, factor, shift)).collect(), labels)
+                }
+                Step::CapInfinite(cap) => (bd_pairs.into_iter().map(|bd| cap_infinite(&bd, cap)).collect(), labels),
+                Step::MinPersistence(min) => bd_pairs
+                    .into_iter()
+                    .zip(labels)
+                    .filter(|(bd, _)| bd.death - bd.birth >= min)
+                    .unzip(),
+                Step::Strict => {
+                    birthdeath::check(&bd_pairs, Strictness::Strict)?;
+                    (bd_pairs, labels)
+                }
+                Step::Canonical => {
+                    let mut labeled: Vec<_> = bd_pairs.into_iter().zip(labels).collect();
+                    labeled.sort_by(|(a, _), (b, _)| birthdeath::canonical_cmp(a, b));
+                    labeled.into_iter().unzip()
+                }
+                Step::TopPairs(_) | Step::Subsample { .. } => {
+                    return Err("Dropping pairs by rank is not available for labeled diagrams".into());
+                }
+            };
+        }
+        Ok((bd_pairs, labels))
+    }
+}
+
+/// Affine map of a diagram onto [0, 1], see [`Rescaling::fit`]
+///
+/// A coordinate `x` maps to `x * factor + shift` as in [`Step::Scale`]. Tents keep slopes of
+/// one, so the heights of a landscape computed in normalized units scale by `factor` alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rescaling {
+    pub factor: f64,
+    pub shift: f64,
+}
+
+/// Units of a landscape computed after a [`Rescaling`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    /// On [0, 1], as swept
+    Normalized,
+    /// Mapped back to the coordinates of the input
+    #[default]
+    Original,
+}
+
+impl Rescaling {
+    /// The map taking the earliest finite birth to 0 and the latest finite death to 1, `None`
+    /// if there are no finite pairs or they all share one value
+    #[must_use]
+    pub fn fit(bd_pairs: &[BirthDeath]) -> Option<Self> {
+        let finite = bd_pairs.iter().filter(|bd| bd.birth.is_finite() && bd.death.is_finite());
+        let low = finite.clone().map(|bd| bd.birth.min(bd.death)).reduce(f64::min)?;
+        let high = finite.map(|bd| bd.birth.max(bd.death)).reduce(f64::max)?;
+        let factor = 1.0 / (high - low);
+        factor.is_finite().then_some(Self { factor, shift: -low * factor })
+    }
+
+    /// The [`Step::Scale`] applying the map
+    #[must_use]
+    pub const fn step(self) -> Step {
+        Step::Scale { factor: self.factor, shift: self.shift }
+    }
+
+    /// Maps `bd_pairs` onto [0, 1]
+    #[must_use]
+    pub fn apply(self, bd_pairs: Vec<BirthDeath>) -> Vec<BirthDeath> {
+        bd_pairs.into_iter().map(|bd| scale(&bd, self.factor, self.shift)).collect()
+    }
+
+    /// A point of a landscape computed in normalized units in the units of the input
+    #[must_use]
+    pub fn invert_point(self, (x, y): (f64, f64)) -> (f64, f64) {
+        ((x - self.shift) / self.factor, y / self.factor)
+    }
+
+    /// Every point of `levels` in the units of the input, see [`Rescaling::invert_point`]
+    #[must_use]
+    pub fn invert(self, levels: Vec<Vec<(f64,f64)>>) -> Vec<Vec<(f64,f64)>> {
+        levels
+            .into_iter()
+            .map(|level| level.into_iter().map(|point| self.invert_point(point)).collect())
+            .collect()
+    }
+}
+
+fn scale(bd: &BirthDeath, factor: f64, shift: f64) -> BirthDeath {
+    BirthDeath {
+        birth: bd.birth.mul_add(factor, shift),
+        death: bd.death.mul_add(factor, shift),
+    }
+}
+
+fn cap_infinite(bd: &BirthDeath, cap: f64) -> BirthDeath {
+    BirthDeath {
+        birth: if bd.birth == f64::NEG_INFINITY { -cap } else { bd.birth },
+        death: if bd.death == f64::INFINITY { cap } else { bd.death },
+    }
+}
+
+impl fmt::Display for Step {
+    #[allow(clippy::float_cmp)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Scale { factor, shift } if shift == 0.0 => write!(f, "scale={factor}"),
+            Self::Scale { factor, shift } if factor == 1.0 => write!(f, "shift={shift}"),
+            Self::Scale { factor, shift } => write!(f, "scale={factor}:{shift}"),
+            Self::BirthPersistence => write!(f, "birth-persistence"),
+            Self::CapInfinite(cap) => write!(f, "cap-infinite={cap}"),
+            Self::MinPersistence(min) => write!(f, "min-persistence={min}"),
+            Self::Strict => write!(f, "strict"),
+            Self::Canonical => write!(f, "canonical"),
+            Self::TopPairs(n) => write!(f, "top={n}"),
+            Self::Subsample { n, weighted, seed } => {
+                let name = if weighted { "weighted-subsample" } else { "subsample" };
+                write!(f, "{name}={n}:{seed}")
+            }
+        }
+    }
+}
+
+impl FromStr for Step {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s.trim().split_once('=').map_or((s.trim(), None), |(name, value)| (name, Some(value)));
+        let number = |value: Option<&str>| -> Result<f64, &'static str> {
+            value.and_then(|v| v.parse().ok()).ok_or("Expected a number after the name of the step")
+        };
+        let count = |value: &str| -> Result<usize, &'static str> {
+            value.parse().map_err(|_| "Expected a whole number after the name of the step")
+        };
+        let step = match (name, value) {
+            ("scale", Some(value)) => match value.split_once(':') {
+                Some((factor, shift)) => Self::Scale { factor: number(Some(factor))?, shift: number(Some(shift))? },
+                None => Self::Scale { factor: number(Some(value))?, shift: 0.0 },
+            },
+            ("shift", value) => Self::Scale { factor: 1.0, shift: number(value)? },
+            ("cap-infinite", value) => Self::CapInfinite(number(value)?),
+            ("min-persistence", value) => Self::MinPersistence(number(value)?),
+            ("birth-persistence", None) => Self::BirthPersistence,
+            ("strict", None) => Self::Strict,
+            ("canonical", None) => Self::Canonical,
+            ("top", Some(value)) => Self::TopPairs(count(value)?),
+            ("subsample" | "weighted-subsample", Some(value)) => {
+                let (n, seed) = value.split_once(':').map_or((value, None), |(n, seed)| (n, Some(seed)));
+                let seed = seed.map_or(Ok(0), |seed| seed.parse().map_err(|_| "Expected a whole number as the seed"))?;
+                Self::Subsample { n: count(n)?, weighted: name == "weighted-subsample", seed }
+            }
+            _ => return Err("Unknown pipeline step, expected birth-persistence, scale, shift, cap-infinite, min-persistence, strict, canonical, top, subsample or weighted-subsample"),
+        };
+        step.validate()?;
+        Ok(step)
+    }
+}
+
+impl fmt::Display for DiagramPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DiagramPipeline {
+    type Err = &'static str;
+
+    /// Parses comma separated [`Step`]s, an empty spec is the empty pipeline
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|step| !step.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(|steps| Self { steps })
+    }
+}
@@ -42,7 +42,7 @@ pub fn landscape(
                ));
     let root = BitMapBackend::new("output.png", (width, height)).into_drawing_area();
     match root.fill(&WHITE) {
-        Ok(_) => (),
+        Ok(()) => (),
         _ => {
             unreachable!("Could not set backgrond color")
         }
@@ -66,7 +66,7 @@ pub fn landscape(
         .y_label_formatter(&|x| format!("{x:.3}"))
         .draw()?;
 
-    let colors = vec![&RED, &GREEN, &BLUE];
+    let colors = [&RED, &GREEN, &BLUE];
     for (i, data) in to_plot.into_iter().enumerate() {
         chart
             .draw_series(LineSeries::new(data, colors[i % colors.len()]))?;
@@ -74,3 +74,145 @@ pub fn landscape(
     root.present()?;
     Ok(())
 }
+
+/// Plots the levels of a difference landscape to `path` and shades the regions where its
+/// absolute value is at least `highlight` times its maximum
+///
+/// # Errors
+///
+/// Will return 'Err' if the chart can not be drawn or saved
+pub fn difference(
+    difference: &[Vec<(f64,f64)>],
+    path: &std::path::Path,
+    height: u32,
+    width: u32,
+    highlight: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let points = || difference.iter().flatten();
+    let x_lower = points().map(|p| FloatOrd(p.0)).min().map_or(0.0, |x| x.0);
+    let x_upper = points().map(|p| FloatOrd(p.0)).max().map_or(1.0, |x| x.0);
+    let y_max = points().map(|p| FloatOrd(p.1.abs())).max().map_or(0.0, |y| y.0);
+    let y_bound = if y_max > 0.0 { y_max } else { 1.0 };
+
+    let root = BitMapBackend::new(path, (width, height)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let root = root.margin(10, 10, 10, 10);
+    let mut chart = ChartBuilder::on(&root)
+        .x_label_area_size(20)
+        .y_label_area_size(40)
+        .build_cartesian_2d(x_lower..x_upper, -y_bound..y_bound)?;
+    chart
+        .configure_mesh()
+        .x_labels(5)
+        .y_labels(5)
+        .y_label_formatter(&|x| format!("{x:.3}"))
+        .draw()?;
+
+    let threshold = highlight * y_max;
+    let shaded = difference.iter().flat_map(|level| level.windows(2)).filter(|w| {
+        y_max > 0.0 && (w[0].1.abs() >= threshold || w[1].1.abs() >= threshold)
+    });
+    chart.draw_series(shaded.map(|w| {
+        let (x0, y0, x1, y1) = (w[0].0, w[0].1, w[1].0, w[1].1);
+        Polygon::new(vec![(x0, 0.0), (x0, y0), (x1, y1), (x1, 0.0)], RED.mix(0.3).filled())
+    }))?;
+    let colors = [&RED, &GREEN, &BLUE];
+    for (i, level) in difference.iter().enumerate() {
+        chart.draw_series(LineSeries::new(level.iter().copied(), colors[i % colors.len()]))?;
+    }
+    root.present()?;
+    Ok(())
+}
+
+/// Plots every level of `center` as a line over the region between the same levels of `lower`
+/// and `upper` to `path`
+///
+/// # Errors
+///
+/// Will return 'Err' if the chart can not be drawn or saved
+pub fn band(
+    lower: &[Vec<(f64,f64)>],
+    center: &[Vec<(f64,f64)>],
+    upper: &[Vec<(f64,f64)>],
+    path: &std::path::Path,
+    height: u32,
+    width: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let points = || lower.iter().chain(center).chain(upper).flatten();
+    let x_lower = points().map(|p| FloatOrd(p.0)).min().map_or(0.0, |x| x.0);
+    let x_upper = points().map(|p| FloatOrd(p.0)).max().map_or(1.0, |x| x.0);
+    let y_lower = points().map(|p| FloatOrd(p.1)).min().map_or(0.0, |y| y.0).min(0.0);
+    let y_upper = points().map(|p| FloatOrd(p.1)).max().map_or(1.0, |y| y.0).max(y_lower + 1e-9);
+
+    let root = BitMapBackend::new(path, (width, height)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let root = root.margin(10, 10, 10, 10);
+    let mut chart = ChartBuilder::on(&root)
+        .x_label_area_size(20)
+        .y_label_area_size(40)
+        .build_cartesian_2d(x_lower..x_upper, y_lower..y_upper)?;
+    chart
+        .configure_mesh()
+        .x_labels(5)
+        .y_labels(5)
+        .y_label_formatter(&|x| format!("{x:.3}"))
+        .draw()?;
+
+    let colors = [&RED, &GREEN, &BLUE];
+    for (i, ((low, mid), high)) in lower.iter().zip(center).zip(upper).enumerate() {
+        let color = colors[i % colors.len()];
+        // Along the upper bound and back along the lower one
+        let outline: Vec<(f64,f64)> = high.iter().chain(low.iter().rev()).copied().collect();
+        chart.draw_series(std::iter::once(Polygon::new(outline, color.mix(0.2).filled())))?;
+        chart.draw_series(LineSeries::new(mid.iter().copied(), color))?;
+    }
+    root.present()?;
+    Ok(())
+}
+
+/// Plots a rank function sampled on `grid` to `path` as a heatmap
+///
+/// `values` are as returned by [`crate::birthdeath::rank_function`]. Births run along the
+/// x-axis and deaths along the y-axis, darker cells hold more pairs.
+///
+/// # Errors
+///
+/// Will return 'Err' if the chart can not be drawn or saved
+#[allow(clippy::cast_precision_loss)]
+pub fn rank_function(
+    values: &[Vec<usize>],
+    grid: &crate::vectorize::Grid,
+    path: &std::path::Path,
+    height: u32,
+    width: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let points = grid.points();
+    // Every sample is the center of a cell as wide as the grid step
+    let half = if points.len() > 1 { (points[1] - points[0]) / 2.0 } else { 0.5 };
+    let (lower, upper) = (grid.x_min - half, grid.x_max + half);
+    let max = values.iter().flatten().copied().max().unwrap_or(0).max(1) as f64;
+
+    let root = BitMapBackend::new(path, (width, height)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let root = root.margin(10, 10, 10, 10);
+    let mut chart = ChartBuilder::on(&root)
+        .x_label_area_size(20)
+        .y_label_area_size(40)
+        .build_cartesian_2d(lower..upper, lower..upper)?;
+    chart
+        .configure_mesh()
+        .x_labels(5)
+        .y_labels(5)
+        .x_desc("birth")
+        .y_desc("death")
+        .draw()?;
+
+    let cells = values.iter().zip(&points).flat_map(|(row, &b)| {
+        row.iter().zip(&points).filter(|(&count, _)| count > 0).map(move |(&count, &d)| (b, d, count))
+    });
+    chart.draw_series(cells.map(|(b, d, count)| {
+        Rectangle::new([(b - half, d - half), (b + half, d + half)], BLUE.mix(count as f64 / max).filled())
+    }))?;
+    root.present()?;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! Commonly used types, `use fast_pl::prelude::*;` is enough for most callers.
+//!
+//! Landscapes are plain `(x, y)` tuples of `f64`, no `FloatOrd` types appear in the public
+//! API, so matching on results needs no other dependency.
+
+pub use crate::birthdeath::BirthDeath;
+pub use crate::builder::{InfinitePolicy, LandscapeBuilder};
+pub use crate::landscape::{Landscape, Normalization, Segment, Simplification, Violation};
+pub use crate::landscapeset::LandscapeSet;
+pub use crate::persistencelandscape::{generate, LimitExceeded, Limits};
+pub use crate::source::{Diagram, DiagramSource};
+pub use crate::vectorize::Grid;
@@ -0,0 +1,136 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Python bindings, built as the `fast_pl` extension module with the `python` feature
+//!
+//! Diagrams are `numpy.ndarray`s of shape `(n, 2)` holding a birth and a death per row. They
+//! are read in place through a borrowed view of their buffer, whatever their strides, so no
+//! contiguous copy is made at the language boundary. Levels and grids are returned as arrays
+//! that take over the memory the sweep wrote them to instead of copying it into `NumPy`.
+
+// The pyo3 macros generate pub(crate) items next to every function
+#![allow(clippy::redundant_pub_crate)]
+
+use numpy::ndarray::{Array2, ArrayView2};
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::generate;
+use crate::vectorize::{sample, Grid};
+
+/// Pairs of the rows of a diagram array
+///
+/// # Errors
+///
+/// Will return 'Err' if the array does not have two columns
+pub fn pairs(diagram: ArrayView2<'_, f64>) -> Result<Vec<BirthDeath>, &'static str> {
+    if diagram.ncols() != 2 {
+        return Err("Diagrams need two columns, birth and death");
+    }
+    Ok(diagram
+        .rows()
+        .into_iter()
+        .map(|row| BirthDeath { birth: row[0], death: row[1] })
+        .collect())
+}
+
+/// Samples of the first `k` levels of the landscape of every diagram on `grid`, one row per
+/// diagram, computed on all cores
+///
+/// # Panics
+///
+/// Will panic if invalid state is discovered during generation
+#[must_use]
+pub fn feature_matrix(diagrams: Vec<Vec<BirthDeath>>, k: usize, grid: &Grid) -> Array2<f64> {
+    let rows = diagrams.len();
+    let values: Vec<f64> = diagrams
+        .into_par_iter()
+        .flat_map_iter(|pairs| sample(&generate(pairs, k, false), k, grid))
+        .collect();
+    Array2::from_shape_vec((rows, k * grid.resolution), values).expect("Every diagram fills one row")
+}
+
+fn value_error(message: &'static str) -> PyErr {
+    PyValueError::new_err(message)
+}
+
+/// Levels of the landscape of `diagram`, each an array of shape `(points, 2)`
+#[pyfunction]
+// pyo3 hands over the arguments by value
+#[allow(clippy::needless_pass_by_value)]
+fn landscape<'py>(
+    py: Python<'py>,
+    diagram: PyReadonlyArray2<'py, f64>,
+    k: usize,
+) -> PyResult<Vec<Bound<'py, PyArray2<f64>>>> {
+    let pairs = pairs(diagram.as_array()).map_err(value_error)?;
+    let levels = py.allow_threads(|| generate(pairs, k, false));
+    levels
+        .into_iter()
+        .map(|level| {
+            let points = level.len();
+            let flat: Vec<f64> = level.into_iter().flat_map(<[f64; 2]>::from).collect();
+            let array = Array2::from_shape_vec((points, 2), flat)
+                .map_err(|_| value_error("Level does not fill its array"))?;
+            Ok(array.into_pyarray(py))
+        })
+        .collect()
+}
+
+/// Samples of the first `k` levels of the landscape of `diagram` at `resolution` evenly
+/// spaced points from `x_min` to `x_max`, an array of shape `(k, resolution)`
+#[pyfunction]
+// pyo3 hands over the arguments by value
+#[allow(clippy::needless_pass_by_value)]
+fn grid<'py>(
+    py: Python<'py>,
+    diagram: PyReadonlyArray2<'py, f64>,
+    k: usize,
+    x_min: f64,
+    x_max: f64,
+    resolution: usize,
+) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let grid = Grid::new(x_min, x_max, resolution).map_err(value_error)?;
+    let pairs = pairs(diagram.as_array()).map_err(value_error)?;
+    let values = py.allow_threads(|| sample(&generate(pairs, k, false), k, &grid));
+    let array = Array2::from_shape_vec((k, resolution), values)
+        .map_err(|_| value_error("Samples do not fill the grid"))?;
+    Ok(array.into_pyarray(py))
+}
+
+/// Feature matrix of `diagrams`, one row of `k * resolution` samples per diagram as in
+/// [`grid`], computed on all cores
+#[pyfunction]
+// pyo3 hands over the arguments by value
+#[allow(clippy::needless_pass_by_value)]
+fn grids<'py>(
+    py: Python<'py>,
+    diagrams: Vec<PyReadonlyArray2<'py, f64>>,
+    k: usize,
+    x_min: f64,
+    x_max: f64,
+    resolution: usize,
+) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let grid = Grid::new(x_min, x_max, resolution).map_err(value_error)?;
+    let pairs = diagrams
+        .iter()
+        .map(|diagram| pairs(diagram.as_array()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(value_error)?;
+    Ok(py.allow_threads(|| feature_matrix(pairs, k, &grid)).into_pyarray(py))
+}
+
+#[pymodule]
+fn fast_pl(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(landscape, module)?)?;
+    module.add_function(wrap_pyfunction!(grid, module)?)?;
+    module.add_function(wrap_pyfunction!(grids, module)?)?;
+    Ok(())
+}
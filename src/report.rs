@@ -0,0 +1,407 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use std::fmt::Write;
+
+use crate::statistics::{Comparison, ConfidenceBand};
+
+/// Size of the per level charts in [`comparison_html`]
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 240.0;
+
+/// Colors of the first and second group in [`comparison_html`]
+const COLORS: [&str; 2] = ["#1f77b4", "#d62728"];
+
+/// `s` as a quoted JSON string
+#[must_use]
+pub fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// JSON has no NaN or infinities, they are written as `null`
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_levels(levels: &[Vec<(f64,f64)>]) -> String {
+    let levels: Vec<String> = levels
+        .iter()
+        .map(|level| {
+            let points: Vec<String> = level
+                .iter()
+                .map(|&(x, y)| format!("[{},{}]", json_number(x), json_number(y)))
+                .collect();
+            format!("[{}]", points.join(","))
+        })
+        .collect();
+    format!("[{}]", levels.join(","))
+}
+
+fn json_group(files: &[String], band: &ConfidenceBand) -> String {
+    let files: Vec<String> = files.iter().map(|f| json_string(f)).collect();
+    format!(
+        "{{\"files\":[{}],\"mean\":{},\"lower\":{},\"upper\":{}}}",
+        files.join(","),
+        json_levels(&band.mean),
+        json_levels(&band.lower),
+        json_levels(&band.upper)
+    )
+}
+
+/// JSON Schema of the output of [`comparison_json`]
+///
+/// Written by hand next to the writer, keep both in sync.
+pub const COMPARISON_SCHEMA: &str = r##"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "Landscape comparison",
+  "type": "object",
+  "required": ["alpha", "n_bootstrap", "n_permutations", "seed", "distance", "p_value", "groups"],
+  "additionalProperties": false,
+  "properties": {
+    "alpha": { "$ref": "#/$defs/number" },
+    "n_bootstrap": { "type": "integer", "minimum": 0 },
+    "n_permutations": { "type": "integer", "minimum": 0 },
+    "seed": { "type": "integer", "minimum": 0 },
+    "distance": { "$ref": "#/$defs/number" },
+    "p_value": { "$ref": "#/$defs/number" },
+    "groups": {
+      "type": "array",
+      "items": { "$ref": "#/$defs/group" },
+      "minItems": 2,
+      "maxItems": 2
+    }
+  },
+  "$defs": {
+    "number": {
+      "description": "NaN and infinities are written as null",
+      "type": ["number", "null"]
+    },
+    "levels": {
+      "description": "Landscape levels, every level a list of [x, y] critical points",
+      "type": "array",
+      "items": {
+        "type": "array",
+        "items": {
+          "type": "array",
+          "prefixItems": [{ "$ref": "#/$defs/number" }, { "$ref": "#/$defs/number" }],
+          "items": false,
+          "minItems": 2
+        }
+      }
+    },
+    "group": {
+      "type": "object",
+      "required": ["files", "mean", "lower", "upper"],
+      "additionalProperties": false,
+      "properties": {
+        "files": { "type": "array", "items": { "type": "string" } },
+        "mean": { "$ref": "#/$defs/levels" },
+        "lower": { "$ref": "#/$defs/levels" },
+        "upper": { "$ref": "#/$defs/levels" }
+      }
+    }
+  }
+}
+"##;
+
+/// TypeScript definitions of the output of [`comparison_json`], matching [`COMPARISON_SCHEMA`]
+///
+/// Seeds above `2^53` lose precision when parsed as a JavaScript number.
+pub const COMPARISON_TYPESCRIPT: &str = "\
+/** NaN and infinities are written as null */
+export type JsonNumber = number | null;
+
+/** Landscape levels, every level a list of [x, y] critical points */
+export type Levels = [JsonNumber, JsonNumber][][];
+
+export interface ComparisonGroup {
+  files: string[];
+  mean: Levels;
+  lower: Levels;
+  upper: Levels;
+}
+
+export interface Comparison {
+  alpha: JsonNumber;
+  n_bootstrap: number;
+  n_permutations: number;
+  seed: number;
+  distance: JsonNumber;
+  p_value: JsonNumber;
+  groups: [ComparisonGroup, ComparisonGroup];
+}
+";
+
+/// Report of [`crate::statistics::compare_groups`] as JSON, `files_a` and `files_b` name the
+/// inputs of both groups, described by [`COMPARISON_SCHEMA`]
+#[must_use]
+pub fn comparison_json(comparison: &Comparison, files_a: &[String], files_b: &[String]) -> String {
+    let settings = &comparison.settings;
+    format!(
+        "{{\"alpha\":{},\"n_bootstrap\":{},\"n_permutations\":{},\"seed\":{},\"distance\":{},\"p_value\":{},\"groups\":[{},{}]}}\n",
+        json_number(settings.alpha),
+        settings.n_bootstrap,
+        settings.n_permutations,
+        settings.seed,
+        json_number(comparison.distance),
+        json_number(comparison.p_value),
+        json_group(files_a, &comparison.band_a),
+        json_group(files_b, &comparison.band_b)
+    )
+}
+
+/// [`crate::distance::LevelNorms`] of every diagram as a JSON array with one object per
+/// diagram holding its index and the norms of every level
+#[must_use]
+pub fn norms_json(norms: &[Vec<crate::distance::LevelNorms>]) -> String {
+    let diagrams: Vec<String> = norms
+        .iter()
+        .enumerate()
+        .map(|(diagram, levels)| {
+            let levels: Vec<String> = levels
+                .iter()
+                .map(|n| {
+                    format!(
+                        "{{\"l1\":{},\"l2\":{},\"sup\":{},\"integral\":{}}}",
+                        json_number(n.l1),
+                        json_number(n.l2),
+                        json_number(n.sup),
+                        json_number(n.integral)
+                    )
+                })
+                .collect();
+            format!("{{\"diagram\":{diagram},\"levels\":[{}]}}", levels.join(","))
+        })
+        .collect();
+    format!("[{}]\n", diagrams.join(","))
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// One SVG chart of `level` of both bands
+fn svg_level(bands: [&ConfidenceBand; 2], level: usize) -> String {
+    let levels = bands.map(|band| {
+        let get = |levels: &[Vec<(f64,f64)>]| levels.get(level).cloned().unwrap_or_default();
+        (get(&band.mean), get(&band.lower), get(&band.upper))
+    });
+    let points = || levels.iter().flat_map(|(mean, lower, upper)| mean.iter().chain(lower).chain(upper));
+    let x_min = points().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let x_max = points().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let y_max = points().map(|p| p.1).fold(0.0, f64::max);
+    let x_range = if x_max > x_min { x_max - x_min } else { 1.0 };
+    let y_range = if y_max > 0.0 { y_max } else { 1.0 };
+    let to_svg = |&(x, y): &(f64,f64)| {
+        format!("{:.2},{:.2}", (x - x_min) / x_range * CHART_WIDTH, (y / y_range).mul_add(-CHART_HEIGHT, CHART_HEIGHT))
+    };
+
+    let mut svg = format!(
+        "<svg width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\">\n"
+    );
+    for ((mean, lower, upper), color) in levels.iter().zip(COLORS) {
+        let outline: Vec<String> = upper.iter().chain(lower.iter().rev()).map(to_svg).collect();
+        let _ = writeln!(
+            svg,
+            "<polygon points=\"{}\" fill=\"{color}\" fill-opacity=\"0.2\" stroke=\"none\"/>",
+            outline.join(" ")
+        );
+        let line: Vec<String> = mean.iter().map(to_svg).collect();
+        let _ = writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\"/>", line.join(" "));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Report of [`crate::statistics::compare_groups`] as a self-contained HTML page with the
+/// means and confidence bands of every level drawn as SVG
+#[must_use]
+pub fn comparison_html(comparison: &Comparison, files_a: &[String], files_b: &[String]) -> String {
+    let settings = &comparison.settings;
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Landscape comparison</title></head>\n<body>\n",
+    );
+    html.push_str("<h1>Landscape comparison</h1>\n<table>\n");
+    let _ = writeln!(html, "<tr><th>L2 distance between means</th><td>{}</td></tr>", comparison.distance);
+    let _ = writeln!(
+        html,
+        "<tr><th>Permutation p-value</th><td>{} ({} permutations)</td></tr>",
+        comparison.p_value, settings.n_permutations
+    );
+    let _ = writeln!(
+        html,
+        "<tr><th>Confidence bands</th><td>{}% pointwise, {} bootstrap samples</td></tr>",
+        (1.0 - settings.alpha) * 100.0,
+        settings.n_bootstrap
+    );
+    html.push_str("</table>\n");
+    for (name, files, color) in [("A", files_a, COLORS[0]), ("B", files_b, COLORS[1])] {
+        let _ = writeln!(html, "<h2 style=\"color:{color}\">Group {name} ({} diagrams)</h2>\n<ul>", files.len());
+        for file in files {
+            let _ = writeln!(html, "<li>{}</li>", html_escape(file));
+        }
+        html.push_str("</ul>\n");
+    }
+    let depth = comparison.band_a.mean.len().max(comparison.band_b.mean.len());
+    for level in 0..depth {
+        let _ = writeln!(html, "<h2>Level {}</h2>", level + 1);
+        html.push_str(&svg_level([&comparison.band_a, &comparison.band_b], level));
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Size of every panel of [`side_by_side_svg`] and the space around them
+const PANEL_WIDTH: f64 = 360.0;
+const PANEL_HEIGHT: f64 = 160.0;
+const PANEL_MARGIN: f64 = 24.0;
+
+/// Height of a row of panels with its captions, and the offsets from its top of the caption
+/// of the row, the titles of the panels and the panels
+const ROW_HEIGHT: f64 = 208.0;
+const ROW_OFFSETS: [f64; 3] = [18.0, 38.0, 42.0];
+
+/// Color of the difference panel of [`side_by_side_svg`]
+const DIFFERENCE_COLOR: &str = "#2ca02c";
+
+/// L1, L2 and sup norm of a difference and the x of the sup, NaN if it is empty
+fn difference_norms(levels: &[Vec<(f64,f64)>]) -> [f64; 4] {
+    let sup = levels
+        .iter()
+        .flatten()
+        .max_by(|p, q| p.1.abs().total_cmp(&q.1.abs()))
+        .map_or((f64::NAN, 0.0), |p| (p.0, p.1.abs()));
+    [crate::distance::l1_distance(levels, &[]), crate::distance::l2_distance(levels, &[]), sup.1, sup.0]
+}
+
+/// Polyline of `level` in the panel at `(left, top)` showing `x_range` and `y_range`
+fn svg_panel(level: &[(f64,f64)], (left, top): (f64, f64), x_range: (f64, f64), y_range: (f64, f64), color: &str) -> String {
+    let width = if x_range.1 > x_range.0 { x_range.1 - x_range.0 } else { 1.0 };
+    let height = if y_range.1 > y_range.0 { y_range.1 - y_range.0 } else { 1.0 };
+    let to_svg = |&(x, y): &(f64,f64)| {
+        format!(
+            "{:.2},{:.2}",
+            ((x - x_range.0) / width).mul_add(PANEL_WIDTH, left),
+            ((y - y_range.0) / height).mul_add(-PANEL_HEIGHT, top + PANEL_HEIGHT)
+        )
+    };
+    let mut svg = format!(
+        "<rect x=\"{left}\" y=\"{top}\" width=\"{PANEL_WIDTH}\" height=\"{PANEL_HEIGHT}\" fill=\"none\" stroke=\"#ccc\"/>\n"
+    );
+    if y_range.0 < 0.0 {
+        let zero = to_svg(&(x_range.0, 0.0));
+        let end = to_svg(&(x_range.1, 0.0));
+        let _ = writeln!(svg, "<polyline points=\"{zero} {end}\" fill=\"none\" stroke=\"#999\" stroke-dasharray=\"4\"/>");
+    }
+    let line: Vec<String> = level.iter().map(to_svg).collect();
+    let _ = writeln!(svg, "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\"/>", line.join(" "));
+    svg
+}
+
+/// Two landscapes side by side as a self-contained SVG, one row per level with a panel for
+/// `a`, one for `b` and one for the difference `a - b`, each row captioned with the norms of
+/// the difference
+///
+/// All panels share the x-axis. The panels of `a` and `b` in a row share their y-axis, the
+/// difference is drawn symmetrically around a dashed zero line.
+#[must_use]
+pub fn side_by_side_svg(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>], names: [&str; 2]) -> String {
+    let difference = crate::distance::difference(a, b);
+    let xs = || a.iter().chain(b).flatten().map(|p| p.0);
+    let x_range = (xs().fold(f64::INFINITY, f64::min), xs().fold(f64::NEG_INFINITY, f64::max));
+    let width = 3.0f64.mul_add(PANEL_WIDTH, 4.0 * PANEL_MARGIN);
+    #[allow(clippy::cast_precision_loss)]
+    let height = (difference.len() as f64).mul_add(ROW_HEIGHT, 2.0 * PANEL_MARGIN);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" font-family=\"sans-serif\" font-size=\"12\">\n"
+    );
+    let [l1, l2, sup, sup_x] = difference_norms(&difference);
+    let _ = writeln!(
+        svg,
+        "<text x=\"{PANEL_MARGIN}\" y=\"{PANEL_MARGIN}\">All levels: L1 {l1}, L2 {l2}, sup {sup} at x = {sup_x}</text>"
+    );
+    let titles = [
+        (html_escape(names[0]), COLORS[0]),
+        (html_escape(names[1]), COLORS[1]),
+        (format!("{} - {}", html_escape(names[0]), html_escape(names[1])), DIFFERENCE_COLOR),
+    ];
+    for (level, diff) in difference.iter().enumerate() {
+        let get = |levels: &[Vec<(f64,f64)>]| levels.get(level).cloned().unwrap_or_default();
+        let (level_a, level_b) = (get(a), get(b));
+        #[allow(clippy::cast_precision_loss)]
+        let top = (level as f64).mul_add(ROW_HEIGHT, 2.0 * PANEL_MARGIN);
+        let y_max = level_a.iter().chain(&level_b).map(|p| p.1).fold(0.0, f64::max);
+        let d_max = diff.iter().map(|p| p.1.abs()).fold(0.0, f64::max);
+        let [l1, l2, sup, sup_x] = difference_norms(std::slice::from_ref(diff));
+        let _ = writeln!(
+            svg,
+            "<text x=\"{PANEL_MARGIN}\" y=\"{}\">Level {}: L1 {l1}, L2 {l2}, sup {sup} at x = {sup_x}</text>",
+            top + ROW_OFFSETS[0],
+            level + 1
+        );
+        let panels = [(&level_a, (0.0, y_max)), (&level_b, (0.0, y_max)), (diff, (-d_max, d_max))];
+        for (column, ((points, y_range), (title, color))) in panels.into_iter().zip(&titles).enumerate() {
+            #[allow(clippy::cast_precision_loss)]
+            let left = (column as f64).mul_add(PANEL_WIDTH + PANEL_MARGIN, PANEL_MARGIN);
+            let _ = writeln!(
+                svg,
+                "<text x=\"{left}\" y=\"{}\" fill=\"{color}\">{title}</text>",
+                top + ROW_OFFSETS[1]
+            );
+            svg.push_str(&svg_panel(points, (left, top + ROW_OFFSETS[2]), x_range, y_range, color));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Two landscapes side by side as a self-contained HTML page, a table of the norms of the
+/// difference `a - b` per level followed by the panels of [`side_by_side_svg`]
+#[must_use]
+pub fn side_by_side_html(a: &[Vec<(f64,f64)>], b: &[Vec<(f64,f64)>], names: [&str; 2]) -> String {
+    let difference = crate::distance::difference(a, b);
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Landscape difference</title></head>\n<body>\n",
+    );
+    let _ = writeln!(
+        html,
+        "<h1>Landscape difference</h1>\n<p><span style=\"color:{}\">{}</span> against <span style=\"color:{}\">{}</span></p>",
+        COLORS[0],
+        html_escape(names[0]),
+        COLORS[1],
+        html_escape(names[1])
+    );
+    html.push_str("<table>\n<tr><th>Level</th><th>L1</th><th>L2</th><th>Sup</th><th>Sup at x</th></tr>\n");
+    let rows = difference
+        .iter()
+        .enumerate()
+        .map(|(i, level)| ((i + 1).to_string(), difference_norms(std::slice::from_ref(level))))
+        .chain(std::iter::once(("All".to_string(), difference_norms(&difference))));
+    for (level, [l1, l2, sup, sup_x]) in rows {
+        let _ = writeln!(html, "<tr><td>{level}</td><td>{l1}</td><td>{l2}</td><td>{sup}</td><td>{sup_x}</td></tr>");
+    }
+    html.push_str("</table>\n");
+    html.push_str(&side_by_side_svg(a, b, names));
+    html.push_str("</body>\n</html>\n");
+    html
+}
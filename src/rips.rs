@@ -0,0 +1,233 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Vietoris-Rips persistence of point clouds and distance matrices, so landscapes can be
+//! computed from raw data in one call
+//!
+//! Dimension 0 comes from the minimum spanning tree and dimension 1 from reducing the
+//! boundary matrix of the triangles over Z/2, which takes `O(n^3)` memory for `n` points
+//! unless a `threshold` keeps the complex small.
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::generate;
+use crate::sublevel::Levels;
+
+/// Highest homology dimension [`persistence`] computes
+pub const MAX_DIMENSION: usize = 1;
+
+/// Checks that `distances` is a square, symmetric matrix of non-negative values with a zero
+/// diagonal
+///
+/// # Errors
+///
+/// Will return 'Err' describing the first violation
+pub fn check_distances(distances: &[Vec<f64>]) -> Result<(), &'static str> {
+    let n = distances.len();
+    if distances.iter().any(|row| row.len() != n) {
+        return Err("Distance matrix must be square");
+    }
+    for (i, row) in distances.iter().enumerate() {
+        if row[i] != 0.0 {
+            return Err("Distance matrix must have a zero diagonal");
+        }
+        if row.iter().any(|d| d.is_nan() || *d < 0.0) {
+            return Err("Distances must not be negative or NaN");
+        }
+        if row.iter().enumerate().any(|(j, d)| distances[j][i].to_bits() != d.to_bits()) {
+            return Err("Distance matrix must be symmetric");
+        }
+    }
+    Ok(())
+}
+
+/// Euclidean distances between every pair of `points`
+///
+/// # Errors
+///
+/// Will return 'Err' if the points differ in dimension or a coordinate is not finite
+pub fn euclidean_distances(points: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, &'static str> {
+    let dimension = points.first().map_or(0, Vec::len);
+    if points.iter().any(|p| p.len() != dimension) {
+        return Err("Every point must have the same number of coordinates");
+    }
+    if points.iter().flatten().any(|x| !x.is_finite()) {
+        return Err("Coordinates must be finite");
+    }
+    Ok(points
+        .iter()
+        .map(|p| {
+            points
+                .iter()
+                .map(|q| p.iter().zip(q).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt())
+                .collect()
+        })
+        .collect())
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Symmetric difference of two columns sorted in decreasing order
+fn add_columns(a: &[usize], b: &[usize]) -> Vec<usize> {
+    let mut sum = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Greater => {
+                sum.push(a[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Less => {
+                sum.push(b[j]);
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    sum.extend_from_slice(&a[i..]);
+    sum.extend_from_slice(&b[j..]);
+    sum
+}
+
+/// Persistence diagrams of the Vietoris-Rips filtration of `distances` in every dimension up
+/// to `max_dimension`, with only the edges of length at most `threshold`
+///
+/// Pairs of zero persistence are left out. Classes still alive at `threshold` die at
+/// infinity, in dimension 0 that is one pair per connected component.
+///
+/// # Errors
+///
+/// Will return 'Err' if `distances` fails [`check_distances`], `max_dimension` is above
+/// [`MAX_DIMENSION`] or `threshold` is NaN
+pub fn persistence(distances: &[Vec<f64>], max_dimension: usize, threshold: f64) -> Result<Vec<Vec<BirthDeath>>, &'static str> {
+    check_distances(distances)?;
+    if max_dimension > MAX_DIMENSION {
+        return Err("Only dimensions 0 and 1 are supported");
+    }
+    if threshold.is_nan() {
+        return Err("Threshold must not be NaN");
+    }
+    let n = distances.len();
+    let mut edges: Vec<(f64, usize, usize)> = (0..n)
+        .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+        .map(|(i, j)| (distances[i][j], i, j))
+        .filter(|&(d, _, _)| d <= threshold)
+        .collect();
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    // Dimension 0: every edge joining two components kills the younger one
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut negative = vec![false; edges.len()];
+    let mut diagrams: Vec<Vec<BirthDeath>> = (0..=max_dimension).map(|_| Vec::new()).collect();
+    for (index, &(d, i, j)) in edges.iter().enumerate() {
+        let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+        if root_i != root_j {
+            parent[root_i.max(root_j)] = root_i.min(root_j);
+            negative[index] = true;
+            if d > 0.0 {
+                diagrams[0].push(BirthDeath { birth: 0.0, death: d });
+            }
+        }
+    }
+    let components = (0..n).filter(|&i| find(&mut parent, i) == i).count();
+    diagrams[0].extend((0..components).map(|_| BirthDeath { birth: 0.0, death: f64::INFINITY }));
+    if max_dimension == 0 {
+        return Ok(diagrams);
+    }
+
+    // Dimension 1: reduce the boundaries of the triangles in filtration order
+    let mut edge_index = vec![usize::MAX; n * n];
+    for (index, &(_, i, j)) in edges.iter().enumerate() {
+        edge_index[i * n + j] = index;
+    }
+    let edge = |i: usize, j: usize| edge_index[i * n + j];
+    let mut triangles: Vec<(f64, [usize; 3])> = Vec::new();
+    for i in 0..n {
+        for j in i + 1..n {
+            if edge(i, j) == usize::MAX {
+                continue;
+            }
+            for l in j + 1..n {
+                let mut boundary = [edge(i, j), edge(i, l), edge(j, l)];
+                if boundary.contains(&usize::MAX) {
+                    continue;
+                }
+                boundary.sort_unstable_by(|a, b| b.cmp(a));
+                triangles.push((edges[boundary[0]].0, boundary));
+            }
+        }
+    }
+    triangles.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut reduced: Vec<Vec<usize>> = Vec::with_capacity(triangles.len());
+    let mut column_of = vec![usize::MAX; edges.len()];
+    for (diameter, boundary) in &triangles {
+        let mut column = boundary.to_vec();
+        while let Some(&other) = column.first().map(|&low| &column_of[low]).filter(|&&other| other != usize::MAX) {
+            column = add_columns(&column, &reduced[other]);
+        }
+        if let Some(&low) = column.first() {
+            column_of[low] = reduced.len();
+            if *diameter > edges[low].0 {
+                diagrams[1].push(BirthDeath { birth: edges[low].0, death: *diameter });
+            }
+        }
+        reduced.push(column);
+    }
+    diagrams[1].extend(
+        edges
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| !negative[index] && column_of[index] == usize::MAX)
+            .map(|(_, &(d, _, _))| BirthDeath { birth: d, death: f64::INFINITY }),
+    );
+    Ok(diagrams)
+}
+
+/// First `k` levels of the landscape of the Vietoris-Rips diagram of `distances` in every
+/// dimension up to `max_dimension`, see [`persistence`]
+///
+/// Pairs that never die are left out and `k` of zero keeps every nonempty level.
+///
+/// # Errors
+///
+/// Will return 'Err' if the diagrams can not be computed, see [`persistence`]
+pub fn landscapes(
+    distances: &[Vec<f64>],
+    max_dimension: usize,
+    k: usize,
+    threshold: f64,
+) -> Result<Vec<Levels>, &'static str> {
+    Ok(persistence(distances, max_dimension, threshold)?
+        .into_iter()
+        .map(|diagram| generate(diagram.into_iter().filter(|bd| bd.death.is_finite()).collect(), k, false))
+        .collect())
+}
+
+/// Same as [`landscapes`] for the diagram in `dimension` only
+///
+/// # Errors
+///
+/// Will return 'Err' if the diagram can not be computed, see [`persistence`]
+pub fn landscape(
+    distances: &[Vec<f64>],
+    dimension: usize,
+    k: usize,
+    threshold: f64,
+) -> Result<Levels, &'static str> {
+    let mut diagrams = persistence(distances, dimension, threshold)?;
+    let diagram = diagrams.swap_remove(dimension);
+    Ok(generate(diagram.into_iter().filter(|bd| bd.death.is_finite()).collect(), k, false))
+}
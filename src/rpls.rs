@@ -12,10 +12,13 @@ use crate::birthdeath::BirthDeath;
 use crate::persistencelandscape;
 use crate::barcode;
 
+/// Drops zero length pairs and, unless `disable_filter` is set, every pair that can not show
+/// up in the first `k` levels
+///
 /// # Errors
 ///
-/// Will return 'Err' if failed to compute persistencelandscape from `bd_pairs`
-pub fn pairs_to_landscape(bd_pairs: Vec<BirthDeath>, k:usize, debug:bool, disable_filter: bool) -> Result<Vec<Vec<(f64,f64)>>, &'static str>{
+/// Will return 'Err' if no pairs remain
+pub fn prepare_pairs(bd_pairs: Vec<BirthDeath>, k:usize, debug:bool, disable_filter: bool) -> Result<Vec<BirthDeath>, &'static str>{
     let bd_pairs: Vec<BirthDeath> = bd_pairs
         .into_iter()
         .filter(|bd| (bd.birth - bd.death).abs() > f64::EPSILON)
@@ -25,21 +28,29 @@ pub fn pairs_to_landscape(bd_pairs: Vec<BirthDeath>, k:usize, debug:bool, disabl
     }
 
     if debug {
-        println!("{bd_pairs:?}");
+        eprintln!("{bd_pairs:?}");
     }
-    let filtered_pairs = if disable_filter{
-        bd_pairs
+    // The filter keeps pairs that reach the top k, with no limit every pair is needed
+    if disable_filter || k == 0 {
+        Ok(bd_pairs)
     }
     else{
         let filtered_pairs = barcode::filter(bd_pairs, k);
         if debug {
-            println!("{filtered_pairs:?}");
+            eprintln!("{filtered_pairs:?}");
         }
-        filtered_pairs
-    };
+        Ok(filtered_pairs)
+    }
+}
+
+/// # Errors
+///
+/// Will return 'Err' if failed to compute persistencelandscape from `bd_pairs`
+pub fn pairs_to_landscape(bd_pairs: Vec<BirthDeath>, k:usize, debug:bool, disable_filter: bool) -> Result<Vec<Vec<(f64,f64)>>, &'static str>{
+    let filtered_pairs = prepare_pairs(bd_pairs, k, debug, disable_filter)?;
     let landscape = persistencelandscape::generate(filtered_pairs, k, debug);
     if debug {
-        println!("{landscape:?}");
+        eprintln!("{landscape:?}");
     }
     Ok(landscape)
 }
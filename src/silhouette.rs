@@ -0,0 +1,66 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+
+use crate::birthdeath::BirthDeath;
+
+/// Power weighted silhouette of `bd_pairs`, the average of the tents of all pairs weighted by
+/// `|death - birth|^p`
+///
+/// `p = 0` is the plain average of the tents, larger `p` emphasizes persistent pairs. Pairs
+/// with a NaN or infinite value are skipped. The result is empty if no pair has weight.
+#[must_use]
+pub fn silhouette(bd_pairs: &[BirthDeath], p: f64) -> Vec<(f64,f64)> {
+    silhouettes(bd_pairs, &[p]).pop().unwrap_or_default()
+}
+
+/// [`silhouette`] for every power in `powers`, sharing one sort of the critical points
+///
+/// The weighted sum of the tents changes slope by `+w` at a birth, `-2w` at a midpoint and
+/// `+w` at a death, so every silhouette is accumulated in a single sweep over those points.
+#[must_use]
+pub fn silhouettes(bd_pairs: &[BirthDeath], powers: &[f64]) -> Vec<Vec<(f64,f64)>> {
+    let pairs: Vec<&BirthDeath> = bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite() && bd.death > bd.birth)
+        .collect();
+    let weights: Vec<Vec<f64>> = powers
+        .iter()
+        .map(|&p| pairs.iter().map(|bd| bd.persistence().powf(p)).collect())
+        .collect();
+    let totals: Vec<f64> = weights.iter().map(|w| w.iter().sum()).collect();
+
+    // (x, pair, slope change in units of the pair weight)
+    let mut events: Vec<(FloatOrd<f64>, usize, f64)> = pairs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, bd)| [(bd.birth, i, 1.0), (bd.midpoint(), i, -2.0), (bd.death, i, 1.0)])
+        .map(|(x, i, change)| (FloatOrd(x), i, change))
+        .collect();
+    events.sort_unstable_by_key(|e| e.0);
+
+    let mut curves = vec![Vec::new(); powers.len()];
+    let mut values = vec![0.0_f64; powers.len()];
+    let mut slopes = vec![0.0_f64; powers.len()];
+    let mut previous_x = events.first().map_or(0.0, |e| e.0 .0);
+    for (at, group) in events.chunk_by(|a, b| a.0 == b.0).map(|g| (g[0].0 .0, g)) {
+        for (((curve, value), slope), total) in curves.iter_mut().zip(&mut values).zip(&mut slopes).zip(&totals) {
+            *value = slope.mul_add(at - previous_x, *value);
+            if *total > 0.0 {
+                curve.push((at, (*value / total).max(0.0)));
+            }
+        }
+        for &(_, i, change) in group {
+            for (slope, w) in slopes.iter_mut().zip(&weights) {
+                *slope = change.mul_add(w[i], *slope);
+            }
+        }
+        previous_x = at;
+    }
+    curves
+}
@@ -0,0 +1,103 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Pluggable inputs of diagrams
+//!
+//! Every reader of [`crate::io`] is available as a [`DiagramSource`], and other formats only
+//! need to implement the trait to work with [`landscapes`] and the rest of the batch API.
+
+use rayon::prelude::*;
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::birthdeath::BirthDeath;
+use crate::io::{self, Header, ReadOptions};
+use crate::persistencelandscape::generate;
+use crate::sublevel::Levels;
+
+/// Pairs of one persistence diagram
+pub type Diagram = Vec<BirthDeath>;
+
+/// Anything diagrams can be read from
+pub trait DiagramSource {
+    /// The diagrams of the source in order
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the diagrams can not be read
+    fn diagrams(&mut self) -> Result<impl Iterator<Item = Diagram>, Box<dyn Error>>;
+}
+
+/// File holding one or more diagrams, see [`io::read_diagrams_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramFile {
+    pub path: PathBuf,
+    pub options: ReadOptions,
+    /// Read the file as a single diagram through a memory map, see [`io::read_diagram_mmap`]
+    pub mmap: bool,
+}
+
+impl DiagramFile {
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), options: ReadOptions::default(), mmap: false }
+    }
+}
+
+impl DiagramSource for DiagramFile {
+    fn diagrams(&mut self) -> Result<impl Iterator<Item = Diagram>, Box<dyn Error>> {
+        Ok(if self.mmap {
+            vec![io::read_diagram_mmap(&self.path, self.options)?]
+        } else {
+            io::read_diagrams_with(&self.path, self.options)?
+        }
+        .into_iter())
+    }
+}
+
+/// Files holding one diagram each, see [`io::read_diagram_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramFiles {
+    pub paths: Vec<PathBuf>,
+    pub header: Header,
+}
+
+impl DiagramFiles {
+    #[must_use]
+    pub fn new(paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self { paths: paths.into_iter().map(Into::into).collect(), header: Header::Detect }
+    }
+}
+
+impl DiagramSource for DiagramFiles {
+    fn diagrams(&mut self) -> Result<impl Iterator<Item = Diagram>, Box<dyn Error>> {
+        // Every file is read before the first diagram is handed out so errors surface here
+        Ok(self
+            .paths
+            .iter()
+            .map(|path| io::read_diagram_with(path, self.header))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter())
+    }
+}
+
+/// Diagrams already in memory, which are moved out of the vector
+impl DiagramSource for Vec<Diagram> {
+    fn diagrams(&mut self) -> Result<impl Iterator<Item = Diagram>, Box<dyn Error>> {
+        Ok(std::mem::take(self).into_iter())
+    }
+}
+
+/// First `k` levels of the landscape of every diagram of `source`, computed in parallel
+///
+/// # Errors
+///
+/// Will return 'Err' if the diagrams can not be read
+pub fn landscapes(source: &mut impl DiagramSource, k: usize) -> Result<Vec<Levels>, Box<dyn Error>> {
+    let diagrams: Vec<Diagram> = source.diagrams()?.collect();
+    Ok(diagrams.into_par_iter().map(|diagram| generate(diagram, k, false)).collect())
+}
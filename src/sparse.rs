@@ -0,0 +1,205 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Landscapes stored as the intervals where each level is nonzero
+//!
+//! Deep levels of a landscape are zero almost everywhere, a [`SparseLandscape`] drops the runs
+//! of zero between the bumps of a level and keeps every bump as its own [`Support`].
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::distance::{self, LevelNorms};
+use crate::landscape::Landscape;
+use crate::persistencelandscape::evaluate;
+
+/// Critical points of a level over one interval where it is nonzero, from the zero at its start
+/// to the zero at its end
+pub type Support = Vec<(f64,f64)>;
+
+/// A landscape as the [`Support`]s of every level, sorted by x
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SparseLandscape {
+    levels: Vec<Vec<Support>>,
+}
+
+/// Splits a level into its supports, dropping segments where it is zero at both ends
+fn supports(level: &[(f64,f64)]) -> Vec<Support> {
+    if let [point] = level {
+        return if point.1 == 0.0 { Vec::new() } else { vec![vec![*point]] };
+    }
+    let mut supports = Vec::new();
+    let mut current: Support = Vec::new();
+    for w in level.windows(2) {
+        let (p, q) = (w[0], w[1]);
+        if p.1 == 0.0 && q.1 == 0.0 {
+            continue;
+        }
+        if current.is_empty() {
+            current.push(p);
+        }
+        current.push(q);
+        // A level touching zero ends one support and starts the next
+        if q.1 == 0.0 {
+            supports.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        supports.push(current);
+    }
+    supports
+}
+
+impl SparseLandscape {
+    /// Sparse form of the critical points of every level
+    #[must_use]
+    pub fn new(levels: &[Vec<(f64,f64)>]) -> Self {
+        Self { levels: levels.iter().map(|level| supports(level)).collect() }
+    }
+
+    /// Supports of every level
+    #[must_use]
+    pub fn levels(&self) -> &[Vec<Support>] {
+        &self.levels
+    }
+
+    /// Number of levels, including empty ones
+    #[must_use]
+    pub fn k(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Number of stored critical points
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.levels.iter().flatten().map(Vec::len).sum()
+    }
+
+    /// Whether every level is zero everywhere
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.levels.iter().all(Vec::is_empty)
+    }
+
+    /// Intervals where `level` is nonzero, empty for levels that do not exist
+    pub fn support(&self, level: usize) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.levels
+            .get(level)
+            .into_iter()
+            .flatten()
+            .filter_map(|support| Some((support.first()?.0, support.last()?.0)))
+    }
+
+    /// Value of `level` at `x`, zero for levels that do not exist
+    #[must_use]
+    pub fn evaluate(&self, level: usize, x: f64) -> f64 {
+        let Some(supports) = self.levels.get(level) else {
+            return 0.0;
+        };
+        // First support not ending before x
+        let i = supports.partition_point(|support| support.last().is_some_and(|p| p.0 < x));
+        supports.get(i).map_or(0.0, |support| evaluate(support, x))
+    }
+
+    /// Exact norms of every level, the same as [`distance::level_norms`] of the dense form
+    #[must_use]
+    pub fn level_norms(&self) -> Vec<LevelNorms> {
+        self.levels
+            .iter()
+            .map(|supports| {
+                let norms = distance::level_norms(supports);
+                LevelNorms {
+                    l1: norms.iter().map(|n| n.l1).sum(),
+                    l2: norms.iter().map(|n| n.l2 * n.l2).sum::<f64>().sqrt(),
+                    sup: norms.iter().map(|n| n.sup).fold(0.0, f64::max),
+                    integral: norms.iter().map(|n| n.integral).sum(),
+                }
+            })
+            .collect()
+    }
+
+    /// Dense critical points of every level, the supports joined where they touch
+    #[must_use]
+    pub fn to_levels(&self) -> Vec<Vec<(f64,f64)>> {
+        self.levels
+            .iter()
+            .map(|supports| {
+                let mut level: Vec<(f64,f64)> = supports.iter().flatten().copied().collect();
+                level.dedup();
+                level
+            })
+            .collect()
+    }
+
+    fn combine(&self, other: &Self, f: impl Fn(&Landscape, &Landscape) -> Landscape) -> Self {
+        let dense = f(&Landscape::new(self.to_levels()), &Landscape::new(other.to_levels()));
+        Self::new(dense.levels())
+    }
+
+    /// Drops the supports a factor of zero flattened
+    fn pruned(mut self) -> Self {
+        for supports in &mut self.levels {
+            supports.retain(|support| support.iter().any(|p| p.1 != 0.0));
+        }
+        self
+    }
+}
+
+impl From<&Landscape> for SparseLandscape {
+    fn from(landscape: &Landscape) -> Self {
+        Self::new(landscape.levels())
+    }
+}
+
+impl From<&SparseLandscape> for Landscape {
+    fn from(sparse: &SparseLandscape) -> Self {
+        Self::new(sparse.to_levels())
+    }
+}
+
+impl Add for &SparseLandscape {
+    type Output = SparseLandscape;
+
+    fn add(self, other: Self) -> SparseLandscape {
+        self.combine(other, |a, b| a + b)
+    }
+}
+
+impl Sub for &SparseLandscape {
+    type Output = SparseLandscape;
+
+    fn sub(self, other: Self) -> SparseLandscape {
+        self.combine(other, |a, b| a - b)
+    }
+}
+
+impl Mul<f64> for &SparseLandscape {
+    type Output = SparseLandscape;
+
+    fn mul(self, factor: f64) -> SparseLandscape {
+        SparseLandscape {
+            levels: self
+                .levels
+                .iter()
+                .map(|supports| {
+                    supports
+                        .iter()
+                        .map(|support| support.iter().map(|&(x, y)| (x, y * factor)).collect())
+                        .collect()
+                })
+                .collect(),
+        }
+        .pruned()
+    }
+}
+
+impl Neg for &SparseLandscape {
+    type Output = SparseLandscape;
+
+    fn neg(self) -> SparseLandscape {
+        self * -1.0
+    }
+}
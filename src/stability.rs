@@ -0,0 +1,236 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Bounds on landscape changes implied by the stability theorem
+//!
+//! Every level of a landscape is 1-Lipschitz in the diagram: the sup-norm distance between
+//! the k-th levels of two diagrams is at most their bottleneck distance.
+
+use float_ord::FloatOrd;
+use std::collections::VecDeque;
+
+use crate::birthdeath::BirthDeath;
+
+/// Distance of two pairs in the sup norm of the plane
+fn cost(a: &BirthDeath, b: &BirthDeath) -> f64 {
+    (a.birth - b.birth).abs().max((a.death - b.death).abs())
+}
+
+/// Distance of a pair to the diagonal in the sup norm of the plane
+fn to_diagonal(bd: &BirthDeath) -> f64 {
+    (bd.death - bd.birth).abs() / 2.0
+}
+
+/// Bipartite graph of the bottleneck matching, the first nodes on either side are the pairs of
+/// that side and the rest stand for the diagonal
+struct Matching<'a> {
+    a: &'a [&'a BirthDeath],
+    b: &'a [&'a BirthDeath],
+}
+
+impl Matching<'_> {
+    const fn size(&self) -> usize {
+        self.a.len() + self.b.len()
+    }
+
+    fn weight(&self, left: usize, right: usize) -> f64 {
+        match (self.a.get(left), self.b.get(right)) {
+            (Some(a), Some(b)) => cost(a, b),
+            (Some(a), None) if right - self.b.len() == left => to_diagonal(a),
+            (None, Some(b)) if left - self.a.len() == right => to_diagonal(b),
+            (None, None) => 0.0,
+            _ => f64::INFINITY,
+        }
+    }
+
+    /// Whether every node can be matched using edges no longer than `threshold`, with
+    /// Hopcroft-Karp
+    fn perfect(&self, threshold: f64) -> bool {
+        let n = self.size();
+        let allowed = |left: usize, right: usize| self.weight(left, right) <= threshold;
+        let mut left_match: Vec<Option<usize>> = vec![None; n];
+        let mut right_match: Vec<Option<usize>> = vec![None; n];
+        let mut matched = 0;
+        loop {
+            // Layer the free left nodes and everything reachable over alternating paths
+            let mut layer = vec![usize::MAX; n];
+            let mut queue: VecDeque<usize> = (0..n).filter(|&u| left_match[u].is_none()).collect();
+            queue.iter().for_each(|&u| layer[u] = 0);
+            let mut found = false;
+            while let Some(u) = queue.pop_front() {
+                for v in (0..n).filter(|&v| allowed(u, v)) {
+                    match right_match.get(v).copied().flatten() {
+                        None => found = true,
+                        Some(w) if layer[w] == usize::MAX => {
+                            layer[w] = layer[u] + 1;
+                            queue.push_back(w);
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            if !found {
+                return matched == n;
+            }
+            let mut next = vec![0; n];
+            for u in 0..n {
+                if left_match[u].is_none()
+                    && augment(u, &allowed, &mut layer, &mut next, &mut left_match, &mut right_match)
+                {
+                    matched += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Follows the layers from the free left node `u` to a free right node and flips the path
+fn augment(
+    u: usize,
+    allowed: &impl Fn(usize, usize) -> bool,
+    layer: &mut [usize],
+    next: &mut [usize],
+    left_match: &mut [Option<usize>],
+    right_match: &mut [Option<usize>],
+) -> bool {
+    let n = layer.len();
+    while next[u] < n {
+        let v = next[u];
+        next[u] += 1;
+        if !allowed(u, v) {
+            continue;
+        }
+        let reaches_free = right_match[v].map_or(true, |w| {
+            layer[w] == layer[u] + 1 && augment(w, allowed, layer, next, left_match, right_match)
+        });
+        if reaches_free {
+            left_match[u] = Some(v);
+            right_match[v] = Some(u);
+            return true;
+        }
+    }
+    layer[u] = usize::MAX;
+    false
+}
+
+fn finite_pairs(diagram: &[BirthDeath]) -> Vec<&BirthDeath> {
+    diagram.iter().filter(|bd| bd.birth.is_finite() && bd.death.is_finite()).collect()
+}
+
+/// Bottleneck distance of two diagrams with the sup norm of the plane
+///
+/// Pairs with NaN or an infinite birth are ignored. Pairs with an infinite death are matched among themselves by
+/// birth, so the distance is infinite when both diagrams have a different number of them.
+/// The matching is exact and takes `O(n^2.5 log n)` for `n` pairs in total.
+#[must_use]
+pub fn bottleneck(a: &[BirthDeath], b: &[BirthDeath]) -> f64 {
+    let essential = |diagram: &[BirthDeath]| {
+        let mut births: Vec<f64> = diagram
+            .iter()
+            .filter(|bd| bd.birth.is_finite() && bd.death.is_infinite())
+            .map(|bd| bd.birth)
+            .collect();
+        births.sort_by(f64::total_cmp);
+        births
+    };
+    let (essential_a, essential_b) = (essential(a), essential(b));
+    if essential_a.len() != essential_b.len() {
+        return f64::INFINITY;
+    }
+    let essential = essential_a
+        .iter()
+        .zip(&essential_b)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f64::max);
+
+    let (a, b) = (finite_pairs(a), finite_pairs(b));
+    let matching = Matching { a: &a, b: &b };
+    let mut candidates: Vec<FloatOrd<f64>> = a
+        .iter()
+        .flat_map(|x| b.iter().map(|y| cost(x, y)))
+        .chain(a.iter().chain(&b).map(|bd| to_diagonal(bd)))
+        .map(FloatOrd)
+        .collect();
+    candidates.push(FloatOrd(0.0));
+    candidates.sort_unstable();
+    candidates.dedup();
+    // Matching every pair to the diagonal always works, so the last candidate is feasible
+    let first_feasible = candidates.partition_point(|t| !matching.perfect(t.0));
+    let finite = candidates.get(first_feasible).map_or(0.0, |t| t.0);
+    finite.max(essential)
+}
+
+/// Bound on the sup-norm distance between every level of the landscapes of `a` and `b`
+#[must_use]
+pub fn landscape_bound(a: &[BirthDeath], b: &[BirthDeath]) -> f64 {
+    bottleneck(a, b)
+}
+
+/// Guaranteed change of one level of a landscape, see [`perturbation_bounds`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelBound {
+    pub sup: f64,
+    pub l1: f64,
+    pub l2: f64,
+}
+
+/// Bounds on how much each of the first `k` levels of the landscape of `bd_pairs`, or every
+/// level that can be nonempty if `k` is zero, changes when pairs move by at most `epsilon`
+///
+/// Every birth and death may move by `epsilon` independently, but pairs must keep their
+/// identity: pairs appearing or vanishing near the diagonal are not covered. No level changes
+/// by more than `epsilon` anywhere, and both landscapes vanish where fewer than `k` pairs
+/// widened by `epsilon` on both sides are alive, which bounds the L1 and L2 norms of the
+/// change by the measure of the remaining support.
+///
+/// # Errors
+///
+/// Will return 'Err' if `epsilon` is negative or not finite
+pub fn perturbation_bounds(bd_pairs: &[BirthDeath], epsilon: f64, k: usize) -> Result<Vec<LevelBound>, &'static str> {
+    if !(epsilon.is_finite() && epsilon >= 0.0) {
+        return Err("Perturbation must be finite and not negative");
+    }
+    // (x, change of the number of alive widened pairs)
+    let mut events: Vec<(FloatOrd<f64>, isize)> = bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite() && bd.death > bd.birth)
+        .flat_map(|bd| [(FloatOrd(bd.birth - epsilon), 1), (FloatOrd(bd.death + epsilon), -1)])
+        .collect();
+    events.sort_unstable();
+
+    // Measure of the set where exactly `alive` widened pairs are alive, then where at least
+    // `level + 1` are
+    let mut exactly: Vec<f64> = Vec::new();
+    let mut alive = 0usize;
+    for w in events.windows(2) {
+        alive = alive.saturating_add_signed(w[0].1);
+        if alive >= exactly.len() {
+            exactly.resize(alive + 1, 0.0);
+        }
+        exactly[alive] += w[1].0 .0 - w[0].0 .0;
+    }
+    let mut support: Vec<f64> = exactly
+        .iter()
+        .skip(1)
+        .rev()
+        .scan(0.0, |total, measure| {
+            *total += measure;
+            Some(*total)
+        })
+        .collect();
+    support.reverse();
+    if k > 0 {
+        support.resize(k, 0.0);
+    }
+    Ok(support
+        .into_iter()
+        .map(|measure| {
+            let sup = if measure > 0.0 { epsilon } else { 0.0 };
+            LevelBound { sup, l1: epsilon * measure, l2: epsilon * measure.sqrt() }
+        })
+        .collect())
+}
@@ -0,0 +1,541 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::persistencelandscape::evaluate;
+
+/// Sorted, deduplicated set of every x-coordinate that is a critical point in any of `levels`
+pub(crate) fn union_grid(levels: &[&[(f64,f64)]]) -> Vec<f64> {
+    let mut grid: Vec<FloatOrd<f64>> = levels
+        .iter()
+        .flat_map(|level| level.iter().map(|p| FloatOrd(p.0)))
+        .collect();
+    grid.sort_unstable();
+    grid.dedup();
+    grid.into_iter().map(|x| x.0).collect()
+}
+
+/// [`union_grid`] of `levels` with every x where two of the levels cross added
+///
+/// Between two of these points no two levels change order, so any order statistic of the
+/// levels is linear there.
+fn crossing_grid(levels: &[&[(f64,f64)]]) -> Vec<f64> {
+    let grid = union_grid(levels);
+    let mut crossings: Vec<FloatOrd<f64>> = Vec::new();
+    for window in grid.windows(2) {
+        let (x0, x1) = (window[0], window[1]);
+        let start: Vec<f64> = levels.iter().map(|l| evaluate(l, x0)).collect();
+        let slopes: Vec<f64> = levels
+            .iter()
+            .zip(&start)
+            .map(|(l, s)| (evaluate(l, x1) - s) / (x1 - x0))
+            .collect();
+        for i in 0..levels.len() {
+            for j in i + 1..levels.len() {
+                // Parallel levels give an infinite or NaN x, which fails the check as well
+                let x = x0 + (start[j] - start[i]) / (slopes[i] - slopes[j]);
+                if x > x0 && x < x1 {
+                    crossings.push(FloatOrd(x));
+                }
+            }
+        }
+    }
+    crossings.extend(grid.into_iter().map(FloatOrd));
+    crossings.sort_unstable();
+    crossings.dedup();
+    crossings.into_iter().map(|x| x.0).collect()
+}
+
+/// The same level of every landscape, empty for landscapes with fewer levels
+fn level_slices(landscapes: &[Vec<Vec<(f64,f64)>>], level: usize) -> Vec<&[(f64,f64)]> {
+    landscapes
+        .iter()
+        .map(|l| l.get(level).map_or(&[][..], Vec::as_slice))
+        .collect()
+}
+
+/// Points of the same level of several landscapes that [`pointwise`] evaluates them at
+type Grid = fn(&[&[(f64,f64)]]) -> Vec<f64>;
+
+/// Combines the landscapes level by level. For every level the points of `grid` are built
+/// and `combine` is called with the values of all landscapes at each of them. Landscapes with
+/// fewer levels contribute zeros.
+fn pointwise<F>(
+    landscapes: &[Vec<Vec<(f64,f64)>>],
+    grid: Grid,
+    mut combine: F,
+) -> Vec<Vec<(f64,f64)>>
+where
+    F: FnMut(&mut [f64]) -> f64,
+{
+    let depth = landscapes.iter().map(Vec::len).max().unwrap_or(0);
+    (0..depth)
+        .map(|level| {
+            let levels = level_slices(landscapes, level);
+            let mut values = vec![0.0; levels.len()];
+            grid(&levels)
+                .into_iter()
+                .map(|x| {
+                    for (value, l) in values.iter_mut().zip(&levels) {
+                        *value = evaluate(l, x);
+                    }
+                    (x, combine(&mut values))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Quantile of `values` using linear interpolation between order statistics
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn quantile_of(values: &mut [f64], q: f64) -> f64 {
+    values.sort_unstable_by_key(|v| FloatOrd(*v));
+    let h = (values.len() - 1) as f64 * q;
+    let lower = h.floor() as usize;
+    let upper = h.ceil() as usize;
+    (h - lower as f64).mul_add(values[upper] - values[lower], values[lower])
+}
+
+/// Pointwise `q`-quantile of a collection of landscapes, evaluated on the union grid of
+/// breakpoints of each level and every x where two of the levels cross
+///
+/// The landscapes keep their order between these points, so the quantile is linear there and
+/// the result is exact.
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty or `q` is not within [0, 1]
+pub fn quantile(landscapes: &[Vec<Vec<(f64,f64)>>], q: f64) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if landscapes.is_empty() {
+        return Err("No landscapes to compute quantile of");
+    }
+    if !(0.0..=1.0).contains(&q) {
+        return Err("Quantile must be within [0, 1]");
+    }
+    Ok(pointwise(landscapes, crossing_grid, |values| quantile_of(values, q)))
+}
+
+/// Pointwise median of a collection of landscapes
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty
+pub fn median(landscapes: &[Vec<Vec<(f64,f64)>>]) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    quantile(landscapes, 0.5)
+}
+
+/// Exact upper envelope of `levels` after multiplying every value by `sign`. Between two grid
+/// points all levels are linear, so the envelope only changes owner where a steeper level
+/// overtakes the current one; those crossings are inserted as extra critical points.
+fn envelope_level(levels: &[&[(f64,f64)]], sign: f64) -> Vec<(f64,f64)> {
+    let grid = union_grid(levels);
+    let mut points = Vec::with_capacity(grid.len());
+    for window in grid.windows(2) {
+        let (x0, x1) = (window[0], window[1]);
+        let start: Vec<f64> = levels.iter().map(|l| sign * evaluate(l, x0)).collect();
+        let slopes: Vec<f64> = levels
+            .iter()
+            .zip(&start)
+            .map(|(l, s)| (sign * evaluate(l, x1) - s) / (x1 - x0))
+            .collect();
+        let value_at = |i: usize, x: f64| slopes[i].mul_add(x - x0, start[i]);
+
+        let mut best = (0..levels.len())
+            .max_by_key(|&i| (FloatOrd(start[i]), FloatOrd(slopes[i])))
+            .expect("Envelope of no levels");
+        let mut x = x0;
+        points.push((x, sign * start[best]));
+        loop {
+            let y = value_at(best, x);
+            let next = (0..levels.len())
+                .filter(|&i| slopes[i] > slopes[best])
+                .map(|i| (x + (y - value_at(i, x)) / (slopes[i] - slopes[best]), i))
+                .filter(|(t, _)| *t > x && *t < x1)
+                .min_by_key(|(t, i)| (FloatOrd(*t), FloatOrd(-slopes[*i])));
+            let Some((t, i)) = next else {
+                break;
+            };
+            x = t;
+            best = i;
+            points.push((x, sign * value_at(best, x)));
+        }
+    }
+    if let Some(&x) = grid.last() {
+        let value = levels
+            .iter()
+            .map(|l| FloatOrd(sign * evaluate(l, x)))
+            .max()
+            .expect("Envelope of no levels");
+        points.push((x, sign * value.0));
+    }
+    points
+}
+
+fn envelope(landscapes: &[Vec<Vec<(f64,f64)>>], sign: f64) -> Vec<Vec<(f64,f64)>> {
+    let depth = landscapes.iter().map(Vec::len).max().unwrap_or(0);
+    (0..depth)
+        .map(|level| {
+            let levels = level_slices(landscapes, level);
+            envelope_level(&levels, sign)
+        })
+        .collect()
+}
+
+/// Pointwise maximum of a collection of landscapes. Crossings between the landscapes are
+/// included as critical points so the result is exact.
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty
+pub fn envelope_max(landscapes: &[Vec<Vec<(f64,f64)>>]) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if landscapes.is_empty() {
+        return Err("No landscapes to compute envelope of");
+    }
+    Ok(envelope(landscapes, 1.0))
+}
+
+/// Pointwise minimum of a collection of landscapes. Crossings between the landscapes are
+/// included as critical points so the result is exact.
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty
+pub fn envelope_min(landscapes: &[Vec<Vec<(f64,f64)>>]) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if landscapes.is_empty() {
+        return Err("No landscapes to compute envelope of");
+    }
+    Ok(envelope(landscapes, -1.0))
+}
+
+/// Weighted pointwise average of a collection of landscapes. Landscapes are linear between the
+/// union grid of breakpoints so the result is exact.
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty, the number of weights does not match the number
+/// of landscapes, any weight is negative or the weights sum to zero
+pub fn weighted_average(
+    landscapes: &[Vec<Vec<(f64,f64)>>],
+    weights: &[f64],
+) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if landscapes.is_empty() {
+        return Err("No landscapes to average");
+    }
+    if landscapes.len() != weights.len() {
+        return Err("Number of weights does not match number of landscapes");
+    }
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err("Weights must be finite and non-negative");
+    }
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return Err("Weights must not sum to zero");
+    }
+    Ok(pointwise(landscapes, union_grid, |values| {
+        values.iter().zip(weights).map(|(v, w)| v * w).sum::<f64>() / total
+    }))
+}
+
+/// Pointwise mean of a collection of landscapes
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty
+pub fn average(landscapes: &[Vec<Vec<(f64,f64)>>]) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    weighted_average(landscapes, &vec![1.0; landscapes.len()])
+}
+
+/// Pointwise population standard deviation of a collection of landscapes, evaluated on the
+/// union grid of breakpoints of each level
+///
+/// The deviation is not linear between breakpoints, so in between the result is only an
+/// interpolation.
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty
+#[allow(clippy::cast_precision_loss)]
+pub fn standard_deviation(landscapes: &[Vec<Vec<(f64,f64)>>]) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if landscapes.is_empty() {
+        return Err("No landscapes to compute deviation of");
+    }
+    Ok(pointwise(landscapes, union_grid, |values| {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt()
+    }))
+}
+
+/// Label of a group and its mean landscape, see [`group_averages`]
+pub type GroupMean = (String, Vec<Vec<(f64,f64)>>);
+
+/// [`weighted_average`] of the landscapes sharing a label in `groups`, one per label in the
+/// order each label first appears
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes`, `weights` and `groups` differ in length or the weights
+/// of a group are invalid for [`weighted_average`]
+pub fn group_averages(
+    landscapes: Vec<Vec<Vec<(f64,f64)>>>,
+    weights: &[f64],
+    groups: &[String],
+) -> Result<Vec<GroupMean>, &'static str> {
+    if landscapes.len() != weights.len() || landscapes.len() != groups.len() {
+        return Err("Every landscape needs exactly one weight and group");
+    }
+    // Label, landscapes and weights of every group
+    let mut members: Vec<(&String, Vec<_>, Vec<f64>)> = Vec::new();
+    for ((landscape, &weight), group) in landscapes.into_iter().zip(weights).zip(groups) {
+        let at = members.iter().position(|(label, _, _)| *label == group).unwrap_or_else(|| {
+            members.push((group, Vec::new(), Vec::new()));
+            members.len() - 1
+        });
+        members[at].1.push(landscape);
+        members[at].2.push(weight);
+    }
+    members
+        .into_iter()
+        .map(|(label, landscapes, weights)| Ok((label.clone(), weighted_average(&landscapes, &weights)?)))
+        .collect()
+}
+
+/// How the width of a bootstrap confidence band is determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandType {
+    /// Percentile interval of the bootstrapped means at every grid point separately
+    Pointwise,
+    /// Band of constant width around the mean controlling the sup-norm deviation of the whole
+    /// level (Chazal et al.)
+    SupNorm,
+}
+
+/// Confidence band for the mean landscape, all levels sampled on the same grid points
+#[derive(Debug, Clone)]
+pub struct ConfidenceBand {
+    pub mean: Vec<Vec<(f64,f64)>>,
+    pub lower: Vec<Vec<(f64,f64)>>,
+    pub upper: Vec<Vec<(f64,f64)>>,
+}
+
+/// Union grid of one level and the values of every landscape on it, indexed [landscape][x]
+fn sample_level(landscapes: &[Vec<Vec<(f64,f64)>>], level: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let levels = level_slices(landscapes, level);
+    let grid = union_grid(&levels);
+    let values = levels
+        .iter()
+        .map(|l| crate::vectorize::sample_level(l, &grid))
+        .collect();
+    (grid, values)
+}
+
+fn mean_of(samples: &[Vec<f64>], indices: impl Iterator<Item = usize>, len: usize) -> Vec<f64> {
+    let mut mean = vec![0.0; len];
+    let mut count = 0_u32;
+    for i in indices {
+        count += 1;
+        for (m, v) in mean.iter_mut().zip(&samples[i]) {
+            *m += v;
+        }
+    }
+    mean.iter_mut().for_each(|m| *m /= f64::from(count).max(1.0));
+    mean
+}
+
+/// Bootstrap confidence band for the mean landscape.
+///
+/// The landscapes are resampled with replacement `n_bootstrap` times and a `1 - alpha` band is computed from the distribution of
+/// the resampled means. Resampling is driven by an RNG seeded with `seed` so results are
+/// reproducible.
+///
+/// # Errors
+///
+/// Will return 'Err' if `landscapes` is empty, `n_bootstrap` is zero or `alpha` is not within
+/// (0, 1)
+pub fn bootstrap_band(
+    landscapes: &[Vec<Vec<(f64,f64)>>],
+    n_bootstrap: usize,
+    alpha: f64,
+    band_type: BandType,
+    seed: u64,
+) -> Result<ConfidenceBand, &'static str> {
+    if landscapes.is_empty() {
+        return Err("No landscapes to bootstrap");
+    }
+    if n_bootstrap == 0 {
+        return Err("Number of bootstrap samples must be positive");
+    }
+    if !(alpha > 0.0 && alpha < 1.0) {
+        return Err("Alpha must be within (0, 1)");
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let n = landscapes.len();
+    let depth = landscapes.iter().map(Vec::len).max().unwrap_or(0);
+    let mut band = ConfidenceBand {
+        mean: Vec::with_capacity(depth),
+        lower: Vec::with_capacity(depth),
+        upper: Vec::with_capacity(depth),
+    };
+    for level in 0..depth {
+        let (grid, samples) = sample_level(landscapes, level);
+        let mean = mean_of(&samples, 0..n, grid.len());
+        let resampled: Vec<Vec<f64>> = (0..n_bootstrap)
+            .map(|_| mean_of(&samples, (0..n).map(|_| rng.gen_range(0..n)), grid.len()))
+            .collect();
+        let (lower, upper): (Vec<f64>, Vec<f64>) = match band_type {
+            BandType::Pointwise => (0..grid.len())
+                .map(|j| {
+                    let mut column: Vec<f64> = resampled.iter().map(|r| r[j]).collect();
+                    (
+                        quantile_of(&mut column, alpha / 2.0),
+                        quantile_of(&mut column, 1.0 - alpha / 2.0),
+                    )
+                })
+                .unzip(),
+            BandType::SupNorm => {
+                let mut deviations: Vec<f64> = resampled
+                    .iter()
+                    .map(|r| {
+                        r.iter()
+                            .zip(&mean)
+                            .map(|(a, b)| FloatOrd((a - b).abs()))
+                            .max()
+                            .map_or(0.0, |d| d.0)
+                    })
+                    .collect();
+                let width = quantile_of(&mut deviations, 1.0 - alpha);
+                mean.iter().map(|m| ((m - width).max(0.0), m + width)).unzip()
+            }
+        };
+        let zip_grid = |values: &[f64]| grid.iter().copied().zip(values.iter().copied()).collect();
+        band.mean.push(zip_grid(&mean));
+        band.lower.push(zip_grid(&lower));
+        band.upper.push(zip_grid(&upper));
+    }
+    Ok(band)
+}
+
+/// Permutation two-sample test.
+///
+/// `statistic` is evaluated on the mean landscapes of both groups, e.g.
+/// [`crate::distance::l2_distance`]. The group labels are then shuffled `n_permutations` times
+/// and the returned p-value is the fraction of permutations with a statistic at least as large
+/// as the observed one (counting the observed labelling itself).
+///
+/// # Errors
+///
+/// Will return 'Err' if either group is empty or `n_permutations` is zero
+pub fn two_sample_test<F>(
+    group_a: &[Vec<Vec<(f64,f64)>>],
+    group_b: &[Vec<Vec<(f64,f64)>>],
+    statistic: F,
+    n_permutations: usize,
+    seed: u64,
+) -> Result<f64, &'static str>
+where
+    F: Fn(&[Vec<(f64,f64)>], &[Vec<(f64,f64)>]) -> f64,
+{
+    if group_a.is_empty() || group_b.is_empty() {
+        return Err("Both groups must contain landscapes");
+    }
+    if n_permutations == 0 {
+        return Err("Number of permutations must be positive");
+    }
+    let observed = statistic(&average(group_a)?, &average(group_b)?);
+
+    let mut pooled: Vec<Vec<Vec<(f64,f64)>>> = group_a.iter().chain(group_b).cloned().collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut at_least_as_extreme = 1_u32;
+    for _ in 0..n_permutations {
+        pooled.shuffle(&mut rng);
+        let (a, b) = pooled.split_at(group_a.len());
+        if statistic(&average(a)?, &average(b)?) >= observed {
+            at_least_as_extreme += 1;
+        }
+    }
+    let total = u32::try_from(n_permutations).map_err(|_| "Too many permutations")? + 1;
+    Ok(f64::from(at_least_as_extreme) / f64::from(total))
+}
+
+/// Settings of [`compare_groups`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComparisonSettings {
+    /// Confidence bands cover `1 - alpha`
+    pub alpha: f64,
+    pub n_bootstrap: usize,
+    pub n_permutations: usize,
+    pub seed: u64,
+}
+
+impl Default for ComparisonSettings {
+    fn default() -> Self {
+        Self { alpha: 0.05, n_bootstrap: 1000, n_permutations: 1000, seed: 0 }
+    }
+}
+
+/// Summary of how two groups of landscapes differ
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    /// Mean and pointwise confidence band of the first group
+    pub band_a: ConfidenceBand,
+    /// Mean and pointwise confidence band of the second group
+    pub band_b: ConfidenceBand,
+    /// L2 distance between the group means
+    pub distance: f64,
+    /// Permutation test p-value of the L2 distance between the group means
+    pub p_value: f64,
+    pub settings: ComparisonSettings,
+}
+
+/// Group means with [`bootstrap_band`], their L2 distance and its [`two_sample_test`]
+///
+/// # Errors
+///
+/// Will return 'Err' if either group is empty or a setting is invalid
+pub fn compare_groups(
+    group_a: &[Vec<Vec<(f64,f64)>>],
+    group_b: &[Vec<Vec<(f64,f64)>>],
+    settings: ComparisonSettings,
+) -> Result<Comparison, &'static str> {
+    let band = |group| bootstrap_band(group, settings.n_bootstrap, settings.alpha, BandType::Pointwise, settings.seed);
+    let (band_a, band_b) = (band(group_a)?, band(group_b)?);
+    let distance = crate::distance::l2_distance(&band_a.mean, &band_b.mean);
+    let p_value = two_sample_test(
+        group_a,
+        group_b,
+        crate::distance::l2_distance,
+        settings.n_permutations,
+        settings.seed,
+    )?;
+    Ok(Comparison { band_a, band_b, distance, p_value, settings })
+}
+
+/// Resamples every landscape onto the union of all their breakpoints, level by level.
+///
+/// Since the levels are linear between these points the result is exact, and afterwards every
+/// landscape has the same x-coordinates in every level so pointwise operations can simply zip
+/// the points. Landscapes with fewer levels are padded with zero levels.
+#[must_use]
+pub fn resample_common(landscapes: &[Vec<Vec<(f64,f64)>>]) -> Vec<Vec<Vec<(f64,f64)>>> {
+    let depth = landscapes.iter().map(Vec::len).max().unwrap_or(0);
+    let mut resampled = vec![Vec::with_capacity(depth); landscapes.len()];
+    for level in 0..depth {
+        let (grid, values) = sample_level(landscapes, level);
+        for (landscape, values) in resampled.iter_mut().zip(values) {
+            landscape.push(grid.iter().copied().zip(values).collect());
+        }
+    }
+    resampled
+}
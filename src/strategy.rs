@@ -0,0 +1,42 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::birthdeath::BirthDeath;
+
+/// Finite pairs with births in `[birth_min, birth_max]` and persistence in
+/// `[0, max_persistence]`
+///
+/// # Panics
+///
+/// Will panic when sampled if a range is empty
+pub fn birth_death(birth_min: f64, birth_max: f64, max_persistence: f64) -> impl Strategy<Value = BirthDeath> {
+    (birth_min..=birth_max, 0.0..=max_persistence)
+        .prop_map(|(birth, persistence)| BirthDeath { birth, death: birth + persistence })
+}
+
+/// Diagrams of up to `max_len` pairs drawn from [`birth_death`]
+pub fn diagram(
+    birth_min: f64,
+    birth_max: f64,
+    max_persistence: f64,
+    max_len: usize,
+) -> impl Strategy<Value = Vec<BirthDeath>> {
+    vec(birth_death(birth_min, birth_max, max_persistence), 0..=max_len)
+}
+
+impl Arbitrary for BirthDeath {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        birth_death(-1000.0, 1000.0, 1000.0).boxed()
+    }
+}
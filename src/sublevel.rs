@@ -0,0 +1,92 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Sublevel set persistence of functions sampled on a grid, such as time series
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::generate;
+
+/// Landscape levels of one dimension
+pub type Levels = Vec<Vec<(f64,f64)>>;
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Dimension 0 persistence of the sublevel sets of `values` on the graph given by `neighbors`
+///
+/// Vertices enter in order of their value. When a vertex joins components, all but the one
+/// born first die at its value (the elder rule) and the survivors of the graph die at
+/// infinity.
+pub(crate) fn components(values: &[f64], neighbors: impl Fn(usize) -> Vec<usize>) -> Vec<BirthDeath> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    // The root of a component is its oldest vertex, so its value is the birth of the component
+    let mut parent: Vec<usize> = (0..values.len()).collect();
+    let mut added = vec![false; values.len()];
+    let mut diagram = Vec::new();
+    for vertex in order {
+        added[vertex] = true;
+        for neighbor in neighbors(vertex).into_iter().filter(|&n| added[n]) {
+            let (root, other) = (find(&mut parent, vertex), find(&mut parent, neighbor));
+            if root == other {
+                continue;
+            }
+            let (elder, younger) = if values[root] <= values[other] { (root, other) } else { (other, root) };
+            if values[vertex] > values[younger] {
+                diagram.push(BirthDeath { birth: values[younger], death: values[vertex] });
+            }
+            parent[younger] = elder;
+        }
+    }
+    diagram.extend(
+        (0..values.len())
+            .filter(|&i| find(&mut parent, i) == i)
+            .map(|root| BirthDeath { birth: values[root], death: f64::INFINITY }),
+    );
+    diagram
+}
+
+/// Lower-star persistence diagram of a time series, consecutive values are adjacent
+///
+/// Every local minimum is born at its value and dies when it merges with an older minimum at
+/// the maximum in between. The global minimum never dies. Pairs of zero persistence are left
+/// out.
+///
+/// # Errors
+///
+/// Will return 'Err' if a value is NaN
+pub fn time_series(values: &[f64]) -> Result<Vec<BirthDeath>, &'static str> {
+    if values.iter().any(|v| v.is_nan()) {
+        return Err("Time series must not contain NaN");
+    }
+    Ok(components(values, |i| {
+        let mut adjacent = Vec::with_capacity(2);
+        if i > 0 {
+            adjacent.push(i - 1);
+        }
+        if i + 1 < values.len() {
+            adjacent.push(i + 1);
+        }
+        adjacent
+    }))
+}
+
+/// First `k` levels of the landscape of [`time_series`], leaving out the pair of the global
+/// minimum. `k` of zero keeps every nonempty level.
+///
+/// # Errors
+///
+/// Will return 'Err' if a value is NaN
+pub fn time_series_landscape(values: &[f64], k: usize) -> Result<Levels, &'static str> {
+    let diagram = time_series(values)?;
+    Ok(generate(diagram.into_iter().filter(|bd| bd.death.is_finite()).collect(), k, false))
+}
@@ -0,0 +1,90 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::birthdeath::BirthDeath;
+
+/// Distribution of a group of random pairs, births are always uniform in
+/// `[birth_min, birth_max)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Persistence uniform in `[0, max_persistence)`
+    Uniform { birth_min: f64, birth_max: f64, max_persistence: f64 },
+    /// Exponentially distributed persistence, pairs cluster near the diagonal like
+    /// topological noise
+    NearDiagonal { birth_min: f64, birth_max: f64, mean_persistence: f64 },
+    /// Persistence uniform in `[min_persistence, max_persistence)`, a few of these model the
+    /// significant features of a diagram
+    LongBars { birth_min: f64, birth_max: f64, min_persistence: f64, max_persistence: f64 },
+}
+
+fn uniform(rng: &mut StdRng, min: f64, max: f64) -> f64 {
+    (max - min).mul_add(rng.gen::<f64>(), min)
+}
+
+impl Shape {
+    fn sample(&self, rng: &mut StdRng) -> BirthDeath {
+        let (birth, persistence) = match *self {
+            Self::Uniform { birth_min, birth_max, max_persistence } => {
+                (uniform(rng, birth_min, birth_max), uniform(rng, 0.0, max_persistence))
+            }
+            Self::NearDiagonal { birth_min, birth_max, mean_persistence } => {
+                let birth = uniform(rng, birth_min, birth_max);
+                (birth, -mean_persistence * (1.0 - rng.gen::<f64>()).ln())
+            }
+            Self::LongBars { birth_min, birth_max, min_persistence, max_persistence } => {
+                (uniform(rng, birth_min, birth_max), uniform(rng, min_persistence, max_persistence))
+            }
+        };
+        BirthDeath { birth, death: birth + persistence }
+    }
+}
+
+/// Draws `count` pairs from every shape, in order, the same seed gives the same diagram
+#[must_use]
+pub fn diagram(groups: &[(Shape, usize)], seed: u64) -> Vec<BirthDeath> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    groups
+        .iter()
+        .flat_map(|&(shape, count)| std::iter::repeat(shape).take(count))
+        .map(|shape| shape.sample(&mut rng))
+        .collect()
+}
+
+/// `n_noise` pairs near the diagonal and `n_long` long bars with births in `[0, 1)`
+#[must_use]
+pub fn noisy_diagram(n_noise: usize, n_long: usize, seed: u64) -> Vec<BirthDeath> {
+    diagram(
+        &[
+            (Shape::NearDiagonal { birth_min: 0.0, birth_max: 1.0, mean_persistence: 0.02 }, n_noise),
+            (Shape::LongBars { birth_min: 0.0, birth_max: 1.0, min_persistence: 0.5, max_persistence: 1.0 }, n_long),
+        ],
+        seed,
+    )
+}
+
+/// `n_pairs` pairs around `n_clusters` centers, with births and persistences up to `spread`
+/// away from their center, like the features of a diagram of several similar objects
+///
+/// Centers have births in `[0, 1)` and persistences in `[0.1, 0.5)`, the same seed gives the
+/// same diagram.
+#[must_use]
+pub fn clustered_diagram(n_pairs: usize, n_clusters: usize, spread: f64, seed: u64) -> Vec<BirthDeath> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let centers: Vec<(f64, f64)> = (0..n_clusters.max(1))
+        .map(|_| (uniform(&mut rng, 0.0, 1.0), uniform(&mut rng, 0.1, 0.5)))
+        .collect();
+    (0..n_pairs)
+        .map(|_| {
+            let (birth, persistence) = centers[rng.gen_range(0..centers.len())];
+            let birth = birth + uniform(&mut rng, -spread, spread);
+            BirthDeath { birth, death: birth + (persistence + uniform(&mut rng, -spread, spread)).abs() }
+        })
+        .collect()
+}
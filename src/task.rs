@@ -0,0 +1,228 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Running the sweep off the calling thread, for embedding in async services
+//!
+//! A [`Task`] is a future that any runtime can await, the work itself runs on a thread of its
+//! own so it never blocks the executor. Inputs are moved into the task and results moved out,
+//! every type involved is `Send + 'static`.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::{generate, generate_with_progress, Progress};
+
+/// The work of a [`Task`] panicked, with the panic message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panicked(pub String);
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Task panicked: {}", self.0)
+    }
+}
+
+impl Error for Panicked {}
+
+struct Slot<T> {
+    result: Option<Result<T, Panicked>>,
+    waker: Option<Waker>,
+}
+
+/// Result of work running on its own thread, see [`spawn_blocking`]
+///
+/// Await it from any runtime or block on it with [`Task::wait`]. Dropping the task detaches
+/// the work, which still runs to completion.
+pub struct Task<T> {
+    shared: Arc<(Mutex<Slot<T>>, Condvar)>,
+}
+
+impl<T> fmt::Debug for Task<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task").field("finished", &self.is_finished()).finish()
+    }
+}
+
+impl<T> Task<T> {
+    /// Whether the result is ready, so awaiting the task completes at once
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.shared.0.lock().unwrap_or_else(PoisonError::into_inner).result.is_some()
+    }
+
+    /// Blocks the calling thread until the result is ready
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the work panicked
+    pub fn wait(self) -> Result<T, Panicked> {
+        let (lock, ready) = &*self.shared;
+        let mut slot = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if let Some(result) = slot.result.take() {
+                return result;
+            }
+            slot = ready.wait(slot).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = Result<T, Panicked>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.shared.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(result) = slot.result.take() {
+            return Poll::Ready(result);
+        }
+        slot.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Runs `work` on a new thread and returns a [`Task`] resolving to its result
+#[must_use]
+pub fn spawn_blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Task<T> {
+    let shared = Arc::new((Mutex::new(Slot { result: None, waker: None }), Condvar::new()));
+    let worker = Arc::clone(&shared);
+    std::thread::spawn(move || {
+        let result = catch_unwind(AssertUnwindSafe(work)).map_err(|payload| {
+            Panicked(
+                payload
+                    .downcast_ref::<&str>()
+                    .map(ToString::to_string)
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "Panic without a message".to_string()),
+            )
+        });
+        let (lock, ready) = &*worker;
+        let waker = {
+            let mut slot = lock.lock().unwrap_or_else(PoisonError::into_inner);
+            slot.result = Some(result);
+            slot.waker.take()
+        };
+        ready.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    });
+    Task { shared }
+}
+
+/// Landscape levels computed by a [`Task`]
+pub type LandscapeTask = Task<Vec<Vec<(f64,f64)>>>;
+
+/// [`generate`] on its own thread
+#[must_use]
+pub fn generate_async(bd_pairs: Vec<BirthDeath>, k: usize) -> LandscapeTask {
+    spawn_blocking(move || generate(bd_pairs, k, false))
+}
+
+struct Updates {
+    queue: VecDeque<Progress>,
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// Progress reports of a running sweep, see [`generate_async_with_progress`]
+///
+/// Reports are queued until they are taken, none are dropped.
+pub struct ProgressStream {
+    shared: Arc<Mutex<Updates>>,
+}
+
+impl fmt::Debug for ProgressStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressStream").finish_non_exhaustive()
+    }
+}
+
+impl ProgressStream {
+    /// The oldest report not taken yet, without waiting
+    #[must_use]
+    pub fn try_next(&self) -> Option<Progress> {
+        self.shared.lock().unwrap_or_else(PoisonError::into_inner).queue.pop_front()
+    }
+
+    /// Waits for the next report, `None` once the sweep is done and every report was taken
+    pub const fn next(&mut self) -> Next<'_> {
+        Next { stream: self }
+    }
+}
+
+/// Future of [`ProgressStream::next`]
+#[derive(Debug)]
+pub struct Next<'a> {
+    stream: &'a mut ProgressStream,
+}
+
+impl Future for Next<'_> {
+    type Output = Option<Progress>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut updates = self.stream.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        match updates.queue.pop_front() {
+            Some(progress) => Poll::Ready(Some(progress)),
+            None if updates.done => Poll::Ready(None),
+            None => {
+                updates.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Closes the stream when the sweep ends, even if it panics
+struct Done(Arc<Mutex<Updates>>);
+
+impl Drop for Done {
+    fn drop(&mut self) {
+        let mut updates = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        updates.done = true;
+        if let Some(waker) = updates.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// [`generate_with_progress`] on its own thread, reporting every `every` events to the
+/// returned stream
+#[must_use]
+pub fn generate_async_with_progress(
+    bd_pairs: Vec<BirthDeath>,
+    k: usize,
+    every: usize,
+) -> (LandscapeTask, ProgressStream) {
+    let shared = Arc::new(Mutex::new(Updates { queue: VecDeque::new(), done: false, waker: None }));
+    let done = Done(Arc::clone(&shared));
+    let task = spawn_blocking(move || {
+        generate_with_progress(bd_pairs, k, false, every, |progress| {
+            let mut updates = done.0.lock().unwrap_or_else(PoisonError::into_inner);
+            updates.queue.push_back(progress);
+            if let Some(waker) = updates.waker.take() {
+                waker.wake();
+            }
+        })
+    });
+    (task, ProgressStream { shared })
+}
+
+// Everything handed to or returned from a task may cross threads and outlive the caller
+const _: () = {
+    const fn assert_send<T: Send + 'static>() {}
+    assert_send::<Vec<BirthDeath>>();
+    assert_send::<LandscapeTask>();
+    assert_send::<ProgressStream>();
+    assert_send::<Progress>();
+};
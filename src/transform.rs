@@ -0,0 +1,279 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Composable operations on landscapes, the counterpart of [`crate::pipeline`] after the sweep
+//!
+//! Every [`Transform`] implements [`LandscapeTransform`] and a [`Pipeline`] of them is parsed
+//! from a comma separated spec such as `crop=0:10,gaussian=0.5@200,normalize=l2`. Transforms
+//! are chained with [`LandscapeTransform::then`], which also ends a chain in [`Vectorize`].
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::landscape::{Landscape, Normalization, Simplification, Smoothing};
+use crate::vectorize::{FeatureDescriptor, Grid};
+
+/// An operation on a landscape
+pub trait LandscapeTransform {
+    type Output;
+
+    /// # Errors
+    ///
+    /// Will return 'Err' if the transform has an invalid setting or does not apply to
+    /// `landscape`
+    fn apply(&self, landscape: Landscape) -> Result<Self::Output, &'static str>;
+
+    /// Applies `next` to the result of this transform
+    #[must_use]
+    fn then<T: LandscapeTransform>(self, next: T) -> Then<Self, T>
+    where
+        Self: Sized + LandscapeTransform<Output = Landscape>,
+    {
+        Then { first: self, second: next }
+    }
+}
+
+/// Two transforms applied one after the other, see [`LandscapeTransform::then`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: LandscapeTransform<Output = Landscape>, B: LandscapeTransform> LandscapeTransform for Then<A, B> {
+    type Output = B::Output;
+
+    fn apply(&self, landscape: Landscape) -> Result<Self::Output, &'static str> {
+        self.second.apply(self.first.apply(landscape)?)
+    }
+}
+
+/// Sample points of a transform, spelled `<resolution>` or `<x_min>:<x_max>:<resolution>`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sampling {
+    /// This many points over the support of the landscape being transformed
+    Covering(usize),
+    /// The same points for every landscape, which keeps results comparable
+    On(Grid),
+}
+
+impl Sampling {
+    /// # Errors
+    ///
+    /// Will return 'Err' if the grid can not be built, see [`Grid::covering`]
+    pub fn grid(&self, landscape: &Landscape) -> Result<Grid, &'static str> {
+        match self {
+            Self::Covering(resolution) => Grid::covering(&[landscape.levels().to_vec()], *resolution),
+            Self::On(grid) => Ok(grid.clone()),
+        }
+    }
+}
+
+/// One operation of a [`Pipeline`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    /// Clip to [`x_min`, `x_max`] with [`Landscape::restrict`], spelled `crop=<x_min>:<x_max>`
+    Crop { x_min: f64, x_max: f64 },
+    /// Smooth with [`Landscape::smooth`], spelled `moving-average=<window>[@<sampling>]` or
+    /// `gaussian=<bandwidth>[@<sampling>]`
+    Smooth { smoothing: Smoothing, sampling: Sampling },
+    /// Divide with [`Landscape::normalize`], spelled `normalize=l1`, `normalize=l2` or
+    /// `normalize-by=<divisor>` for the diagram based normalizations
+    Normalize(Normalization),
+    /// Replace the critical points by samples, spelled `resample=<sampling>`
+    Resample(Sampling),
+    /// Drop critical points with [`Landscape::simplify`], spelled `simplify=<tolerance>` or
+    /// `simplify-lossy=<epsilon>`
+    Simplify(Simplification),
+}
+
+/// Grid of [`Transform::Smooth`] when the spec leaves it out
+pub const DEFAULT_SAMPLING: Sampling = Sampling::Covering(1000);
+
+impl LandscapeTransform for Transform {
+    type Output = Landscape;
+
+    fn apply(&self, landscape: Landscape) -> Result<Landscape, &'static str> {
+        match self {
+            Self::Crop { x_min, x_max } => landscape.restrict(*x_min, *x_max),
+            Self::Smooth { smoothing, sampling } => landscape.smooth(&sampling.grid(&landscape)?, *smoothing),
+            Self::Normalize(normalization) => landscape.normalize(*normalization),
+            Self::Resample(sampling) => {
+                let grid = sampling.grid(&landscape)?;
+                let samples = crate::vectorize::sample(landscape.levels(), landscape.k(), &grid);
+                Ok(Landscape::new(crate::vectorize::unsample(&samples, &grid)))
+            }
+            Self::Simplify(simplification) => Ok(landscape.simplify(*simplification)),
+        }
+    }
+}
+
+/// Samples the landscape into a feature vector with [`Landscape::to_feature_vector`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vectorize(pub Sampling);
+
+impl LandscapeTransform for Vectorize {
+    type Output = (Vec<f32>, FeatureDescriptor);
+
+    fn apply(&self, landscape: Landscape) -> Result<Self::Output, &'static str> {
+        Ok(landscape.to_feature_vector(&self.0.grid(&landscape)?))
+    }
+}
+
+/// Ordered [`Transform`]s applied to a landscape
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pipeline {
+    transforms: Vec<Transform>,
+}
+
+impl Pipeline {
+    /// A pipeline that leaves every landscape as it is
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { transforms: Vec::new() }
+    }
+
+    /// Appends `transform`
+    #[must_use]
+    pub fn push(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    #[must_use]
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+}
+
+impl LandscapeTransform for Pipeline {
+    type Output = Landscape;
+
+    fn apply(&self, landscape: Landscape) -> Result<Landscape, &'static str> {
+        self.transforms.iter().try_fold(landscape, |landscape, transform| transform.apply(landscape))
+    }
+}
+
+impl fmt::Display for Sampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Covering(resolution) => write!(f, "{resolution}"),
+            Self::On(grid) => write!(f, "{}:{}:{}", grid.x_min, grid.x_max, grid.resolution),
+        }
+    }
+}
+
+impl FromStr for Sampling {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let resolution = |r: &str| r.parse::<usize>().map_err(|_| "Expected a whole number of sample points");
+        match parts[..] {
+            [r] => match resolution(r)? {
+                0 => Err("Grid resolution must be positive"),
+                r => Ok(Self::Covering(r)),
+            },
+            [x_min, x_max, r] => {
+                let (Ok(x_min), Ok(x_max)) = (x_min.parse(), x_max.parse()) else {
+                    return Err("Expected the range of the grid as <x_min>:<x_max>");
+                };
+                Ok(Self::On(Grid::new(x_min, x_max, resolution(r)?)?))
+            }
+            _ => Err("Expected <resolution> or <x_min>:<x_max>:<resolution>"),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Crop { x_min, x_max } => write!(f, "crop={x_min}:{x_max}"),
+            Self::Smooth { smoothing: Smoothing::MovingAverage { window }, sampling } => {
+                write!(f, "moving-average={window}@{sampling}")
+            }
+            Self::Smooth { smoothing: Smoothing::Gaussian { bandwidth }, sampling } => {
+                write!(f, "gaussian={bandwidth}@{sampling}")
+            }
+            Self::Normalize(Normalization::L1) => write!(f, "normalize=l1"),
+            Self::Normalize(Normalization::L2) => write!(f, "normalize=l2"),
+            Self::Normalize(Normalization::TotalPersistence(persistence)) => {
+                write!(f, "normalize-by={persistence}")
+            }
+            #[allow(clippy::cast_precision_loss)]
+            Self::Normalize(Normalization::PairCount(count)) => write!(f, "normalize-by={}", *count as f64),
+            Self::Resample(sampling) => write!(f, "resample={sampling}"),
+            Self::Simplify(Simplification::Redundant { tolerance }) => write!(f, "simplify={tolerance}"),
+            Self::Simplify(Simplification::DouglasPeucker { epsilon }) => write!(f, "simplify-lossy={epsilon}"),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s.trim().split_once('=').ok_or("Expected <transform>=<value>")?;
+        let number = |value: &str| -> Result<f64, &'static str> {
+            value.parse().map_err(|_| "Expected a number after the name of the transform")
+        };
+        let (value, sampling) = match value.split_once('@') {
+            Some((value, sampling)) => (value, Some(sampling.parse()?)),
+            None => (value, None),
+        };
+        if sampling.is_some() && !matches!(name, "moving-average" | "gaussian") {
+            return Err("Only smoothing transforms take @<sampling>");
+        }
+        let sampling = sampling.unwrap_or(DEFAULT_SAMPLING);
+        Ok(match name {
+            "crop" => {
+                let (x_min, x_max) = value.split_once(':').ok_or("Expected crop=<x_min>:<x_max>")?;
+                Self::Crop { x_min: number(x_min)?, x_max: number(x_max)? }
+            }
+            "moving-average" => {
+                let window = value.parse().map_err(|_| "Expected a whole number as the window")?;
+                Self::Smooth { smoothing: Smoothing::MovingAverage { window }, sampling }
+            }
+            "gaussian" => Self::Smooth { smoothing: Smoothing::Gaussian { bandwidth: number(value)? }, sampling },
+            "normalize" => match value.to_ascii_lowercase().as_str() {
+                "l1" => Self::Normalize(Normalization::L1),
+                "l2" => Self::Normalize(Normalization::L2),
+                _ => return Err("Expected normalize=l1 or normalize=l2"),
+            },
+            "normalize-by" => Self::Normalize(Normalization::TotalPersistence(number(value)?)),
+            "resample" => Self::Resample(value.parse()?),
+            "simplify" => Self::Simplify(Simplification::Redundant { tolerance: number(value)? }),
+            "simplify-lossy" => Self::Simplify(Simplification::DouglasPeucker { epsilon: number(value)? }),
+            _ => return Err("Unknown transform, expected crop, moving-average, gaussian, normalize, normalize-by, resample, simplify or simplify-lossy"),
+        })
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, transform) in self.transforms.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{transform}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = &'static str;
+
+    /// Parses comma separated [`Transform`]s, an empty spec is the empty pipeline
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|transform| !transform.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(|transforms| Self { transforms })
+    }
+}
@@ -0,0 +1,304 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use float_ord::FloatOrd;
+use half::{bf16, f16};
+use rayon::prelude::*;
+use wide::f64x4;
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::generate;
+
+
+/// Evenly spaced sample points over [`x_min`, `x_max`], both ends included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    pub x_min: f64,
+    pub x_max: f64,
+    pub resolution: usize,
+}
+
+impl Grid {
+    /// # Errors
+    ///
+    /// Will return 'Err' if the range is not finite, `x_min` > `x_max` or `resolution` is zero
+    pub fn new(x_min: f64, x_max: f64, resolution: usize) -> Result<Self, &'static str> {
+        if !x_min.is_finite() || !x_max.is_finite() || x_min > x_max {
+            return Err("Grid range must be finite and non-empty");
+        }
+        if resolution == 0 {
+            return Err("Grid resolution must be positive");
+        }
+        Ok(Self { x_min, x_max, resolution })
+    }
+
+    /// Smallest grid spanning the support of every level in `landscapes`
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if all landscapes are empty or `resolution` is zero
+    pub fn covering(landscapes: &[Vec<Vec<(f64,f64)>>], resolution: usize) -> Result<Self, &'static str> {
+        let xs = landscapes.iter().flatten().flatten().map(|p| FloatOrd(p.0));
+        let (Some(x_min), Some(x_max)) = (xs.clone().min(), xs.max()) else {
+            return Err("Cannot build grid over empty landscapes");
+        };
+        Self::new(x_min.0, x_max.0, resolution)
+    }
+
+    /// The x-coordinates of the sample points
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn points(&self) -> Vec<f64> {
+        if self.resolution == 1 {
+            return vec![self.x_min];
+        }
+        let step = (self.x_max - self.x_min) / (self.resolution - 1) as f64;
+        (0..self.resolution)
+            .map(|i| (i as f64).mul_add(step, self.x_min))
+            .collect()
+    }
+}
+
+/// Samples the first `k` levels of `landscape` on `grid` and concatenates them, level by level.
+/// Missing levels are sampled as zero so the output always has `k * grid.resolution` values.
+#[must_use]
+pub fn sample(landscape: &[Vec<(f64,f64)>], k: usize, grid: &Grid) -> Vec<f64> {
+    let points = grid.points();
+    (0..k)
+        .flat_map(|level| sample_level(landscape.get(level).map_or(&[][..], Vec::as_slice), &points))
+        .collect()
+}
+
+/// Same as calling [`crate::persistencelandscape::evaluate`] at every point of the sorted `points`, bit for bit
+///
+/// Every linear piece of the level covers a contiguous run of points, which is filled four
+/// points at a time with SIMD.
+#[must_use]
+pub fn sample_level(level: &[(f64,f64)], points: &[f64]) -> Vec<f64> {
+    let mut values = vec![0.0; points.len()];
+    for w in level.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        if x1 <= x0 {
+            continue;
+        }
+        let start = points.partition_point(|&x| x < x0);
+        let end = points.partition_point(|&x| x < x1);
+        let (dy, dx) = (y1 - y0, x1 - x0);
+        let (xs, out) = (&points[start..end], &mut values[start..end]);
+        let mut xs_chunks = xs.chunks_exact(4);
+        let mut out_chunks = out.chunks_exact_mut(4);
+        let (start_y, start_x) = (f64x4::splat(y0), f64x4::splat(x0));
+        let (rise, run) = (f64x4::splat(dy), f64x4::splat(dx));
+        for (x, out) in (&mut xs_chunks).zip(&mut out_chunks) {
+            let x = f64x4::from([x[0], x[1], x[2], x[3]]);
+            out.copy_from_slice(&(start_y + rise * (x - start_x) / run).to_array());
+        }
+        for (x, out) in xs_chunks.remainder().iter().zip(out_chunks.into_remainder()) {
+            *out = y0 + dy * (x - x0) / dx;
+        }
+    }
+    if let Some(&(x_last, y_last)) = level.last() {
+        let start = points.partition_point(|&x| x < x_last);
+        let end = points.partition_point(|&x| x <= x_last);
+        values[start..end].fill(y_last);
+    }
+    values
+}
+
+/// Inverse of [`sample`], turns a sampled vector back into a landscape with a critical point at
+/// every grid point
+#[must_use]
+pub fn unsample(values: &[f64], grid: &Grid) -> Vec<Vec<(f64,f64)>> {
+    let points = grid.points();
+    values
+        .chunks(grid.resolution)
+        .map(|level| points.iter().copied().zip(level.iter().copied()).collect())
+        .collect()
+}
+
+/// Layout of a flattened feature vector: `k` levels of `resolution` samples each, taken
+/// evenly over [`x_min`, `x_max`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureDescriptor {
+    pub k: usize,
+    pub resolution: usize,
+    pub x_min: f64,
+    pub x_max: f64,
+}
+
+impl FeatureDescriptor {
+    #[must_use]
+    pub const fn new(k: usize, grid: &Grid) -> Self {
+        Self {
+            k,
+            resolution: grid.resolution,
+            x_min: grid.x_min,
+            x_max: grid.x_max,
+        }
+    }
+
+    /// The grid the features were sampled on
+    #[must_use]
+    pub const fn grid(&self) -> Grid {
+        Grid {
+            x_min: self.x_min,
+            x_max: self.x_max,
+            resolution: self.resolution,
+        }
+    }
+
+    /// Total number of features
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.k * self.resolution
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Position of sample `i` of `level` in the feature vector
+    #[must_use]
+    pub const fn index(&self, level: usize, i: usize) -> usize {
+        level * self.resolution + i
+    }
+
+    /// Rebuilds a landscape with a critical point at every grid point from a feature vector
+    ///
+    /// # Errors
+    ///
+    /// Will return 'Err' if the length of `features` does not match the descriptor
+    pub fn to_landscape(&self, features: &[f32]) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+        if features.len() != self.len() {
+            return Err("Feature vector length does not match descriptor");
+        }
+        let values: Vec<f64> = features.iter().map(|v| f64::from(*v)).collect();
+        Ok(unsample(&values, &self.grid()))
+    }
+}
+
+/// Feature vectors of many landscapes on one grid, stacked into a single buffer of shape
+/// `n_diagrams` x `k` x `resolution`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stacked {
+    /// Feature vectors one after the other, see [`FeatureDescriptor::index`]
+    pub values: Vec<f32>,
+    /// Layout of every feature vector
+    pub descriptor: FeatureDescriptor,
+}
+
+impl Stacked {
+    /// Number of stacked feature vectors
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len().checked_div(self.descriptor.len()).unwrap_or(0)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// `[n_diagrams, k, resolution]`
+    #[must_use]
+    pub fn shape(&self) -> [usize; 3] {
+        [self.len(), self.descriptor.k, self.descriptor.resolution]
+    }
+
+    /// Feature vector of diagram `i`
+    #[must_use]
+    pub fn diagram(&self, i: usize) -> &[f32] {
+        let len = self.descriptor.len();
+        &self.values[i * len..(i + 1) * len]
+    }
+}
+
+/// Computes the first `k` levels of the landscape of every diagram in parallel and samples them
+/// on `grid` straight into one [`Stacked`] buffer
+///
+/// # Errors
+///
+/// Will return 'Err' if `k` is zero, the stack needs a fixed number of levels
+#[allow(clippy::cast_possible_truncation)]
+pub fn generate_batch_gridded(diagrams: Vec<Vec<BirthDeath>>, k: usize, grid: &Grid) -> Result<Stacked, &'static str> {
+    if k == 0 {
+        return Err("Stacked landscapes need a positive number of levels");
+    }
+    let descriptor = FeatureDescriptor::new(k, grid);
+    let points = grid.points();
+    let mut values = vec![0.0; diagrams.len() * descriptor.len()];
+    values
+        .par_chunks_mut(descriptor.len())
+        .zip(diagrams.into_par_iter())
+        .for_each(|(features, diagram)| {
+            let landscape = generate(diagram, k, false);
+            for (level, out) in landscape.iter().zip(features.chunks_mut(grid.resolution)) {
+                for (out, v) in out.iter_mut().zip(sample_level(level, &points)) {
+                    *out = v as f32;
+                }
+            }
+        });
+    Ok(Stacked { values, descriptor })
+}
+
+/// Number format used to store grid samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    #[default]
+    F32,
+    /// IEEE half precision, about three significant digits
+    F16,
+    /// bfloat16, the range of f32 with about two significant digits
+    Bf16,
+}
+
+impl Precision {
+    /// Bytes per stored value
+    #[must_use]
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F16 | Self::Bf16 => 2,
+        }
+    }
+}
+
+/// Converts samples to `precision` and packs them little-endian
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn encode(values: &[f64], precision: Precision) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(values.len() * precision.bytes());
+    for &v in values {
+        match precision {
+            Precision::F32 => bytes.extend_from_slice(&(v as f32).to_le_bytes()),
+            Precision::F16 => bytes.extend_from_slice(&f16::from_f64(v).to_le_bytes()),
+            Precision::Bf16 => bytes.extend_from_slice(&bf16::from_f64(v).to_le_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Inverse of [`encode`]
+///
+/// # Errors
+///
+/// Will return 'Err' if the length of `bytes` is not a multiple of the value size
+pub fn decode(bytes: &[u8], precision: Precision) -> Result<Vec<f64>, &'static str> {
+    if bytes.len() % precision.bytes() != 0 {
+        return Err("Byte length is not a multiple of the value size");
+    }
+    Ok(bytes
+        .chunks_exact(precision.bytes())
+        .map(|c| match precision {
+            Precision::F32 => f64::from(f32::from_le_bytes([c[0], c[1], c[2], c[3]])),
+            Precision::F16 => f16::from_le_bytes([c[0], c[1]]).to_f64(),
+            Precision::Bf16 => bf16::from_le_bytes([c[0], c[1]]).to_f64(),
+        })
+        .collect())
+}
+
@@ -0,0 +1,102 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Cross-checking landscapes against a naive reference evaluated point by point
+//!
+//! The reference sorts the values of every tent at each point, which shares nothing with the
+//! sweep but the definition of the landscape and is slow enough that it is usually run on a
+//! sample of the pairs.
+
+use float_ord::FloatOrd;
+use rayon::prelude::*;
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::evaluate;
+
+/// Values of the first `k` levels at `x`, or of every level that is not zero there if `k` is
+/// zero, as the largest values of the tents of `bd_pairs`
+#[must_use]
+pub fn naive_values(bd_pairs: &[BirthDeath], k: usize, x: f64) -> Vec<f64> {
+    let mut values: Vec<f64> = bd_pairs
+        .iter()
+        .filter(|bd| bd.birth.is_finite() && bd.death.is_finite())
+        .map(|bd| (x - bd.birth).min(bd.death - x))
+        .filter(|&value| value > 0.0)
+        .collect();
+    values.sort_by(|a, b| b.total_cmp(a));
+    if k > 0 {
+        values.resize(k, 0.0);
+    }
+    values
+}
+
+/// Largest difference between a landscape and the reference, see [`verify`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Discrepancy {
+    /// Absolute difference of the values
+    pub value: f64,
+    /// Level of the difference, counting from one
+    pub level: usize,
+    pub x: f64,
+}
+
+/// Result of [`verify`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Verification {
+    /// Number of points the landscape was compared at
+    pub points: usize,
+    /// The largest difference, `None` if the landscape and the reference are both empty
+    pub worst: Option<Discrepancy>,
+}
+
+impl Verification {
+    /// The largest difference found, zero if there was none
+    #[must_use]
+    pub fn max_discrepancy(&self) -> f64 {
+        self.worst.map_or(0.0, |worst| worst.value)
+    }
+}
+
+/// Compares the first `k` levels of `landscapes`, or every level if `k` is zero, to
+/// [`naive_values`] of `bd_pairs`
+///
+/// The levels are compared at each of their critical points, at every birth, peak and death
+/// and at `resolution` evenly spaced points across the diagram. Both are linear between the
+/// critical points of the reference, so a landscape with every critical point right can
+/// still hide a wrong crossing between two of these points, which the evenly spaced points
+/// make unlikely to go unnoticed.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn verify(bd_pairs: &[BirthDeath], landscapes: &[Vec<(f64,f64)>], k: usize, resolution: usize) -> Verification {
+    let finite = || bd_pairs.iter().filter(|bd| bd.birth.is_finite() && bd.death.is_finite());
+    let mut xs: Vec<f64> = finite()
+        .flat_map(|bd| [bd.birth, (bd.birth + bd.death) / 2.0, bd.death])
+        .chain(landscapes.iter().flatten().map(|p| p.0))
+        .collect();
+    let x_min = finite().map(|bd| FloatOrd(bd.birth)).min();
+    let x_max = finite().map(|bd| FloatOrd(bd.death)).max();
+    if let (Some(x_min), Some(x_max)) = (x_min, x_max) {
+        let step = (x_max.0 - x_min.0) / resolution.max(1) as f64;
+        xs.extend((0..=resolution).map(|i| (i as f64).mul_add(step, x_min.0)));
+    }
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+
+    let levels = if k == 0 { landscapes.len() } else { k };
+    let worst = xs
+        .par_iter()
+        .flat_map_iter(|&x| {
+            let reference = naive_values(bd_pairs, k, x);
+            (0..levels.max(reference.len())).map(move |level| {
+                let computed = landscapes.get(level).map_or(0.0, |points| evaluate(points, x));
+                let expected = reference.get(level).copied().unwrap_or(0.0);
+                Discrepancy { value: (computed - expected).abs(), level: level + 1, x }
+            })
+        })
+        .max_by(|a, b| a.value.total_cmp(&b.value).then(b.x.total_cmp(&a.x)).then(b.level.cmp(&a.level)));
+    Verification { points: xs.len(), worst }
+}
@@ -0,0 +1,69 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+use rayon::prelude::*;
+
+use crate::birthdeath::BirthDeath;
+use crate::distance::Metric;
+use crate::persistencelandscape::generate;
+
+/// Distance reported between consecutive landscapes of a vineyard, the name from before
+/// [`Metric`] was shared by all distance computations
+pub type StepDistance = Metric;
+
+/// Landscapes of a sequence of diagrams
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vineyard {
+    /// One landscape per diagram, in input order
+    pub landscapes: Vec<Vec<Vec<(f64,f64)>>>,
+    /// `distances[i]` is the distance between landscapes `i` and `i + 1`, empty if not requested
+    pub distances: Vec<f64>,
+    /// Number of landscapes that were actually computed, the rest were reused
+    pub computed: usize,
+}
+
+/// Order independent key of a diagram, used to spot steps whose diagram did not change
+fn diagram_key(diagram: &[BirthDeath]) -> Vec<(u64, u64)> {
+    let mut key: Vec<(u64, u64)> = diagram
+        .iter()
+        .map(|bd| (bd.birth.to_bits(), bd.death.to_bits()))
+        .collect();
+    key.sort_unstable();
+    key
+}
+
+/// Computes the landscapes of an ordered sequence of diagrams
+///
+/// A step whose diagram is the same multiset of pairs as the previous step reuses the
+/// previous landscape, which is common for sliding windows over slowly changing data.
+/// The remaining steps are independent and are computed in parallel. When `distance`
+/// is given the distance between every pair of consecutive landscapes is reported too.
+#[must_use]
+pub fn vineyard(diagrams: Vec<Vec<BirthDeath>>, k: usize, distance: Option<Metric>) -> Vineyard {
+    let keys: Vec<Vec<(u64, u64)>> = diagrams.iter().map(|d| diagram_key(d)).collect();
+    let changed: Vec<bool> = (0..keys.len())
+        .map(|i| i == 0 || keys[i] != keys[i - 1])
+        .collect();
+    let n_computed = changed.iter().filter(|&&changed| changed).count();
+    let computed: Vec<_> = diagrams
+        .into_par_iter()
+        .zip(changed.par_iter())
+        .map(|(diagram, &changed)| changed.then(|| generate(diagram, k, false)))
+        .collect();
+
+    let mut landscapes: Vec<Vec<Vec<(f64,f64)>>> = Vec::with_capacity(computed.len());
+    let mut distances = Vec::new();
+    for landscape in computed {
+        // The first step is always computed, so there is a previous landscape to reuse
+        let landscape = landscape.unwrap_or_else(|| landscapes.last().cloned().unwrap_or_default());
+        if let (Some(distance), Some(previous)) = (distance, landscapes.last()) {
+            distances.push(distance.evaluate(previous, &landscape));
+        }
+        landscapes.push(landscape);
+    }
+    Vineyard { landscapes, distances, computed: n_computed }
+}
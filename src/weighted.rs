@@ -0,0 +1,277 @@
+#![warn(
+     clippy::all,
+     clippy::pedantic,
+     clippy::nursery,
+     clippy::cargo,
+ )]
+
+//! Landscapes of weighted diagrams
+//!
+//! The tent of a pair with weight `w` rises with slope `w` from the birth to its midpoint and
+//! falls with slope `w` to the death. Tents of different weights are no longer parallel, which
+//! the sweep of [`crate::persistencelandscape`] depends on, so they are swept here with the
+//! crossings of adjacent tents queued by position until they are reached. Crossings that were
+//! superseded meanwhile are recognised by version tags and dropped when they come up.
+
+use float_ord::FloatOrd;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::birthdeath::BirthDeath;
+use crate::persistencelandscape::{empty_landscape, finalize};
+
+#[derive(Debug, Clone, Copy)]
+struct Tent {
+    birth: f64,
+    death: f64,
+    weight: f64,
+    rising: bool,
+}
+
+impl Tent {
+    const fn slope(&self) -> f64 {
+        if self.rising { self.weight } else { -self.weight }
+    }
+
+    /// Point where the current segment is zero
+    const fn anchor(&self) -> f64 {
+        if self.rising { self.birth } else { self.death }
+    }
+
+    /// End of the current segment
+    fn end(&self) -> f64 {
+        if self.rising { self.middle() } else { self.death }
+    }
+
+    fn middle(&self) -> f64 {
+        (self.death - self.birth).mul_add(0.5, self.birth)
+    }
+
+    fn value(&self, x: f64) -> f64 {
+        (self.slope() * (x - self.anchor())).max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Death,
+    Peak,
+    Birth,
+}
+
+/// Position, kind, weight with the steepest first and index of a tent event
+type Event = (FloatOrd<f64>, Kind, Reverse<FloatOrd<f64>>, usize);
+
+/// Point where the tent `lower` overtakes the tent `upper` directly above it
+///
+/// The versions of both tents at the time it was queued tell whether it still holds when it
+/// is reached, see [`Sweep::version`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Crossing {
+    x: FloatOrd<f64>,
+    upper: usize,
+    lower: usize,
+    versions: (usize, usize),
+}
+
+struct Sweep {
+    tents: Vec<Tent>,
+    /// Alive tents from the highest to the lowest
+    status: Vec<usize>,
+    position: Vec<Option<usize>>,
+    /// Bumped whenever a tent swaps, peaks or dies. Insertions only happen at the bottom and
+    /// removals keep the order of the rest, so two adjacent tents stay adjacent with the
+    /// same lines as long as neither version changes
+    version: Vec<usize>,
+    crossings: BinaryHeap<Reverse<Crossing>>,
+    /// Queued crossing of every tent with the one below it, the same pair is queued only once
+    pending: Vec<Option<Crossing>>,
+    landscapes: Vec<Vec<(f64,f64)>>,
+    limit: usize,
+}
+
+impl Sweep {
+    fn record(&mut self, level: usize, point: (f64,f64)) {
+        if level >= self.limit {
+            return;
+        }
+        if self.landscapes.len() <= level {
+            self.landscapes.resize_with(level + 1, Vec::new);
+        }
+        self.landscapes[level].push(point);
+    }
+
+    /// Where the line of `lower` reaches the line of `upper`, if before either segment ends
+    fn crossing(&self, upper: usize, lower: usize) -> Option<f64> {
+        let (u, l) = (&self.tents[upper], &self.tents[lower]);
+        if l.slope() <= u.slope() {
+            return None;
+        }
+        let raw = u.slope().mul_add(u.anchor(), -l.slope() * l.anchor()) / (u.slope() - l.slope());
+        (raw < u.end().min(l.end())).then_some(raw)
+    }
+
+    /// Queues the crossing of the tents at `at` and right below it, not before `now`
+    fn schedule(&mut self, at: usize, now: f64) {
+        let (Some(&upper), Some(&lower)) = (self.status.get(at), self.status.get(at + 1)) else {
+            return;
+        };
+        let versions = (self.version[upper], self.version[lower]);
+        if self.pending[upper].is_some_and(|c| c.lower == lower && c.versions == versions) {
+            return;
+        }
+        let Some(x) = self.crossing(upper, lower) else {
+            return;
+        };
+        let crossing = Crossing { x: FloatOrd(x.max(now)), upper, lower, versions };
+        self.pending[upper] = Some(crossing);
+        self.crossings.push(Reverse(crossing));
+    }
+
+    /// Swaps the tents of a crossing unless either changed since it was queued
+    fn cross(&mut self, crossing: Crossing) {
+        if self.pending[crossing.upper] == Some(crossing) {
+            self.pending[crossing.upper] = None;
+        }
+        if crossing.versions != (self.version[crossing.upper], self.version[crossing.lower]) {
+            return;
+        }
+        let Some(at) = self.position[crossing.upper] else {
+            return;
+        };
+        let x = crossing.x.0;
+        let y = self.tents[crossing.upper].value(x);
+        self.version[crossing.upper] += 1;
+        self.version[crossing.lower] += 1;
+        self.status.swap(at, at + 1);
+        self.position[crossing.upper] = Some(at + 1);
+        self.position[crossing.lower] = Some(at);
+        self.record(at, (x, y));
+        self.record(at + 1, (x, y));
+        if let Some(above) = at.checked_sub(1) {
+            self.schedule(above, x);
+        }
+        self.schedule(at + 1, x);
+    }
+
+    fn birth(&mut self, id: usize) {
+        let tent = self.tents[id];
+        let at = self.status.len();
+        self.status.push(id);
+        self.position[id] = Some(at);
+        self.record(at, (tent.birth, 0.0));
+        if let Some(above) = at.checked_sub(1) {
+            self.schedule(above, tent.birth);
+        }
+    }
+
+    fn peak(&mut self, id: usize) {
+        let Some(at) = self.position[id] else {
+            return;
+        };
+        let x = self.tents[id].middle();
+        let y = self.tents[id].value(x);
+        self.tents[id].rising = false;
+        self.version[id] += 1;
+        self.record(at, (x, y));
+        if let Some(above) = at.checked_sub(1) {
+            self.schedule(above, x);
+        }
+        self.schedule(at, x);
+    }
+
+    fn death(&mut self, id: usize) {
+        let Some(at) = self.position[id].take() else {
+            return;
+        };
+        let x = self.tents[id].death;
+        self.version[id] += 1;
+        self.status.remove(at);
+        for (i, &other) in self.status.iter().enumerate().skip(at) {
+            self.position[other] = Some(i);
+        }
+        for level in at..=self.status.len().min(self.limit) {
+            let y = self.status.get(level).map_or(0.0, |&other| self.tents[other].value(x));
+            self.record(level, (x, y));
+        }
+        if let Some(above) = at.checked_sub(1) {
+            self.schedule(above, x);
+        }
+    }
+}
+
+/// Computes the first `k` levels of the landscape of `bd_pairs` with the tent of every pair
+/// scaled by its weight in `weights`, or every nonempty level if `k` is zero
+///
+/// Pairs with a non-finite value or a weight of zero contribute nothing. With every weight one
+/// the result matches [`crate::persistencelandscape::generate`].
+///
+/// # Errors
+///
+/// Will return 'Err' if there is not one weight per pair or a weight is negative or not finite
+pub fn generate_weighted(
+    bd_pairs: &[BirthDeath],
+    weights: &[f64],
+    k: usize,
+) -> Result<Vec<Vec<(f64,f64)>>, &'static str> {
+    if bd_pairs.len() != weights.len() {
+        return Err("Every pair needs exactly one weight");
+    }
+    if weights.iter().any(|w| !(w.is_finite() && *w >= 0.0)) {
+        return Err("Weights must be finite and not negative");
+    }
+    let tents: Vec<Tent> = bd_pairs
+        .iter()
+        .zip(weights)
+        .filter(|(bd, &weight)| {
+            bd.birth.is_finite() && bd.death.is_finite() && bd.death > bd.birth && weight > 0.0
+        })
+        .map(|(bd, &weight)| Tent { birth: bd.birth, death: bd.death, weight, rising: true })
+        .collect();
+
+    // Among births at one x the steepest tent goes first, so it starts above the others
+    let mut events: Vec<Event> = tents
+        .iter()
+        .enumerate()
+        .flat_map(|(id, tent)| {
+            [(tent.birth, Kind::Birth), (tent.middle(), Kind::Peak), (tent.death, Kind::Death)]
+                .map(|(x, kind)| (FloatOrd(x), kind, Reverse(FloatOrd(tent.weight)), id))
+        })
+        .collect();
+    events.sort_unstable();
+
+    let mut sweep = Sweep {
+        position: vec![None; tents.len()],
+        version: vec![0; tents.len()],
+        pending: vec![None; tents.len()],
+        tents,
+        status: Vec::new(),
+        crossings: BinaryHeap::new(),
+        landscapes: empty_landscape(k),
+        limit: if k == 0 { usize::MAX } else { k },
+    };
+    let mut events = events.into_iter().peekable();
+    loop {
+        let next_crossing = sweep.crossings.peek().map(|c| c.0.x);
+        match (events.peek(), next_crossing) {
+            (None, None) => break,
+            (Some(event), Some(x)) if x < event.0 => {}
+            (Some(_), _) => {
+                let Some((_, kind, _, id)) = events.next() else { break };
+                match kind {
+                    Kind::Death => sweep.death(id),
+                    Kind::Peak => sweep.peak(id),
+                    Kind::Birth => sweep.birth(id),
+                }
+                continue;
+            }
+            (None, Some(_)) => {}
+        }
+        if let Some(Reverse(crossing)) = sweep.crossings.pop() {
+            sweep.cross(crossing);
+        }
+    }
+
+    finalize(&mut sweep.landscapes, false);
+    Ok(sweep.landscapes)
+}